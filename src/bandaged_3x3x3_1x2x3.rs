@@ -1,11 +1,14 @@
+use std::str::FromStr;
+
 use derive_more::Display;
-use enum_iterator::Sequence;
+use enum_iterator::{all, Sequence};
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
+use crate::cubesearch::{assert_bit_budget, HasSolvedState, SimpleStartState};
+use crate::describe::PrettyState;
 use crate::idasearch::heuristic_helpers::bounded_cache;
 use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CubeMoveAmt};
+use crate::moves::{impl_can_reverse, parse_by_display, CubeMoveAmt, ParseMoveError};
 use crate::orientations::{CornerOrientation, EdgeOrientation};
 use crate::scrambles::RandomInit;
 
@@ -44,7 +47,8 @@ enum EdgeCubelet {
 
 impl EdgeCubelet {
     fn pack(self, source: &mut u64) {
-        *source = (*source << 3) + (self as u64);
+        // nine of them, so three bits isn't enough
+        *source = (*source << 4) + (self as u64);
     }
 }
 
@@ -281,7 +285,7 @@ impl OrientationState {
     }
 }
 
-impl Bandaged3x3x3with1x2x3 {
+impl HasSolvedState for Bandaged3x3x3with1x2x3 {
     #[inline(always)]
     fn solved() -> Self {
         Self {
@@ -289,7 +293,9 @@ impl Bandaged3x3x3with1x2x3 {
             orr: OrientationState::solved(),
         }
     }
+}
 
+impl Bandaged3x3x3with1x2x3 {
     #[inline(always)]
     fn r(&self) -> Self {
         Self {
@@ -326,13 +332,16 @@ pub enum Move {
     U(CubeMoveAmt),
 }
 
-impl CanReverse for Move {
-    fn reverse(&self) -> Self {
-        match self {
-            Move::Rw(amt) => Move::Rw(amt.reverse()),
-            Move::R(amt) => Move::R(amt.reverse()),
-            Move::U(amt) => Move::U(amt.reverse()),
-        }
+impl_can_reverse!(Move {
+    self_inverse: [],
+    by_amount: [R, Rw, U],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
     }
 }
 
@@ -357,6 +366,10 @@ impl Solvable for Bandaged3x3x3with1x2x3 {
         ]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         match last_move {
             Move::U(_) => matches!(next_move, Move::U(_)),
@@ -403,12 +416,13 @@ impl SimpleStartState for Bandaged3x3x3with1x2x3 {
 
     fn uniq_key(&self) -> Self::UniqueKey {
         // 6 corners: 3 bits for pos, 2 bits for orr        30 bits
-        // 9 edges: 3 bits for pos, 1 bit for orr           36 bits
+        // 9 edges: 4 bits for pos, 3 bits for orr (1 used) 63 bits
         // 4 centers: 2 bits for pos, orr is fixed          8 bits
 
         // so we'll put the edges on one side, and the corners and center into the other
 
         // pack corners & centers
+        const { assert_bit_budget(6 * 3 + 6 * 2 + 4 * 2, 64) };
         let mut out = 0;
         self.pos.ufl.pack(&mut out);
         self.pos.ufr.pack(&mut out);
@@ -432,16 +446,17 @@ impl SimpleStartState for Bandaged3x3x3with1x2x3 {
         let out_corners = out;
 
         // pack edges
+        const { assert_bit_budget(9 * 4 + 9 * 3, 64) };
         let mut out = 0;
         self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
-        self.pos.uf.pack(&mut out);
+        self.pos.ur.pack(&mut out);
+        self.pos.ul.pack(&mut out);
+        self.pos.ub.pack(&mut out);
+        self.pos.fr.pack(&mut out);
+        self.pos.br.pack(&mut out);
+        self.pos.df.pack(&mut out);
+        self.pos.db.pack(&mut out);
+        self.pos.dr.pack(&mut out);
 
         out = (out << 3) | self.orr.uf.as_u8_one_bit() as u64;
         out = (out << 3) | self.orr.ur.as_u8_one_bit() as u64;
@@ -458,6 +473,8 @@ impl SimpleStartState for Bandaged3x3x3with1x2x3 {
     }
 }
 
+impl PrettyState for Bandaged3x3x3with1x2x3 {}
+
 impl RandomInit for Bandaged3x3x3with1x2x3 {
     fn random_state<R: Rng>(_r: &mut R) -> Self {
         todo!("not sure about this yet, still working")
@@ -470,7 +487,8 @@ mod tests {
 
     use ahash::HashSet;
 
-    use super::CornerCubelet;
+    use super::{Bandaged3x3x3with1x2x3, CornerCubelet, EdgeCubelet};
+    use crate::cubesearch::SimpleStartState;
 
     #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
     struct CornerPositionState {
@@ -546,4 +564,63 @@ mod tests {
 
         assert_eq!(seen.len(), 120);
     }
+
+    /// The solved state, but with the edges in these slots, in the order `uniq_key` packs them
+    fn with_edges(edges: [EdgeCubelet; 9]) -> Bandaged3x3x3with1x2x3 {
+        let mut out = Bandaged3x3x3with1x2x3::start();
+        let pos = &mut out.pos;
+        [pos.uf, pos.ur, pos.ul, pos.ub, pos.fr, pos.br, pos.df, pos.db, pos.dr] = edges;
+        out
+    }
+
+    #[test]
+    fn edge_swaps_change_the_key() {
+        use EdgeCubelet::*;
+
+        let solved = [UF, UR, UL, UB, FR, BR, DF, DB, DR];
+
+        for (a, b) in [(0, 1), (2, 3), (0, 8), (7, 8)] {
+            let mut swapped = solved;
+            swapped.swap(a, b);
+
+            assert_ne!(
+                with_edges(swapped).uniq_key(),
+                with_edges(solved).uniq_key(),
+                "Swapping edges {a} and {b} should change the key"
+            );
+        }
+    }
+
+    #[test]
+    fn edges_dont_carry_into_each_other() {
+        use EdgeCubelet::*;
+
+        // DR is 8, so at three bits per edge it carried into the slot before it, and these two
+        // came out the same
+        let a = with_edges([UL, UB, UR, FR, BR, DF, DR, DB, UF]);
+        let b = with_edges([UL, UB, UR, FR, BR, DB, UF, DF, DR]);
+
+        assert_ne!(a.uniq_key(), b.uniq_key());
+    }
+
+    #[test]
+    fn reverse_matches_the_hand_written_one() {
+        use enum_iterator::all;
+
+        use super::Move;
+        use crate::moves::CanReverse;
+
+        // what the impl used to say, before impl_can_reverse wrote it
+        fn hand_written(m: Move) -> Move {
+            match m {
+                Move::Rw(amt) => Move::Rw(amt.reverse()),
+                Move::R(amt) => Move::R(amt.reverse()),
+                Move::U(amt) => Move::U(amt.reverse()),
+            }
+        }
+
+        for m in all::<Move>() {
+            assert_eq!(m.reverse(), hand_written(m), "Reverse of {m}");
+        }
+    }
 }
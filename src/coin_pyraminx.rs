@@ -1,7 +1,10 @@
-use crate::cubesearch::State;
+use enum_iterator::Sequence;
+
+use crate::cubesearch::{assert_bit_budget, FromKey, HasSolvedState, IndexedState, State};
+use crate::describe::KeyReader;
 use crate::orientations::CornerOrientation;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Ord, PartialOrd, Sequence)]
 enum FaceFacelet {
     F,
     D,
@@ -150,7 +153,7 @@ impl State for CoinPyraminx {
     fn uniq_key(&self) -> u32 {
         let mut out: u32 = 0;
 
-        debug_assert!(8 + 6 + 6 + 6 < 32, "Bitpacking should fit");
+        const { assert_bit_budget(8 + 4 * 6, 32) };
 
         // 8 bits for the axials
         out = (out << 2) | self.r_axial.as_u8_two_bits() as u32;
@@ -240,3 +243,217 @@ impl State for CoinPyraminx {
         }
     }
 }
+
+impl HasSolvedState for CoinPyraminx {}
+
+impl FromKey for CoinPyraminx {
+    fn from_key(key: &u32) -> Self {
+        let mut reader = KeyReader::new(*key as u64);
+
+        // they come off last-packed first
+        let mut facelet = || {
+            reader
+                .take_exact(2, |f: &FaceFacelet| f.as_u8_two_bits() as u64)
+                .expect("Every two bits are some facelet")
+        };
+        let [dr, dl, db, ru, rb, rr, lu, lb, ll, fu, fl, fr] = std::array::from_fn(|_| facelet());
+
+        let mut axial = || {
+            reader
+                .take_exact(2, |o: &CornerOrientation| o.as_u8_two_bits() as u64)
+                .expect("Keys come from uniq_key, so every axial decodes")
+        };
+        let [u_axial, b_axial, l_axial, r_axial] = std::array::from_fn(|_| axial());
+
+        Self {
+            u_axial,
+            l_axial,
+            r_axial,
+            b_axial,
+            fu,
+            fl,
+            fr,
+            lu,
+            lb,
+            ll,
+            ru,
+            rb,
+            rr,
+            dl,
+            dr,
+            db,
+        }
+    }
+}
+
+/// The number of ways to lay out facelets with the given number of each color; that is, the
+/// multinomial coefficient (sum of counts)! / (product of count!)
+fn arrangements(counts: &[usize; 4]) -> usize {
+    const FACTORIALS: [usize; 13] = [
+        1, 1, 2, 6, 24, 120, 720, 5040, 40320, 362880, 3628800, 39916800, 479001600,
+    ];
+
+    let total: usize = counts.iter().sum();
+    counts.iter().fold(FACTORIALS[total], |acc, &c| acc / FACTORIALS[c])
+}
+
+/// The twelve facelets hold three of each color, and this is how many ways there are to do that
+const FACELET_ARRANGEMENTS: usize = 369600;
+
+impl IndexedState for CoinPyraminx {
+    // the axials are independent of the facelets, and each other
+    const NUM_INDICES: usize = 81 * FACELET_ARRANGEMENTS;
+
+    fn index(&self) -> usize {
+        let axials = [self.r_axial, self.l_axial, self.b_axial, self.u_axial]
+            .into_iter()
+            .fold(0, |acc, a| acc * 3 + a.as_u8_two_bits() as usize);
+
+        let facelets = [
+            self.fr, self.fl, self.fu, self.ll, self.lb, self.lu, self.rr, self.rb, self.ru, self.db, self.dl, self.dr,
+        ];
+
+        // rank the facelets among all the arrangements with three of each color, in lexicographic
+        // order; that is, for each facelet, count the arrangements which agree up to here, but
+        // have a smaller color in this spot
+        let mut remaining = [3; 4];
+        let mut rank = 0;
+
+        for facelet in facelets {
+            let color = facelet.as_u8_two_bits() as usize;
+
+            for smaller in 0..color {
+                if remaining[smaller] > 0 {
+                    remaining[smaller] -= 1;
+                    rank += arrangements(&remaining);
+                    remaining[smaller] += 1;
+                }
+            }
+
+            remaining[color] -= 1;
+        }
+
+        axials * FACELET_ARRANGEMENTS + rank
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMap;
+
+    use super::*;
+    use crate::cubesearch::{enumerate_dense, enumerate_key_frontier, enumerate_state_space};
+
+    /// Every state within `depth` moves of solved
+    fn states_near_start(depth: usize) -> Vec<CoinPyraminx> {
+        let mut seen: HashMap<u32, CoinPyraminx> = HashMap::default();
+        let mut frontier = vec![CoinPyraminx::start()];
+
+        for _ in 0..=depth {
+            let mut next = Vec::new();
+            for state in frontier {
+                if seen.insert(state.uniq_key(), state).is_none() {
+                    state.neighbors(&mut |n| next.push(n));
+                }
+            }
+            frontier = next;
+        }
+
+        seen.into_values().collect()
+    }
+
+    #[test]
+    fn arrangements_of_the_facelets() {
+        assert_eq!(arrangements(&[3, 3, 3, 3]), FACELET_ARRANGEMENTS);
+        assert_eq!(arrangements(&[0, 0, 0, 0]), 1);
+        assert_eq!(arrangements(&[1, 1, 0, 0]), 2);
+        assert_eq!(arrangements(&[2, 1, 0, 0]), 3);
+    }
+
+    #[test]
+    fn distinct_states_have_distinct_indices() {
+        let states = states_near_start(6);
+        assert_eq!(states.len(), 188397);
+
+        let mut seen: HashMap<usize, CoinPyraminx> = HashMap::default();
+
+        for state in states {
+            let index = state.index();
+            assert!(index < CoinPyraminx::NUM_INDICES, "Index {index} is out of range");
+
+            if let Some(other) = seen.insert(index, state) {
+                panic!("{state:?} and {other:?} share index {index}");
+            }
+        }
+    }
+
+    // the HashSet side needs several GB of memory, and both sides take a while
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn dense_enumeration_matches_hashset() {
+        let (_, dense) = enumerate_dense::<CoinPyraminx>(None).unwrap();
+
+        // every index is reached, so the index is onto, as well as one-to-one
+        assert_eq!(dense.values().sum::<u128>(), CoinPyraminx::NUM_INDICES as u128);
+
+        let (_, sparse) = enumerate_state_space::<CoinPyraminx>(None).unwrap();
+        assert_eq!(sparse, dense);
+    }
+
+    #[test]
+    fn keys_rebuild_the_state() {
+        for state in states_near_start(5) {
+            assert_eq!(CoinPyraminx::from_key(&state.uniq_key()), state);
+        }
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn key_frontier_matches_full_states() {
+        let (_, keys) = enumerate_key_frontier::<CoinPyraminx>(None).unwrap();
+        let (_, states) = enumerate_state_space::<CoinPyraminx>(None).unwrap();
+        assert_eq!(keys, states);
+    }
+
+    #[test]
+    fn extreme_arrangements_hit_the_ends_of_the_range() {
+        let mut state = CoinPyraminx::start();
+        [
+            &mut state.fr,
+            &mut state.fl,
+            &mut state.fu,
+            &mut state.ll,
+            &mut state.lb,
+            &mut state.lu,
+            &mut state.rr,
+            &mut state.rb,
+            &mut state.ru,
+            &mut state.db,
+            &mut state.dl,
+            &mut state.dr,
+        ]
+        .into_iter()
+        .zip(
+            [FaceFacelet::F, FaceFacelet::D, FaceFacelet::L, FaceFacelet::R]
+                .into_iter()
+                .flat_map(|f| [f; 3]),
+        )
+        .for_each(|(slot, f)| *slot = f);
+
+        // sorted by color, untwisted, is the very first index
+        assert_eq!(state.index(), 0);
+
+        // and sorted backwards, twisted all the way, is the very last
+        let mut last = state;
+        (last.fr, last.fl, last.fu) = (FaceFacelet::R, FaceFacelet::R, FaceFacelet::R);
+        (last.ll, last.lb, last.lu) = (FaceFacelet::L, FaceFacelet::L, FaceFacelet::L);
+        (last.rr, last.rb, last.ru) = (FaceFacelet::D, FaceFacelet::D, FaceFacelet::D);
+        (last.db, last.dl, last.dr) = (FaceFacelet::F, FaceFacelet::F, FaceFacelet::F);
+        last.r_axial = CornerOrientation::CCW;
+        last.l_axial = CornerOrientation::CCW;
+        last.b_axial = CornerOrientation::CCW;
+        last.u_axial = CornerOrientation::CCW;
+
+        assert_eq!(last.index(), CoinPyraminx::NUM_INDICES - 1);
+    }
+}
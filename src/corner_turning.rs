@@ -0,0 +1,117 @@
+//! Puzzles whose moves all twist a corner a third of a turn, carrying the pieces around it along:
+//! the Ivy, Dino, Redi and Rex cubes, among others. A puzzle only has to say which pieces each
+//! corner moves; the moves themselves, and everything the search needs, come from here.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use enum_iterator::{all, Sequence};
+
+use crate::idasearch::Solvable;
+use crate::moves::{parse_by_display, CanReverse, CornerTwistAmt, ParseMoveError};
+use crate::orientations::CornerOrientation;
+
+pub trait CornerTurningPuzzle: Clone {
+    /// The corners which turn; their moves are tried in this order
+    type Corner: Copy + Eq + Display + Sequence;
+    type Piece: Copy;
+
+    /// The pieces a clockwise twist of `corner` moves, by position, as 3-cycles; the piece at the
+    /// first position of each goes to the second, the second to the third, and the third back to
+    /// the first.
+    fn cycles(corner: Self::Corner) -> &'static [[usize; 3]];
+
+    /// All the pieces, indexed by position the same way as [CornerTurningPuzzle::cycles]
+    fn pieces_mut(&mut self) -> &mut [Self::Piece];
+
+    /// For puzzles where the corner itself is a piece, which shows how far it's been twisted. By
+    /// default the corner is just an axis, and there's nothing to track.
+    #[allow(unused_variables)]
+    fn corner_orientation_mut(&mut self, corner: Self::Corner) -> Option<&mut CornerOrientation> {
+        None
+    }
+
+    fn is_solved(&self) -> bool;
+
+    /// Same as [Solvable::max_fuel]
+    fn max_fuel() -> usize;
+
+    /// Same as [Solvable::is_redundant], but by corner; the default only rules out twisting the
+    /// same corner twice in a row, which is never useful. Puzzles whose twists commute can say so.
+    fn is_redundant(last: Self::Corner, next: Self::Corner) -> bool {
+        last == next
+    }
+
+    fn twist_cw(&self, corner: Self::Corner) -> Self {
+        let mut out = self.clone();
+
+        let pieces = out.pieces_mut();
+        for &[a, b, c] in Self::cycles(corner) {
+            let moved = pieces[c];
+            pieces[c] = pieces[b];
+            pieces[b] = pieces[a];
+            pieces[a] = moved;
+        }
+
+        if let Some(orientation) = out.corner_orientation_mut(corner) {
+            *orientation = orientation.cw();
+        }
+
+        out
+    }
+}
+
+/// A third of a turn, either way, of one corner
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Sequence)]
+pub struct CornerMove<C>(pub C, pub CornerTwistAmt);
+
+impl<C: Display> Display for CornerMove<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.0, self.1)
+    }
+}
+
+impl<C: Copy> CanReverse for CornerMove<C> {
+    fn reverse(&self) -> Self {
+        CornerMove(self.0, self.1.reverse())
+    }
+}
+
+impl<C: Copy + Sequence + Display> FromStr for CornerMove<C> {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
+impl<P: CornerTurningPuzzle> Solvable for P {
+    type Move = CornerMove<P::Corner>;
+
+    fn is_solved(&self) -> bool {
+        CornerTurningPuzzle::is_solved(self)
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<P::Corner>().flat_map(|c| [CornerMove(c, CornerTwistAmt::Cw), CornerMove(c, CornerTwistAmt::Ccw)])
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Self::Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        <P as CornerTurningPuzzle>::is_redundant(last_move.0, next_move.0)
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        match m.1 {
+            CornerTwistAmt::Cw => self.twist_cw(m.0),
+            CornerTwistAmt::Ccw => self.twist_cw(m.0).twist_cw(m.0),
+        }
+    }
+
+    fn max_fuel() -> usize {
+        <P as CornerTurningPuzzle>::max_fuel()
+    }
+}
@@ -1,24 +1,107 @@
+use std::collections::hash_map::Entry;
 use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
-use ahash::{HashMap, HashSet};
+use ahash::{HashMap, HashSet, RandomState};
 use itertools::Itertools;
+use rayon::prelude::*;
 
 use crate::idasearch::Solvable;
+use crate::symmetry::SymmetricState;
 
-pub fn nice_print(puzzle_name: &str, counts: &HashMap<u128, u128>) {
-    println!("Configuration depth summary for {puzzle_name}:");
+/// How [nice_print] lays out the depth summary
+#[derive(Copy, Clone, Debug)]
+pub struct PrintOptions {
+    /// Digits after the decimal point, for the percentages
+    pub precision: usize,
+    /// If set, only show this many depths; the ones with the most configurations, still in depth
+    /// order
+    pub top_n: Option<usize>,
+    /// Also show the running total percentage, of everything at that depth or less
+    pub cumulative: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            precision: 3,
+            top_n: None,
+            cumulative: false,
+        }
+    }
+}
+
+pub fn nice_print(puzzle_name: &str, counts: &HashMap<u128, u128>, options: &PrintOptions) {
+    print!("{}", format_summary(puzzle_name, counts, options));
+}
+
+/// The text [nice_print] prints
+pub fn format_summary(puzzle_name: &str, counts: &HashMap<u128, u128>, options: &PrintOptions) -> String {
+    let PrintOptions {
+        precision,
+        top_n,
+        cumulative,
+    } = *options;
+
+    let mut out = format!("Configuration depth summary for {puzzle_name}:\n");
     let total: u128 = counts.values().sum();
 
-    let sorted_keys = counts.keys().copied().sorted();
+    out.push_str(&format!("\tThere are {total} total configurations.\n"));
+
+    let shown: HashSet<u128> = match top_n {
+        Some(n) => {
+            out.push_str(&format!(
+                "\tShowing the {} largest of {} depths.\n",
+                n.min(counts.len()),
+                counts.len()
+            ));
+            counts
+                .iter()
+                .sorted_by_key(|&(&depth, &val)| (std::cmp::Reverse(val), depth))
+                .take(n)
+                .map(|(&depth, _)| depth)
+                .collect()
+        }
+        None => counts.keys().copied().collect(),
+    };
 
-    println!("\tThere are {total} total configurations.");
+    // the running total counts every depth, shown or not, so it always ends at 100%
+    let mut so_far: u128 = 0;
 
-    for k in sorted_keys {
+    for k in counts.keys().copied().sorted() {
         let val = counts.get(&k).copied().unwrap();
+        so_far += val;
+
+        if !shown.contains(&k) {
+            continue;
+        }
+
         let pct = (val as f64) / (total as f64) * 100.;
-        println!("\t{k} moves: {val} configurations ({pct:0.3} %)");
+        out.push_str(&format!("\t{k} moves: {val} configurations ({pct:0.precision$} %"));
+        if cumulative {
+            let cumulative_pct = (so_far as f64) / (total as f64) * 100.;
+            out.push_str(&format!("; {cumulative_pct:0.precision$} % up to here"));
+        }
+        out.push_str(")\n");
     }
+
+    out
+}
+
+/// The extra lines for the summary, from [enumerate_local_maxima]: how many configurations in each
+/// of the last `last_n` depths are locally maximal, out of all those at that depth
+pub fn format_local_maxima(counts: &HashMap<u128, u128>, local_maxima: &HashMap<u128, u128>, last_n: usize) -> String {
+    let mut out = format!("\tLocally maximal configurations (no neighbor is deeper), last {last_n} depths:\n");
+
+    for k in counts.keys().copied().sorted().rev().take(last_n).rev() {
+        let val = counts.get(&k).copied().unwrap();
+        let maxima = local_maxima.get(&k).copied().unwrap_or(0);
+        out.push_str(&format!("\t{k} moves: {maxima} of {val} configurations\n"));
+    }
+
+    out
 }
 
 pub trait State: Sized {
@@ -51,6 +134,34 @@ pub trait State: Sized {
     fn uniq_key(&self) -> Self::UniqueKey;
 }
 
+/// States which can be rebuilt from their keys. A search can then hold just the keys in its
+/// frontier, and make the states again when it needs their neighbors; see [enumerate_key_frontier].
+pub trait FromKey: State {
+    /// Only ever given keys which came from [State::uniq_key], so it's fine to panic on others
+    fn from_key(key: &Self::UniqueKey) -> Self;
+}
+
+/// The solved state of a puzzle, under that name. Every puzzle here starts solved, so the default
+/// is just [State::start]; puzzles which already build their solved state by hand put that here
+/// instead, and have `start` call it.
+pub trait HasSolvedState: State {
+    fn solved() -> Self {
+        <Self as State>::start()
+    }
+}
+
+/// Checks that a packed [State::uniq_key] layout of `bits` bits fits in a key of `max` bits. Call it
+/// in a `const` block, so that a layout which doesn't fit is a compile error, rather than a
+/// collision (or a debug assert) at runtime:
+///
+/// ```compile_fail
+/// // seven corners at 3 bits, plus seven twists at 2 bits, won't fit in a u32
+/// const { assert_bit_budget(7 * 3 + 7 * 2, 32) };
+/// ```
+pub const fn assert_bit_budget(bits: u32, max: u32) {
+    assert!(bits <= max, "Packed state doesn't fit in its key");
+}
+
 /// Simple trait to implement if you have a solvable implementation already, and want a State
 /// implementation for free
 pub trait SimpleStartState: Sized {
@@ -111,28 +222,129 @@ where
     }
 }
 
-pub fn enumerate_state_space_started<T>(starts: Vec<T>) -> (Duration, HashMap<u128, u128>)
+/// A state which can be packed into a small dense index, so that enumeration can track seen
+/// states with a bitset instead of a HashSet.
+pub trait IndexedState: State {
+    /// Every index returned by `index` must be strictly less than this
+    const NUM_INDICES: usize;
+
+    /// Index identifying the state; should agree with `uniq_key`, in the sense that two states
+    /// have the same index exactly when they have the same key.
+    fn index(&self) -> usize;
+}
+
+/// An [IndexedState] which can be rebuilt from its index, so a table over the indices can be
+/// filled in without holding on to the states themselves
+pub trait FromIndex: IndexedState {
+    /// Only ever given indices which came from [IndexedState::index], so it's fine to panic on others
+    fn from_index(index: usize) -> Self;
+}
+
+/// Fixed-size bitset over the indices of an IndexedState
+struct DenseSeen {
+    words: Vec<u64>,
+}
+
+impl DenseSeen {
+    fn new(num_indices: usize) -> Self {
+        Self {
+            words: vec![0; num_indices.div_ceil(64)],
+        }
+    }
+
+    /// Marks the index as seen; returns true if it was not already seen (like HashSet::insert)
+    fn insert(&mut self, index: usize) -> bool {
+        let word = &mut self.words[index / 64];
+        let mask = 1 << (index % 64);
+        let is_new = *word & mask == 0;
+        *word |= mask;
+        is_new
+    }
+}
+
+/// Rough guess at how long the next stage will take. The work in a stage is about proportional to
+/// the number of states it processes, and the state count tends to grow by a steady ratio until
+/// near the diameter, so this assumes the next stage grows by the same ratio as this one did.
+///
+/// None if there isn't a previous stage to get a ratio from.
+fn estimate_next_stage(prev_stage_configs: u128, this_stage_configs: u128, stage_time: Duration) -> Option<Duration> {
+    if prev_stage_configs == 0 {
+        return None;
+    }
+
+    let ratio = this_stage_configs as f64 / prev_stage_configs as f64;
+
+    Duration::try_from_secs_f64(stage_time.as_secs_f64() * ratio).ok()
+}
+
+/// Records the number of new configurations found at the next distance, and prints progress.
+/// Returns false if the total is now over `max_states`, meaning the search should give up.
+fn record_stage(
+    counts: &mut HashMap<u128, u128>,
+    this_stage_new_configs: u128,
+    max_states: Option<u128>,
+    start_time: Instant,
+    stage_time: Duration,
+) -> bool {
+    let next_distance = counts.len() as u128;
+    counts.insert(next_distance, this_stage_new_configs);
+
+    let total_so_far: u128 = counts.values().sum();
+    if max_states.is_some_and(|max_states| total_so_far > max_states) {
+        eprintln!("Gave up after finding {total_so_far} states, which is over the limit");
+        return false;
+    }
+
+    // TODO: find a nice way to enable/disable this with the CLI, without adding a ton of typing.
+    // It's progress, not results, so it goes to stderr and leaves stdout to whatever's reporting
+    eprintln!(
+        "Many distance! Up to {} without stopping; up to {total_so_far} unique states so far. Elapsed: {:?}",
+        next_distance + 1,
+        start_time.elapsed()
+    );
+
+    let prev_stage_configs = next_distance
+        .checked_sub(1)
+        .and_then(|prev| counts.get(&prev))
+        .copied()
+        .unwrap_or(0);
+    if let Some(estimate) = estimate_next_stage(prev_stage_configs, this_stage_new_configs, stage_time) {
+        eprintln!("    (estimate, from the growth so far: the next stage might take around {estimate:.1?})");
+    }
+
+    true
+}
+
+/// Breadth-first enumeration shared by the HashSet and dense paths. The `first_visit` function
+/// should mark the state as seen, returning true if it had not been seen before.
+///
+/// If `max_states` is given and the search finds more states than that, it gives up and returns
+/// None.
+fn enumerate_with_seen<T, F>(
+    starts: Vec<T>,
+    max_states: Option<u128>,
+    mut first_visit: F,
+) -> Option<(Duration, HashMap<u128, u128>)>
 where
-    T: State + Hash + Eq,
+    T: State,
+    F: FnMut(&T) -> bool,
 {
     let start_time = Instant::now();
 
     let mut counts: HashMap<_, _> = Default::default();
 
-    let mut all_seen: HashSet<_> = Default::default();
-
-    let mut next_distance = 0;
     let mut to_process: Vec<T> = starts;
     let mut next_stage: Vec<T> = Vec::default();
 
     loop {
+        let stage_start = Instant::now();
         let mut this_stage_new_configs = 0;
         let mut recv = |neighbor| {
             next_stage.push(neighbor);
         };
 
         for state in to_process.iter() {
-            if !all_seen.insert(state.uniq_key()) {
+            if !first_visit(state) {
                 continue;
             }
 
@@ -147,15 +359,15 @@ where
             break;
         }
 
-        counts.insert(next_distance, this_stage_new_configs);
-        next_distance += 1;
-
-        // TODO: find a nice way to enable/disable this with the CLI, without adding a ton of typing
-        println!(
-            "Many distance! Up to {next_distance} without stopping; up to {} unique states so far. Elapsed: {:?}",
-            counts.values().sum::<u128>(),
-            start_time.elapsed()
-        );
+        if !record_stage(
+            &mut counts,
+            this_stage_new_configs,
+            max_states,
+            start_time,
+            stage_start.elapsed(),
+        ) {
+            return None;
+        }
 
         to_process.clear();
         std::mem::swap(&mut to_process, &mut next_stage);
@@ -163,12 +375,696 @@ where
 
     let elapsed = start_time.elapsed();
 
-    (elapsed, counts)
+    Some((elapsed, counts))
+}
+
+pub fn enumerate_state_space_started<T>(
+    starts: Vec<T>,
+    max_states: Option<u128>,
+) -> Option<(Duration, HashMap<u128, u128>)>
+where
+    T: State,
+{
+    let mut all_seen: HashSet<_> = Default::default();
+
+    enumerate_with_seen(starts, max_states, |state: &T| all_seen.insert(state.uniq_key()))
 }
 
-pub fn enumerate_state_space<T>() -> (Duration, HashMap<u128, u128>)
+pub fn enumerate_state_space<T>(max_states: Option<u128>) -> Option<(Duration, HashMap<u128, u128>)>
 where
     T: State + Hash + Eq,
 {
-    enumerate_state_space_started(vec![T::start()])
+    enumerate_state_space_started(vec![T::start()], max_states)
+}
+
+/// A state which is only its key; the real state is rebuilt whenever it's needed
+struct Keyed<T: FromKey>(T::UniqueKey);
+
+impl<T> State for Keyed<T>
+where
+    T: FromKey,
+    T::UniqueKey: Copy,
+{
+    type UniqueKey = T::UniqueKey;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        T::from_key(&self.0).neighbors(&mut |n: T| to_add(Keyed(n.uniq_key())));
+    }
+
+    fn start() -> Self {
+        Keyed(T::start().uniq_key())
+    }
+
+    fn should_count_as_config(&self) -> bool {
+        T::from_key(&self.0).should_count_as_config()
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        self.0
+    }
+}
+
+/// Same as [enumerate_state_space], but the frontier holds keys instead of states. For puzzles
+/// whose keys are bitpacked, that's a fraction of the memory, at the cost of rebuilding each state
+/// from its key to get the neighbors.
+pub fn enumerate_key_frontier<T>(max_states: Option<u128>) -> Option<(Duration, HashMap<u128, u128>)>
+where
+    T: FromKey,
+    T::UniqueKey: Copy,
+{
+    enumerate_state_space_started(vec![Keyed::<T>::start()], max_states)
+}
+
+/// Same as enumerate_state_space, but tracks seen states with a bitset over the dense index
+/// rather than a HashSet of keys. Much faster when the index space is small.
+pub fn enumerate_dense<T>(max_states: Option<u128>) -> Option<(Duration, HashMap<u128, u128>)>
+where
+    T: IndexedState,
+{
+    let mut all_seen = DenseSeen::new(T::NUM_INDICES);
+
+    enumerate_with_seen(vec![T::start()], max_states, |state: &T| all_seen.insert(state.index()))
+}
+
+/// The neighbors of every reachable state of an IndexedState, by index, worked out once up front.
+/// Enumerating with it never builds a state at all, just follows indices, which pays off when the
+/// moves themselves are the expensive part.
+pub struct TransitionTable {
+    num_indices: usize,
+    moves_per_state: usize,
+    start: u32,
+    /// row `i` (of length `moves_per_state`) is the neighbors of index `i`, in the same order
+    /// `neighbors` gives them; rows of unreachable indices are left as UNREACHED
+    next: Vec<u32>,
+}
+
+impl TransitionTable {
+    const UNREACHED: u32 = u32::MAX;
+
+    /// Walks the whole reachable state space (once) to fill in the table. Every state needs to
+    /// have the same number of neighbors.
+    pub fn new<T: IndexedState>() -> Self {
+        assert!(T::NUM_INDICES < Self::UNREACHED as usize, "Indices should fit in a u32");
+
+        let start = T::start();
+
+        let mut moves_per_state = 0;
+        start.neighbors(&mut |_| moves_per_state += 1);
+
+        let mut next = vec![Self::UNREACHED; T::NUM_INDICES * moves_per_state];
+        let mut seen = DenseSeen::new(T::NUM_INDICES);
+        seen.insert(start.index());
+
+        let mut to_process = vec![start];
+
+        while let Some(state) = to_process.pop() {
+            let row_start = state.index() * moves_per_state;
+            let mut filled = 0;
+
+            state.neighbors(&mut |neighbor| {
+                let index = neighbor.index();
+                next[row_start + filled] = index as u32;
+                filled += 1;
+
+                if seen.insert(index) {
+                    to_process.push(neighbor);
+                }
+            });
+
+            debug_assert_eq!(
+                filled, moves_per_state,
+                "Every state should have the same number of moves"
+            );
+        }
+
+        Self {
+            num_indices: T::NUM_INDICES,
+            moves_per_state,
+            start: T::start().index() as u32,
+            next,
+        }
+    }
+
+    /// The indices of the neighbors of the state with the given index, in the order `neighbors`
+    /// gives them
+    pub fn neighbors(&self, index: u32) -> &[u32] {
+        let row_start = index as usize * self.moves_per_state;
+        &self.next[row_start..row_start + self.moves_per_state]
+    }
+}
+
+/// Same as enumerate_dense, but follows a precomputed TransitionTable rather than applying moves.
+/// Every index counts as a configuration; that is, this doesn't support `should_count_as_config`.
+pub fn enumerate_with_table(
+    table: &TransitionTable,
+    max_states: Option<u128>,
+) -> Option<(Duration, HashMap<u128, u128>)> {
+    let start_time = Instant::now();
+
+    let mut counts: HashMap<_, _> = Default::default();
+    let mut seen = DenseSeen::new(table.num_indices);
+
+    let mut to_process: Vec<u32> = vec![table.start];
+    let mut next_stage: Vec<u32> = Vec::default();
+
+    loop {
+        let stage_start = Instant::now();
+        let mut this_stage_new_configs = 0;
+
+        for &index in to_process.iter() {
+            if !seen.insert(index as usize) {
+                continue;
+            }
+
+            this_stage_new_configs += 1;
+            next_stage.extend_from_slice(table.neighbors(index));
+        }
+
+        if this_stage_new_configs == 0 {
+            break;
+        }
+
+        if !record_stage(
+            &mut counts,
+            this_stage_new_configs,
+            max_states,
+            start_time,
+            stage_start.elapsed(),
+        ) {
+            return None;
+        }
+
+        to_process.clear();
+        std::mem::swap(&mut to_process, &mut next_stage);
+    }
+
+    Some((start_time.elapsed(), counts))
+}
+
+/// HashSet split into independently locked shards, so many threads can insert at once without
+/// all waiting on the same lock.
+struct ShardedSeen<K> {
+    hasher: RandomState,
+    shards: Vec<Mutex<HashSet<K>>>,
+}
+
+impl<K: Hash + Eq> ShardedSeen<K> {
+    const NUM_SHARDS: usize = 64;
+
+    fn new() -> Self {
+        Self {
+            hasher: RandomState::new(),
+            shards: (0..Self::NUM_SHARDS).map(|_| Mutex::default()).collect(),
+        }
+    }
+
+    /// Returns true if the key was not already present; like HashSet::insert, exactly one caller
+    /// gets true for any given key.
+    fn insert(&self, key: K) -> bool {
+        let shard = (self.hasher.hash_one(&key) as usize) % self.shards.len();
+        self.shards[shard].lock().unwrap().insert(key)
+    }
+}
+
+/// Same as enumerate_state_space_started, but each stage is processed in parallel with rayon. The
+/// counts do not depend on the number of threads: every state in a stage is checked against the
+/// seen set before any of the next stage is, and the seen set lets exactly one copy of each state
+/// through.
+pub fn enumerate_state_space_par_started<T>(
+    starts: Vec<T>,
+    max_states: Option<u128>,
+) -> Option<(Duration, HashMap<u128, u128>)>
+where
+    T: State + Send + Sync,
+    T::UniqueKey: Send,
+{
+    let start_time = Instant::now();
+
+    let mut counts: HashMap<_, _> = Default::default();
+    let all_seen: ShardedSeen<T::UniqueKey> = ShardedSeen::new();
+
+    let mut to_process: Vec<T> = starts;
+
+    loop {
+        let stage_start = Instant::now();
+        let this_stage_new_configs = AtomicUsize::new(0);
+
+        let next_stage: Vec<T> = to_process
+            .par_iter()
+            .filter(|state| all_seen.insert(state.uniq_key()))
+            .flat_map_iter(|state| {
+                if state.should_count_as_config() {
+                    this_stage_new_configs.fetch_add(1, Ordering::Relaxed);
+                }
+
+                let mut neighbors = Vec::new();
+                state.neighbors(&mut |neighbor| neighbors.push(neighbor));
+                neighbors
+            })
+            .collect();
+
+        let this_stage_new_configs = this_stage_new_configs.into_inner() as u128;
+
+        if this_stage_new_configs == 0 {
+            break;
+        }
+
+        if !record_stage(
+            &mut counts,
+            this_stage_new_configs,
+            max_states,
+            start_time,
+            stage_start.elapsed(),
+        ) {
+            return None;
+        }
+
+        to_process = next_stage;
+    }
+
+    let elapsed = start_time.elapsed();
+
+    Some((elapsed, counts))
+}
+
+pub fn enumerate_state_space_par<T>(max_states: Option<u128>) -> Option<(Duration, HashMap<u128, u128>)>
+where
+    T: State + Send + Sync,
+    T::UniqueKey: Send,
+{
+    enumerate_state_space_par_started(vec![T::start()], max_states)
+}
+
+/// The result of [enumerate_state_space_pruned]; the usual counts per distance, plus how much of
+/// the neighbor generation the pruning saved
+pub struct PrunedEnumeration {
+    pub elapsed: Duration,
+    pub counts: HashMap<u128, u128>,
+    /// Neighbors actually generated (and checked against the seen set)
+    pub neighbors_generated: u128,
+    /// Moves skipped because they were redundant with the move that reached the state; without the
+    /// pruning, these would have been generated too
+    pub neighbors_skipped: u128,
+}
+
+/// Same as enumerate_state_space_started, but the neighbors come from the Solvable moves, and (like
+/// the IDA* search) any move which is redundant with the one that first reached a state is
+/// skipped. The counts come out the same, since a redundant move only ever leads to a state which
+/// is just as close some other way; but fewer neighbors get generated and checked.
+pub fn enumerate_state_space_pruned<T>(starts: Vec<T>, max_states: Option<u128>) -> Option<PrunedEnumeration>
+where
+    T: State + Solvable,
+{
+    let start_time = Instant::now();
+
+    let mut counts: HashMap<_, _> = Default::default();
+    let mut all_seen: HashSet<T::UniqueKey> = Default::default();
+    let mut neighbors_generated: u128 = 0;
+    let mut neighbors_skipped: u128 = 0;
+
+    let mut to_process: Vec<(T, Option<T::Move>)> = starts.into_iter().map(|s| (s, None)).collect();
+    let mut next_stage: Vec<(T, Option<T::Move>)> = Vec::default();
+
+    loop {
+        let stage_start = Instant::now();
+        let mut this_stage_new_configs = 0;
+
+        for (state, last_move) in to_process.iter() {
+            if !all_seen.insert(state.uniq_key()) {
+                continue;
+            }
+
+            if state.should_count_as_config() {
+                this_stage_new_configs += 1;
+            }
+
+            for m in state.available_moves() {
+                if last_move.is_some_and(|last| T::is_redundant(last, m)) {
+                    neighbors_skipped += 1;
+                    continue;
+                }
+
+                neighbors_generated += 1;
+                next_stage.push((state.apply(m), Some(m)));
+            }
+        }
+
+        if this_stage_new_configs == 0 {
+            break;
+        }
+
+        if !record_stage(
+            &mut counts,
+            this_stage_new_configs,
+            max_states,
+            start_time,
+            stage_start.elapsed(),
+        ) {
+            return None;
+        }
+
+        to_process.clear();
+        std::mem::swap(&mut to_process, &mut next_stage);
+    }
+
+    Some(PrunedEnumeration {
+        elapsed: start_time.elapsed(),
+        counts,
+        neighbors_generated,
+        neighbors_skipped,
+    })
+}
+
+/// The result of [enumerate_local_maxima]: the usual counts, plus the locally maximal ones
+pub struct MaximaEnumeration {
+    pub elapsed: Duration,
+    pub counts: HashMap<u128, u128>,
+    /// How many configurations at each depth have no neighbor any deeper
+    pub local_maxima: HashMap<u128, u128>,
+}
+
+/// Same as enumerate_state_space_started, but also counts the configurations at each depth which
+/// are locally maximal: no move leads any further from the start, only back toward it (or across,
+/// to the same depth). Everything in the deepest layer is one of these, but the tail of a puzzle
+/// often has more of them, a few layers in.
+///
+/// Deciding that needs the depth of every neighbor, not just whether it's been seen, so the seen set
+/// is a map from key to depth, and states are checked against it as they're found (so that one
+/// found earlier in the same stage still counts as deeper).
+pub fn enumerate_local_maxima<T>(starts: Vec<T>, max_states: Option<u128>) -> Option<MaximaEnumeration>
+where
+    T: State,
+{
+    let start_time = Instant::now();
+
+    let mut counts: HashMap<_, _> = Default::default();
+    let mut local_maxima: HashMap<_, _> = Default::default();
+    let mut depths: HashMap<T::UniqueKey, u128> = Default::default();
+
+    let mut to_process: Vec<T> = starts
+        .into_iter()
+        .filter(|state| depths.insert(state.uniq_key(), 0).is_none())
+        .collect();
+
+    loop {
+        let stage_start = Instant::now();
+        let depth = counts.len() as u128;
+        let mut this_stage_new_configs = 0;
+        let mut this_stage_maxima = 0;
+        let mut next_stage: Vec<T> = Vec::default();
+
+        for state in to_process.iter() {
+            let mut has_deeper = false;
+
+            state.neighbors(&mut |neighbor| match depths.entry(neighbor.uniq_key()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(depth + 1);
+                    next_stage.push(neighbor);
+                    has_deeper = true;
+                }
+                Entry::Occupied(entry) => has_deeper |= *entry.get() > depth,
+            });
+
+            if state.should_count_as_config() {
+                this_stage_new_configs += 1;
+                if !has_deeper {
+                    this_stage_maxima += 1;
+                }
+            }
+        }
+
+        if this_stage_new_configs == 0 {
+            break;
+        }
+
+        if !record_stage(
+            &mut counts,
+            this_stage_new_configs,
+            max_states,
+            start_time,
+            stage_start.elapsed(),
+        ) {
+            return None;
+        }
+        local_maxima.insert(depth, this_stage_maxima);
+
+        to_process = next_stage;
+    }
+
+    Some(MaximaEnumeration {
+        elapsed: start_time.elapsed(),
+        counts,
+        local_maxima,
+    })
+}
+
+/// The result of [enumerate_deduped_frontier]: the usual counts, plus how big the frontier got
+pub struct FrontierEnumeration {
+    pub elapsed: Duration,
+    pub counts: HashMap<u128, u128>,
+    /// The most states waiting to be processed at once
+    pub peak_frontier: usize,
+}
+
+/// Same as enumerate_state_space, but states are checked against the seen set as they're found,
+/// rather than when they're processed, so nothing is in the frontier twice.
+///
+/// With `canonical`, states are keyed by [SymmetricState::canonical_under_symmetry], so only one
+/// state from each symmetry class ever gets into the frontier, and it's counted as its whole
+/// orbit. Symmetric images are the same distance from the start, so the counts come out the same
+/// as without, but the frontier (and the seen set) is up to 48 times smaller.
+pub fn enumerate_deduped_frontier<T>(max_states: Option<u128>, canonical: bool) -> Option<FrontierEnumeration>
+where
+    T: SymmetricState,
+{
+    let start_time = Instant::now();
+
+    let key = |state: &T| {
+        if canonical {
+            state.canonical_under_symmetry()
+        } else {
+            state.uniq_key()
+        }
+    };
+    let weight = |state: &T| if canonical { state.orbit_size() } else { 1 };
+
+    let mut counts: HashMap<_, _> = Default::default();
+    let mut all_seen: HashSet<T::UniqueKey> = Default::default();
+
+    let start = T::start();
+    all_seen.insert(key(&start));
+
+    let mut to_process: Vec<T> = vec![start];
+    let mut peak_frontier = to_process.len();
+
+    loop {
+        let stage_start = Instant::now();
+
+        let this_stage_new_configs: u128 = to_process
+            .iter()
+            .filter(|state| state.should_count_as_config())
+            .map(weight)
+            .sum();
+
+        if this_stage_new_configs == 0 {
+            break;
+        }
+
+        if !record_stage(
+            &mut counts,
+            this_stage_new_configs,
+            max_states,
+            start_time,
+            stage_start.elapsed(),
+        ) {
+            return None;
+        }
+
+        let mut next_stage: Vec<T> = Vec::default();
+        for state in to_process.iter() {
+            state.neighbors(&mut |neighbor| {
+                if all_seen.insert(key(&neighbor)) {
+                    next_stage.push(neighbor);
+                }
+            });
+        }
+
+        peak_frontier = peak_frontier.max(next_stage.len());
+        to_process = next_stage;
+    }
+
+    Some(FrontierEnumeration {
+        elapsed: start_time.elapsed(),
+        counts,
+        peak_frontier,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cuboid_2x2x3::Cuboid2x2x3;
+    use crate::cuboid_2x3x3::Cuboid2x3x3;
+
+    #[test]
+    fn summary_top_n_and_cumulative() {
+        let (_, counts) = enumerate_state_space::<Cuboid2x2x3>(None).unwrap();
+        assert!(counts.len() > 3, "Need more depths than get shown");
+
+        let options = PrintOptions {
+            precision: 2,
+            top_n: Some(3),
+            cumulative: false,
+        };
+        let summary = format_summary("Cuboid", &counts, &options);
+        let rows = summary.lines().filter(|line| line.contains(" moves: ")).count();
+        assert_eq!(rows, 3);
+        assert_eq!(
+            summary.lines().count(),
+            3 + 3,
+            "Header, total, and the top-n note, then the rows"
+        );
+
+        let options = PrintOptions {
+            precision: 2,
+            top_n: None,
+            cumulative: true,
+        };
+        let summary = format_summary("Cuboid", &counts, &options);
+        let last_row = summary.lines().last().unwrap();
+        assert!(last_row.ends_with("; 100.00 % up to here)"), "Got {last_row:?}");
+    }
+
+    #[test]
+    fn antipodes_are_the_deepest_layer() {
+        use crate::floppy_1x3x3::Floppy1x3x3;
+
+        let (_, expected) = enumerate_state_space::<Floppy1x3x3>(None).unwrap();
+        let MaximaEnumeration {
+            counts, local_maxima, ..
+        } = enumerate_local_maxima(vec![Floppy1x3x3::start()], None).unwrap();
+        assert_eq!(counts, expected, "Tracking depths shouldn't change the counts");
+
+        // nothing at the deepest layer can have a deeper neighbor, so all of them are maxima
+        let deepest = counts.keys().copied().max().unwrap();
+        assert_eq!(local_maxima[&deepest], counts[&deepest]);
+        assert_eq!(local_maxima[&0], 0, "Solved has somewhere to go");
+
+        for (depth, maxima) in local_maxima.iter() {
+            assert!(maxima <= &counts[depth]);
+        }
+
+        let summary = format_local_maxima(&counts, &local_maxima, 2);
+        let last_row = summary.lines().last().unwrap();
+        assert_eq!(
+            last_row,
+            format!("\t{deepest} moves: {0} of {0} configurations", counts[&deepest])
+        );
+        assert_eq!(summary.lines().count(), 1 + 2);
+    }
+
+    fn assert_par_matches_serial<T>()
+    where
+        T: State + Hash + Eq + Send + Sync,
+        T::UniqueKey: Send,
+    {
+        let (_, expected) = enumerate_state_space::<T>(None).unwrap();
+
+        for num_threads in [1, 4] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            let (_, actual) = pool.install(|| enumerate_state_space_par::<T>(None)).unwrap();
+
+            assert_eq!(
+                actual, expected,
+                "Counts with {num_threads} threads should match serial"
+            );
+        }
+    }
+
+    #[test]
+    fn par_matches_serial_cuboid_2x2x3() {
+        assert_par_matches_serial::<Cuboid2x2x3>();
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn par_matches_serial_cuboid_2x3x3() {
+        assert_par_matches_serial::<Cuboid2x3x3>();
+    }
+
+    #[test]
+    fn next_stage_estimates_follow_growth() {
+        // grows quickly, then levels off and shrinks to nothing near the diameter
+        let stages: [u128; 9] = [1, 8, 60, 400, 2500, 9000, 4000, 30, 1];
+        // processing a stage takes 1ms per state, more or less
+        let stage_time = |configs: u128| Duration::from_millis(configs as u64);
+
+        assert_eq!(estimate_next_stage(0, stages[0], stage_time(stages[0])), None);
+
+        for window in stages.windows(2) {
+            let [prev, this] = [window[0], window[1]];
+            let this_time = stage_time(this);
+            let estimate = estimate_next_stage(prev, this, this_time).unwrap();
+
+            if this > prev {
+                assert!(
+                    estimate > this_time,
+                    "{prev} -> {this} is growing, so should the estimate"
+                );
+            } else {
+                assert!(
+                    estimate < this_time,
+                    "{prev} -> {this} is shrinking, so should the estimate"
+                );
+            }
+        }
+
+        // degenerate stages shouldn't panic
+        assert_eq!(estimate_next_stage(5, 0, Duration::from_secs(1)), Some(Duration::ZERO));
+        assert_eq!(estimate_next_stage(1, u128::MAX, Duration::MAX), None);
+    }
+
+    #[test]
+    fn par_respects_max_states() {
+        assert!(enumerate_state_space_par::<Cuboid2x2x3>(Some(241920)).is_some());
+        assert!(enumerate_state_space_par::<Cuboid2x2x3>(Some(241919)).is_none());
+    }
+
+    fn assert_pruned_matches_full<T>()
+    where
+        T: State + Solvable + Hash + Eq,
+    {
+        let (_, expected) = enumerate_state_space::<T>(None).unwrap();
+        let pruned = enumerate_state_space_pruned(vec![T::start()], None).unwrap();
+
+        assert_eq!(pruned.counts, expected);
+        assert!(pruned.neighbors_skipped > 0, "Should have pruned something");
+    }
+
+    #[test]
+    fn pruned_matches_full_cuboid_2x2x3() {
+        assert_pruned_matches_full::<Cuboid2x2x3>();
+    }
+
+    #[test]
+    fn pruned_matches_full_others() {
+        // a few different kinds of redundancy: same face, commuting faces in a fixed order, and
+        // the slice which can't repeat
+        assert_pruned_matches_full::<crate::floppy_1x3x3::Floppy1x3x3>();
+        assert_pruned_matches_full::<crate::square_one_shape::SquareOneShape>();
+        assert_pruned_matches_full::<crate::skewb::SkewbCornersOnly>();
+        assert_pruned_matches_full::<crate::ivy_cube::IvyCube>();
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn pruned_matches_full_cuboid_2x3x3() {
+        assert_pruned_matches_full::<Cuboid2x3x3>();
+    }
 }
@@ -1,16 +1,20 @@
+use std::str::FromStr;
+
 use derive_more::Display;
 use enum_iterator::{all, Sequence};
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::describe::PrettyState;
+use crate::half_turn_cuboid::HalfTurnCuboid;
 use crate::idasearch::heuristic_helpers::bounded_cache;
 use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CubeMoveAmt};
+use crate::moves::{impl_can_reverse, parse_by_display, CubeMoveAmt, ParseMoveError};
 use crate::random_helpers;
 use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
-enum CornerCubelet {
+pub enum CornerCubelet {
     UFL,
     UFR,
     UBL,
@@ -31,15 +35,8 @@ enum CenterCubelet {
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub struct Cuboid2x2x3 {
-    // eight corners
-    ufl: CornerCubelet,
-    ufr: CornerCubelet,
-    ubl: CornerCubelet,
-    ubr: CornerCubelet,
-    dfl: CornerCubelet,
-    dfr: CornerCubelet,
-    dbl: CornerCubelet,
-    dbr: CornerCubelet,
+    // eight corners, in the order HalfTurnCuboid uses
+    corners: [CornerCubelet; 8],
 
     // three movable centers
     flc: CenterCubelet,
@@ -47,74 +44,42 @@ pub struct Cuboid2x2x3 {
     brc: CenterCubelet,
 }
 
-impl Cuboid2x2x3 {
+impl HasSolvedState for Cuboid2x2x3 {
     #[inline(always)]
     fn solved() -> Self {
         Self {
-            ufl: CornerCubelet::UFL,
-            ufr: CornerCubelet::UFR,
-            ubl: CornerCubelet::UBL,
-            ubr: CornerCubelet::UBR,
-            dfl: CornerCubelet::DFL,
-            dfr: CornerCubelet::DFR,
-            dbl: CornerCubelet::DBL,
-            dbr: CornerCubelet::DBR,
+            corners: [
+                CornerCubelet::UFL,
+                CornerCubelet::UFR,
+                CornerCubelet::UBL,
+                CornerCubelet::UBR,
+                CornerCubelet::DFL,
+                CornerCubelet::DFR,
+                CornerCubelet::DBL,
+                CornerCubelet::DBR,
+            ],
             flc: CenterCubelet::FL,
             frc: CenterCubelet::FR,
             brc: CenterCubelet::BR,
         }
     }
+}
 
-    #[inline(always)]
-    fn u(&self) -> Self {
-        Self {
-            ufl: self.ufr,
-            ufr: self.ubr,
-            ubr: self.ubl,
-            ubl: self.ufl,
-            ..*self
-        }
-    }
+impl HalfTurnCuboid for Cuboid2x2x3 {
+    type Corner = CornerCubelet;
 
-    #[inline(always)]
-    fn d(&self) -> Self {
-        Self {
-            dfl: self.dbl,
-            dbl: self.dbr,
-            dbr: self.dfr,
-            dfr: self.dfl,
-            ..*self
-        }
+    fn corners_mut(&mut self) -> &mut [CornerCubelet; 8] {
+        &mut self.corners
     }
 
-    #[inline(always)]
-    fn f2(&self) -> Self {
-        Self {
-            // cycle front corners
-            dfl: self.ufr,
-            ufr: self.dfl,
-            dfr: self.ufl,
-            ufl: self.dfr,
-            // mess with front two centers
-            flc: self.frc,
-            frc: self.flc,
-            ..*self
-        }
+    fn r2_rest(&mut self) {
+        // mess with right two centers
+        std::mem::swap(&mut self.frc, &mut self.brc);
     }
 
-    #[inline(always)]
-    fn r2(&self) -> Self {
-        Self {
-            // cycle right corners
-            dfr: self.ubr,
-            ubr: self.dfr,
-            dbr: self.ufr,
-            ufr: self.dbr,
-            // mess with right two centers
-            frc: self.brc,
-            brc: self.frc,
-            ..*self
-        }
+    fn f2_rest(&mut self) {
+        // mess with front two centers
+        std::mem::swap(&mut self.flc, &mut self.frc);
     }
 }
 
@@ -142,14 +107,16 @@ pub enum Move {
     D(CubeMoveAmt),
 }
 
-impl CanReverse for Move {
-    fn reverse(&self) -> Self {
-        match self {
-            Move::R2 => Move::R2,
-            Move::F2 => Move::F2,
-            Move::U(amt) => Move::U(amt.reverse()),
-            Move::D(amt) => Move::D(amt.reverse()),
-        }
+impl_can_reverse!(Move {
+    self_inverse: [R2, F2],
+    by_amount: [U, D],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
     }
 }
 
@@ -173,6 +140,10 @@ impl Solvable for Cuboid2x2x3 {
         ]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         match last_move {
             Move::R2 => next_move == Move::R2,
@@ -186,16 +157,8 @@ impl Solvable for Cuboid2x2x3 {
         match m {
             Move::R2 => self.r2(),
             Move::F2 => self.f2(),
-            Move::U(amt) => match amt {
-                CubeMoveAmt::One => self.u(),
-                CubeMoveAmt::Two => self.u().u(),
-                CubeMoveAmt::Rev => self.u().u().u(),
-            },
-            Move::D(amt) => match amt {
-                CubeMoveAmt::One => self.d(),
-                CubeMoveAmt::Two => self.d().d(),
-                CubeMoveAmt::Rev => self.d().d().d(),
-            },
+            Move::U(amt) => self.turn_u(amt),
+            Move::D(amt) => self.turn_d(amt),
         }
     }
 
@@ -204,6 +167,8 @@ impl Solvable for Cuboid2x2x3 {
     }
 }
 
+impl PrettyState for Cuboid2x2x3 {}
+
 impl RandomInit for Cuboid2x2x3 {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         // any permutation is fine
@@ -211,14 +176,7 @@ impl RandomInit for Cuboid2x2x3 {
         let (centers, _) = random_helpers::shuffle_any(r, all::<CenterCubelet>());
 
         Self {
-            ufl: corners[0],
-            ufr: corners[1],
-            ubl: corners[2],
-            ubr: corners[3],
-            dfl: corners[4],
-            dfr: corners[5],
-            dbl: corners[6],
-            dbr: corners[7],
+            corners: corners.try_into().expect("Shuffling keeps all eight corners"),
             flc: centers[0],
             frc: centers[1],
             brc: centers[2],
@@ -229,3 +187,49 @@ impl RandomInit for Cuboid2x2x3 {
 pub fn make_heuristic() -> impl Heuristic<Cuboid2x2x3> {
     bounded_cache::<Cuboid2x2x3>(8)
 }
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<Cuboid2x2x3, _>(
+            &bounded_cache::<Cuboid2x2x3>(5),
+            15,
+            &[
+                "U2 R2 U' F2 U F2 U' F2 U F2 U' R2",
+                "R2 D R2 U R2 U' F2 D R2 U R2 U' R2",
+                "R2 D2 R2 D' F2 U2 R2 U' F2",
+                "U2 R2 U F2 D2 U' R2 U' F2 R2",
+                "R2 D' F2 U' F2 U R2 D' F2 U2 R2",
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enum_iterator::all;
+
+    use super::*;
+    use crate::moves::CanReverse;
+
+    #[test]
+    fn reverse_matches_the_hand_written_one() {
+        // what the impl used to say, before impl_can_reverse wrote it
+        fn hand_written(m: Move) -> Move {
+            match m {
+                Move::R2 => Move::R2,
+                Move::F2 => Move::F2,
+                Move::U(amt) => Move::U(amt.reverse()),
+                Move::D(amt) => Move::D(amt.reverse()),
+            }
+        }
+
+        for m in all::<Move>() {
+            assert_eq!(m.reverse(), hand_written(m), "Reverse of {m}");
+        }
+    }
+}
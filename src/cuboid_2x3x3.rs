@@ -1,12 +1,18 @@
+use std::str::FromStr;
+
 use derive_more::Display;
 use enum_iterator::{all, Sequence};
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
-use crate::idasearch::heuristic_helpers::bounded_cache;
+use crate::cubesearch::{FromKey, HasSolvedState, SimpleStartState};
+use crate::describe::{KeyReader, PrettyState};
+use crate::idasearch::heuristic_helpers::{bounded_cache, CachedPlusLowerBound};
 use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CubeMoveAmt};
+use crate::legality::permutation_parity;
+use crate::moves::{impl_can_reverse, parse_by_display, CubeMoveAmt, ParseMoveError};
+use crate::orientations::CenterOrientation;
 use crate::random_helpers;
+use crate::random_helpers::TwoParity;
 use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
@@ -86,7 +92,7 @@ pub struct Cuboid2x3x3 {
     dc: CenterCubelet,
 }
 
-impl Cuboid2x3x3 {
+impl HasSolvedState for Cuboid2x3x3 {
     #[inline(always)]
     fn solved() -> Self {
         Self {
@@ -112,7 +118,9 @@ impl Cuboid2x3x3 {
             dc: CenterCubelet::D,
         }
     }
+}
 
+impl Cuboid2x3x3 {
     #[inline(always)]
     fn u(&self) -> Self {
         Self {
@@ -253,6 +261,65 @@ impl SimpleStartState for Cuboid2x3x3 {
     }
 }
 
+impl FromKey for Cuboid2x3x3 {
+    fn from_key(key: &u64) -> Self {
+        let mut reader = KeyReader::new(*key);
+
+        // they come off last-packed first
+        let uc = reader
+            .take_exact(1, |c: &CenterCubelet| *c as u64)
+            .expect("Every bit is some center");
+        let dc = match uc {
+            CenterCubelet::U => CenterCubelet::D,
+            CenterCubelet::D => CenterCubelet::U,
+        };
+
+        let mut corner = || {
+            reader
+                .take_exact(3, |c: &CornerCubelet| *c as u64)
+                .expect("Keys come from uniq_key, so every corner decodes")
+        };
+        let [dfr, dfl, ubr, ubl, ufr, ufl] = std::array::from_fn(|_| corner());
+
+        let mut edge = || {
+            reader
+                .take_exact(3, |e: &EdgeCubelet| *e as u64)
+                .expect("Every three bits are some edge")
+        };
+        let [db, dl, df, ul, ub, ur, uf] = std::array::from_fn(|_| edge());
+
+        // the unpacked ones are whichever pieces are left over
+        let packed_corners = [ufl, ufr, ubl, ubr, dfl, dfr];
+        let dbr = all::<CornerCubelet>()
+            .find(|c| !packed_corners.contains(c))
+            .expect("Six of seven corners are packed");
+        let packed_edges = [uf, ur, ub, ul, df, dl, db];
+        let dr = all::<EdgeCubelet>()
+            .find(|e| !packed_edges.contains(e))
+            .expect("Seven of eight edges are packed");
+
+        Self {
+            ufl,
+            ufr,
+            ubl,
+            ubr,
+            dfl,
+            dfr,
+            dbr,
+            uf,
+            ur,
+            ub,
+            ul,
+            df,
+            dr,
+            db,
+            dl,
+            uc,
+            dc,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Hash, Sequence)]
 pub enum Move {
     // R and F can only go 2
@@ -265,15 +332,16 @@ pub enum Move {
     U(CubeMoveAmt),
 }
 
-impl CanReverse for Move {
-    fn reverse(&self) -> Self {
-        match self {
-            Move::Rw2 => Move::Rw2,
-            Move::R2 => Move::R2,
-            Move::Fw2 => Move::Fw2,
-            Move::F2 => Move::F2,
-            Move::U(amt) => Move::U(amt.reverse()),
-        }
+impl_can_reverse!(Move {
+    self_inverse: [Rw2, R2, Fw2, F2],
+    by_amount: [U],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
     }
 }
 
@@ -296,6 +364,10 @@ impl Solvable for Cuboid2x3x3 {
         ]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         match last_move {
             Move::Rw2 => next_move == Move::R2 || next_move == Move::Rw2,
@@ -325,9 +397,13 @@ impl Solvable for Cuboid2x3x3 {
     }
 }
 
+impl PrettyState for Cuboid2x3x3 {}
+
 impl RandomInit for Cuboid2x3x3 {
     fn random_state<R: Rng>(r: &mut R) -> Self {
-        // any permutation is fine
+        // any permutation is fine, and the three orbits don't constrain each other: U is odd on the
+        // corners and edges, R2 and F2 only on the edges, and the wide turns on the edges and the
+        // centers. Those combine to flip any one parity on its own.
         let (corners, _) = random_helpers::shuffle_any(r, all::<CornerCubelet>());
         let (edges, _) = random_helpers::shuffle_any(r, all::<EdgeCubelet>());
         let (centers, _) = random_helpers::shuffle_any(r, all::<CenterCubelet>());
@@ -358,9 +434,129 @@ pub fn make_heuristic() -> impl Heuristic<Cuboid2x3x3> {
     bounded_cache::<Cuboid2x3x3>(8)
 }
 
+/// The "super" version of the Cuboid 2x3x3 (that is, the Domino), where the U and D centers are
+/// marked, so turning them counts. Only U turns turn a center in place; the wide half turns carry
+/// the centers to the other side, and Rw2 turns them upside down on the way.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct SuperCuboid2x3x3 {
+    cuboid: Cuboid2x3x3,
+    // the orientations of whichever centers are in the U and D positions
+    uc_orr: CenterOrientation,
+    dc_orr: CenterOrientation,
+}
+
+impl HasSolvedState for SuperCuboid2x3x3 {
+    #[inline(always)]
+    fn solved() -> Self {
+        Self {
+            cuboid: Cuboid2x3x3::solved(),
+            uc_orr: CenterOrientation::Normal,
+            dc_orr: CenterOrientation::Normal,
+        }
+    }
+}
+
+impl Solvable for SuperCuboid2x3x3 {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self == &Self::solved()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        self.cuboid.available_moves()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        Cuboid2x3x3::all_moves()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        Cuboid2x3x3::is_redundant(last_move, next_move)
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        let cuboid = self.cuboid.apply(m);
+
+        // orientations are measured from the direction of the F face, so a wide F turn (which
+        // keeps F where it is) just swaps the centers, but a wide R turn points them at B
+        let (uc_orr, dc_orr) = match m {
+            Move::R2 | Move::F2 => (self.uc_orr, self.dc_orr),
+            Move::Rw2 => (self.dc_orr.half(), self.uc_orr.half()),
+            Move::Fw2 => (self.dc_orr, self.uc_orr),
+            Move::U(amt) => match amt {
+                CubeMoveAmt::One => (self.uc_orr.cw(), self.dc_orr),
+                CubeMoveAmt::Two => (self.uc_orr.half(), self.dc_orr),
+                CubeMoveAmt::Rev => (self.uc_orr.ccw(), self.dc_orr),
+            },
+        };
+
+        Self { cuboid, uc_orr, dc_orr }
+    }
+
+    fn max_fuel() -> usize {
+        // the real diameter hasn't been computed, so this is a generous guess; scrambles which need
+        // more can raise the cap with --max-fuel
+        40
+    }
+}
+
+impl SimpleStartState for SuperCuboid2x3x3 {
+    type UniqueKey = u64;
+
+    fn start() -> Self {
+        Self::solved()
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        // the plain key only takes 40 bits
+        let mut out = SimpleStartState::uniq_key(&self.cuboid);
+
+        out = (out << 2) | self.uc_orr.as_u8_two_bits() as u64;
+        out = (out << 2) | self.dc_orr.as_u8_two_bits() as u64;
+
+        out
+    }
+}
+
+impl RandomInit for SuperCuboid2x3x3 {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        let cuboid = Cuboid2x3x3::random_state(r);
+
+        // a U turn is the only move which turns a center in place, and it also does an odd
+        // permutation of the corners; every other move does an even one. So the total center turn
+        // is odd exactly when the corner permutation is.
+        let c = &cuboid;
+        let corner_parity = permutation_parity(
+            &all::<CornerCubelet>().collect::<Vec<_>>(),
+            &[c.ufl, c.ufr, c.ubl, c.ubr, c.dfl, c.dfr, c.dbr],
+        )
+        .expect("Random corners should be a permutation");
+
+        let uc_orr: CenterOrientation = r.gen();
+        let mut dc_orr: CenterOrientation = r.gen();
+
+        let total_is_odd = (uc_orr.as_u8_two_bits() + dc_orr.as_u8_two_bits()) % 2 == 1;
+        if total_is_odd != (corner_parity == TwoParity::Odd) {
+            dc_orr = dc_orr.cw();
+        }
+
+        Self { cuboid, uc_orr, dc_orr }
+    }
+}
+
+/// The plain cache makes a good lower bound; the centers just add a few moves to the end
+pub fn make_super_heuristic() -> impl Heuristic<SuperCuboid2x3x3> {
+    let plain = bounded_cache::<Cuboid2x3x3>(8);
+    let plain_bound = move |s: &SuperCuboid2x3x3| plain.estimated_remaining_cost(&s.cuboid);
+
+    CachedPlusLowerBound::new(bounded_cache::<SuperCuboid2x3x3>(7), plain_bound)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cubesearch::State;
 
     #[test]
     fn ensure_corner_cubelets_fit_in_space() {
@@ -382,4 +578,299 @@ mod tests {
             assert!((c as u8) < 2);
         }
     }
+
+    #[test]
+    fn keys_rebuild_the_state() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        use crate::test_helpers::random_walk_states;
+
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        let random = (0..200)
+            .map(|_| Cuboid2x3x3::random_state(&mut rng))
+            .collect::<Vec<_>>();
+        let walked = random_walk_states::<Cuboid2x3x3, _>(&mut rng, 100);
+
+        for state in random.into_iter().chain(walked) {
+            assert_eq!(Cuboid2x3x3::from_key(&State::uniq_key(&state)), state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod parity_tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::solve;
+    use crate::scrambles::apply_moves;
+
+    /// Whether the corner, edge and center permutations are odd, in that order
+    fn odd_parities(c: &Cuboid2x3x3) -> [bool; 3] {
+        let corners = permutation_parity(
+            &all::<CornerCubelet>().collect::<Vec<_>>(),
+            &[c.ufl, c.ufr, c.ubl, c.ubr, c.dfl, c.dfr, c.dbr],
+        );
+        let edges = permutation_parity(
+            &all::<EdgeCubelet>().collect::<Vec<_>>(),
+            &[c.uf, c.ul, c.ub, c.ur, c.df, c.dl, c.db, c.dr],
+        );
+        let centers = permutation_parity(&all::<CenterCubelet>().collect::<Vec<_>>(), &[c.uc, c.dc]);
+
+        [corners, edges, centers].map(|p| p.expect("Should be a permutation") == TwoParity::Odd)
+    }
+
+    #[test]
+    fn move_parities_span_every_combination() {
+        let move_parities: Vec<[bool; 3]> = Cuboid2x3x3::all_moves()
+            .into_iter()
+            .map(|m| odd_parities(&Cuboid2x3x3::solved().apply(m)))
+            .collect();
+
+        // doing one move after another adds their parities, so close the set under that
+        let mut reached = vec![[false; 3]];
+        let mut i = 0;
+        while i < reached.len() {
+            for m in move_parities.iter() {
+                let combined: [bool; 3] = std::array::from_fn(|k| reached[i][k] ^ m[k]);
+                if !reached.contains(&combined) {
+                    reached.push(combined);
+                }
+            }
+            i += 1;
+        }
+
+        assert_eq!(reached.len(), 8, "Every parity combination should be reachable");
+    }
+
+    #[test]
+    fn lone_swaps_are_solvable() {
+        let h = make_heuristic();
+        let solved = Cuboid2x3x3::solved();
+
+        // each of these is a single swap in one orbit, and solved everywhere else; if the parities
+        // were coupled, these would be exactly the states an independent shuffle gets wrong
+        let swaps = [
+            Cuboid2x3x3 {
+                ufl: solved.ufr,
+                ufr: solved.ufl,
+                ..solved
+            },
+            Cuboid2x3x3 {
+                uf: solved.ur,
+                ur: solved.uf,
+                ..solved
+            },
+            Cuboid2x3x3 {
+                uc: solved.dc,
+                dc: solved.uc,
+                ..solved
+            },
+        ];
+
+        for state in swaps {
+            let solution = solve(&state, &h).expect("A lone swap should be solvable");
+            assert!(apply_moves(&state, &solution).is_solved());
+        }
+    }
+
+    #[test]
+    fn random_states_are_solvable() {
+        let h = make_heuristic();
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..5 {
+            let state = Cuboid2x3x3::random_state(&mut rng);
+            let solution = solve(&state, &h).expect("Random states should be solvable");
+            assert!(apply_moves(&state, &solution).is_solved());
+        }
+    }
+}
+
+#[cfg(test)]
+mod super_tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::solve;
+    use crate::scrambles::{apply_moves, parse_moves};
+
+    #[test]
+    fn plain_solution_can_leave_centers_turned() {
+        let moves = parse_moves("Rw2 Fw2 U2 Fw2 R2 Rw2 Fw2 U2 Fw2 R2").unwrap();
+        let end = apply_moves(&SuperCuboid2x3x3::solved(), &moves);
+
+        // the plain Domino is back where it started ...
+        assert!(end.cuboid.is_solved());
+        // ... but both centers have been turned halfway around
+        assert!(!end.is_solved());
+        assert_eq!(end.uc_orr, CenterOrientation::Half);
+        assert_eq!(end.dc_orr, CenterOrientation::Half);
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn random_states_are_solvable() {
+        let h = make_super_heuristic();
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..20 {
+            let state = SuperCuboid2x3x3::random_state(&mut rng);
+            let solution = solve(&state, &h).expect("Random super states should be solvable");
+
+            assert!(apply_moves(&state, &solution).is_solved());
+        }
+    }
+}
+
+#[cfg(test)]
+mod reduction_tests {
+    use std::collections::hash_map::Entry;
+
+    use ahash::HashMap;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::cubesearch::State;
+    use crate::idasearch::heuristic_helpers::{bounded_cache_from, BoundedStateCache};
+    use crate::idasearch::{solve_phased, Phase, SolveError};
+    use crate::scrambles::apply_moves;
+
+    /// Everything but the quarter turns of U
+    fn is_half_turn(m: Move) -> bool {
+        !matches!(m, Move::U(CubeMoveAmt::One | CubeMoveAmt::Rev))
+    }
+
+    /// A two-phase solver: first get into the subgroup of states which half turns alone can
+    /// solve, then solve with half turns. Solutions are longer than optimal, and on a puzzle this
+    /// size the optimal solver is already fast, so this is only an example of [solve_phased]; the
+    /// subgroup is small (27,648 states), so it's cheap to build.
+    struct HalfTurnReduction {
+        /// Every state in the subgroup, with how many half turns it takes to solve; this is the
+        /// whole of the second phase
+        subgroup: HashMap<u64, usize>,
+        /// Distances to the nearest state in the subgroup, for the first phase
+        to_subgroup: BoundedStateCache<u64>,
+    }
+
+    impl HalfTurnReduction {
+        fn new() -> Self {
+            let start = <Cuboid2x3x3 as State>::start();
+
+            let mut subgroup: HashMap<u64, usize> = HashMap::default();
+            subgroup.insert(State::uniq_key(&start), 0);
+
+            let mut members = vec![start];
+            let mut frontier = vec![start];
+
+            for depth in 1.. {
+                let mut next = Vec::new();
+
+                for state in frontier {
+                    for m in state.available_moves().into_iter().filter(|&m| is_half_turn(m)) {
+                        let neighbor = state.apply(m);
+                        if let Entry::Vacant(entry) = subgroup.entry(State::uniq_key(&neighbor)) {
+                            entry.insert(depth);
+                            next.push(neighbor);
+                        }
+                    }
+                }
+
+                if next.is_empty() {
+                    break;
+                }
+
+                members.extend_from_slice(&next);
+                frontier = next;
+            }
+
+            // deeper caches make the first phase faster, but take much longer to build; 4 is over a
+            // second even in release
+            let to_subgroup = bounded_cache_from(members, 3);
+
+            Self { subgroup, to_subgroup }
+        }
+
+        fn in_subgroup(&self, state: &Cuboid2x3x3) -> bool {
+            self.subgroup.contains_key(&State::uniq_key(state))
+        }
+
+        /// The moves of each phase, in order
+        fn solve(&self, state: &Cuboid2x3x3) -> Result<Vec<Vec<Move>>, SolveError> {
+            // half turns never leave the subgroup, so the second phase always finds its states here
+            let half_turns_left = |s: &Cuboid2x3x3| self.subgroup.get(&State::uniq_key(s)).copied().unwrap_or(0);
+
+            let phases = [
+                Phase {
+                    goal: &|s| self.in_subgroup(s),
+                    allowed: &|_| true,
+                    heuristic: &self.to_subgroup,
+                },
+                Phase {
+                    goal: &Cuboid2x3x3::is_solved,
+                    allowed: &is_half_turn,
+                    heuristic: &half_turns_left,
+                },
+            ];
+
+            solve_phased(state, &phases, Cuboid2x3x3::max_fuel())
+        }
+    }
+
+    #[test]
+    fn subgroup_membership() {
+        let reduction = HalfTurnReduction::new();
+        let solved = Cuboid2x3x3::solved();
+
+        assert!(reduction.in_subgroup(&solved));
+        assert!(reduction.in_subgroup(&solved.apply(Move::Rw2).apply(Move::U(CubeMoveAmt::Two))));
+        assert!(!reduction.in_subgroup(&solved.apply(Move::U(CubeMoveAmt::One))));
+    }
+
+    #[test]
+    fn two_phase_solutions_solve() {
+        let reduction = HalfTurnReduction::new();
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..5 {
+            let state = Cuboid2x3x3::random_state(&mut rng);
+            let phases = reduction.solve(&state).expect("Random states should be solvable");
+            assert_eq!(phases.len(), 2);
+
+            let reduced = apply_moves(&state, &phases[0]);
+            assert!(reduction.in_subgroup(&reduced), "Phase one should end in the subgroup");
+            assert!(
+                phases[1].iter().all(|&m| is_half_turn(m)),
+                "Phase two only uses half turns"
+            );
+
+            assert!(apply_moves(&reduced, &phases[1]).is_solved());
+        }
+    }
+}
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<Cuboid2x3x3, _>(
+            &bounded_cache::<Cuboid2x3x3>(5),
+            15,
+            &[
+                "Fw2 Rw2 U F2 U2 R2 Fw2 Rw2 U R2 U Rw2 U' F2",
+                "F2 U' Fw2 Rw2 U' F2 U' F2 R2 U R2 U' Fw2 Rw2",
+                "R2 U' Rw2 U2 Rw2 F2 U' Fw2 Rw2 U F2 Rw2 Fw2",
+                "U' R2 F2 U2 F2 U' Rw2 U Rw2 R2 Fw2 U' Fw2",
+                "U' Fw2 R2 F2 Rw2 Fw2 U R2 Fw2 U2 Fw2 U' Rw2 U2 Fw2",
+            ],
+        );
+    }
 }
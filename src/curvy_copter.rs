@@ -1,20 +1,23 @@
-//! Curvy copter. No jumbling today.
+//! Curvy copter. No jumbling by default; the `jumbling` feature adds a simplified version of it,
+//! see [JumbleCorner].
 
-use crate::cubesearch::SimpleStartState;
-use crate::idasearch::heuristic_helpers::bounded_cache;
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::idasearch::heuristic_helpers::{bounded_cache, misoriented_edge_heuristic, CountsMisoriented, MaxHeuristic};
 use crate::idasearch::{Heuristic, Solvable};
 use crate::moves::CanReverse;
 use crate::orientations::{CornerOrientation, EdgeOrientation};
 use crate::random_helpers;
-use crate::random_helpers::{shuffle_with_parity, TwoParity};
+use crate::random_helpers::{couple_orbit_to_edges, take_six};
 use crate::scrambles::RandomInit;
 use derive_more::Display;
+#[cfg(feature = "jumbling")]
+use enum_iterator::{all, Sequence};
 use rand::Rng;
 
 type PackedBits = (u64, u64);
 
 // pretty clearly 12 bits to pack this, no matter what you do
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
 struct EdgeStates {
     uf: EdgeOrientation,
     ur: EdgeOrientation,
@@ -49,6 +52,15 @@ impl EdgeStates {
         Self::default()
     }
 
+    fn num_flipped(&self) -> usize {
+        [
+            self.uf, self.ur, self.ul, self.ub, self.df, self.dr, self.dl, self.db, self.fl, self.fr, self.bl, self.br,
+        ]
+        .into_iter()
+        .filter(|e| *e == EdgeOrientation::Flipped)
+        .count()
+    }
+
     // 12 * 1 == 12 bits
     fn pack(&self, bits: &mut u64) {
         self.uf.pack(bits);
@@ -83,11 +95,14 @@ impl EdgeStates {
     flip_edge!(br);
 }
 
+// the centers and corners (and how each edge turn swaps them) are shared with the helicopter
+// cube, which is this puzzle without the edges
+
 // 3 bits each
 // can pack a little tighter if we multiply by 6 at each point instead of <<< 3
 #[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-enum CenterCubelet {
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub(crate) enum CenterCubelet {
     F,
     B,
     L,
@@ -105,50 +120,50 @@ impl CenterCubelet {
 
 // 24 pieces at 3 bits each equals 72 bits to pack
 // with tighter packing (mul by 6 each time) we can fit into 63 bits (!)
-#[derive(Clone, Eq, PartialEq)]
-struct CenterStates {
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub(crate) struct CenterStates {
     // front face
-    f_ul: CenterCubelet,
-    f_ur: CenterCubelet,
-    f_dl: CenterCubelet,
-    f_dr: CenterCubelet,
+    pub(crate) f_ul: CenterCubelet,
+    pub(crate) f_ur: CenterCubelet,
+    pub(crate) f_dl: CenterCubelet,
+    pub(crate) f_dr: CenterCubelet,
 
     // back face
-    b_ul: CenterCubelet,
-    b_ur: CenterCubelet,
-    b_dl: CenterCubelet,
-    b_dr: CenterCubelet,
+    pub(crate) b_ul: CenterCubelet,
+    pub(crate) b_ur: CenterCubelet,
+    pub(crate) b_dl: CenterCubelet,
+    pub(crate) b_dr: CenterCubelet,
 
     // left face
-    l_ub: CenterCubelet,
-    l_uf: CenterCubelet,
-    l_db: CenterCubelet,
-    l_df: CenterCubelet,
+    pub(crate) l_ub: CenterCubelet,
+    pub(crate) l_uf: CenterCubelet,
+    pub(crate) l_db: CenterCubelet,
+    pub(crate) l_df: CenterCubelet,
 
     // right face
-    r_ub: CenterCubelet,
-    r_uf: CenterCubelet,
-    r_db: CenterCubelet,
-    r_df: CenterCubelet,
+    pub(crate) r_ub: CenterCubelet,
+    pub(crate) r_uf: CenterCubelet,
+    pub(crate) r_db: CenterCubelet,
+    pub(crate) r_df: CenterCubelet,
 
     // up face
-    u_bl: CenterCubelet,
-    u_br: CenterCubelet,
-    u_fl: CenterCubelet,
-    u_fr: CenterCubelet,
+    pub(crate) u_bl: CenterCubelet,
+    pub(crate) u_br: CenterCubelet,
+    pub(crate) u_fl: CenterCubelet,
+    pub(crate) u_fr: CenterCubelet,
 
     // down face
-    d_bl: CenterCubelet,
-    d_br: CenterCubelet,
-    d_fl: CenterCubelet,
-    d_fr: CenterCubelet,
+    pub(crate) d_bl: CenterCubelet,
+    pub(crate) d_br: CenterCubelet,
+    pub(crate) d_fl: CenterCubelet,
+    pub(crate) d_fr: CenterCubelet,
 }
 
 macro_rules! swap_centers {
     // turning edge_name should swap a_1 and a_2 as well as b_1 and b_2
     ($edge_name:ident, $a_1:ident, $a_2:ident, $b_1:ident, $b_2:ident) => {
         #[inline(always)]
-        fn $edge_name(&self) -> Self {
+        pub(crate) fn $edge_name(&self) -> Self {
             Self {
                 $a_2: self.$a_1,
                 $a_1: self.$a_2,
@@ -163,7 +178,7 @@ macro_rules! swap_centers {
 }
 
 impl CenterStates {
-    fn solved() -> Self {
+    pub(crate) fn solved() -> Self {
         Self {
             f_ul: CenterCubelet::F,
             f_ur: CenterCubelet::F,
@@ -197,7 +212,7 @@ impl CenterStates {
         }
     }
 
-    fn pack(&self, bits: &mut u64) {
+    pub(crate) fn pack(&self, bits: &mut u64) {
         // F face
         self.f_ul.pack_tight(bits);
         self.f_ur.pack_tight(bits);
@@ -256,10 +271,51 @@ impl CenterStates {
     swap_centers!(br, b_ur, r_db, b_dr, r_ub);
 }
 
+#[cfg(feature = "jumbling")]
+macro_rules! cycle_centers {
+    // the pieces at a, b, c (clockwise around a corner) move to b, c, a respectively
+    ($cw_name:ident, $ccw_name:ident, $a:ident, $b:ident, $c:ident) => {
+        #[inline(always)]
+        fn $cw_name(&self) -> Self {
+            Self {
+                $b: self.$a,
+                $c: self.$b,
+                $a: self.$c,
+                ..*self
+            }
+        }
+
+        #[inline(always)]
+        fn $ccw_name(&self) -> Self {
+            Self {
+                $a: self.$b,
+                $b: self.$c,
+                $c: self.$a,
+                ..*self
+            }
+        }
+    };
+}
+
+#[cfg(feature = "jumbling")]
+impl CenterStates {
+    // the three centers around each corner, starting from the U/D one and going clockwise; each
+    // of the three is in a different orbit
+    cycle_centers!(jumble_ufl_cw, jumble_ufl_ccw, u_fl, f_ul, l_uf);
+    cycle_centers!(jumble_ufr_cw, jumble_ufr_ccw, u_fr, r_uf, f_ur);
+    cycle_centers!(jumble_ubl_cw, jumble_ubl_ccw, u_bl, l_ub, b_ul);
+    cycle_centers!(jumble_ubr_cw, jumble_ubr_ccw, u_br, b_ur, r_ub);
+
+    cycle_centers!(jumble_dfl_cw, jumble_dfl_ccw, d_fl, l_df, f_dl);
+    cycle_centers!(jumble_dfr_cw, jumble_dfr_ccw, d_fr, f_dr, r_df);
+    cycle_centers!(jumble_dbl_cw, jumble_dbl_ccw, d_bl, b_dl, l_db);
+    cycle_centers!(jumble_dbr_cw, jumble_dbr_ccw, d_br, r_db, b_dr);
+}
+
 // 8 values; takes 3 bits no matter how you slice it
 #[repr(u8)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-enum CornerCubelet {
+pub(crate) enum CornerCubelet {
     FUL,
     FUR,
     BUL,
@@ -280,22 +336,22 @@ impl CornerCubelet {
 // 3 bits each * 8 corners -> 24 bits total, although we can skip the last one
 // since it's a permutation
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-struct CornersPositionState {
-    ful: CornerCubelet,
-    fur: CornerCubelet,
-    fdl: CornerCubelet,
-    fdr: CornerCubelet,
-
-    bul: CornerCubelet,
-    bur: CornerCubelet,
-    bdl: CornerCubelet,
-    bdr: CornerCubelet,
+pub(crate) struct CornersPositionState {
+    pub(crate) ful: CornerCubelet,
+    pub(crate) fur: CornerCubelet,
+    pub(crate) fdl: CornerCubelet,
+    pub(crate) fdr: CornerCubelet,
+
+    pub(crate) bul: CornerCubelet,
+    pub(crate) bur: CornerCubelet,
+    pub(crate) bdl: CornerCubelet,
+    pub(crate) bdr: CornerCubelet,
 }
 
 macro_rules! swap_corner_pos {
     ($edge_name: ident, $corner_a:ident, $corner_b:ident) => {
         #[inline(always)]
-        fn $edge_name(&self) -> Self {
+        pub(crate) fn $edge_name(&self) -> Self {
             Self {
                 $corner_a: self.$corner_b,
                 $corner_b: self.$corner_a,
@@ -306,7 +362,7 @@ macro_rules! swap_corner_pos {
 }
 
 impl CornersPositionState {
-    fn solved() -> Self {
+    pub(crate) fn solved() -> Self {
         Self {
             ful: CornerCubelet::FUL,
             fur: CornerCubelet::FUR,
@@ -320,7 +376,7 @@ impl CornersPositionState {
     }
 
     // 8 * 3 == 24 bits
-    fn pack(&self, bits: &mut u64) {
+    pub(crate) fn pack(&self, bits: &mut u64) {
         self.fur.pack(bits);
         self.ful.pack(bits);
         self.fdr.pack(bits);
@@ -350,22 +406,22 @@ impl CornersPositionState {
 
 // 2 bits each * 8 corners -> 16 bits total
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-struct CornersOrientationState {
-    ful: CornerOrientation,
-    fur: CornerOrientation,
-    fdl: CornerOrientation,
-    fdr: CornerOrientation,
-
-    bul: CornerOrientation,
-    bur: CornerOrientation,
-    bdl: CornerOrientation,
-    bdr: CornerOrientation,
+pub(crate) struct CornersOrientationState {
+    pub(crate) ful: CornerOrientation,
+    pub(crate) fur: CornerOrientation,
+    pub(crate) fdl: CornerOrientation,
+    pub(crate) fdr: CornerOrientation,
+
+    pub(crate) bul: CornerOrientation,
+    pub(crate) bur: CornerOrientation,
+    pub(crate) bdl: CornerOrientation,
+    pub(crate) bdr: CornerOrientation,
 }
 
 macro_rules! swap_corner_orr {
     ($edge_name:ident, $corner_a:ident, $a_swap:ident, $corner_b:ident, $b_swap:ident) => {
         #[inline(always)]
-        fn $edge_name(&self) -> Self {
+        pub(crate) fn $edge_name(&self) -> Self {
             Self {
                 $corner_a: self.$corner_b.$b_swap(),
                 $corner_b: self.$corner_a.$a_swap(),
@@ -376,7 +432,7 @@ macro_rules! swap_corner_orr {
 }
 
 impl CornersOrientationState {
-    fn solved() -> Self {
+    pub(crate) fn solved() -> Self {
         Self {
             ful: CornerOrientation::Normal,
             fur: CornerOrientation::Normal,
@@ -390,7 +446,7 @@ impl CornersOrientationState {
     }
 
     // 16 bits
-    fn pack(&self, bits: &mut u64) {
+    pub(crate) fn pack(&self, bits: &mut u64) {
         self.fur.pack_two_bits_u64(bits);
         self.ful.pack_two_bits_u64(bits);
         self.fdr.pack_two_bits_u64(bits);
@@ -423,7 +479,7 @@ impl CornersOrientationState {
 }
 
 // can BARELY be packed into a u128 (or probably a pair of u64)
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Hash, Eq, PartialEq)]
 pub struct CurvyCopter {
     // 12 bits
     edges: EdgeStates,
@@ -449,7 +505,7 @@ macro_rules! pass_through {
     };
 }
 
-impl CurvyCopter {
+impl HasSolvedState for CurvyCopter {
     #[inline(always)]
     fn solved() -> Self {
         CurvyCopter {
@@ -459,7 +515,9 @@ impl CurvyCopter {
             corner_orientations: CornersOrientationState::solved(),
         }
     }
+}
 
+impl CurvyCopter {
     pass_through!(uf);
     pass_through!(ur);
     pass_through!(ul);
@@ -474,6 +532,70 @@ impl CurvyCopter {
     pass_through!(fl);
     pass_through!(br);
     pass_through!(bl);
+
+    #[cfg(feature = "jumbling")]
+    fn jumble(&self, corner: JumbleCorner, clockwise: bool) -> Self {
+        let c = &self.centers;
+        let centers = match (corner, clockwise) {
+            (JumbleCorner::UFL, true) => c.jumble_ufl_cw(),
+            (JumbleCorner::UFL, false) => c.jumble_ufl_ccw(),
+            (JumbleCorner::UFR, true) => c.jumble_ufr_cw(),
+            (JumbleCorner::UFR, false) => c.jumble_ufr_ccw(),
+            (JumbleCorner::UBL, true) => c.jumble_ubl_cw(),
+            (JumbleCorner::UBL, false) => c.jumble_ubl_ccw(),
+            (JumbleCorner::UBR, true) => c.jumble_ubr_cw(),
+            (JumbleCorner::UBR, false) => c.jumble_ubr_ccw(),
+            (JumbleCorner::DFL, true) => c.jumble_dfl_cw(),
+            (JumbleCorner::DFL, false) => c.jumble_dfl_ccw(),
+            (JumbleCorner::DFR, true) => c.jumble_dfr_cw(),
+            (JumbleCorner::DFR, false) => c.jumble_dfr_ccw(),
+            (JumbleCorner::DBL, true) => c.jumble_dbl_cw(),
+            (JumbleCorner::DBL, false) => c.jumble_dbl_ccw(),
+            (JumbleCorner::DBR, true) => c.jumble_dbr_cw(),
+            (JumbleCorner::DBR, false) => c.jumble_dbr_ccw(),
+        };
+
+        Self {
+            centers,
+            ..self.clone()
+        }
+    }
+}
+
+/// Jumbling turns an edge by about 71.57 degrees instead of 180, which puts the puzzle out of cube
+/// shape; a few more turns along the new cuts bring it back, with center pieces moved between
+/// orbits, which the ordinary turns can never do. Modeling the in-between shapes would mean a
+/// whole new set of pieces, so instead each jumble is the net effect of the shortest such sequence:
+/// the three centers around a corner cycle, and nothing else moves.
+#[cfg(feature = "jumbling")]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Display, Sequence)]
+pub enum JumbleCorner {
+    UFL,
+    UFR,
+    UBL,
+    UBR,
+    DFL,
+    DFR,
+    DBL,
+    DBR,
+}
+
+#[cfg(feature = "jumbling")]
+impl JumbleCorner {
+    /// The edges meeting at this corner; these are the only turns which move any of the centers a
+    /// jumble here cycles, so every other turn commutes with it
+    fn edges(self) -> [Move; 3] {
+        match self {
+            JumbleCorner::UFL => [Move::UF, Move::UL, Move::FL],
+            JumbleCorner::UFR => [Move::UF, Move::UR, Move::FR],
+            JumbleCorner::UBL => [Move::UB, Move::UL, Move::BL],
+            JumbleCorner::UBR => [Move::UB, Move::UR, Move::BR],
+            JumbleCorner::DFL => [Move::DF, Move::DL, Move::FL],
+            JumbleCorner::DFR => [Move::DF, Move::DR, Move::FR],
+            JumbleCorner::DBL => [Move::DB, Move::DL, Move::BL],
+            JumbleCorner::DBR => [Move::DB, Move::DR, Move::BR],
+        }
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
@@ -490,39 +612,27 @@ pub enum Move {
     FR,
     BL,
     BR,
+    #[cfg(feature = "jumbling")]
+    #[display(fmt = "J{}", _0)]
+    JumbleCw(JumbleCorner),
+    #[cfg(feature = "jumbling")]
+    #[display(fmt = "J{}'", _0)]
+    JumbleCcw(JumbleCorner),
 }
 
 impl CanReverse for Move {
     fn reverse(&self) -> Self {
-        // all moves are self inverse
-        *self
-    }
-}
-
-fn total_parity(eo: &[EdgeOrientation]) -> TwoParity {
-    let mut total_flipped = EdgeOrientation::Normal;
-
-    for e in eo.iter().copied() {
-        total_flipped = match e {
-            EdgeOrientation::Normal => total_flipped,
-            EdgeOrientation::Flipped => total_flipped.flipped(),
-        };
-    }
-
-    match total_flipped {
-        EdgeOrientation::Normal => TwoParity::Even,
-        EdgeOrientation::Flipped => TwoParity::Odd,
+        match *self {
+            #[cfg(feature = "jumbling")]
+            Move::JumbleCw(corner) => Move::JumbleCcw(corner),
+            #[cfg(feature = "jumbling")]
+            Move::JumbleCcw(corner) => Move::JumbleCw(corner),
+            // all the edge turns are self inverse
+            other => other,
+        }
     }
 }
 
-fn take_six<T: Copy>(v: Vec<T>) -> [T; 6] {
-    assert_eq!(v.len(), 6);
-
-    // i feel like i should be able to use const generics here but whatever
-
-    [v[0], v[1], v[2], v[3], v[4], v[5]]
-}
-
 impl RandomInit for CurvyCopter {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         // parity of edge flips and corner orientations should match
@@ -587,7 +697,6 @@ impl RandomInit for CurvyCopter {
 
         // Orbit 1 -- U_FL, F_UR, R_DF, D_BR, B_DL, L_UB
         //      uses edges UF, FR, DR, DB, BL, UL
-        let parity = total_parity(&[edges.uf, edges.fr, edges.dr, edges.db, edges.bl, edges.ul]);
         let centers = vec![
             CenterCubelet::U,
             CenterCubelet::F,
@@ -596,12 +705,15 @@ impl RandomInit for CurvyCopter {
             CenterCubelet::B,
             CenterCubelet::L,
         ];
-        let centers = shuffle_with_parity(r, &centers, parity);
+        let centers = couple_orbit_to_edges(
+            r,
+            &centers,
+            &[edges.uf, edges.fr, edges.dr, edges.db, edges.bl, edges.ul],
+        );
         let [u_fl, f_ur, r_df, d_br, b_dl, l_ub] = take_six(centers);
 
         // Orbit 2 -- U_FR, R_UB, B_DR, D_BL, L_DF, F_UL
         //      uses edges UR, BR, DB, DL, FL, UF
-        let parity = total_parity(&[edges.ur, edges.br, edges.db, edges.dl, edges.fl, edges.uf]);
         let centers = vec![
             CenterCubelet::U,
             CenterCubelet::R,
@@ -610,12 +722,15 @@ impl RandomInit for CurvyCopter {
             CenterCubelet::L,
             CenterCubelet::F,
         ];
-        let centers = shuffle_with_parity(r, &centers, parity);
+        let centers = couple_orbit_to_edges(
+            r,
+            &centers,
+            &[edges.ur, edges.br, edges.db, edges.dl, edges.fl, edges.uf],
+        );
         let [u_fr, r_ub, b_dr, d_bl, l_df, f_ul] = take_six(centers);
 
         // Orbit 3 -- U_BL, L_UF, F_DL, D_FR, R_DB, B_UR
         //      uses edges UL, FL, DF, DR, BR, UB
-        let parity = total_parity(&[edges.ul, edges.fl, edges.df, edges.dr, edges.br, edges.ub]);
         let centers = vec![
             CenterCubelet::U,
             CenterCubelet::L,
@@ -624,12 +739,15 @@ impl RandomInit for CurvyCopter {
             CenterCubelet::R,
             CenterCubelet::B,
         ];
-        let centers = shuffle_with_parity(r, &centers, parity);
+        let centers = couple_orbit_to_edges(
+            r,
+            &centers,
+            &[edges.ul, edges.fl, edges.df, edges.dr, edges.br, edges.ub],
+        );
         let [u_bl, l_uf, f_dl, d_fr, r_db, b_ur] = take_six(centers);
 
         // Orbit 4 -- U_BR, R_UF, F_DR, D_FL, L_DB, B_UL
         //      uses edges UR, FR, DF, DL, BL, UB
-        let parity = total_parity(&[edges.ur, edges.fr, edges.df, edges.dl, edges.bl, edges.ub]);
         let centers = vec![
             CenterCubelet::U,
             CenterCubelet::R,
@@ -638,7 +756,11 @@ impl RandomInit for CurvyCopter {
             CenterCubelet::L,
             CenterCubelet::B,
         ];
-        let centers = shuffle_with_parity(r, &centers, parity);
+        let centers = couple_orbit_to_edges(
+            r,
+            &centers,
+            &[edges.ur, edges.fr, edges.df, edges.dl, edges.bl, edges.ub],
+        );
         let [u_br, r_uf, f_dr, d_fl, l_db, b_ul] = take_six(centers);
 
         let centers = CenterStates {
@@ -712,7 +834,8 @@ impl Solvable for CurvyCopter {
     type Move = Move;
 
     fn max_fuel() -> usize {
-        // i guess? this probably isn't enough but we need some A* business in this business
+        // not known to be enough; the real diameter hasn't been computed. Scrambles which need
+        // more can raise the cap with --max-fuel
         24
     }
 
@@ -730,11 +853,15 @@ impl Solvable for CurvyCopter {
             Move::FR => self.fr(),
             Move::BL => self.bl(),
             Move::BR => self.br(),
+            #[cfg(feature = "jumbling")]
+            Move::JumbleCw(corner) => self.jumble(corner, true),
+            #[cfg(feature = "jumbling")]
+            Move::JumbleCcw(corner) => self.jumble(corner, false),
         }
     }
 
     fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
-        [
+        let turns = [
             Move::UF,
             Move::UL,
             Move::UR,
@@ -747,7 +874,19 @@ impl Solvable for CurvyCopter {
             Move::FR,
             Move::BL,
             Move::BR,
-        ]
+        ];
+
+        #[cfg(feature = "jumbling")]
+        let turns = turns
+            .into_iter()
+            .chain(all::<JumbleCorner>().flat_map(|c| [Move::JumbleCw(c), Move::JumbleCcw(c)]));
+
+        turns
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        // every move is available from every state
+        Self::solved().available_moves().into_iter().collect()
     }
 
     fn is_solved(&self) -> bool {
@@ -833,11 +972,215 @@ impl Solvable for CurvyCopter {
                     || next_move == Move::FR
                     || next_move == Move::BL
             }
+            // jumbles at different corners move different centers, so they all commute; put them
+            // in corner order, and never jumble the same corner twice in a row. A jumble also
+            // commutes with any turn of an edge away from its corner, so those turns go first
+            #[cfg(feature = "jumbling")]
+            Move::JumbleCw(last) | Move::JumbleCcw(last) => match next_move {
+                Move::JumbleCw(next) | Move::JumbleCcw(next) => next <= last,
+                turn => !last.edges().contains(&turn),
+            },
         }
     }
 }
 
+impl CountsMisoriented for CurvyCopter {
+    // each move flips its own edge, and only that one
+    const MAX_FLIPS_PER_MOVE: usize = 1;
+
+    fn misoriented_edges(&self) -> usize {
+        self.edges.num_flipped()
+    }
+}
+
 pub fn make_heuristic() -> impl Heuristic<CurvyCopter> {
-    // max depth is picked to keep the compute time low
-    bounded_cache::<CurvyCopter>(9)
+    // max depth is picked to keep the compute time low; past the cache, the flipped edges still
+    // give a bound (and it can run as high as 12)
+    #[cfg(not(feature = "jumbling"))]
+    const CACHE_DEPTH: usize = 9;
+    // the jumbles more than double the branching factor, so the cache has to stop much sooner
+    #[cfg(feature = "jumbling")]
+    const CACHE_DEPTH: usize = 5;
+
+    MaxHeuristic::new(
+        bounded_cache::<CurvyCopter>(CACHE_DEPTH),
+        misoriented_edge_heuristic::<CurvyCopter>,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::idasearch::{solve, solve_with_fuel, SolveError};
+    use crate::test_helpers::{assert_commuting_pairs_cut, random_walk_states};
+
+    /// The edge turns, leaving out any jumbles
+    const TURNS: [Move; 12] = [
+        Move::UF,
+        Move::UL,
+        Move::UR,
+        Move::UB,
+        Move::DF,
+        Move::DL,
+        Move::DR,
+        Move::DB,
+        Move::FL,
+        Move::FR,
+        Move::BL,
+        Move::BR,
+    ];
+
+    /// Like [random_walk_states], but only turning edges; jumbles (when they're on) don't flip any
+    /// edges, so this keeps the walks comparable with or without them
+    fn random_turn_states(rng: &mut StdRng, num_moves: usize) -> Vec<CurvyCopter> {
+        let mut state = CurvyCopter::solved();
+        let mut out = vec![state.clone()];
+        let mut last = None;
+
+        for _ in 0..num_moves {
+            let m = loop {
+                let m = *TURNS.choose(rng).unwrap();
+                if !last.is_some_and(|last| CurvyCopter::is_redundant(last, m)) {
+                    break m;
+                }
+            };
+            state = state.apply(m);
+            last = Some(m);
+            out.push(state.clone());
+        }
+
+        out
+    }
+
+    /// Two edge turns commute exactly when they don't share a corner, and jumbles commute with
+    /// everything away from their corner; checks that graph against the moves themselves, and that
+    /// the rule cuts every commuting pair one way or the other, so the search never tries both orders
+    #[test]
+    fn every_commuting_pair_is_cut() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let states: Vec<CurvyCopter> = (0..10).map(|_| CurvyCopter::random_state(&mut rng)).collect();
+
+        assert_commuting_pairs_cut(&states, &CurvyCopter::all_moves());
+
+        // each edge shares a corner with four others, leaving 12 * 7 / 2 commuting pairs
+        let commuting_turns = assert_commuting_pairs_cut(&states, &TURNS);
+        assert_eq!(commuting_turns.len(), 2 * 42);
+    }
+
+    #[test]
+    fn fuel_caps_solution_length() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let heuristic = bounded_cache::<CurvyCopter>(3);
+
+        let state = random_walk_states::<CurvyCopter, _>(&mut rng, 6).pop().unwrap();
+        let n = solve(&state, &heuristic).unwrap().len();
+        assert!(n > 0, "Random walk should not end up solved");
+
+        match solve_with_fuel(&state, &heuristic, n - 1) {
+            Err(SolveError::OutOfGas { max_fuel }) => assert_eq!(max_fuel, n - 1),
+            other => panic!("Expected to run out of gas, got {other:?}"),
+        }
+
+        let solution = solve_with_fuel(&state, &heuristic, n).unwrap();
+        assert_eq!(solution.len(), n);
+    }
+
+    #[test]
+    fn flipped_edges_bound_is_admissible_and_prunes() {
+        let mut rng = StdRng::from_seed([16; 32]);
+        let cache = bounded_cache::<CurvyCopter>(3);
+
+        // counts how many states each search looks at
+        let calls = Cell::new(0usize);
+        let cache_only = |s: &CurvyCopter| {
+            calls.set(calls.get() + 1);
+            cache.estimated_remaining_cost(s)
+        };
+        let combined = MaxHeuristic::new(&cache_only, misoriented_edge_heuristic::<CurvyCopter>);
+
+        let (mut cache_only_calls, mut combined_calls) = (0, 0);
+
+        for state in random_turn_states(&mut rng, 9) {
+            calls.set(0);
+            let optimal = solve(&state, &cache_only).unwrap().len();
+            cache_only_calls += calls.get();
+
+            assert!(
+                misoriented_edge_heuristic(&state) <= optimal,
+                "Bound overestimated a state {optimal} moves from solved"
+            );
+
+            calls.set(0);
+            assert_eq!(solve(&state, &combined).unwrap().len(), optimal);
+            combined_calls += calls.get();
+        }
+
+        assert!(
+            combined_calls < cache_only_calls,
+            "Adding the bound should prune the search ({combined_calls} vs {cache_only_calls} states)"
+        );
+    }
+
+    #[cfg(feature = "jumbling")]
+    mod jumbling {
+        use super::*;
+
+        /// Without jumbling, each orbit of centers (see `random_state`) has one center of each
+        /// color, wherever they've moved to within it
+        fn orbits_have_every_color(state: &CurvyCopter) -> bool {
+            let c = &state.centers;
+            let orbits = [
+                [c.u_fl, c.f_ur, c.r_df, c.d_br, c.b_dl, c.l_ub],
+                [c.u_fr, c.r_ub, c.b_dr, c.d_bl, c.l_df, c.f_ul],
+                [c.u_bl, c.l_uf, c.f_dl, c.d_fr, c.r_db, c.b_ur],
+                [c.u_br, c.r_uf, c.f_dr, c.d_fl, c.l_db, c.b_ul],
+            ];
+
+            orbits.iter().all(|orbit| {
+                let mut colors = orbit.map(|center| center as u8);
+                colors.sort();
+                colors == [0, 1, 2, 3, 4, 5]
+            })
+        }
+
+        #[test]
+        fn jumble_reaches_states_turns_cannot() {
+            let mut rng = StdRng::from_seed([15; 32]);
+
+            for state in random_turn_states(&mut rng, 9) {
+                assert!(orbits_have_every_color(&state), "Ordinary turns keep the orbits apart");
+            }
+
+            for corner in all::<JumbleCorner>() {
+                let jumbled = CurvyCopter::solved().apply(Move::JumbleCw(corner));
+                assert!(
+                    !orbits_have_every_color(&jumbled),
+                    "Jumbling at {corner} should move centers between orbits"
+                );
+            }
+        }
+
+        #[test]
+        fn jumbled_states_are_solvable() {
+            let mut rng = StdRng::from_seed([16; 32]);
+            let heuristic = bounded_cache::<CurvyCopter>(3);
+
+            let mut state = CurvyCopter::solved()
+                .apply(Move::JumbleCw(JumbleCorner::UFL))
+                .apply(Move::JumbleCcw(JumbleCorner::DBR));
+            for _ in 0..3 {
+                state = state.apply(*TURNS.choose(&mut rng).unwrap());
+            }
+
+            let solution = solve(&state, &heuristic).unwrap();
+            assert!(solution.len() <= 5, "The scramble itself is a solution of 5 moves");
+            assert!(crate::scrambles::apply_moves(&state, &solution).is_solved());
+        }
+    }
 }
@@ -0,0 +1,100 @@
+//! Human-readable breakdowns of bitpacked keys. When a packing bug shows up (two different states
+//! with the same key, say), the raw number is no help; this unpacks it field by field, so it can be
+//! compared against what the state was supposed to be.
+
+use std::fmt::Debug;
+
+use itertools::Itertools;
+
+use enum_iterator::{all, Sequence};
+
+use crate::cubesearch::State;
+use crate::legality::ValidState;
+
+pub trait DescribeKey: State {
+    /// Unpacks the key into its fields, one per line. Bits which don't decode to anything are
+    /// shown raw, rather than panicking, since a bad key is usually the reason to look.
+    fn describe_key(key: &Self::UniqueKey) -> String;
+
+    fn describe_state(&self) -> String {
+        Self::describe_key(&self.uniq_key())
+    }
+}
+
+/// A state laid out for reading, as each slot and the piece in it. Puzzles which don't list their
+/// slots fall back to the derived Debug, which has everything, but takes some reading.
+pub trait PrettyState: State + Debug {
+    /// The name of each slot and what's in it; empty means there's no listing
+    fn slots(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// One line per slot, like `UFR: UBL`
+    fn pretty(&self) -> String {
+        let slots = self.slots();
+
+        if slots.is_empty() {
+            return format!("{self:#?}");
+        }
+
+        slots.iter().map(|(slot, piece)| format!("{slot}: {piece}")).join("\n")
+    }
+}
+
+/// Keys which hold the whole state, so they can be unpacked back into it; that's what makes a key
+/// good for sharing a position, rather than just for telling states apart
+pub trait DecodeKey: State<UniqueKey = u64> + ValidState {
+    /// None if some field doesn't decode to anything. The result still needs checking for
+    /// legality; every field can make sense on its own while the whole doesn't.
+    fn decode_key(key: u64) -> Option<Self>;
+}
+
+/// Reads the fields of a packed key back off, starting from the low bits; that is, in the reverse
+/// of the order they were packed in.
+pub struct KeyReader {
+    remaining: u64,
+}
+
+impl KeyReader {
+    pub fn new(key: u64) -> Self {
+        Self { remaining: key }
+    }
+
+    pub fn take(&mut self, bits: u32) -> u64 {
+        let out = self.remaining & ((1 << bits) - 1);
+        self.remaining >>= bits;
+        out
+    }
+
+    /// Reads `bits` bits, and gives the value of `T` whose encoding they are, if there is one
+    pub fn take_exact<T: Sequence>(&mut self, bits: u32, encode: impl Fn(&T) -> u64) -> Option<T> {
+        let raw = self.take(bits);
+        all::<T>().find(|t| encode(t) == raw)
+    }
+
+    /// Reads `bits` bits, and names the value of `T` whose encoding they are
+    pub fn take_value<T: Sequence + Debug>(&mut self, bits: u32, encode: impl Fn(&T) -> u64) -> String {
+        let raw = self.take(bits);
+
+        match all::<T>().find(|t| encode(t) == raw) {
+            Some(t) => format!("{t:?}"),
+            None => format!("?{raw}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_fields_last_first() {
+        // packed as 101, then 11, then 0110
+        let mut reader = KeyReader::new((0b101 << 6) | (0b11 << 4) | 0b0110);
+
+        assert_eq!(reader.take(4), 0b0110);
+        assert_eq!(reader.take(2), 0b11);
+        assert_eq!(reader.take(3), 0b101);
+        assert_eq!(reader.take(3), 0);
+    }
+}
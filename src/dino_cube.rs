@@ -1,18 +1,22 @@
 use std::fmt::Formatter;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use derive_more::Display;
 use enum_iterator::{all, Sequence};
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
-use crate::idasearch::heuristic_helpers::bounded_cache;
-use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CornerTwistAmt};
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::describe::{DecodeKey, DescribeKey, KeyReader, PrettyState};
+use crate::idasearch::heuristic_helpers::{bounded_cache, AdmissibleMin, BoundedStateCache};
+use crate::idasearch::{solve_to, solve_with_fuel, Heuristic, Solvable, SolveError};
+use crate::legality::{require_even, IllegalReason, ValidState};
+use crate::moves::{parse_by_display, CanReverse, CornerTwistAmt, ParseMoveError};
 use crate::random_helpers::{shuffle_with_parity, TwoParity};
 use crate::scrambles::RandomInit;
 
 #[repr(u8)]
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence, Display)]
 enum EdgeCubelet {
     // we leave the UF fixed; everything else is represented
     // u layer
@@ -38,6 +42,21 @@ impl EdgeCubelet {
     fn pack(self, bits: &mut u64) {
         *bits = (*bits << 4) | (self as u64);
     }
+
+    /// The cubelet with L and R swapped in its name
+    fn mirrored(self) -> Self {
+        match self {
+            EdgeCubelet::UL => EdgeCubelet::UR,
+            EdgeCubelet::UR => EdgeCubelet::UL,
+            EdgeCubelet::FL => EdgeCubelet::FR,
+            EdgeCubelet::FR => EdgeCubelet::FL,
+            EdgeCubelet::BL => EdgeCubelet::BR,
+            EdgeCubelet::BR => EdgeCubelet::BL,
+            EdgeCubelet::DL => EdgeCubelet::DR,
+            EdgeCubelet::DR => EdgeCubelet::DL,
+            EdgeCubelet::UB | EdgeCubelet::DB | EdgeCubelet::DF => self,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
@@ -60,8 +79,8 @@ pub struct DinoCube {
     df: EdgeCubelet,
 }
 
-impl DinoCube {
-    pub fn solved_state() -> Self {
+impl HasSolvedState for DinoCube {
+    fn solved() -> Self {
         Self {
             // U layer
             ul: EdgeCubelet::UL,
@@ -79,7 +98,9 @@ impl DinoCube {
             df: EdgeCubelet::DF,
         }
     }
+}
 
+impl DinoCube {
     pub fn solved_mirrored() -> Self {
         // L and R are mirrored; everything else is the same
         Self {
@@ -100,6 +121,26 @@ impl DinoCube {
         }
     }
 
+    /// The same state, with every cubelet renamed to its mirror image. Moves only shuffle
+    /// positions, so renaming commutes with them; and it takes the mirrored solution to the
+    /// original one, so a state is exactly as far from the mirrored solution as its renaming is
+    /// from the original.
+    fn relabel_mirrored(&self) -> Self {
+        Self {
+            ul: self.ul.mirrored(),
+            ub: self.ub.mirrored(),
+            ur: self.ur.mirrored(),
+            fl: self.fl.mirrored(),
+            fr: self.fr.mirrored(),
+            bl: self.bl.mirrored(),
+            br: self.br.mirrored(),
+            dl: self.dl.mirrored(),
+            db: self.db.mirrored(),
+            dr: self.dr.mirrored(),
+            df: self.df.mirrored(),
+        }
+    }
+
     #[inline(always)]
     fn ubl(&self) -> Self {
         Self {
@@ -203,12 +244,49 @@ impl DinoCube {
     }
 }
 
+impl DinoCube {
+    fn as_array(&self) -> [EdgeCubelet; 11] {
+        [
+            self.ul, self.ub, self.ur, self.fl, self.fr, self.bl, self.br, self.dl, self.db, self.dr, self.df,
+        ]
+    }
+}
+
+impl ValidState for DinoCube {
+    fn is_legal(&self) -> Result<(), IllegalReason> {
+        // every move is a 3-cycle of edges (UF is held fixed, so wide twists are too)
+        require_even("edges", &Self::solved().as_array(), &self.as_array())
+    }
+}
+
+impl PrettyState for DinoCube {
+    fn slots(&self) -> Vec<(&'static str, String)> {
+        // UF is fixed, so it's left out
+        [
+            ("UL", self.ul),
+            ("UB", self.ub),
+            ("UR", self.ur),
+            ("FL", self.fl),
+            ("FR", self.fr),
+            ("BL", self.bl),
+            ("BR", self.br),
+            ("DL", self.dl),
+            ("DB", self.db),
+            ("DR", self.dr),
+            ("DF", self.df),
+        ]
+        .into_iter()
+        .map(|(slot, edge)| (slot, edge.to_string()))
+        .collect()
+    }
+}
+
 impl RandomInit for DinoCube {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         let edges: Vec<EdgeCubelet> = all::<EdgeCubelet>().collect();
         let edges = shuffle_with_parity(r, &edges, TwoParity::Even);
 
-        Self {
+        let out = Self {
             ul: edges[0],
             ub: edges[1],
             ur: edges[2],
@@ -220,7 +298,10 @@ impl RandomInit for DinoCube {
             db: edges[8],
             dr: edges[9],
             df: edges[10],
-        }
+        };
+        debug_assert_eq!(out.is_legal(), Ok(()), "Random states should be legal");
+
+        out
     }
 }
 
@@ -234,7 +315,10 @@ enum Dir {
     DFR,
     DBL,
     DBR,
-    // two-layer twists of the UF-antipodal corners
+    // two-layer twists of the UF-antipodal corners; since the puzzle is all edges, twisting
+    // everything but a corner is the same as twisting that corner and rotating the whole cube.
+    // So these are really the UFR and UFL twists, done so the UF edge stays put. There's no
+    // reason for wide twists on any other corner; they'd just be rotations of the moves above.
     DBLw,
     DBRw,
 }
@@ -254,11 +338,19 @@ impl CanReverse for Move {
     }
 }
 
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
 impl Solvable for DinoCube {
     type Move = Move;
 
     fn is_solved(&self) -> bool {
-        self == &Self::solved_state() || self == &Self::solved_mirrored()
+        self == &Self::solved() || self == &Self::solved_mirrored()
     }
 
     fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
@@ -287,6 +379,10 @@ impl Solvable for DinoCube {
         ]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         let last_dir = last_move.0;
         let next_dir = next_move.0;
@@ -334,7 +430,7 @@ impl SimpleStartState for DinoCube {
     type UniqueKey = u64;
 
     fn start() -> Self {
-        Self::solved_state()
+        Self::solved()
     }
 
     fn uniq_key(&self) -> Self::UniqueKey {
@@ -362,9 +458,203 @@ impl SimpleStartState for DinoCube {
     }
 }
 
-pub fn make_heuristic() -> impl Heuristic<DinoCube> {
+impl DescribeKey for DinoCube {
+    fn describe_key(key: &u64) -> String {
+        // the positions, in the order uniq_key packs them; UB is left out
+        const NAMES: [&str; 10] = ["UL", "UR", "FL", "FR", "BL", "BR", "DF", "DL", "DB", "DR"];
+
+        let mut reader = KeyReader::new(*key);
+
+        let mut pieces: Vec<String> = NAMES
+            .iter()
+            .map(|_| reader.take_value::<EdgeCubelet>(4, |e| *e as u64))
+            .collect();
+
+        // they came off last-packed first
+        pieces.reverse();
+
+        NAMES
+            .iter()
+            .zip(pieces)
+            .map(|(name, piece)| format!("{name}: {piece}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl DecodeKey for DinoCube {
+    fn decode_key(key: u64) -> Option<Self> {
+        let mut reader = KeyReader::new(key);
+
+        // the order uniq_key packs them in; they come off last-packed first
+        let mut packed = [EdgeCubelet::UL; 10];
+        for edge in packed.iter_mut().rev() {
+            *edge = reader.take_exact(4, |e: &EdgeCubelet| *e as u64)?;
+        }
+
+        // UB isn't packed; it's whichever edge is left over
+        let ub = all::<EdgeCubelet>().find(|e| !packed.contains(e))?;
+        let [ul, ur, fl, fr, bl, br, df, dl, db, dr] = packed;
+
+        Some(Self {
+            ul,
+            ub,
+            ur,
+            fl,
+            fr,
+            bl,
+            br,
+            dl,
+            db,
+            dr,
+            df,
+        })
+    }
+}
+
+/// The same puzzle as [DinoCube], but only the original color scheme counts as solved; the
+/// mirrored solution is just another scrambled state. Useful for seeing how much the second
+/// solution actually helps.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct DinoCubeOneSolution(DinoCube);
+
+impl PrettyState for DinoCubeOneSolution {
+    fn slots(&self) -> Vec<(&'static str, String)> {
+        self.0.slots()
+    }
+}
+
+impl RandomInit for DinoCubeOneSolution {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        Self(DinoCube::random_state(r))
+    }
+}
+
+impl Solvable for DinoCubeOneSolution {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self.0 == DinoCube::solved()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        self.0.available_moves()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        DinoCube::all_moves()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        DinoCube::is_redundant(last_move, next_move)
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        Self(self.0.apply(m))
+    }
+
+    fn max_fuel() -> usize {
+        DinoCube::max_fuel()
+    }
+}
+
+impl SimpleStartState for DinoCubeOneSolution {
+    type UniqueKey = u64;
+
+    fn start() -> Self {
+        Self(DinoCube::solved())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        SimpleStartState::uniq_key(&self.0)
+    }
+}
+
+impl HasSolvedState for DinoCubeOneSolution {}
+
+/// Determine whether the given solution takes the given state to the mirrored solution (as
+/// opposed to the original one).
+pub fn ends_mirrored(state: &DinoCube, solution: &[Move]) -> bool {
+    let mut state = *state;
+
+    for m in solution.iter().copied() {
+        state = state.apply(m);
+    }
+
+    state == DinoCube::solved_mirrored()
+}
+
+pub fn make_heuristic() -> impl Heuristic<DinoCubeOneSolution> {
     // max depth is picked to keep the compute time low
-    bounded_cache::<DinoCube>(6)
+    bounded_cache::<DinoCubeOneSolution>(6)
+}
+
+/// Heuristic for the distance to the _nearest_ solution. Note the single-solution heuristic
+/// would overestimate this, which breaks IDA*.
+pub fn make_heuristic_multigoal() -> impl Heuristic<DinoCube> {
+    make_heuristic_toward(SolveTarget::Nearest)
+}
+
+/// Heuristic for the distance to `target`; only admissible for that target
+pub fn make_heuristic_toward(target: SolveTarget) -> impl Heuristic<DinoCube> {
+    // max depth is picked to keep the compute time low
+    toward_target(bounded_cache::<DinoCubeOneSolution>(6), target)
+}
+
+/// Which of the two solutions a solve aims for. They look the same once the stickers are on, so
+/// either one counts as solved; but it can be worth studying the solves to one of them.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SolveTarget {
+    Solved,
+    Mirror,
+    Nearest,
+}
+
+/// Solves toward `target`, within `max_fuel` moves. The heuristic has to be admissible for the
+/// target, like the one [make_heuristic_toward] gives.
+pub fn solve_toward<H: Heuristic<DinoCube>>(
+    state: &DinoCube,
+    target: SolveTarget,
+    h: &H,
+    max_fuel: usize,
+) -> Result<Vec<Move>, SolveError> {
+    match target {
+        SolveTarget::Solved => solve_to(state, &DinoCube::solved(), h, max_fuel),
+        SolveTarget::Mirror => solve_to(state, &DinoCube::solved_mirrored(), h, max_fuel),
+        SolveTarget::Nearest => solve_with_fuel(state, h, max_fuel),
+    }
+}
+
+/// Distance toward one of the two solutions, looked up in a cache toward the original one
+struct TowardSolution {
+    cache: Arc<BoundedStateCache<u64>>,
+    mirrored: bool,
+}
+
+impl Heuristic<DinoCube> for TowardSolution {
+    fn estimated_remaining_cost(&self, t: &DinoCube) -> usize {
+        if self.mirrored {
+            self.cache.estimated_remaining_cost(&t.relabel_mirrored())
+        } else {
+            self.cache.estimated_remaining_cost(t)
+        }
+    }
+}
+
+/// Turns a cache toward the original solution into a heuristic toward the target; the same cache
+/// serves both solutions, since the mirrored distance is just the distance of the relabeled state
+fn toward_target(cache: BoundedStateCache<u64>, target: SolveTarget) -> AdmissibleMin<TowardSolution> {
+    let cache = Arc::new(cache);
+    let toward = |mirrored: bool| TowardSolution {
+        cache: cache.clone(),
+        mirrored,
+    };
+
+    AdmissibleMin::new(match target {
+        SolveTarget::Solved => vec![toward(false)],
+        SolveTarget::Mirror => vec![toward(true)],
+        SolveTarget::Nearest => vec![toward(false), toward(true)],
+    })
 }
 
 #[cfg(test)]
@@ -380,3 +670,190 @@ mod bits_test {
         }
     }
 }
+
+#[cfg(test)]
+mod wide_twist_tests {
+    use super::*;
+
+    #[test]
+    fn wide_twists_leave_the_opposite_corner_alone() {
+        // start from something where every edge is distinguishable from its neighbors
+        let state = DinoCube::solved().ubl().dfr();
+
+        // DBLw is a UFR twist (in disguise), so UR and FR (and UF) don't move
+        let dblw = state.dblw();
+        assert_eq!((dblw.ur, dblw.fr), (state.ur, state.fr));
+
+        // DBRw is a UFL twist (in disguise), so UL and FL (and UF) don't move
+        let dbrw = state.dbrw();
+        assert_eq!((dbrw.ul, dbrw.fl), (state.ul, state.fl));
+    }
+}
+
+#[cfg(test)]
+mod legality_tests {
+    use super::*;
+
+    #[test]
+    fn swapped_edges_are_illegal() {
+        assert_eq!(DinoCube::solved().is_legal(), Ok(()));
+        assert_eq!(DinoCube::solved_mirrored().is_legal(), Ok(()));
+        assert_eq!(DinoCube::solved().dblw().dbr().is_legal(), Ok(()));
+
+        let mut state = DinoCube::solved();
+        std::mem::swap(&mut state.ul, &mut state.ub);
+        assert_eq!(state.is_legal(), Err(IllegalReason::OddPermutation("edges")));
+    }
+}
+
+#[cfg(test)]
+mod goal_tests {
+    use ahash::HashMap;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::heuristic_helpers::bounded_cache_from;
+    use crate::idasearch::solve;
+
+    #[test]
+    fn nearest_solution_is_never_overestimated() {
+        // the cache is shallower than the search below, so its fallback gets checked too
+        let heuristic = toward_target(bounded_cache::<DinoCubeOneSolution>(3), SolveTarget::Nearest);
+
+        // true distances to the nearer solution, by BFS from both at once
+        let mut distances: HashMap<DinoCube, usize> = HashMap::default();
+        let mut frontier = vec![DinoCube::solved(), DinoCube::solved_mirrored()];
+        for depth in 0..=5 {
+            let mut next = vec![];
+            for state in frontier {
+                if distances.contains_key(&state) {
+                    continue;
+                }
+                distances.insert(state, depth);
+                next.extend(state.available_moves().into_iter().map(|m| state.apply(m)));
+            }
+            frontier = next;
+        }
+
+        for (state, distance) in distances {
+            let estimate = heuristic.estimated_remaining_cost(&state);
+            assert!(
+                estimate <= distance,
+                "Estimated {estimate} for {state:?}, which is {distance} from the nearer solution"
+            );
+        }
+    }
+
+    #[test]
+    fn either_solution_is_never_longer() {
+        // smaller caches than the real thing, to keep the test fast
+        let one_heuristic = bounded_cache::<DinoCubeOneSolution>(5);
+        let either_heuristic = bounded_cache_from(vec![DinoCube::solved(), DinoCube::solved_mirrored()], 5);
+
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..5 {
+            let state = DinoCube::random_state(&mut rng);
+
+            let one_solution = solve(&DinoCubeOneSolution(state), &one_heuristic).unwrap();
+            let either_solution = solve(&state, &either_heuristic).unwrap();
+
+            assert!(
+                either_solution.len() <= one_solution.len(),
+                "Either-goal solution {either_solution:?} should be no longer than {one_solution:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn solves_toward_the_chosen_target() {
+        // smaller caches than the real thing, to keep the test fast
+        let toward = |target| toward_target(bounded_cache::<DinoCubeOneSolution>(5), target);
+        let (to_solved, to_mirror, to_nearest) = (
+            toward(SolveTarget::Solved),
+            toward(SolveTarget::Mirror),
+            toward(SolveTarget::Nearest),
+        );
+        let max_fuel = DinoCube::max_fuel();
+
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..5 {
+            let state = DinoCube::random_state(&mut rng);
+
+            let solved = solve_toward(&state, SolveTarget::Solved, &to_solved, max_fuel).unwrap();
+            let mirror = solve_toward(&state, SolveTarget::Mirror, &to_mirror, max_fuel).unwrap();
+            let nearest = solve_toward(&state, SolveTarget::Nearest, &to_nearest, max_fuel).unwrap();
+
+            assert!(
+                !ends_mirrored(&state, &solved),
+                "{solved:?} should end at the original solution"
+            );
+            assert!(
+                ends_mirrored(&state, &mirror),
+                "{mirror:?} should end at the mirrored solution"
+            );
+            assert!(
+                nearest.len() <= solved.len().min(mirror.len()),
+                "Nearest solution {nearest:?} should be no longer than {solved:?} or {mirror:?}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod pretty_tests {
+    use super::*;
+
+    #[test]
+    fn solved_has_every_piece_at_home() {
+        let pretty = DinoCube::start().pretty();
+        let lines: Vec<&str> = pretty.lines().collect();
+
+        // every edge but the fixed UF
+        assert_eq!(lines.len(), 11, "Whole listing: {pretty}");
+
+        for line in lines {
+            let (slot, piece) = line.split_once(": ").unwrap();
+            assert_eq!(slot, piece, "Whole listing: {pretty}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::idasearch::heuristic_helpers::bounded_cache_from;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles_one_solution() {
+        assert_golden_scrambles::<DinoCubeOneSolution, _>(
+            &bounded_cache::<DinoCubeOneSolution>(5),
+            15,
+            &[
+                "DBRw DFR' UBR DBLw' DFL DBRw' DBL",
+                "DFR' DBR UBR' DBLw DBL DFR' DFL' UBL",
+                "UBL' DBRw' DFR' DBLw' DBRw' DBLw' DBL",
+                "DBRw' DBLw' DFL DFR' DBR' DFL DFR' UBR'",
+                "DBRw' DFL' UBR' UBL DBRw' DBLw DBL' UBR'",
+            ],
+        );
+    }
+
+    #[test]
+    fn golden_scrambles_either_solution() {
+        assert_golden_scrambles::<DinoCube, _>(
+            &bounded_cache_from(vec![DinoCube::solved(), DinoCube::solved_mirrored()], 5),
+            15,
+            &[
+                "DBRw DFR' UBR DBLw' DFL DBRw' DBL",
+                "DBR' DFL' DBL UBR' DBR' DFL' UBL",
+                "UBL' DBRw' DFR' DBLw' DBRw' DBLw' DBL",
+                "DBRw' DBLw' DFL DFR' DBR' DFL DFR' UBR'",
+                "DBL DFR UBL UBR DBLw' DBR DBL' UBL'",
+            ],
+        );
+    }
+}
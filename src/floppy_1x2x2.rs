@@ -1,10 +1,14 @@
+use std::str::FromStr;
+
 use derive_more::Display;
+use enum_iterator::{all, Sequence};
 use rand::seq::SliceRandom;
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::describe::PrettyState;
 use crate::idasearch::Solvable;
-use crate::moves::CanReverse;
+use crate::moves::{parse_by_display, CanReverse, ParseMoveError};
 use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
@@ -21,7 +25,7 @@ pub struct Floppy1x2x2 {
     dr: CornerCubelet,
 }
 
-impl Floppy1x2x2 {
+impl HasSolvedState for Floppy1x2x2 {
     fn solved() -> Self {
         Self {
             ul: CornerCubelet::UL,
@@ -29,7 +33,9 @@ impl Floppy1x2x2 {
             dr: CornerCubelet::DR,
         }
     }
+}
 
+impl Floppy1x2x2 {
     fn u2(&self) -> Self {
         Self {
             ul: self.ur,
@@ -59,6 +65,8 @@ impl SimpleStartState for Floppy1x2x2 {
     }
 }
 
+impl PrettyState for Floppy1x2x2 {}
+
 impl RandomInit for Floppy1x2x2 {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         // any permutation of the cubelets is possible, so we can just shuffle the possibilities
@@ -74,7 +82,7 @@ impl RandomInit for Floppy1x2x2 {
 }
 
 /// The moves for a Floppy 1x2x2 are just R/U, as half turns
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Display)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Display, Sequence)]
 pub enum Move {
     R2,
     U2,
@@ -89,6 +97,14 @@ impl CanReverse for Move {
     }
 }
 
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
 impl Solvable for Floppy1x2x2 {
     type Move = Move;
 
@@ -104,6 +120,10 @@ impl Solvable for Floppy1x2x2 {
         [Move::R2, Move::U2]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn apply(&self, m: Self::Move) -> Self {
         match m {
             Move::U2 => self.u2(),
@@ -115,3 +135,15 @@ impl Solvable for Floppy1x2x2 {
         3
     }
 }
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::idasearch::no_heuristic;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<Floppy1x2x2, _>(&no_heuristic, 15, &["U2", "R2 U2 R2", "R2 U2", "R2", ""]);
+    }
+}
@@ -1,10 +1,14 @@
+use std::str::FromStr;
+
 use derive_more::Display;
+use enum_iterator::{all, Sequence};
 use rand::prelude::SliceRandom;
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::describe::PrettyState;
 use crate::idasearch::Solvable;
-use crate::moves::CanReverse;
+use crate::moves::{parse_by_display, CanReverse, ParseMoveError};
 use crate::orientations::EdgeOrientation;
 use crate::scrambles::RandomInit;
 
@@ -25,7 +29,7 @@ pub struct Floppy1x2x3 {
     rc_solved: EdgeOrientation,
 }
 
-impl Floppy1x2x3 {
+impl HasSolvedState for Floppy1x2x3 {
     fn solved() -> Self {
         Self {
             ul: CornerCubelet::UL,
@@ -35,7 +39,9 @@ impl Floppy1x2x3 {
             rc_solved: EdgeOrientation::Normal,
         }
     }
+}
 
+impl Floppy1x2x3 {
     fn u2(&self) -> Self {
         Self {
             ul: self.ur,
@@ -74,6 +80,8 @@ impl SimpleStartState for Floppy1x2x3 {
     }
 }
 
+impl PrettyState for Floppy1x2x3 {}
+
 impl RandomInit for Floppy1x2x3 {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         // any permutation of the cubelets is possible, so we can just shuffle the possibilities
@@ -96,7 +104,7 @@ impl RandomInit for Floppy1x2x3 {
 }
 
 /// The moves for a Floppy 1x2x3 are just R/U/D, as half turns
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Display)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Display, Sequence)]
 pub enum Move {
     R2,
     U2,
@@ -110,6 +118,14 @@ impl CanReverse for Move {
     }
 }
 
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
 impl Solvable for Floppy1x2x3 {
     type Move = Move;
 
@@ -125,6 +141,10 @@ impl Solvable for Floppy1x2x3 {
         [Move::R2, Move::U2, Move::D2]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn apply(&self, m: Self::Move) -> Self {
         match m {
             Move::U2 => self.u2(),
@@ -137,3 +157,25 @@ impl Solvable for Floppy1x2x3 {
         6
     }
 }
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::idasearch::no_heuristic;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<Floppy1x2x3, _>(
+            &no_heuristic,
+            15,
+            &[
+                "D2 U2 R2 D2",
+                "D2 R2 D2 R2 U2 R2",
+                "R2 D2 U2 R2",
+                "R2 D2 R2 U2 R2",
+                "U2 R2 D2 R2",
+            ],
+        );
+    }
+}
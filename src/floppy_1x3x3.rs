@@ -1,10 +1,13 @@
+use std::str::FromStr;
+
 use derive_more::Display;
 use enum_iterator::{all, Sequence};
 use rand::Rng;
 
-use crate::cubesearch::State;
+use crate::cubesearch::{HasSolvedState, State};
+use crate::describe::PrettyState;
 use crate::idasearch::Solvable;
-use crate::moves::CanReverse;
+use crate::moves::{parse_by_display, CanReverse, ParseMoveError};
 use crate::orientations::EdgeOrientation;
 use crate::random_helpers;
 use crate::scrambles::RandomInit;
@@ -29,7 +32,7 @@ pub struct Floppy1x3x3 {
     lc_solved: EdgeOrientation,
 }
 
-impl Floppy1x3x3 {
+impl HasSolvedState for Floppy1x3x3 {
     fn solved() -> Self {
         Self {
             ul: CornerCubelet::UL,
@@ -42,7 +45,9 @@ impl Floppy1x3x3 {
             dc_solved: EdgeOrientation::Normal,
         }
     }
+}
 
+impl Floppy1x3x3 {
     fn u2(&self) -> Self {
         Self {
             ul: self.ur,
@@ -102,7 +107,7 @@ impl State for Floppy1x3x3 {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Hash, Sequence)]
 pub enum Move {
     R2,
     U2,
@@ -117,9 +122,20 @@ impl CanReverse for Move {
     }
 }
 
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
+impl PrettyState for Floppy1x3x3 {}
+
 impl RandomInit for Floppy1x3x3 {
     fn random_state<R: Rng>(r: &mut R) -> Self {
-        // the total parity of the position permutation ...
+        // every move swaps two corners and flips one center, so the total parity of the position
+        // permutation ...
         let (cubelets, pos_parity) = random_helpers::shuffle_any(r, all::<CornerCubelet>());
         // ... must match the total parity of the center orientations
         let orientations = random_helpers::flips_with_parity(r, 4, pos_parity);
@@ -149,6 +165,10 @@ impl Solvable for Floppy1x3x3 {
         [Move::D2, Move::U2, Move::L2, Move::R2]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         last_move == next_move
     }
@@ -166,3 +186,56 @@ impl Solvable for Floppy1x3x3 {
         8
     }
 }
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::idasearch::no_heuristic;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<Floppy1x3x3, _>(
+            &no_heuristic,
+            15,
+            &["L2 U2", "L2 D2 L2 D2 L2", "U2 R2 L2", "U2 R2 U2 L2 D2", "L2 U2 R2 L2"],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::{no_heuristic, solve, SolveError};
+
+    #[test]
+    fn random_states_are_solvable() {
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..1000 {
+            let state = Floppy1x3x3::random_state(&mut rng);
+
+            let solution = solve(&state, &no_heuristic);
+            assert!(solution.is_ok(), "{state:?} should be solvable");
+        }
+    }
+
+    #[test]
+    fn decoupled_parity_is_unsolvable() {
+        // one center flipped, but the corners in an even (solved) permutation
+        let state = Floppy1x3x3 {
+            rc_solved: EdgeOrientation::Flipped,
+            ..Floppy1x3x3::solved()
+        };
+
+        assert_eq!(
+            solve(&state, &no_heuristic),
+            Err(SolveError::OutOfGas {
+                max_fuel: Floppy1x3x3::max_fuel()
+            })
+        );
+    }
+}
@@ -1,11 +1,14 @@
+use std::fmt::Formatter;
 use std::mem::swap;
+use std::str::FromStr;
 
-use derive_more::Display;
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
 use crate::idasearch::Solvable;
-use crate::moves::CanReverse;
+use crate::moves::{CanReverse, ParseMoveError};
+use crate::orientations::EdgeOrientation;
+use crate::random_helpers::{flips_with_parity, shuffle_any, shuffle_with_parity, TwoParity};
 use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
@@ -27,8 +30,8 @@ pub struct Floppy1xMxN<const H: usize, const W: usize> {
 
     // centers can be the right or wrong color (that is, flipped or not), but that's all
     // "true" means correct
-    // index as [x][y] where [0][0] is the upper-right corner of the center
-    centers: [[bool; W]; H], // index as [y][x]
+    // index as [y][x] where [0][0] is the upper-right corner of the center
+    centers: [[bool; W]; H],
 
     // edges can be the right or wrong position (left-on-right or not) and they can also be
     // flipped (disoriented), so they have two bits of information each.
@@ -51,7 +54,7 @@ pub struct Floppy1xMxN<const H: usize, const W: usize> {
     bot_edge_orr: [bool; W],
 }
 
-impl<const H: usize, const W: usize> Floppy1xMxN<H, W> {
+impl<const H: usize, const W: usize> HasSolvedState for Floppy1xMxN<H, W> {
     fn solved() -> Self {
         Self {
             ul: CornerCubelet::UL,
@@ -73,7 +76,9 @@ impl<const H: usize, const W: usize> Floppy1xMxN<H, W> {
             bot_edge_pos: [true; W],
         }
     }
+}
 
+impl<const H: usize, const W: usize> Floppy1xMxN<H, W> {
     /// Do a U2(y) move. y=0 means just the top slice (no effect on centers). y=1 means the top
     /// and the top layer of centers, and so on; y=H means everything but the bottom.
     /// There is a requirement that y<=H.
@@ -177,37 +182,243 @@ impl<const H: usize, const W: usize> Floppy1xMxN<H, W> {
     }
 }
 
+/// Packs the flags into the low bits, one bit each, after shifting over what's already there
+fn pack_flags(bits: &mut u64, flags: &[bool]) {
+    for &flag in flags {
+        *bits = (*bits << 1) | (flag as u64);
+    }
+}
+
+impl<const H: usize, const W: usize> Floppy1xMxN<H, W> {
+    /// Bits in the packed key: 2 for each of the three corners, 1 for each of the H*W centers, and
+    /// 2 (position and orientation) for each of the 2H side edges and 2W top and bottom edges. So
+    /// 6 + H*W + 4(H+W) in all; the biggest one the CLI enumerates (1x6x6, so H=W=4) needs 54.
+    const KEY_BITS: usize = 6 + H * W + 4 * (H + W);
+}
+
 impl<const H: usize, const W: usize> SimpleStartState for Floppy1xMxN<H, W> {
-    type UniqueKey = Self;
+    type UniqueKey = u64;
 
     fn start() -> Self {
         Self::solved()
     }
 
     fn uniq_key(&self) -> Self::UniqueKey {
-        *self
+        const { assert!(Self::KEY_BITS <= 64, "Floppy is too big to pack into a u64") };
+
+        let mut bits: u64 = 0;
+
+        for corner in [self.ul, self.ur, self.dr] {
+            bits = (bits << 2) | (corner as u64);
+        }
+
+        for row in &self.centers {
+            pack_flags(&mut bits, row);
+        }
+
+        pack_flags(&mut bits, &self.left_edge_pos);
+        pack_flags(&mut bits, &self.left_edge_orr);
+        pack_flags(&mut bits, &self.right_edge_pos);
+        pack_flags(&mut bits, &self.right_edge_orr);
+
+        pack_flags(&mut bits, &self.top_edge_pos);
+        pack_flags(&mut bits, &self.top_edge_orr);
+        pack_flags(&mut bits, &self.bot_edge_pos);
+        pack_flags(&mut bits, &self.bot_edge_orr);
+
+        bits
     }
 }
 
+/// Parity from a flag, for the helpers that want one; true means odd
+fn two_parity(odd: bool) -> TwoParity {
+    if odd {
+        TwoParity::Odd
+    } else {
+        TwoParity::Even
+    }
+}
+
+/// Flips a pair of pieces at random, with an odd number of flips exactly when asked. Returns the
+/// flags for the pair, so true means "not flipped."
+fn flip_pair<R: Rng>(r: &mut R, odd: bool) -> [bool; 2] {
+    let flips = flips_with_parity(r, 2, two_parity(odd));
+
+    [0, 1].map(|i| flips[i] == EdgeOrientation::Normal)
+}
+
+/// Flags for a four-piece orbit, laid out as [near, near_opp, far, far_opp], where far is the other
+/// side (top to bottom, left to right, or the other half of the centers) and opp is the other end of
+/// the same side. The i'th piece is the one that lives in the i'th slot. Every move that touches the
+/// orbit swaps two pieces and flips both, so a piece is facing the right way exactly when it has
+/// crossed over an even number of times. Returns (on the right side, facing the right way) per slot.
+fn four_orbit_flags(pieces: &[usize]) -> [(bool, bool); 4] {
+    let far = |i: usize| i >= 2;
+    let crossings = |i: usize| far(i) ^ (i % 2 == 1);
+
+    std::array::from_fn(|slot| {
+        let piece = pieces[slot];
+        (far(slot) == far(piece), crossings(slot) == crossings(piece))
+    })
+}
+
 impl<const H: usize, const W: usize> RandomInit for Floppy1xMxN<H, W> {
-    fn random_state<R: Rng>(_r: &mut R) -> Self {
-        todo!("I actually know how to do this now, so we could do it")
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        // The pieces fall into orbits: the corners, the top and bottom edges in columns x and
+        // W-1-x, the side edges in rows y and H-1-y, and the centers in the rectangle those span.
+        // Corners and edges can be shuffled freely (only the middle edges choose their own flips),
+        // but each orbit of centers then has its parity, or its flips, pinned down by the pieces
+        // sharing its rows and columns.
+        let mut out = Self::solved();
+
+        let (corners, corner_parity) = shuffle_any(r, [CornerCubelet::UL, CornerCubelet::UR, CornerCubelet::DR]);
+        [out.ul, out.ur, out.dr] = [corners[0], corners[1], corners[2]];
+        let corners_odd = corner_parity == TwoParity::Odd;
+
+        // permutation parity of the edges in each column (or row) and its opposite
+        let mut col_odd = [false; W];
+        let mut row_odd = [false; H];
+
+        for (x, odd) in col_odd.iter_mut().enumerate().take(W / 2) {
+            let x_opp = W - 1 - x;
+            let (pieces, parity) = shuffle_any(r, 0..4);
+            *odd = parity == TwoParity::Odd;
+
+            let [near, near_opp, far, far_opp] = four_orbit_flags(&pieces);
+            (out.top_edge_pos[x], out.top_edge_orr[x]) = near;
+            (out.top_edge_pos[x_opp], out.top_edge_orr[x_opp]) = near_opp;
+            (out.bot_edge_pos[x], out.bot_edge_orr[x]) = far;
+            (out.bot_edge_pos[x_opp], out.bot_edge_orr[x_opp]) = far_opp;
+        }
+
+        for (y, odd) in row_odd.iter_mut().enumerate().take(H / 2) {
+            let y_opp = H - 1 - y;
+            let (pieces, parity) = shuffle_any(r, 0..4);
+            *odd = parity == TwoParity::Odd;
+
+            let [near, near_opp, far, far_opp] = four_orbit_flags(&pieces);
+            (out.left_edge_pos[y], out.left_edge_orr[y]) = near;
+            (out.left_edge_pos[y_opp], out.left_edge_orr[y_opp]) = near_opp;
+            (out.right_edge_pos[y], out.right_edge_orr[y]) = far;
+            (out.right_edge_pos[y_opp], out.right_edge_orr[y_opp]) = far_opp;
+        }
+
+        // the middle edges only ever trade places with each other, and can be flipped in place;
+        // the only catch is that, when there are both, their flips are tied to the corners
+        let mut col_flips_odd = false;
+        let mut row_flips_odd = false;
+
+        if W % 2 == 1 {
+            let mid = W / 2;
+            col_odd[mid] = r.gen();
+            out.top_edge_pos[mid] = !col_odd[mid];
+            out.bot_edge_pos[mid] = !col_odd[mid];
+
+            col_flips_odd = r.gen();
+            [out.top_edge_orr[mid], out.bot_edge_orr[mid]] = flip_pair(r, col_flips_odd);
+        }
+
+        if H % 2 == 1 {
+            let mid = H / 2;
+            row_odd[mid] = r.gen();
+            out.left_edge_pos[mid] = !row_odd[mid];
+            out.right_edge_pos[mid] = !row_odd[mid];
+
+            row_flips_odd = if W % 2 == 1 {
+                col_flips_odd ^ corners_odd
+            } else {
+                r.gen()
+            };
+            [out.left_edge_orr[mid], out.right_edge_orr[mid]] = flip_pair(r, row_flips_odd);
+        }
+
+        for (y, &y_odd) in row_odd.iter().enumerate().take(H / 2) {
+            let y_opp = H - 1 - y;
+
+            for (x, &x_odd) in col_odd.iter().enumerate().take(W / 2) {
+                let x_opp = W - 1 - x;
+                let parity = two_parity(y_odd ^ x_odd ^ corners_odd);
+                let pieces = shuffle_with_parity(r, &[0, 1, 2, 3], parity);
+
+                let [near, near_opp, far, far_opp] = four_orbit_flags(&pieces);
+                out.centers[y][x] = near.1;
+                out.centers[y][x_opp] = near_opp.1;
+                out.centers[y_opp][x] = far.1;
+                out.centers[y_opp][x_opp] = far_opp.1;
+            }
+
+            // centers in the middle column swap in pairs, so only their flips are visible
+            if W % 2 == 1 {
+                let mid = W / 2;
+                let odd = col_flips_odd ^ y_odd ^ corners_odd;
+                [out.centers[y][mid], out.centers[y_opp][mid]] = flip_pair(r, odd);
+            }
+        }
+
+        if H % 2 == 1 {
+            let mid = H / 2;
+
+            for (x, &x_odd) in col_odd.iter().enumerate().take(W / 2) {
+                let x_opp = W - 1 - x;
+                let odd = row_flips_odd ^ x_odd ^ corners_odd;
+                [out.centers[mid][x], out.centers[mid][x_opp]] = flip_pair(r, odd);
+            }
+
+            // and the very middle center has nowhere to go, so it's flipped by every middle swap
+            if W % 2 == 1 {
+                out.centers[mid][W / 2] = row_odd[mid] == col_odd[W / 2];
+            }
+        }
+
+        out
     }
 }
 
-/// The moves for a Floppy 1x2x2 are just R/U, as half turns
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Display)]
+/// The moves for a big floppy are just R/U, as half turns, along with how many layers of centers
+/// they take along. They're written the usual way, with a w per extra layer, so R2(2) is Rww2.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub enum Move {
     R2(usize),
     U2(usize),
 }
 
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (face, layers) = match self {
+            Move::R2(x) => ("R", x),
+            Move::U2(y) => ("U", y),
+        };
+
+        write!(f, "{face}{}2", "w".repeat(*layers))
+    }
+}
+
 impl CanReverse for Move {
     fn reverse(&self) -> Self {
         *self
     }
 }
 
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseMoveError(s.to_string());
+
+        let (face, rest) = s.split_at_checked(1).ok_or_else(err)?;
+        let layers = rest.strip_suffix('2').ok_or_else(err)?;
+        if !layers.chars().all(|c| c == 'w') {
+            return Err(err());
+        }
+
+        match face {
+            "R" => Ok(Move::R2(layers.len())),
+            "U" => Ok(Move::U2(layers.len())),
+            _ => Err(err()),
+        }
+    }
+}
+
 impl<const H: usize, const W: usize> Solvable for Floppy1xMxN<H, W> {
     type Move = Move;
 
@@ -222,6 +433,11 @@ impl<const H: usize, const W: usize> Solvable for Floppy1xMxN<H, W> {
         u_moves.chain(r_moves)
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        // every move is available from every state
+        Self::solved().available_moves().into_iter().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         last_move == next_move
     }
@@ -243,3 +459,6 @@ mod tests_133;
 
 #[cfg(test)]
 mod tests_134;
+
+#[cfg(test)]
+mod tests_144;
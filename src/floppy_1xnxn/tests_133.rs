@@ -1,4 +1,9 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
 use super::*;
+use crate::idasearch::{no_heuristic, solve};
+use crate::scrambles::apply_moves;
 
 const FLOPPY_133: Floppy1xMxN<1, 1> = Floppy1xMxN::<1, 1> {
     ul: CornerCubelet::UL,
@@ -109,3 +114,14 @@ fn test_rw2_133() {
 
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn random_states_are_solvable_133() {
+    let mut rng = StdRng::from_seed([13; 32]);
+
+    for _ in 0..50 {
+        let state = Floppy1xMxN::<1, 1>::random_state(&mut rng);
+        let solution = solve(&state, &no_heuristic).expect("Random states should be solvable");
+        assert!(apply_moves(&state, &solution).is_solved());
+    }
+}
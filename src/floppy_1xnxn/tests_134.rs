@@ -1,4 +1,9 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
 use super::*;
+use crate::idasearch::{no_heuristic, solve};
+use crate::scrambles::{apply_moves, format_moves, parse_moves, parse_scramble};
 
 // 1 center row, 2 center columns
 const FLOPPY_134: Floppy1xMxN<1, 2> = Floppy1xMxN::<1, 2> {
@@ -140,3 +145,99 @@ fn test_rww2_134() {
 
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn test_rw2_uw2_134() {
+    let start = FLOPPY_134;
+
+    let actual = start.r2(1).u2(1);
+
+    let expected = Floppy1xMxN::<1, 2> {
+        ul: CornerCubelet::DR,
+        ur: CornerCubelet::UL,
+        dr: CornerCubelet::UR,
+
+        // the flipped column came along to the left side, and got flipped back over
+        centers: [[false, true]],
+
+        left_edge_pos: [false],
+        left_edge_orr: [true],
+
+        right_edge_pos: [false],
+        right_edge_orr: [false],
+
+        top_edge_pos: [true, false],
+        top_edge_orr: [false, true],
+
+        bot_edge_pos: [false, true],
+        bot_edge_orr: [false, true],
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_parsed_rw2_uw2_134() {
+    let start = FLOPPY_134;
+
+    let moves: Vec<Move> = parse_moves("Rw2 Uw2").unwrap();
+    assert_eq!(moves, vec![Move::R2(1), Move::U2(1)]);
+
+    assert_eq!(apply_moves(&start, &moves), start.r2(1).u2(1));
+}
+
+#[test]
+fn test_parsed_alg_134() {
+    let start = FLOPPY_134;
+
+    let alg = "Uw2 R2 Rww2 Uw2 U2 Rw2 R2 Uw2";
+    let moves: Vec<Move> = parse_moves(alg).unwrap();
+
+    let expected = vec![
+        Move::U2(1),
+        Move::R2(0),
+        Move::R2(2),
+        Move::U2(1),
+        Move::U2(0),
+        Move::R2(1),
+        Move::R2(0),
+        Move::U2(1),
+    ];
+    assert_eq!(moves, expected);
+    assert_eq!(format_moves(&moves), alg, "Should print the same way it parses");
+
+    let by_hand = start.u2(1).r2(0).r2(2).u2(1).u2(0).r2(1).r2(0).u2(1);
+    assert_eq!(apply_moves(&start, &moves), by_hand);
+}
+
+#[test]
+fn test_bad_moves_dont_parse_134() {
+    for bad in ["", "R", "Rw", "Rx2", "F2", "wR2", "R2w"] {
+        assert!(bad.parse::<Move>().is_err(), "{bad:?} should not parse");
+    }
+}
+
+#[test]
+fn test_too_wide_moves_dont_parse_as_scramble_134() {
+    // these spell moves fine, but this floppy only has so many layers
+    for bad in ["Uww2", "Rwww2", "R2 Uww2"] {
+        assert!(parse_moves::<Move>(bad).is_ok(), "{bad:?} should parse as moves");
+        assert!(
+            parse_scramble::<Floppy1xMxN<1, 2>>(bad).is_err(),
+            "{bad:?} should not parse as a scramble"
+        );
+    }
+
+    assert!(parse_scramble::<Floppy1xMxN<1, 2>>("Uw2 Rww2").is_ok());
+}
+
+#[test]
+fn random_states_are_solvable_134() {
+    let mut rng = StdRng::from_seed([14; 32]);
+
+    for _ in 0..50 {
+        let state = Floppy1xMxN::<1, 2>::random_state(&mut rng);
+        let solution = solve(&state, &no_heuristic).expect("Random states should be solvable");
+        assert!(apply_moves(&state, &solution).is_solved());
+    }
+}
@@ -0,0 +1,288 @@
+use ahash::{HashMap, HashSet};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::*;
+use crate::cubesearch::{enumerate_state_space, State};
+use crate::test_helpers::assert_keys_distinct;
+
+// 2 center rows, 2 center columns
+const FLOPPY_144: Floppy1xMxN<2, 2> = Floppy1xMxN::<2, 2> {
+    ul: CornerCubelet::UL,
+    ur: CornerCubelet::UR,
+    dr: CornerCubelet::DR,
+
+    centers: [[true, true], [true, true]],
+
+    left_edge_pos: [true, true],
+    left_edge_orr: [true, true],
+
+    right_edge_pos: [true, true],
+    right_edge_orr: [true, true],
+
+    top_edge_pos: [true, true],
+    top_edge_orr: [true, true],
+
+    bot_edge_pos: [true, true],
+    bot_edge_orr: [true, true],
+};
+
+// not necessarily reachable, but every row and column is lopsided, so swaps show up
+const LOPSIDED_144: Floppy1xMxN<2, 2> = Floppy1xMxN::<2, 2> {
+    centers: [[true, false], [true, true]],
+
+    left_edge_pos: [true, false],
+    left_edge_orr: [true, true],
+
+    right_edge_pos: [true, true],
+    right_edge_orr: [false, true],
+
+    top_edge_pos: [true, false],
+    top_edge_orr: [true, true],
+
+    bot_edge_pos: [true, true],
+    bot_edge_orr: [true, false],
+
+    ..FLOPPY_144
+};
+
+#[test]
+fn test_u2_144() {
+    let start = FLOPPY_144;
+
+    let actual = start.u2(0);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ul: CornerCubelet::UR,
+        ur: CornerCubelet::UL,
+
+        top_edge_orr: [false, false],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_uw2_144() {
+    let start = FLOPPY_144;
+
+    let actual = start.u2(1);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ul: CornerCubelet::UR,
+        ur: CornerCubelet::UL,
+
+        top_edge_orr: [false, false],
+
+        // indexing is top first, then down
+        left_edge_pos: [false, true],
+        left_edge_orr: [false, true],
+
+        centers: [[false, false], [true, true]],
+
+        right_edge_pos: [false, true],
+        right_edge_orr: [false, true],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_uww2_144() {
+    let start = FLOPPY_144;
+
+    let actual = start.u2(2);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ul: CornerCubelet::UR,
+        ur: CornerCubelet::UL,
+
+        top_edge_orr: [false, false],
+
+        left_edge_pos: [false, false],
+        left_edge_orr: [false, false],
+
+        centers: [[false, false], [false, false]],
+
+        right_edge_pos: [false, false],
+        right_edge_orr: [false, false],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_r2_144() {
+    let start = FLOPPY_144;
+
+    let actual = start.r2(0);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ur: CornerCubelet::DR,
+        dr: CornerCubelet::UR,
+
+        right_edge_orr: [false, false],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_rw2_144() {
+    let start = FLOPPY_144;
+
+    let actual = start.r2(1);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ur: CornerCubelet::DR,
+        dr: CornerCubelet::UR,
+
+        right_edge_orr: [false, false],
+
+        // indexing is right first, then left
+        top_edge_pos: [false, true],
+        top_edge_orr: [false, true],
+
+        centers: [[false, true], [false, true]],
+
+        bot_edge_pos: [false, true],
+        bot_edge_orr: [false, true],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_rww2_144() {
+    let start = FLOPPY_144;
+
+    let actual = start.r2(2);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ur: CornerCubelet::DR,
+        dr: CornerCubelet::UR,
+
+        right_edge_orr: [false, false],
+
+        top_edge_pos: [false, false],
+        top_edge_orr: [false, false],
+
+        centers: [[false, false], [false, false]],
+
+        bot_edge_pos: [false, false],
+        bot_edge_orr: [false, false],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_uww2_lopsided_144() {
+    let start = LOPSIDED_144;
+
+    let actual = start.u2(2);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ul: CornerCubelet::UR,
+        ur: CornerCubelet::UL,
+
+        // top edges trade places, then all flip
+        top_edge_pos: [false, true],
+        top_edge_orr: [false, false],
+
+        // left and right edges trade places within each row, then all flip
+        left_edge_pos: [false, false],
+        left_edge_orr: [true, false],
+
+        right_edge_pos: [false, true],
+        right_edge_orr: [false, false],
+
+        // each row is mirrored, then flipped
+        centers: [[true, false], [false, false]],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_rww2_lopsided_144() {
+    let start = LOPSIDED_144;
+
+    let actual = start.r2(2);
+
+    let expected = Floppy1xMxN::<2, 2> {
+        ur: CornerCubelet::DR,
+        dr: CornerCubelet::UR,
+
+        // right edges trade places, then all flip
+        right_edge_pos: [true, true],
+        right_edge_orr: [false, true],
+
+        // top and bottom edges trade places within each column, then all flip
+        top_edge_pos: [false, false],
+        top_edge_orr: [false, true],
+
+        bot_edge_pos: [false, true],
+        bot_edge_orr: [false, false],
+
+        // each column is mirrored, then flipped
+        centers: [[false, false], [false, true]],
+
+        ..start
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn distinct_states_have_distinct_keys_144() {
+    // small enough to take the whole puzzle; nothing is more than 11 moves out
+    let keys = assert_keys_distinct::<Floppy1xMxN<2, 2>>(11);
+
+    assert_eq!(keys.len(), 20736);
+    assert!(keys.iter().all(|&k| k < (1 << Floppy1xMxN::<2, 2>::KEY_BITS)));
+}
+
+#[test]
+fn enumeration_counts_144() {
+    let (_, counts) = enumerate_state_space::<Floppy1xMxN<2, 2>>(None).unwrap();
+
+    let expected: HashMap<u128, u128> = [1, 6, 24, 91, 339, 1086, 2670, 4754, 5562, 4139, 1772, 292]
+        .into_iter()
+        .enumerate()
+        .map(|(depth, count)| (depth as u128, count))
+        .collect();
+
+    assert_eq!(counts, expected);
+}
+
+#[test]
+fn random_states_are_reachable_144() {
+    // the only size here with centers in orbits of four, so the only test of that parity branch
+    let reachable = assert_keys_distinct::<Floppy1xMxN<2, 2>>(11);
+    let mut rng = StdRng::from_seed([14; 32]);
+
+    let mut seen: HashSet<u64> = HashSet::default();
+    for _ in 0..200_000 {
+        let key = State::uniq_key(&Floppy1xMxN::<2, 2>::random_state(&mut rng));
+        assert!(reachable.contains(&key), "Random states should be reachable");
+        seen.insert(key);
+    }
+
+    // about ten samples per state, so a uniform generator misses only a handful of them
+    assert!(seen.len() > 20_700, "Only {} states came up", seen.len());
+}
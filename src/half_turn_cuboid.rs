@@ -0,0 +1,88 @@
+//! Cuboids whose U and D layers turn freely, but whose sides only turn by half: the 2x2x3, the
+//! Square Zero, and so on. The corners move the same way on all of them, so a puzzle only has to
+//! say where it keeps its corners, and what else its half turns carry along.
+
+use crate::moves::CubeMoveAmt;
+
+// positions of the corners, as indices into HalfTurnCuboid::corners_mut
+pub const UFL: usize = 0;
+pub const UFR: usize = 1;
+pub const UBL: usize = 2;
+pub const UBR: usize = 3;
+pub const DFL: usize = 4;
+pub const DFR: usize = 5;
+pub const DBL: usize = 6;
+pub const DBR: usize = 7;
+
+pub trait HalfTurnCuboid: Copy {
+    type Corner: Copy;
+
+    /// The eight corners, indexed by position (see [UFL] and the rest)
+    fn corners_mut(&mut self) -> &mut [Self::Corner; 8];
+
+    /// Moves whatever else an R2 carries along, besides the corners; centers, say, or the middle
+    /// layer
+    fn r2_rest(&mut self);
+
+    /// Same as [HalfTurnCuboid::r2_rest], but for F2. Most of these puzzles have no F2 at all, so
+    /// by default there's nothing else to move.
+    fn f2_rest(&mut self) {}
+
+    fn u(&self) -> Self {
+        self.with_corners(|c| cycle(c, [UFR, UFL, UBL, UBR]))
+    }
+
+    fn d(&self) -> Self {
+        self.with_corners(|c| cycle(c, [DBL, DFL, DFR, DBR]))
+    }
+
+    fn r2(&self) -> Self {
+        let mut out = self.with_corners(|c| {
+            c.swap(DFR, UBR);
+            c.swap(DBR, UFR);
+        });
+        out.r2_rest();
+        out
+    }
+
+    fn f2(&self) -> Self {
+        let mut out = self.with_corners(|c| {
+            c.swap(DFL, UFR);
+            c.swap(DFR, UFL);
+        });
+        out.f2_rest();
+        out
+    }
+
+    fn turn_u(&self, amt: CubeMoveAmt) -> Self {
+        match amt {
+            CubeMoveAmt::One => self.u(),
+            CubeMoveAmt::Two => self.u().u(),
+            CubeMoveAmt::Rev => self.u().u().u(),
+        }
+    }
+
+    fn turn_d(&self, amt: CubeMoveAmt) -> Self {
+        match amt {
+            CubeMoveAmt::One => self.d(),
+            CubeMoveAmt::Two => self.d().d(),
+            CubeMoveAmt::Rev => self.d().d().d(),
+        }
+    }
+
+    fn with_corners(&self, f: impl FnOnce(&mut [Self::Corner; 8])) -> Self {
+        let mut out = *self;
+        f(out.corners_mut());
+        out
+    }
+}
+
+/// Moves the piece at each position to the next one, and the piece at the last back to the first
+fn cycle<T: Copy>(pieces: &mut [T; 8], positions: [usize; 4]) {
+    let [a, b, c, d] = positions;
+    let moved = pieces[d];
+    pieces[d] = pieces[c];
+    pieces[c] = pieces[b];
+    pieces[b] = pieces[a];
+    pieces[a] = moved;
+}
@@ -0,0 +1,461 @@
+//! Helicopter cube, without jumbling. Mechanically it's the curvy copter with the edge pieces
+//! taken out: each turn is a 180 degree turn around an edge, which swaps the two corners next to
+//! it (twisting them, unless it's a middle layer edge) and two pairs of centers.
+
+use std::str::FromStr;
+
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::curvy_copter;
+use crate::curvy_copter::{
+    CenterCubelet, CenterStates, CornerCubelet, CornersOrientationState, CornersPositionState, CurvyCopter,
+};
+use crate::idasearch::heuristic_helpers::bounded_cache;
+use crate::idasearch::{Heuristic, Solvable};
+use crate::moves::{impl_can_reverse, parse_by_display, ParseMoveError};
+use crate::orientations::CornerOrientation;
+use crate::random_helpers::{shuffle_any, shuffle_with_parity, take_six, TwoParity};
+use crate::scrambles::RandomInit;
+use derive_more::Display;
+use enum_iterator::{all, Sequence};
+use rand::Rng;
+
+type PackedBits = (u64, u64);
+
+// the centers and corners are exactly the curvy copter's, so a helicopter cube is built from its
+// pieces, turned by the same per-edge swaps
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct HelicopterCube {
+    // 63 bits if you pack tight
+    centers: CenterStates,
+    // 24 bits
+    corner_positions: CornersPositionState,
+    // 16 bits
+    corner_orientations: CornersOrientationState,
+}
+
+macro_rules! pass_through {
+    ($move_name:ident) => {
+        #[inline(always)]
+        fn $move_name(&self) -> Self {
+            Self {
+                centers: self.centers.$move_name(),
+                corner_positions: self.corner_positions.$move_name(),
+                corner_orientations: self.corner_orientations.$move_name(),
+            }
+        }
+    };
+}
+
+impl HasSolvedState for HelicopterCube {
+    #[inline(always)]
+    fn solved() -> Self {
+        HelicopterCube {
+            centers: CenterStates::solved(),
+            corner_positions: CornersPositionState::solved(),
+            corner_orientations: CornersOrientationState::solved(),
+        }
+    }
+}
+
+impl HelicopterCube {
+    pass_through!(uf);
+    pass_through!(ur);
+    pass_through!(ul);
+    pass_through!(ub);
+
+    pass_through!(df);
+    pass_through!(dr);
+    pass_through!(dl);
+    pass_through!(db);
+
+    pass_through!(fr);
+    pass_through!(fl);
+    pass_through!(br);
+    pass_through!(bl);
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Sequence)]
+pub enum Move {
+    UF,
+    UL,
+    UR,
+    UB,
+    DF,
+    DL,
+    DR,
+    DB,
+    FL,
+    FR,
+    BL,
+    BR,
+}
+
+// every turn is a half turn, so they're all self inverse
+impl_can_reverse!(Move {
+    self_inverse: [UF, UL, UR, UB, DF, DL, DR, DB, FL, FR, BL, BR],
+    by_amount: [],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
+impl From<Move> for curvy_copter::Move {
+    fn from(m: Move) -> Self {
+        match m {
+            Move::UF => curvy_copter::Move::UF,
+            Move::UL => curvy_copter::Move::UL,
+            Move::UR => curvy_copter::Move::UR,
+            Move::UB => curvy_copter::Move::UB,
+            Move::DF => curvy_copter::Move::DF,
+            Move::DL => curvy_copter::Move::DL,
+            Move::DR => curvy_copter::Move::DR,
+            Move::DB => curvy_copter::Move::DB,
+            Move::FL => curvy_copter::Move::FL,
+            Move::FR => curvy_copter::Move::FR,
+            Move::BL => curvy_copter::Move::BL,
+            Move::BR => curvy_copter::Move::BR,
+        }
+    }
+}
+
+impl RandomInit for HelicopterCube {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        // corners can be put anywhere; with no edges, there's nothing for their parity to match
+        let corners = vec![
+            CornerCubelet::FUL,
+            CornerCubelet::FUR,
+            CornerCubelet::FDL,
+            CornerCubelet::FDR,
+            CornerCubelet::BUL,
+            CornerCubelet::BUR,
+            CornerCubelet::BDL,
+            CornerCubelet::BDR,
+        ];
+        let (corner_cubelets, _) = shuffle_any(r, corners);
+
+        let corner_positions = CornersPositionState {
+            ful: corner_cubelets[0],
+            fur: corner_cubelets[1],
+            fdl: corner_cubelets[2],
+            fdr: corner_cubelets[3],
+            bul: corner_cubelets[4],
+            bur: corner_cubelets[5],
+            bdl: corner_cubelets[6],
+            bdr: corner_cubelets[7],
+        };
+
+        // total orientation of corners should be zero
+        let mut corner_orientations: Vec<CornerOrientation> =
+            vec![r.gen(), r.gen(), r.gen(), r.gen(), r.gen(), r.gen(), r.gen()];
+        let total_orientation = CornerOrientation::total(&corner_orientations);
+        corner_orientations.push(total_orientation.flip());
+
+        let corner_orientations = CornersOrientationState {
+            ful: corner_orientations[0],
+            fur: corner_orientations[1],
+            fdl: corner_orientations[2],
+            fdr: corner_orientations[3],
+            bul: corner_orientations[4],
+            bur: corner_orientations[5],
+            bdl: corner_orientations[6],
+            bdr: corner_orientations[7],
+        };
+
+        // the centers split into the same four orbits as on the curvy copter. Every turn swaps
+        // two pairs of centers in two different orbits, so the only constraint is that the
+        // orbits' parities add up to even; the first three are free and the last one makes up
+        // the difference
+
+        // Orbit 1 -- U_FL, F_UR, R_DF, D_BR, B_DL, L_UB
+        let (centers, parity_1) = shuffle_any(
+            r,
+            [
+                CenterCubelet::U,
+                CenterCubelet::F,
+                CenterCubelet::R,
+                CenterCubelet::D,
+                CenterCubelet::B,
+                CenterCubelet::L,
+            ],
+        );
+        let [u_fl, f_ur, r_df, d_br, b_dl, l_ub] = take_six(centers);
+
+        // Orbit 2 -- U_FR, R_UB, B_DR, D_BL, L_DF, F_UL
+        let (centers, parity_2) = shuffle_any(
+            r,
+            [
+                CenterCubelet::U,
+                CenterCubelet::R,
+                CenterCubelet::B,
+                CenterCubelet::D,
+                CenterCubelet::L,
+                CenterCubelet::F,
+            ],
+        );
+        let [u_fr, r_ub, b_dr, d_bl, l_df, f_ul] = take_six(centers);
+
+        // Orbit 3 -- U_BL, L_UF, F_DL, D_FR, R_DB, B_UR
+        let (centers, parity_3) = shuffle_any(
+            r,
+            [
+                CenterCubelet::U,
+                CenterCubelet::L,
+                CenterCubelet::F,
+                CenterCubelet::D,
+                CenterCubelet::R,
+                CenterCubelet::B,
+            ],
+        );
+        let [u_bl, l_uf, f_dl, d_fr, r_db, b_ur] = take_six(centers);
+
+        // Orbit 4 -- U_BR, R_UF, F_DR, D_FL, L_DB, B_UL
+        let num_odd = [parity_1, parity_2, parity_3]
+            .into_iter()
+            .filter(|p| *p == TwoParity::Odd)
+            .count();
+        let parity_4 = if num_odd % 2 == 0 {
+            TwoParity::Even
+        } else {
+            TwoParity::Odd
+        };
+        let centers = shuffle_with_parity(
+            r,
+            &[
+                CenterCubelet::U,
+                CenterCubelet::R,
+                CenterCubelet::F,
+                CenterCubelet::D,
+                CenterCubelet::L,
+                CenterCubelet::B,
+            ],
+            parity_4,
+        );
+        let [u_br, r_uf, f_dr, d_fl, l_db, b_ul] = take_six(centers);
+
+        let centers = CenterStates {
+            // orbit 1
+            u_fl,
+            f_ur,
+            r_df,
+            d_br,
+            b_dl,
+            l_ub,
+
+            // orbit 2
+            u_fr,
+            r_ub,
+            b_dr,
+            d_bl,
+            l_df,
+            f_ul,
+
+            // orbit 3
+            u_bl,
+            l_uf,
+            f_dl,
+            d_fr,
+            r_db,
+            b_ur,
+
+            // orbit 4
+            u_br,
+            r_uf,
+            f_dr,
+            d_fl,
+            l_db,
+            b_ul,
+        };
+
+        HelicopterCube {
+            centers,
+            corner_positions,
+            corner_orientations,
+        }
+    }
+}
+
+impl SimpleStartState for HelicopterCube {
+    type UniqueKey = PackedBits;
+
+    fn start() -> Self {
+        Self::solved()
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        // center state needs 63 bits
+        let mut center_bits: u64 = 0;
+
+        self.centers.pack(&mut center_bits);
+
+        // corners fit in 40 bits
+        let mut corner_bits: u64 = 0;
+
+        self.corner_positions.pack(&mut corner_bits); // 24 bits
+        self.corner_orientations.pack(&mut corner_bits); // 16 bits
+
+        (center_bits, corner_bits)
+    }
+}
+
+impl Solvable for HelicopterCube {
+    type Move = Move;
+
+    fn max_fuel() -> usize {
+        // a guess, like the curvy copter's; scrambles which need more can raise the cap with
+        // --max-fuel
+        24
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        match m {
+            Move::UF => self.uf(),
+            Move::UL => self.ul(),
+            Move::UR => self.ur(),
+            Move::UB => self.ub(),
+            Move::DF => self.df(),
+            Move::DL => self.dl(),
+            Move::DR => self.dr(),
+            Move::DB => self.db(),
+            Move::FL => self.fl(),
+            Move::FR => self.fr(),
+            Move::BL => self.bl(),
+            Move::BR => self.br(),
+        }
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_solved(&self) -> bool {
+        self == &Self::solved()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        // the edges sit in the same places as on the curvy copter and touch the same corners, so
+        // exactly the same pairs commute; borrow its ordering rather than keeping a second copy
+        CurvyCopter::is_redundant(last_move.into(), next_move.into())
+    }
+}
+
+pub fn make_heuristic() -> impl Heuristic<HelicopterCube> {
+    // max depth is picked to keep the compute time low
+    bounded_cache::<HelicopterCube>(9)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::solve;
+    use crate::legality::permutation_parity;
+    use crate::scrambles::apply_moves;
+    use crate::test_helpers::{assert_commuting_pairs_cut, random_walk_states};
+
+    /// The four center orbits, in the order `random_state` lists them
+    fn orbits(c: &CenterStates) -> [[CenterCubelet; 6]; 4] {
+        [
+            [c.u_fl, c.f_ur, c.r_df, c.d_br, c.b_dl, c.l_ub],
+            [c.u_fr, c.r_ub, c.b_dr, c.d_bl, c.l_df, c.f_ul],
+            [c.u_bl, c.l_uf, c.f_dl, c.d_fr, c.r_db, c.b_ur],
+            [c.u_br, c.r_uf, c.f_dr, c.d_fl, c.l_db, c.b_ul],
+        ]
+    }
+
+    /// Parity of each center orbit; panics if any orbit is missing a color
+    fn orbit_parities(state: &HelicopterCube) -> [TwoParity; 4] {
+        let solved = orbits(&CenterStates::solved());
+        let actual = orbits(&state.centers);
+
+        [0, 1, 2, 3].map(|i| permutation_parity(&solved[i], &actual[i]).unwrap())
+    }
+
+    fn num_odd(parities: [TwoParity; 4]) -> usize {
+        parities.into_iter().filter(|p| *p == TwoParity::Odd).count()
+    }
+
+    #[test]
+    fn turns_swap_and_twist_corners() {
+        let turned = HelicopterCube::solved().apply(Move::UF);
+        assert_eq!(turned.corner_positions.ful, CornerCubelet::FUR);
+        assert_eq!(turned.corner_positions.fur, CornerCubelet::FUL);
+        assert_ne!(turned.corner_orientations.ful, CornerOrientation::Normal);
+        assert_ne!(turned.corner_orientations.fur, CornerOrientation::Normal);
+
+        // mid layer turns still swap, but don't twist
+        let turned = HelicopterCube::solved().apply(Move::FR);
+        assert_eq!(turned.corner_positions.fur, CornerCubelet::FDR);
+        assert_eq!(turned.corner_positions.fdr, CornerCubelet::FUR);
+        assert_eq!(turned.corner_orientations, CornersOrientationState::solved());
+    }
+
+    /// The borrowed ordering is only right if the same pairs of edges commute here as on the curvy
+    /// copter; checks that, and that it cuts every one of them
+    #[test]
+    fn commutes_like_the_curvy_copter() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let states: Vec<HelicopterCube> = (0..10).map(|_| HelicopterCube::random_state(&mut rng)).collect();
+        let copter_states: Vec<CurvyCopter> = (0..10).map(|_| CurvyCopter::random_state(&mut rng)).collect();
+
+        let commuting = assert_commuting_pairs_cut(&states, &HelicopterCube::all_moves());
+
+        let copter_moves: Vec<curvy_copter::Move> = HelicopterCube::all_moves().into_iter().map(Into::into).collect();
+        let copter_commuting = assert_commuting_pairs_cut(&copter_states, &copter_moves);
+
+        let commuting: Vec<(curvy_copter::Move, curvy_copter::Move)> =
+            commuting.into_iter().map(|(a, b)| (a.into(), b.into())).collect();
+        assert_eq!(commuting, copter_commuting);
+    }
+
+    #[test]
+    fn orbit_parities_sum_to_even() {
+        let mut rng = StdRng::from_seed([16; 32]);
+
+        for state in random_walk_states::<HelicopterCube, _>(&mut rng, 30) {
+            assert_eq!(num_odd(orbit_parities(&state)) % 2, 0);
+        }
+
+        let mut saw_odd = false;
+        for _ in 0..100 {
+            let state = HelicopterCube::random_state(&mut rng);
+            let parities = orbit_parities(&state);
+            assert_eq!(num_odd(parities) % 2, 0);
+            saw_odd |= num_odd(parities) > 0;
+
+            let twists = [
+                state.corner_orientations.ful,
+                state.corner_orientations.fur,
+                state.corner_orientations.fdl,
+                state.corner_orientations.fdr,
+                state.corner_orientations.bul,
+                state.corner_orientations.bur,
+                state.corner_orientations.bdl,
+                state.corner_orientations.bdr,
+            ];
+            assert_eq!(CornerOrientation::total(&twists), CornerOrientation::Normal);
+        }
+        assert!(saw_odd, "Orbits can be oddly permuted, in pairs");
+    }
+
+    #[test]
+    fn short_scrambles_are_solved() {
+        let mut rng = StdRng::from_seed([17; 32]);
+        let heuristic = bounded_cache::<HelicopterCube>(3);
+
+        let state = random_walk_states::<HelicopterCube, _>(&mut rng, 6).pop().unwrap();
+        let solution = solve(&state, &heuristic).unwrap();
+        assert!(solution.len() <= 6, "The walk itself is a solution of 6 moves");
+        assert!(apply_moves(&state, &solution).is_solved());
+    }
+}
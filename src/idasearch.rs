@@ -1,6 +1,12 @@
 //! Helper functionality for IDA* search.
 
-use crate::idasearch::SolveError::OutOfGas;
+use std::cell::{Cell, RefCell};
+
+use ahash::HashMap;
+use rayon::prelude::*;
+
+use crate::cubesearch::{HasSolvedState, State};
+use crate::idasearch::SolveError::{BadSolution, OutOfGas};
 
 /// Estimator of the remaining cost. This must never OVER estimate (that is, if it says 10,
 /// there really needs to not be a solution of size 9, or the algorithm will generate wrong answers)
@@ -39,6 +45,10 @@ pub trait Solvable: Sized + Clone {
     // plugin yet
     fn available_moves(&self) -> impl IntoIterator<Item = Self::Move>;
 
+    /// Every move the puzzle has, whatever state it's in; `available_moves` only ever yields moves
+    /// from this list. Parsers and UIs want the whole vocabulary, not just what's legal right now.
+    fn all_moves() -> Vec<Self::Move>;
+
     /// Performance optimization; the implementor may describe which moves are redundant with
     /// each other; that is, if a particular move could follow another move in an optimal solution.
     /// If the moves are redundant but not filtered out by this function, the algorithm
@@ -64,72 +74,577 @@ pub trait Solvable: Sized + Clone {
     fn max_fuel() -> usize;
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, derive_more::Display)]
 pub enum SolveError {
+    /// There's no solution of at most `max_fuel` moves; either the state is deeper than the limit
+    /// allows for, or it can't be solved at all (an illegal hand-built state, say)
+    #[display(fmt = "No solution of at most {} moves (the search depth limit)", max_fuel)]
     OutOfGas { max_fuel: usize },
+    /// Replaying the solution found by the search did not work; the move at `step` was not
+    /// available from the state it was applied to, or (if `step` is the solution length) the
+    /// replay did not end up solved. Means `apply` and `available_moves` are inconsistent.
+    #[display(
+        fmt = "The solution found did not replay correctly at step {}; the puzzle's moves are inconsistent",
+        step
+    )]
+    BadSolution { step: usize },
 }
 
+impl std::error::Error for SolveError {}
+
 pub fn solve<S: Solvable, H: Heuristic<S>>(state: &S, heuristic: &H) -> Result<Vec<<S as Solvable>::Move>, SolveError> {
+    solve_with_fuel(state, heuristic, S::max_fuel())
+}
+
+/// Same as solve, but with the given search depth limit in place of the puzzle's own
+/// [Solvable::max_fuel]; for scrambles which turn out to be deeper than the puzzle expected.
+pub fn solve_with_fuel<S: Solvable, H: Heuristic<S>>(
+    state: &S,
+    heuristic: &H,
+    max_fuel: usize,
+) -> Result<Vec<<S as Solvable>::Move>, SolveError> {
+    solve_predicate(state, heuristic, S::is_solved, max_fuel)
+}
+
+/// Same as [solve_with_fuel], but the goal is `target` (compared by key) rather than the solved
+/// state, so the result takes `state` to `target`. The heuristic has to estimate the distance to
+/// `target`, not to solved.
+pub fn solve_to<S: Solvable + State, H: Heuristic<S>>(
+    state: &S,
+    target: &S,
+    heuristic: &H,
+    max_fuel: usize,
+) -> Result<Vec<<S as Solvable>::Move>, SolveError> {
+    let target_key = State::uniq_key(target);
+    let is_target = |s: &S| State::uniq_key(s) == target_key;
+
+    solve_predicate(state, heuristic, is_target, max_fuel)
+}
+
+/// Same as [solve_with_fuel], but the goal is any state satisfying `goal`, rather than solved;
+/// for partial solves, like hints which only finish the first layer. The heuristic has to be
+/// admissible for `goal` ([no_heuristic] always is).
+pub fn solve_predicate<S: Solvable, H: Heuristic<S>, G: Fn(&S) -> bool>(
+    state: &S,
+    heuristic: &H,
+    goal: G,
+    max_len: usize,
+) -> Result<Vec<<S as Solvable>::Move>, SolveError> {
+    search_for_goal::<MovesOnly, _, _, _, _>(state, heuristic, goal, &|_| true, max_len)
+        .ok_or(OutOfGas { max_fuel: max_len })
+}
+
+/// Same as [solve], but each move comes with the state it leaves the puzzle in, so the last one is
+/// solved; for showing the solution a step at a time, without replaying it.
+pub fn solve_with_path<S: Solvable, H: Heuristic<S>>(
+    state: &S,
+    heuristic: &H,
+) -> Result<Vec<(<S as Solvable>::Move, S)>, SolveError> {
     let max_fuel = S::max_fuel();
 
-    #[derive(Eq, PartialEq, Copy, Clone, Debug)]
-    enum SearchResult {
-        Found,
-        NotFound,
+    search_for_goal::<WithStates, _, _, _, _>(state, heuristic, S::is_solved, &|_| true, max_fuel)
+        .ok_or(OutOfGas { max_fuel })
+}
+
+/// Finds the shortest move sequence which, applied from the solved state, gives a state with the
+/// desired `effect`; for instance "these three edges are cycled, and everything else is solved".
+/// Ties go to whichever comes first in move order. None if nothing of at most `max_len` moves
+/// does it.
+///
+/// There's no heuristic for an arbitrary goal, so this is a plain iterative deepening search, and
+/// only practical for fairly short sequences.
+pub fn find_algorithm<S, F>(effect: F, max_len: usize) -> Option<Vec<<S as Solvable>::Move>>
+where
+    S: Solvable + HasSolvedState,
+    F: Fn(&S) -> bool,
+{
+    search_for_goal::<MovesOnly, _, _, _, _>(&S::solved(), &no_heuristic, effect, &|_| true, max_len)
+}
+
+/// One stage of [solve_phased]: get to a state satisfying `goal`, using only the `allowed` moves.
+/// The heuristic has to be admissible for that goal, with those moves.
+#[cfg(test)]
+pub struct Phase<'a, S: Solvable> {
+    pub goal: &'a dyn Fn(&S) -> bool,
+    pub allowed: &'a dyn Fn(S::Move) -> bool,
+    pub heuristic: &'a dyn Heuristic<S>,
+}
+
+/// Solves in stages, each one optimal from wherever the last one left off; the usual reduction
+/// approach, where the early phases get into smaller and smaller subgroups and the last one
+/// solves. The result, one move list per phase, is usually longer than an optimal solution, but
+/// can be found much faster. The fuel limit applies to each phase separately.
+// nothing here is too big to solve optimally yet, so only the tests use this
+#[cfg(test)]
+pub fn solve_phased<S: Solvable>(
+    state: &S,
+    phases: &[Phase<S>],
+    max_fuel: usize,
+) -> Result<Vec<Vec<<S as Solvable>::Move>>, SolveError> {
+    let mut state = state.clone();
+    let mut out = Vec::with_capacity(phases.len());
+
+    for phase in phases {
+        let heuristic = |s: &S| phase.heuristic.estimated_remaining_cost(s);
+        let moves = search_for_goal::<MovesOnly, _, _, _, _>(&state, &heuristic, phase.goal, phase.allowed, max_fuel)
+            .ok_or(OutOfGas { max_fuel })?;
+
+        // the next phase starts where this one ends; replaying is cheap next to the search
+        state = moves.iter().fold(state, |s, &m| s.apply(m));
+        out.push(moves);
     }
 
-    fn dfs<M: Copy, S: Solvable<Move = M>, H: Heuristic<S>>(
-        state: &S,
-        heuristic: &H,
-        moves_so_far: &mut Vec<M>,
-        rem_fuel: usize,
-    ) -> SearchResult {
-        if state.is_solved() {
-            return SearchResult::Found;
-        }
+    Ok(out)
+}
 
-        let last_move = moves_so_far.last().copied();
+/// What the search records for each move of the path it builds. Plain solves only need the moves;
+/// [solve_with_path] wants the states too, but cloning one at every node isn't free, so only it
+/// pays for that.
+trait PathRecord<S: Solvable> {
+    type Step;
 
-        for m in state.available_moves() {
-            // Note -- we don't need this in the config-depth algorithm because that
-            // one has a HashSet that automatically deduplicates states.
-            if last_move.is_some() && S::is_redundant(last_move.unwrap(), m) {
-                continue;
-            }
+    fn step(m: S::Move, next: &S) -> Self::Step;
 
-            let next = state.apply(m);
+    fn move_of(step: &Self::Step) -> S::Move;
+}
 
-            let min_cost = heuristic.estimated_remaining_cost(&next) + 1;
+/// Just the moves
+struct MovesOnly;
 
-            if min_cost > rem_fuel {
-                continue;
-            }
+impl<S: Solvable> PathRecord<S> for MovesOnly {
+    type Step = S::Move;
 
-            moves_so_far.push(m);
+    #[inline(always)]
+    fn step(m: S::Move, _next: &S) -> Self::Step {
+        m
+    }
 
-            let sr_child = dfs(&next, heuristic, moves_so_far, rem_fuel - 1);
-            if sr_child == SearchResult::Found {
-                return sr_child;
-            }
+    #[inline(always)]
+    fn move_of(step: &Self::Step) -> S::Move {
+        *step
+    }
+}
 
-            moves_so_far.pop();
-        }
+/// Each move, with the state it leads to
+struct WithStates;
+
+impl<S: Solvable> PathRecord<S> for WithStates {
+    type Step = (S::Move, S);
+
+    #[inline(always)]
+    fn step(m: S::Move, next: &S) -> Self::Step {
+        (m, next.clone())
+    }
 
-        SearchResult::NotFound
+    #[inline(always)]
+    fn move_of(step: &Self::Step) -> S::Move {
+        step.0
     }
+}
 
+/// IDA* toward any state satisfying `goal`, only using moves which are `allowed`. The heuristic has
+/// to be admissible for that goal, with those moves. Each move is recorded the way `R` says.
+fn search_for_goal<R, S, H, G, A>(
+    state: &S,
+    heuristic: &H,
+    goal: G,
+    allowed: &A,
+    max_fuel: usize,
+) -> Option<Vec<R::Step>>
+where
+    R: PathRecord<S>,
+    S: Solvable,
+    H: Heuristic<S>,
+    G: Fn(&S) -> bool,
+    A: Fn(S::Move) -> bool + ?Sized,
+{
     for fuel in 0..=max_fuel {
         // let iter_start = Instant::now();
         let mut solution = Vec::new();
 
-        let sr = dfs(state, heuristic, &mut solution, fuel);
+        let sr = dfs::<R, _, _, _, _>(state, heuristic, &goal, allowed, &mut solution, fuel);
 
         if sr == SearchResult::Found {
-            return Ok(solution);
+            return Some(solution);
         }
 
         // println!("With fuel {fuel}, failed to find a solution in {:?}", iter_start.elapsed());
     }
 
+    None
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum SearchResult {
+    Found,
+    NotFound,
+}
+
+/// One bounded depth-first pass of IDA*, extending `path` (which ends at `state`) by at most
+/// `rem_fuel` moves. On success, `path` is the whole way to the goal.
+fn dfs<R, S, H, G, A>(
+    state: &S,
+    heuristic: &H,
+    goal: &G,
+    allowed: &A,
+    path: &mut Vec<R::Step>,
+    rem_fuel: usize,
+) -> SearchResult
+where
+    R: PathRecord<S>,
+    S: Solvable,
+    H: Heuristic<S>,
+    G: Fn(&S) -> bool,
+    A: Fn(S::Move) -> bool + ?Sized,
+{
+    if goal(state) {
+        return SearchResult::Found;
+    }
+
+    let last_move = path.last().map(R::move_of);
+
+    for m in state.available_moves() {
+        // Note -- we don't need this in the config-depth algorithm because that
+        // one has a HashSet that automatically deduplicates states.
+        if last_move.is_some() && S::is_redundant(last_move.unwrap(), m) {
+            continue;
+        }
+
+        if !allowed(m) {
+            continue;
+        }
+
+        let next = state.apply(m);
+
+        let min_cost = heuristic.estimated_remaining_cost(&next) + 1;
+
+        if min_cost > rem_fuel {
+            continue;
+        }
+
+        path.push(R::step(m, &next));
+
+        let sr_child = dfs::<R, _, _, _, _>(&next, heuristic, goal, allowed, path, rem_fuel - 1);
+        if sr_child == SearchResult::Found {
+            return sr_child;
+        }
+
+        path.pop();
+    }
+
+    SearchResult::NotFound
+}
+
+/// Same as [solve], but each first move's subtree is searched on its own rayon task. Every subtree
+/// is done with one depth before any is tried deeper, so the solution is still optimal; and of the
+/// ones found at that depth, the one under the earliest first move wins, so it's the same solution
+/// [solve] gives. Only worth it for single hard states; for many states, solving them in parallel
+/// (like [bulk_solve](crate::scrambles::bulk_solve)) is better.
+pub fn solve_parallel<S, H>(state: &S, heuristic: &H) -> Result<Vec<<S as Solvable>::Move>, SolveError>
+where
+    S: Solvable + Send + Sync,
+    S::Move: Send + Sync,
+    H: Heuristic<S> + Sync,
+{
+    let max_fuel = S::max_fuel();
+
+    if state.is_solved() {
+        return Ok(Vec::new());
+    }
+
+    let roots: Vec<(S::Move, S)> = state
+        .available_moves()
+        .into_iter()
+        .map(|m| (m, state.apply(m)))
+        .collect();
+
+    for fuel in 1..=max_fuel {
+        let found = roots.par_iter().find_map_first(|(m, next)| {
+            if heuristic.estimated_remaining_cost(next) + 1 > fuel {
+                return None;
+            }
+
+            let mut path = vec![*m];
+            let sr = dfs::<MovesOnly, _, _, _, _>(next, heuristic, &S::is_solved, &|_| true, &mut path, fuel - 1);
+
+            (sr == SearchResult::Found).then_some(path)
+        });
+
+        if let Some(path) = found {
+            return Ok(path);
+        }
+    }
+
     Err(OutOfGas { max_fuel })
 }
+
+/// Same as solve, but replays the solution from the start state afterward, checking each move is
+/// actually available when it's applied and that the result is solved. The replay is cheap next to
+/// the search; it's meant for debug builds, to catch bugs in new puzzles at the point of use.
+pub fn solve_verified<S: Solvable, H: Heuristic<S>>(
+    state: &S,
+    heuristic: &H,
+    max_fuel: usize,
+) -> Result<Vec<<S as Solvable>::Move>, SolveError> {
+    let solution = solve_with_fuel(state, heuristic, max_fuel)?;
+
+    let mut replayed = state.clone();
+    for (step, &m) in solution.iter().enumerate() {
+        if !replayed.available_moves().into_iter().any(|available| available == m) {
+            return Err(BadSolution { step });
+        }
+        replayed = replayed.apply(m);
+    }
+
+    if !replayed.is_solved() {
+        return Err(BadSolution { step: solution.len() });
+    }
+
+    Ok(solution)
+}
+
+/// Remembers the solutions to recently solved states, by key, so solving one of them again is
+/// instant; for sessions (the REPL, solve-stream) where the same state can come up more than once.
+/// Holds at most `capacity` solutions, forgetting the least recently used first. Failures aren't
+/// remembered.
+///
+/// The cache doesn't know how a solution was found, so everything solved through one cache should
+/// be solved the same way (same goal, same fuel limit).
+pub struct SolveCache<S: Solvable + State> {
+    capacity: usize,
+    entries: RefCell<HashMap<S::UniqueKey, CachedSolution<S::Move>>>,
+    clock: Cell<u64>,
+    hits: Cell<usize>,
+}
+
+struct CachedSolution<M> {
+    solution: Vec<M>,
+    last_used: u64,
+}
+
+impl<S: Solvable + State> SolveCache<S> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RefCell::default(),
+            clock: Cell::new(0),
+            hits: Cell::new(0),
+        }
+    }
+
+    /// How many solves have been answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    /// The remembered solution for `state`, if there is one; otherwise whatever `solve` finds,
+    /// which is then remembered
+    pub fn solve<F>(&self, state: &S, solve: F) -> Result<Vec<S::Move>, SolveError>
+    where
+        F: FnOnce(&S) -> Result<Vec<S::Move>, SolveError>,
+    {
+        let now = self.clock.get();
+        self.clock.set(now + 1);
+
+        let key = State::uniq_key(state);
+
+        if let Some(cached) = self.entries.borrow_mut().get_mut(&key) {
+            cached.last_used = now;
+            self.hits.set(self.hits.get() + 1);
+            return Ok(cached.solution.clone());
+        }
+
+        let solution = solve(state)?;
+
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() >= self.capacity {
+            if let Some(oldest) = entries.values().map(|cached| cached.last_used).min() {
+                entries.retain(|_, cached| cached.last_used != oldest);
+            }
+        }
+        if self.capacity > 0 {
+            entries.insert(
+                key,
+                CachedSolution {
+                    solution: solution.clone(),
+                    last_used: now,
+                },
+            );
+        }
+
+        Ok(solution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Four positions in a cycle, turned one step at a time. Every `apply` is counted, and once the
+    /// count passes `good_applies` the move table is "corrupted" and every turn does nothing, which
+    /// simulates an `apply` that disagrees with itself.
+    #[derive(Clone)]
+    struct Dial {
+        pos: u8,
+        applies: Rc<Cell<usize>>,
+        good_applies: usize,
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+    enum Turn {
+        Up,
+        Down,
+    }
+
+    impl Solvable for Dial {
+        type Move = Turn;
+
+        fn is_solved(&self) -> bool {
+            self.pos == 0
+        }
+
+        fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+            [Turn::Up, Turn::Down]
+        }
+
+        fn all_moves() -> Vec<Self::Move> {
+            vec![Turn::Up, Turn::Down]
+        }
+
+        fn is_redundant(_last_move: Self::Move, _next_move: Self::Move) -> bool {
+            false
+        }
+
+        fn apply(&self, m: Self::Move) -> Self {
+            self.applies.set(self.applies.get() + 1);
+
+            let step = match m {
+                _ if self.applies.get() > self.good_applies => 0,
+                Turn::Up => 1,
+                Turn::Down => 3,
+            };
+
+            Self {
+                pos: (self.pos + step) % 4,
+                ..self.clone()
+            }
+        }
+
+        fn max_fuel() -> usize {
+            4
+        }
+    }
+
+    fn dial(good_applies: usize) -> Dial {
+        Dial {
+            pos: 2,
+            applies: Rc::new(Cell::new(0)),
+            good_applies,
+        }
+    }
+
+    #[test]
+    fn verified_solve_matches_solve() {
+        let expected = solve(&dial(usize::MAX), &no_heuristic).unwrap();
+        let actual = solve_verified(&dial(usize::MAX), &no_heuristic, Dial::max_fuel()).unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual, vec![Turn::Up, Turn::Up]);
+    }
+
+    #[test]
+    fn verified_solve_catches_broken_moves() {
+        // count how many applies the search needs, so the table breaks right after the search
+        let counting = dial(usize::MAX);
+        solve(&counting, &no_heuristic).unwrap();
+        let search_applies = counting.applies.get();
+
+        let result = solve_verified(&dial(search_applies), &no_heuristic, Dial::max_fuel());
+
+        assert!(
+            matches!(result, Err(BadSolution { step: 2 })),
+            "Replay should not be solved after the table breaks; got {result:?}"
+        );
+    }
+
+    #[test]
+    fn errors_display_clearly() {
+        let message = OutOfGas { max_fuel: 30 }.to_string();
+        assert!(message.contains("30"), "{message}");
+        assert!(message.contains("No solution"), "{message}");
+
+        let message = BadSolution { step: 4 }.to_string();
+        assert!(message.contains("step 4"), "{message}");
+    }
+
+    #[test]
+    fn cache_forgets_the_least_recently_used() {
+        use crate::pocket_cube::PocketCube;
+        use crate::scrambles::{apply_moves, parse_scramble};
+
+        let state =
+            |scramble: &str| apply_moves(&PocketCube::solved(), &parse_scramble::<PocketCube>(scramble).unwrap());
+        let (a, b) = (state("R U"), state("F R"));
+
+        let cache = SolveCache::new(1);
+        let solve = |s: &PocketCube| super::solve(s, &no_heuristic);
+
+        cache.solve(&a, solve).unwrap();
+        cache.solve(&b, solve).unwrap();
+        cache.solve(&a, solve).unwrap();
+        assert_eq!(
+            cache.hits(),
+            0,
+            "There's only room for one, so b should have pushed a out"
+        );
+
+        assert_eq!(cache.solve(&a, solve), solve(&a));
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn path_replays_to_solved() {
+        use crate::idasearch::heuristic_helpers::bounded_cache;
+        use crate::pocket_cube::{Move, PocketCube};
+        use crate::scrambles::{apply_moves, parse_scramble};
+
+        let scramble = parse_scramble::<PocketCube>("R U F2 R' U2 F").unwrap();
+        let state = apply_moves(&PocketCube::solved(), &scramble);
+        let heuristic = bounded_cache::<PocketCube>(4);
+
+        let path = solve_with_path(&state, &heuristic).unwrap();
+        let moves: Vec<Move> = path.iter().map(|&(m, _)| m).collect();
+        assert_eq!(moves, solve(&state, &heuristic).unwrap());
+
+        let mut previous = state;
+        for (m, after) in &path {
+            assert_eq!(after, &previous.apply(*m), "State after {m}");
+            previous = *after;
+        }
+        assert!(previous.is_solved());
+    }
+
+    #[test]
+    fn parallel_finds_the_same_solution() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        use crate::idasearch::heuristic_helpers::bounded_cache;
+        use crate::ivy_cube::IvyCube;
+        use crate::scrambles::RandomInit;
+
+        // a weak heuristic, so there's real searching to split up
+        let heuristic = bounded_cache::<IvyCube>(3);
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        assert_eq!(solve_parallel(&IvyCube::solved(), &heuristic), Ok(vec![]));
+
+        for _ in 0..50 {
+            let state = IvyCube::random_state(&mut rng);
+            assert_eq!(solve_parallel(&state, &heuristic), solve(&state, &heuristic));
+        }
+    }
+}
@@ -1,8 +1,18 @@
+use std::any::TypeId;
+use std::fs::{self, File};
 use std::hash::Hash;
+use std::io::{self, BufWriter};
+use std::marker::PhantomData;
+use std::path::Path;
+#[cfg(feature = "hit_rate")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 
-use ahash::{HashMap, HashSet};
+use ahash::{HashMap, HashSet, RandomState};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
-use crate::cubesearch::State;
+use crate::cubesearch::{FromIndex, IndexedState, State};
 use crate::idasearch::Heuristic;
 
 pub struct BoundedStateCache<H: Hash + Eq> {
@@ -20,6 +30,12 @@ impl<H: Hash + Eq> BoundedStateCache<H> {
     pub fn remaining_cost_if_known<S: State<UniqueKey = H>>(&self, t: &S) -> Option<usize> {
         self.stored.get(&t.uniq_key()).copied()
     }
+
+    /// Every stored key, with its exact distance
+    #[cfg(test)]
+    pub fn entries(&self) -> impl Iterator<Item = (&H, usize)> {
+        self.stored.iter().map(|(key, &dist)| (key, dist))
+    }
 }
 
 impl<H: Hash + Eq, S: State<UniqueKey = H>> Heuristic<S> for BoundedStateCache<H> {
@@ -28,16 +44,318 @@ impl<H: Hash + Eq, S: State<UniqueKey = H>> Heuristic<S> for BoundedStateCache<H
     }
 }
 
+/// Combines a bounded cache with a cheap (but admissible) lower bound. Known states use the cached
+/// (exact) distance; anything else gets the larger of the cache's fallback depth and the lower
+/// bound.
+pub struct CachedPlusLowerBound<S: State, H: Heuristic<S>> {
+    cache: BoundedStateCache<S::UniqueKey>,
+    lower_bound: H,
+    #[cfg(feature = "hit_rate")]
+    lower_bound_hits: AtomicUsize,
+    #[cfg(feature = "hit_rate")]
+    lower_bound_misses: AtomicUsize,
+}
+
+impl<S: State, H: Heuristic<S>> CachedPlusLowerBound<S, H> {
+    pub fn new(cache: BoundedStateCache<S::UniqueKey>, lower_bound: H) -> Self {
+        Self {
+            cache,
+            lower_bound,
+            #[cfg(feature = "hit_rate")]
+            lower_bound_hits: Default::default(),
+            #[cfg(feature = "hit_rate")]
+            lower_bound_misses: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "hit_rate")]
+impl<S: State, H: Heuristic<S>> Drop for CachedPlusLowerBound<S, H> {
+    fn drop(&mut self) {
+        let hits = self.lower_bound_hits.load(Ordering::Relaxed);
+        let misses = self.lower_bound_misses.load(Ordering::Relaxed);
+        let pct = (hits as f32) / ((hits + misses) as f32) * 100.0;
+        println!(
+            "Final stats for cached heuristic: {hits} lower bound hits and {misses} lower bound misses ({pct:.2}% hit rate)"
+        )
+    }
+}
+
+impl<S: State, H: Heuristic<S>> Heuristic<S> for CachedPlusLowerBound<S, H> {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        // turns out this hashmap lookup is still the pain point; more efficient packing or lookups
+        // may help performance further
+        if let Some(known_cost) = self.cache.remaining_cost_if_known(t) {
+            return known_cost;
+        }
+
+        let fb = self.cache.fallback_depth();
+        let lower_bound = self.lower_bound.estimated_remaining_cost(t);
+
+        #[cfg(feature = "hit_rate")]
+        {
+            if lower_bound > fb {
+                self.lower_bound_hits.fetch_add(1, Ordering::Relaxed);
+            } else {
+                self.lower_bound_misses.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fb.max(lower_bound)
+    }
+}
+
+/// The larger of two admissible heuristics, which is still admissible, and never worse than either
+pub struct MaxHeuristic<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> MaxHeuristic<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<S, A: Heuristic<S>, B: Heuristic<S>> Heuristic<S> for MaxHeuristic<A, B> {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        let first = self.first.estimated_remaining_cost(t);
+        let second = self.second.estimated_remaining_cost(t);
+        first.max(second)
+    }
+}
+
+/// A smaller puzzle which this one maps onto, such that every move of this puzzle maps to a move
+/// (or to nothing) of the smaller one, and solved maps to solved. So nothing is further from solved
+/// in the projection than it is in the full puzzle, and distances there are admissible here.
+pub trait Project<P: State> {
+    fn project(&self) -> P;
+}
+
+/// Distances in the projected puzzle `P`, looked up by projecting each state. `P` is usually small
+/// enough to cache all of, so this is exact for the projection, and cheap to build.
+pub struct ProjectedCache<P: State> {
+    cache: BoundedStateCache<P::UniqueKey>,
+}
+
+impl<P: Clone + State> ProjectedCache<P> {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            cache: bounded_cache::<P>(max_depth),
+        }
+    }
+}
+
+impl<P: State, S: Project<P>> Heuristic<S> for ProjectedCache<P> {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        self.cache.estimated_remaining_cost(&t.project())
+    }
+}
+
+/// Same as [ProjectedCache], but the projection is cached whole in a [DenseCache]; for projections
+/// with too many states to hash, but few enough to give each a byte
+pub struct ProjectedDenseCache<P> {
+    cache: DenseCache,
+    _projection: PhantomData<P>,
+}
+
+impl<P: FromIndex> ProjectedDenseCache<P> {
+    pub fn new() -> Self {
+        Self {
+            cache: DenseCache::by_scanning::<P>(),
+            _projection: PhantomData,
+        }
+    }
+}
+
+impl<P: IndexedState, S: Project<P>> Heuristic<S> for ProjectedDenseCache<P> {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        self.cache.estimated_remaining_cost(&t.project())
+    }
+}
+
+/// The smallest of several heuristics, each toward a different goal. None of them overestimates
+/// the distance to its own goal, so the smallest never overestimates the distance to the nearest
+/// goal; on its own, a heuristic toward one goal can, which breaks IDA* for puzzles with more than
+/// one solved state.
+pub struct AdmissibleMin<H> {
+    goals: Vec<H>,
+}
+
+impl<H> AdmissibleMin<H> {
+    pub fn new(goals: Vec<H>) -> Self {
+        Self { goals }
+    }
+}
+
+impl<S, H: Heuristic<S>> Heuristic<S> for AdmissibleMin<H> {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        self.goals
+            .iter()
+            .map(|h| h.estimated_remaining_cost(t))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Exact distances to the start state for every reachable state, stored by [IndexedState::index]
+/// rather than hashed. For puzzles small enough to enumerate, this is a perfect heuristic, and a
+/// lookup is just an array read.
+pub struct DenseCache {
+    distances: Vec<u8>,
+}
+
+impl DenseCache {
+    const UNREACHED: u8 = u8::MAX;
+
+    /// BFS over the whole puzzle from the start state
+    pub fn new<S: IndexedState>() -> Self {
+        let mut distances = vec![Self::UNREACHED; S::NUM_INDICES];
+
+        let mut to_process: Vec<S> = vec![S::start()];
+        let mut next_stage: Vec<S> = vec![];
+
+        for depth in 0.. {
+            assert!(depth < Self::UNREACHED, "Distances should fit in a u8");
+
+            for s in to_process.drain(..) {
+                let slot = &mut distances[s.index()];
+                if *slot != Self::UNREACHED {
+                    continue;
+                }
+                *slot = depth;
+
+                s.neighbors(&mut |neighbor| next_stage.push(neighbor));
+            }
+
+            std::mem::swap(&mut to_process, &mut next_stage);
+
+            if to_process.is_empty() {
+                break;
+            }
+        }
+
+        Self::reporting_size::<S>(distances)
+    }
+
+    /// Same as [DenseCache::new], but without a frontier: each depth scans the whole table for the
+    /// states found at the depth before, and rebuilds them with [FromIndex]. That's more work, but
+    /// takes no memory past the table itself, which matters when the middle layers alone are tens
+    /// of millions of states.
+    pub fn by_scanning<S: FromIndex>() -> Self {
+        let mut distances = vec![Self::UNREACHED; S::NUM_INDICES];
+        distances[S::start().index()] = 0;
+
+        for depth in 0.. {
+            assert!(depth + 1 < Self::UNREACHED, "Distances should fit in a u8");
+
+            let mut found_any = false;
+
+            for index in 0..S::NUM_INDICES {
+                if distances[index] != depth {
+                    continue;
+                }
+
+                S::from_index(index).neighbors(&mut |neighbor| {
+                    let slot = &mut distances[neighbor.index()];
+                    if *slot == Self::UNREACHED {
+                        *slot = depth + 1;
+                        found_any = true;
+                    }
+                });
+            }
+
+            if !found_any {
+                break;
+            }
+        }
+
+        Self::reporting_size::<S>(distances)
+    }
+
+    fn reporting_size<S: IndexedState>(distances: Vec<u8>) -> Self {
+        let out = Self { distances };
+        println!(
+            "Filled dense cache with {} of {} possible indices",
+            out.num_reachable(),
+            S::NUM_INDICES
+        );
+        out
+    }
+
+    /// The number of states the BFS reached
+    pub fn num_reachable(&self) -> usize {
+        self.distances.iter().filter(|&&d| d != Self::UNREACHED).count()
+    }
+
+    pub fn distance<S: IndexedState>(&self, t: &S) -> Option<usize> {
+        match self.distances[t.index()] {
+            Self::UNREACHED => None,
+            d => Some(d as usize),
+        }
+    }
+}
+
+impl<S: IndexedState> Heuristic<S> for DenseCache {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        // every reachable state is in there, so a miss shouldn't come up; if it does, zero is
+        // at least admissible
+        self.distance(t).unwrap_or(0)
+    }
+}
+
+/// Puzzles whose edges can be flipped in place, where each move flips a bounded number of them
+pub trait CountsMisoriented {
+    /// The most edges a single move can change the orientation of
+    const MAX_FLIPS_PER_MOVE: usize;
+
+    fn misoriented_edges(&self) -> usize;
+}
+
+/// Every flipped edge has to be flipped back, and each move fixes at most `per_move` of them, so
+/// this many moves are needed at least
+pub fn misoriented_edge_bound(flipped_count: usize, per_move: usize) -> usize {
+    flipped_count.div_ceil(per_move)
+}
+
+/// [misoriented_edge_bound] for a puzzle which knows its own counts, as a heuristic
+pub fn misoriented_edge_heuristic<S: CountsMisoriented>(t: &S) -> usize {
+    misoriented_edge_bound(t.misoriented_edges(), S::MAX_FLIPS_PER_MOVE)
+}
+
 pub fn bounded_cache<S: Clone + State>(max_depth: usize) -> BoundedStateCache<<S as State>::UniqueKey> {
-    let mut out: HashMap<<S as State>::UniqueKey, usize> = HashMap::default();
+    bounded_cache_from(vec![S::start()], max_depth)
+}
+
+/// Same as [bounded_cache], but the BFS starts from all the given states at once, so the stored
+/// value is the distance to the _nearest_ of them. This is the right cache for puzzles which have
+/// more than one solved state.
+pub fn bounded_cache_from<S: Clone + State>(
+    starts: Vec<S>,
+    max_depth: usize,
+) -> BoundedStateCache<<S as State>::UniqueKey> {
+    BoundedStateCache {
+        stored: distances_up_to(starts, max_depth, RandomState::new()),
+        // we got everything of depth up to max_depth; so anything
+        // else has more than that
+        fallback_depth: max_depth + 1,
+    }
+}
+
+/// BFS out from the given states, giving the distance to every state within `max_depth` of them.
+/// The map iterates in an order fixed by `hasher`, which only matters if it's written out.
+fn distances_up_to<S: Clone + State>(
+    starts: Vec<S>,
+    max_depth: usize,
+    hasher: RandomState,
+) -> HashMap<<S as State>::UniqueKey, usize> {
+    let mut out: HashMap<<S as State>::UniqueKey, usize> = HashMap::with_hasher(hasher);
 
     // essentially just do a BFS until we hit the max depth
-    let mut to_process: Vec<S> = vec![];
+    let mut to_process: Vec<S> = starts;
     let mut next_state: Vec<S> = vec![];
     let mut seen: HashSet<<S as State>::UniqueKey> = HashSet::default();
 
-    to_process.push(S::start());
-
     for depth in 0..=max_depth {
         for s in to_process.drain(..) {
             if !seen.insert(s.uniq_key()) {
@@ -63,10 +381,203 @@ pub fn bounded_cache<S: Clone + State>(max_depth: usize) -> BoundedStateCache<<S
         }
     }
 
-    BoundedStateCache {
-        stored: out,
-        // we got everything of depth up to max_depth; so anything
-        // else has more than that
-        fallback_depth: max_depth + 1,
+    out
+}
+
+/// Enumerates the whole puzzle and writes every state's key, along with its exact distance from
+/// the start state, to `path`. That's a complete pattern database; for puzzles small enough to
+/// enumerate, [load_pattern_db] turns it back into a perfect heuristic without redoing the BFS.
+///
+/// The states are written in hash order, which is random unless a `hash_seed` is given; with the
+/// same seed, the file is the same every time.
+///
+/// Returns the number of states at each distance, in the same shape as the enumeration functions.
+pub fn enumerate_and_dump_pattern_db<S>(path: &Path, hash_seed: Option<usize>) -> io::Result<HashMap<u128, u128>>
+where
+    S: Clone + State,
+    S::UniqueKey: Serialize,
+{
+    let hasher = hash_seed.map_or_else(RandomState::new, RandomState::with_seed);
+    let distances = distances_up_to(vec![S::start()], usize::MAX, hasher);
+
+    let mut counts: HashMap<u128, u128> = HashMap::default();
+    for &depth in distances.values() {
+        *counts.entry(depth as u128).or_default() += 1;
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+    postcard::to_io(&distances, file).map_err(io::Error::other)?;
+
+    Ok(counts)
+}
+
+/// Loads a pattern database written by [enumerate_and_dump_pattern_db], as a heuristic giving the
+/// exact distance to solved. The database has every reachable state, so a state which isn't in it
+/// shouldn't come up; if one does, it gets the (useless, but admissible) estimate of zero.
+pub fn load_pattern_db<K>(path: &Path) -> io::Result<BoundedStateCache<K>>
+where
+    K: Hash + Eq + DeserializeOwned,
+{
+    let bytes = fs::read(path)?;
+    let stored: HashMap<K, usize> = postcard::from_bytes(&bytes).map_err(io::Error::other)?;
+
+    Ok(BoundedStateCache {
+        stored,
+        fallback_depth: 0,
+    })
+}
+
+/// All the states at exactly `depth` moves from the start state, by key. Only the frontier (and the
+/// keys seen so far, to keep the search from doubling back) is ever held, so this is much lighter
+/// than enumerating the whole puzzle when the layer is near the start. Past the end of the puzzle,
+/// the layer is empty.
+pub fn states_at_depth<S: Clone + State>(depth: usize) -> Vec<<S as State>::UniqueKey> {
+    states_at_depth_from(vec![S::start()], depth)
+}
+
+/// The smallest depth at which [bounded_cache] holds every reachable state, so its fallback never
+/// comes up and the heuristic is exact. That's the diameter of the puzzle. It takes a full BFS to
+/// find, so the answer is kept around for the next time it's asked for the same puzzle.
+pub fn min_complete_depth<S: Clone + State + 'static>() -> usize {
+    static DEPTHS: OnceLock<Mutex<HashMap<TypeId, usize>>> = OnceLock::new();
+    let depths = DEPTHS.get_or_init(Mutex::default);
+
+    if let Some(&depth) = depths.lock().unwrap().get(&TypeId::of::<S>()) {
+        return depth;
+    }
+
+    let mut seen: HashSet<<S as State>::UniqueKey> = HashSet::default();
+    seen.insert(S::start().uniq_key());
+
+    let mut frontier: Vec<S> = vec![S::start()];
+    let mut depth = 0;
+
+    loop {
+        let mut next: Vec<S> = Vec::new();
+
+        for s in frontier.drain(..) {
+            let mut recv = |neighbor: S| {
+                if seen.insert(neighbor.uniq_key()) {
+                    next.push(neighbor);
+                }
+            };
+
+            s.neighbors(&mut recv);
+        }
+
+        if next.is_empty() {
+            break;
+        }
+
+        frontier = next;
+        depth += 1;
+    }
+
+    depths.lock().unwrap().insert(TypeId::of::<S>(), depth);
+
+    depth
+}
+
+/// Same as [states_at_depth], but measures distance to the _nearest_ of the given states
+pub fn states_at_depth_from<S: Clone + State>(starts: Vec<S>, depth: usize) -> Vec<<S as State>::UniqueKey> {
+    let mut seen: HashSet<<S as State>::UniqueKey> = HashSet::default();
+
+    let mut frontier: Vec<S> = starts.into_iter().filter(|s| seen.insert(s.uniq_key())).collect();
+
+    for _ in 0..depth {
+        let mut next: Vec<S> = Vec::new();
+
+        for s in frontier.drain(..) {
+            let mut recv = |neighbor: S| {
+                if seen.insert(neighbor.uniq_key()) {
+                    next.push(neighbor);
+                }
+            };
+
+            s.neighbors(&mut recv);
+        }
+
+        frontier = next;
+    }
+
+    frontier.iter().map(|s| s.uniq_key()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::cubesearch::enumerate_state_space;
+    use crate::floppy_1x2x2::Floppy1x2x2;
+    use crate::idasearch::solve;
+    use crate::ivy_cube::{self, IvyCube};
+    use crate::test_helpers::random_walk_states;
+
+    #[test]
+    fn complete_depth_is_the_diameter() {
+        let (_, counts) = enumerate_state_space::<Floppy1x2x2>(None).unwrap();
+        assert_eq!(
+            min_complete_depth::<Floppy1x2x2>(),
+            *counts.keys().max().unwrap() as usize
+        );
+        // the second time comes from the stored answer, and should agree
+        assert_eq!(min_complete_depth::<Floppy1x2x2>(), 3);
+
+        let (_, counts) = enumerate_state_space::<IvyCube>(None).unwrap();
+        assert_eq!(min_complete_depth::<IvyCube>(), *counts.keys().max().unwrap() as usize);
+        assert_eq!(min_complete_depth::<IvyCube>(), 8);
+
+        // and a cache that deep knows every state
+        let cache = bounded_cache::<IvyCube>(8);
+        assert_eq!(cache.stored.len() as u128, counts.values().sum::<u128>());
+    }
+
+    #[test]
+    fn layers_match_enumeration() {
+        let (_, counts) = enumerate_state_space::<Floppy1x2x2>(None).unwrap();
+        let diameter = *counts.keys().max().unwrap();
+        assert_eq!(diameter, 3);
+
+        for depth in 0..=diameter {
+            let layer = states_at_depth::<Floppy1x2x2>(depth as usize);
+            assert_eq!(layer.len() as u128, counts[&depth], "Layer size at depth {depth}");
+        }
+
+        // the antipodes are the last layer; there's nothing past them
+        assert!(states_at_depth::<Floppy1x2x2>(diameter as usize + 1).is_empty());
+    }
+
+    #[test]
+    fn seeded_pattern_db_is_reproducible() {
+        let dump = |run: usize| {
+            let path = std::env::temp_dir().join(format!("twisty-seeded-db-{}-{run}.bin", std::process::id()));
+            enumerate_and_dump_pattern_db::<IvyCube>(&path, Some(15)).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            bytes
+        };
+
+        assert!(dump(0) == dump(1), "Same seed should give the same file");
+    }
+
+    #[test]
+    fn reloaded_pattern_db_gives_exact_distances() {
+        let path = std::env::temp_dir().join(format!("twisty-ivy-pattern-db-{}.bin", std::process::id()));
+
+        let counts = enumerate_and_dump_pattern_db::<IvyCube>(&path, None).unwrap();
+        let db: BoundedStateCache<<IvyCube as State>::UniqueKey> = load_pattern_db(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(db.stored.len() as u128, counts.values().sum::<u128>());
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        let solver_heuristic = ivy_cube::make_heuristic();
+
+        for state in random_walk_states::<IvyCube, _>(&mut rng, 100) {
+            let optimal = solve(&state, &solver_heuristic).unwrap().len();
+            assert_eq!(db.estimated_remaining_cost(&state), optimal);
+        }
     }
 }
@@ -1,22 +1,24 @@
-use std::fmt::Formatter;
-
 use derive_more::Display;
+use enum_iterator::Sequence;
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
-use crate::idasearch::heuristic_helpers::bounded_cache;
-use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CornerTwistAmt};
+use crate::corner_turning::CornerTurningPuzzle;
+use crate::cubesearch::{HasSolvedState, IndexedState, SimpleStartState};
+use crate::describe::PrettyState;
+use crate::idasearch::heuristic_helpers::DenseCache;
+use crate::idasearch::Heuristic;
 use crate::orientations::CornerOrientation;
+use crate::permutation_helpers::lehmer_rank;
 use crate::random_helpers::{shuffle_with_parity, TwoParity};
 use crate::scrambles::RandomInit;
 
 // 2 bits per corner * 4 corners plus 3 bits per center * 6 centers is 24 bits
 type PackedBits = u32;
 
+/// Centers are indexed by the face they start on, so this doubles as the list of positions
 #[repr(u8)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-enum CenterCubelet {
+pub enum CenterCubelet {
     F,
     R,
     L,
@@ -33,273 +35,228 @@ impl CenterCubelet {
 }
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-struct CenterState {
-    f: CenterCubelet,
-    u: CenterCubelet,
-    r: CenterCubelet,
-    l: CenterCubelet,
-    d: CenterCubelet,
-    b: CenterCubelet,
+pub struct IvyCube {
+    // indexed by CenterCubelet
+    centers: [CenterCubelet; 6],
+    // indexed by Dir
+    corners: [CornerOrientation; 4],
 }
 
-impl CenterState {
-    #[inline(always)]
-    fn pack(&self, bits: &mut PackedBits) {
-        self.f.pack(bits);
-        self.u.pack(bits);
-        self.l.pack(bits);
-        self.r.pack(bits);
-        self.b.pack(bits);
-        self.d.pack(bits);
-    }
-
-    #[inline(always)]
-    fn ufl(self) -> Self {
+impl HasSolvedState for IvyCube {
+    fn solved() -> Self {
         Self {
-            u: self.l,
-            l: self.f,
-            f: self.u,
-            ..self
+            corners: [CornerOrientation::Normal; 4],
+            centers: [
+                CenterCubelet::F,
+                CenterCubelet::R,
+                CenterCubelet::L,
+                CenterCubelet::U,
+                CenterCubelet::D,
+                CenterCubelet::B,
+            ],
         }
     }
-
-    #[inline(always)]
-    fn dfr(self) -> Self {
-        Self {
-            f: self.d,
-            d: self.r,
-            r: self.f,
-            ..self
-        }
-    }
-
-    #[inline(always)]
-    fn ubr(self) -> Self {
-        Self {
-            u: self.r,
-            r: self.b,
-            b: self.u,
-            ..self
-        }
-    }
-
-    #[inline(always)]
-    fn dbl(self) -> Self {
-        Self {
-            l: self.b,
-            b: self.d,
-            d: self.l,
-            ..self
-        }
-    }
-}
-
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-struct CornerState {
-    ufl: CornerOrientation,
-    dfr: CornerOrientation,
-    ubr: CornerOrientation,
-    dbl: CornerOrientation,
 }
 
-impl CornerState {
-    fn pack(self, bits: &mut PackedBits) {
-        self.ufl.pack_two_bits_u32(bits);
-        self.dfr.pack_two_bits_u32(bits);
-        self.ubr.pack_two_bits_u32(bits);
-        self.dbl.pack_two_bits_u32(bits);
+impl IvyCube {
+    #[inline(always)]
+    fn center(&self, pos: CenterCubelet) -> CenterCubelet {
+        self.centers[pos as usize]
     }
 }
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
-pub struct IvyCube {
-    centers: CenterState,
-    corners: CornerState,
-}
+impl PrettyState for IvyCube {}
 
-impl IvyCube {
-    pub fn solved_state() -> Self {
-        Self {
-            corners: CornerState {
-                ufl: CornerOrientation::Normal,
-                dfr: CornerOrientation::Normal,
-                ubr: CornerOrientation::Normal,
-                dbl: CornerOrientation::Normal,
-            },
-            centers: CenterState {
-                u: CenterCubelet::U,
-                d: CenterCubelet::D,
-                f: CenterCubelet::D,
-                b: CenterCubelet::B,
-                r: CenterCubelet::R,
-                l: CenterCubelet::L,
-            },
-        }
-    }
-
-    #[inline(always)]
-    fn ufl(&self) -> Self {
-        Self {
-            centers: self.centers.ufl(),
-            corners: CornerState {
-                ufl: self.corners.ufl.cw(),
-                ..self.corners
-            },
-        }
-    }
+impl RandomInit for IvyCube {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        use CenterCubelet::*;
 
-    #[inline(always)]
-    fn dfr(&self) -> Self {
-        Self {
-            centers: self.centers.dfr(),
-            corners: CornerState {
-                dfr: self.corners.dfr.cw(),
-                ..self.corners
-            },
-        }
-    }
+        let shuffled = shuffle_with_parity(r, &[U, D, R, L, F, B], TwoParity::Even);
 
-    #[inline(always)]
-    fn ubr(&self) -> Self {
-        Self {
-            centers: self.centers.ubr(),
-            corners: CornerState {
-                ubr: self.corners.ubr.cw(),
-                ..self.corners
-            },
+        let mut centers = [F; 6];
+        for (pos, center) in [U, D, R, L, F, B].into_iter().zip(shuffled) {
+            centers[pos as usize] = center;
         }
-    }
 
-    #[inline(always)]
-    fn dbl(&self) -> Self {
-        Self {
-            centers: self.centers.dbl(),
-            corners: CornerState {
-                dbl: self.corners.dbl.cw(),
-                ..self.corners
-            },
+        let mut corners = [CornerOrientation::Normal; 4];
+        for dir in [Dir::UFL, Dir::UBR, Dir::DBL, Dir::DFR] {
+            corners[dir as usize] = r.gen();
         }
-    }
-}
 
-impl RandomInit for IvyCube {
-    fn random_state<R: Rng>(r: &mut R) -> Self {
-        let centers = [
-            CenterCubelet::U,
-            CenterCubelet::D,
-            CenterCubelet::R,
-            CenterCubelet::L,
-            CenterCubelet::F,
-            CenterCubelet::B,
-        ];
-        let centers = shuffle_with_parity(r, &centers, TwoParity::Even);
-
-        Self {
-            centers: CenterState {
-                u: centers[0],
-                d: centers[1],
-                r: centers[2],
-                l: centers[3],
-                f: centers[4],
-                b: centers[5],
-            },
-            corners: CornerState {
-                ufl: r.gen(),
-                ubr: r.gen(),
-                dbl: r.gen(),
-                dfr: r.gen(),
-            },
-        }
+        Self { centers, corners }
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
-enum Dir {
-    UFL,
+/// The corners which turn; the other four are fixed. The order is the order moves are tried in.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Sequence)]
+pub enum Dir {
     UBR,
-    DBL,
+    UFL,
     DFR,
+    DBL,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-pub struct Move(Dir, CornerTwistAmt);
-
-impl std::fmt::Display for Move {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.0, self.1)
-    }
+const fn cycle(a: CenterCubelet, b: CenterCubelet, c: CenterCubelet) -> [usize; 3] {
+    [a as usize, b as usize, c as usize]
 }
 
-impl CanReverse for Move {
-    fn reverse(&self) -> Self {
-        Move(self.0, self.1.reverse())
-    }
-}
+/// Each corner carries the three centers around it; indexed by Dir
+const CENTER_CYCLES: [[[usize; 3]; 1]; 4] = {
+    use CenterCubelet::*;
 
-impl Solvable for IvyCube {
-    type Move = Move;
+    [
+        [cycle(R, U, B)], // UBR
+        [cycle(L, U, F)], // UFL
+        [cycle(D, F, R)], // DFR
+        [cycle(B, L, D)], // DBL
+    ]
+};
 
-    fn is_solved(&self) -> bool {
-        self == &Self::solved_state()
+impl CornerTurningPuzzle for IvyCube {
+    type Corner = Dir;
+    type Piece = CenterCubelet;
+
+    fn cycles(corner: Dir) -> &'static [[usize; 3]] {
+        &CENTER_CYCLES[corner as usize]
     }
 
-    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
-        [
-            // top layer
-            Move(Dir::UBR, CornerTwistAmt::Cw),
-            Move(Dir::UBR, CornerTwistAmt::Ccw),
-            Move(Dir::UFL, CornerTwistAmt::Cw),
-            Move(Dir::UFL, CornerTwistAmt::Ccw),
-            // bottom front
-            Move(Dir::DFR, CornerTwistAmt::Cw),
-            Move(Dir::DFR, CornerTwistAmt::Ccw),
-            // bottom back
-            Move(Dir::DBL, CornerTwistAmt::Cw),
-            Move(Dir::DBL, CornerTwistAmt::Ccw),
-        ]
+    fn pieces_mut(&mut self) -> &mut [CenterCubelet] {
+        &mut self.centers
     }
 
-    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
-        // none of the twists commute with each other so there's nothing going on here
-        last_move.0 == next_move.0
+    fn corner_orientation_mut(&mut self, corner: Dir) -> Option<&mut CornerOrientation> {
+        Some(&mut self.corners[corner as usize])
     }
 
-    fn apply(&self, m: Self::Move) -> Self {
-        match (m.0, m.1) {
-            (Dir::UBR, CornerTwistAmt::Cw) => self.ubr(),
-            (Dir::UBR, CornerTwistAmt::Ccw) => self.ubr().ubr(),
-            (Dir::UFL, CornerTwistAmt::Cw) => self.ufl(),
-            (Dir::UFL, CornerTwistAmt::Ccw) => self.ufl().ufl(),
-            (Dir::DFR, CornerTwistAmt::Cw) => self.dfr(),
-            (Dir::DFR, CornerTwistAmt::Ccw) => self.dfr().dfr(),
-            (Dir::DBL, CornerTwistAmt::Cw) => self.dbl(),
-            (Dir::DBL, CornerTwistAmt::Ccw) => self.dbl().dbl(),
-        }
+    fn is_solved(&self) -> bool {
+        self == &Self::solved()
     }
 
     fn max_fuel() -> usize {
         11
     }
+
+    // none of the twists commute with each other, so the default (never the same corner twice)
+    // is all there is
 }
 
 impl SimpleStartState for IvyCube {
     type UniqueKey = PackedBits;
 
     fn start() -> Self {
-        Self::solved_state()
+        Self::solved()
     }
 
     fn uniq_key(&self) -> Self::UniqueKey {
+        use CenterCubelet::*;
+
         let mut out: PackedBits = 0;
 
-        self.corners.pack(&mut out);
-        self.centers.pack(&mut out);
+        for dir in [Dir::UFL, Dir::DFR, Dir::UBR, Dir::DBL] {
+            self.corners[dir as usize].pack_two_bits_u32(&mut out);
+        }
+
+        for pos in [F, U, L, R, B, D] {
+            self.center(pos).pack(&mut out);
+        }
 
         out
     }
 }
 
+impl IndexedState for IvyCube {
+    // only the even center permutations are reachable, and they're exactly the ones with an even
+    // Lehmer rank among their partners (the same permutation with the last two swapped), so
+    // halving the rank packs them in with no gaps
+    const NUM_INDICES: usize = 360 * 81;
+
+    fn index(&self) -> usize {
+        let pieces = self.centers.map(|c| c as usize);
+
+        let perm = lehmer_rank(&pieces);
+
+        let corners = self
+            .corners
+            .iter()
+            .fold(0, |acc, orr| acc * 3 + orr.as_u8_two_bits() as usize);
+
+        (perm / 2) * 81 + corners
+    }
+}
+
 pub fn make_heuristic() -> impl Heuristic<IvyCube> {
-    // max depth is picked to keep the compute time low
-    bounded_cache::<IvyCube>(6)
+    // there are few enough states to just store the distance to every one of them
+    DenseCache::new::<IvyCube>()
+}
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<IvyCube, _>(
+            &make_heuristic(),
+            15,
+            &[
+                "UBR' DFR UFL' UBR' DBL' UFL'",
+                "DFR UFL' DFR' UFL UBR",
+                "UBR' UFL DFR DBL' DFR UFL'",
+                "DFR' UBR' UFL UBR UFL DFR UBR'",
+                "DFR UBR DFR' DBL DFR'",
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::{HashMap, HashSet};
+
+    use super::*;
+    use crate::cubesearch::{enumerate_state_space, State};
+    use crate::idasearch::heuristic_helpers::bounded_cache;
+    use crate::idasearch::solve;
+
+    #[test]
+    fn dense_cache_is_exact() {
+        let (_, counts) = enumerate_state_space::<IvyCube>(None).unwrap();
+        let cache = DenseCache::new::<IvyCube>();
+
+        assert_eq!(cache.num_reachable() as u128, counts.values().sum::<u128>());
+
+        // every state, from a BFS independent of the cache; the solver gets a cache too shallow
+        // to cover the whole puzzle, so it has to actually search
+        let solver_heuristic = bounded_cache::<IvyCube>(4);
+        let mut seen = HashSet::default();
+        let mut to_process = vec![IvyCube::solved()];
+
+        while let Some(state) = to_process.pop() {
+            if !seen.insert(state.index()) {
+                continue;
+            }
+
+            let optimal = solve(&state, &solver_heuristic).unwrap().len();
+            assert_eq!(cache.distance(&state), Some(optimal), "Wrong distance for {state:?}");
+
+            state.neighbors(&mut |neighbor| to_process.push(neighbor));
+        }
+
+        assert_eq!(seen.len(), cache.num_reachable());
+    }
+
+    #[test]
+    fn enumeration_counts() {
+        // taken before the move to CornerTurningPuzzle, which shouldn't have changed anything
+        let (_, counts) = enumerate_state_space::<IvyCube>(None).unwrap();
+
+        let expected: HashMap<u128, u128> = [1, 8, 48, 288, 1640, 7582, 15262, 4221, 110]
+            .into_iter()
+            .enumerate()
+            .map(|(depth, count)| (depth as u128, count))
+            .collect();
+
+        assert_eq!(counts, expected);
+    }
 }
@@ -0,0 +1,92 @@
+//! Checks that a puzzle state is actually reachable from the solved state, for states which didn't
+//! come from applying moves (hand-built ones, mostly). The search can't tell the difference, and
+//! will happily spin until it runs out of fuel trying to solve an illegal state.
+
+use std::fmt::Debug;
+
+use crate::random_helpers::TwoParity;
+
+/// Why a state can't be reached from the solved state
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+pub enum IllegalReason {
+    /// The same piece shows up in more than one position (so some other piece is missing)
+    #[display(fmt = "Piece {} appears more than once", _0)]
+    DuplicatePiece(String),
+    /// The pieces of the named kind are in an odd permutation, but the moves are all even
+    #[display(fmt = "The {} are in an odd permutation", _0)]
+    OddPermutation(&'static str),
+    /// The corner twists don't add up to zero
+    #[display(fmt = "The corner twists don't add up to zero")]
+    TwistedCorner,
+    /// Some raw value doesn't name anything on this puzzle (a piece or twist past the end, say)
+    #[display(fmt = "{} is out of range", _0)]
+    OutOfRange(String),
+    /// A piece which the puzzle holds in place has been moved or twisted
+    #[display(fmt = "The {} piece is held in place, and can't be moved or twisted", _0)]
+    FixedPieceMoved(&'static str),
+}
+
+pub trait ValidState {
+    /// Checks the invariants preserved by every move (parity, orientation sums, and so on), and
+    /// returns the first one which fails, if any.
+    fn is_legal(&self) -> Result<(), IllegalReason>;
+}
+
+/// Finds the parity of the permutation taking `solved` to `actual`, checking along the way that
+/// each piece appears exactly once. `solved` is assumed to have no repeats.
+pub fn permutation_parity<T: Eq + Debug>(solved: &[T], actual: &[T]) -> Result<TwoParity, IllegalReason> {
+    assert_eq!(solved.len(), actual.len(), "Should compare the same number of pieces");
+
+    let mut seen = vec![false; solved.len()];
+    let mut indices = Vec::with_capacity(actual.len());
+
+    for piece in actual {
+        let i = solved
+            .iter()
+            .position(|p| p == piece)
+            .expect("Every piece should be one of the solved pieces");
+
+        if seen[i] {
+            return Err(IllegalReason::DuplicatePiece(format!("{piece:?}")));
+        }
+        seen[i] = true;
+        indices.push(i);
+    }
+
+    let inversions = (0..indices.len())
+        .flat_map(|i| (i + 1..indices.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| indices[i] > indices[j])
+        .count();
+
+    if inversions % 2 == 0 {
+        Ok(TwoParity::Even)
+    } else {
+        Ok(TwoParity::Odd)
+    }
+}
+
+/// Convenience for the common case of pieces which can only be evenly permuted
+pub fn require_even<T: Eq + Debug>(name: &'static str, solved: &[T], actual: &[T]) -> Result<(), IllegalReason> {
+    match permutation_parity(solved, actual)? {
+        TwoParity::Even => Ok(()),
+        TwoParity::Odd => Err(IllegalReason::OddPermutation(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parity_and_duplicates() {
+        let solved = [0, 1, 2, 3];
+
+        assert_eq!(permutation_parity(&solved, &[0, 1, 2, 3]), Ok(TwoParity::Even));
+        assert_eq!(permutation_parity(&solved, &[1, 0, 2, 3]), Ok(TwoParity::Odd));
+        assert_eq!(permutation_parity(&solved, &[1, 2, 0, 3]), Ok(TwoParity::Even));
+        assert_eq!(
+            permutation_parity(&solved, &[0, 1, 1, 3]),
+            Err(IllegalReason::DuplicatePiece("1".to_string()))
+        );
+    }
+}
@@ -2,42 +2,77 @@
 #![allow(clippy::assertions_on_constants)]
 #![allow(clippy::collapsible_else_if)] // sometimes it allows more symmetrical code, bite me
 
-use std::time::Instant;
+use std::fmt::Display;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use ahash::HashMap;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use enum_iterator::{all, Sequence};
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
+use crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3;
 use crate::coin_pyraminx::CoinPyraminx;
-use crate::cubesearch::nice_print;
-use crate::cubesearch::{enumerate_state_space, enumerate_state_space_started};
+use crate::cubesearch::{
+    enumerate_deduped_frontier, enumerate_dense, enumerate_key_frontier, enumerate_local_maxima, enumerate_state_space,
+    enumerate_state_space_par, enumerate_state_space_par_started, enumerate_state_space_pruned,
+    enumerate_state_space_started, enumerate_with_table, format_local_maxima, MaximaEnumeration, TransitionTable,
+};
+use crate::cubesearch::{nice_print, FromKey, HasSolvedState, PrintOptions, State};
 use crate::cuboid_2x2x3::Cuboid2x2x3;
-use crate::cuboid_2x3x3::Cuboid2x3x3;
-use crate::dino_cube::DinoCube;
+use crate::cuboid_2x3x3::{Cuboid2x3x3, SuperCuboid2x3x3};
+use crate::describe::{DecodeKey, DescribeKey, PrettyState};
+use crate::dino_cube::{DinoCube, DinoCubeOneSolution, SolveTarget};
 use crate::floppy_1x2x2::Floppy1x2x2;
 use crate::floppy_1x2x3::Floppy1x2x3;
 use crate::floppy_1x3x3::Floppy1x3x3;
 use crate::floppy_1xnxn::Floppy1xMxN;
-use crate::idasearch::{no_heuristic, SolveError};
+use crate::helicopter_cube::HelicopterCube;
+use crate::idasearch::heuristic_helpers::{
+    bounded_cache, bounded_cache_from, enumerate_and_dump_pattern_db, load_pattern_db, min_complete_depth,
+    states_at_depth, states_at_depth_from,
+};
+use crate::idasearch::{no_heuristic, Heuristic, Solvable, SolveCache, SolveError};
 use crate::ivy_cube::IvyCube;
 use crate::mirror_pocket_cube::MirrorPocketCube;
-use crate::pocket_cube::PocketCube;
-use crate::pyraminx::Pyraminx;
+use crate::moves::{CanReverse, ParseMoveError};
+use crate::pocket_cube::{PocketCube, PocketCubeQtm, PocketCubeUpToSymmetry};
+use crate::pyraminx::{Pyraminx, PyraminxWithTips};
+use crate::redi_cube::{EdgeCubelet, RediCube};
+use crate::rubiks_3x3x3::{predicates, Rubiks3x3x3};
+use crate::scrambles::{RandomInit, Scrambler, SweepRow};
 use crate::square_one_shape::SquareOneShape;
 use crate::square_zero::SquareZero;
 
 // reusable state modules
+mod corner_turning;
+mod describe;
+mod half_turn_cuboid;
+mod legality;
 mod moves;
 mod orientations;
 mod permutation_helpers;
 mod random_helpers;
+mod repl;
 mod scrambles;
+mod symmetry;
 
 // reusable algorithm logic
 mod cubesearch;
 mod idasearch;
 
+// generic checks run against every puzzle
+#[cfg(test)]
+mod state_counts;
+#[cfg(test)]
+mod test_helpers;
+
 // actual puzzles
 mod bandaged_3x3x3_1x2x3;
 mod coin_pyraminx;
@@ -49,33 +84,413 @@ mod floppy_1x2x2;
 mod floppy_1x2x3;
 mod floppy_1x3x3;
 mod floppy_1xnxn;
+mod helicopter_cube;
 mod ivy_cube;
 mod mirror_pocket_cube;
 mod pocket_cube;
 mod pyraminx;
 mod redi_cube;
+mod rubiks_3x3x3;
 mod skewb;
 mod square_one_shape;
 mod square_zero;
 
 #[derive(Parser)]
 struct Cli {
+    /// How many threads the parallel work (bulk solving, parallel enumeration) gets; defaults to
+    /// one per core
+    #[arg(long, global = true)]
+    threads: Option<usize>,
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    ConfigDepth(ConfigArgs),
+    ConfigDepthSampling(SamplingArgs),
+    RandomScramble(ScrambleArgs),
+    /// Enumerate every puzzle in turn, then print a table of their state counts and diameters
+    DiameterTable {
+        /// Skip any puzzle which turns out to have more than this many states. Some puzzles run out
+        /// of memory long before they finish, so there's always a limit; raise it to go further
+        #[arg(long, default_value_t = 10_000_000)]
+        max_states: u128,
+    },
+    /// Count the states which are exactly this many moves from solved
+    LayerSize(LayerArgs),
+    /// Time building a cache heuristic at each of a range of depths, and solving with it
+    HeuristicSweep(SweepArgs),
+    /// Print every move the puzzle has, as scrambles spell them
+    ListMoves {
+        #[arg(value_enum)]
+        alg: MovesAlg,
+    },
+    /// Print the inverse of a scramble, which undoes it
+    Invert {
+        #[arg(value_enum)]
+        alg: MovesAlg,
+        /// The scramble, with moves separated by spaces
+        scramble: String,
+    },
+    /// Print the shortest sequence of moves taking the state one scramble produces to the state
+    /// another one does
+    Connect {
+        #[arg(value_enum)]
+        alg: MovesAlg,
+        /// The scramble giving the state to start from
+        from: String,
+        /// The scramble giving the state to end up at
+        to: String,
+        /// How far out from the target state to cache exact distances, for the heuristic
+        #[arg(long, default_value_t = 6)]
+        cache_depth: usize,
+    },
+    /// Play with a puzzle interactively: apply moves, solve from wherever it's got to, and so on
+    Repl {
+        #[arg(value_enum)]
+        alg: StreamAlg,
+        /// Remember the solutions to up to this many states, so solving any of them again is instant
+        #[arg(long)]
+        cache_size: Option<usize>,
+    },
+    /// Read scrambles from stdin, one per line, and print the solution to each on its own line
+    SolveStream {
+        #[arg(value_enum)]
+        alg: StreamAlg,
+        /// Give up on any scramble which needs more than this many moves to solve
+        #[arg(long)]
+        max_fuel: Option<usize>,
+        /// Which solution to aim for; only the Dino Cube has more than one
+        #[arg(long, value_enum)]
+        target: Option<DinoTarget>,
+        /// Remember the solutions to up to this many states, so a repeated scramble is instant
+        #[arg(long)]
+        cache_size: Option<usize>,
+    },
+    /// Enumerate the whole puzzle, writing every state's distance from solved to a file, for use
+    /// with random-scramble --pattern-db
+    DumpPatternDb {
+        #[arg(value_enum)]
+        alg: PatternDbAlg,
+        path: PathBuf,
+        /// Seed the hashing, so the file comes out the same every time; otherwise the states are
+        /// written in a different order on each run
+        #[arg(long)]
+        hash_seed: Option<usize>,
+    },
+    /// Enumerate the puzzle twice, with and without skipping redundant moves, and compare
     #[command(subcommand)]
-    ConfigDepth(ConfigAlg),
-    // TODO: somehow figure out how to take more args to a subcommand here, I got tired of googling docs
+    ComparePruning(ScrambleAlg),
+    /// Enumerate the Pyraminx (without tips) with and without a precomputed transition table, and
+    /// compare the times
+    CompareTransitionTable,
+    /// Enumerate the Pocket Cube keeping one state per symmetry class in the frontier, rather than
+    /// every state, and compare the frontier sizes
+    CompareCanonicalFrontier,
+    /// Enumerate the puzzle twice, holding states and then just their keys in the frontier, and
+    /// compare the times and the sizes of what's held
+    CompareKeyFrontier {
+        #[arg(value_enum)]
+        alg: KeyFrontierAlg,
+    },
+    /// Solve random Redi Cube states one at a time, both on one thread and with the first moves
+    /// split across threads, and compare the times
+    CompareParallelSolve {
+        #[arg(long, default_value_t = 3)]
+        samples: usize,
+    },
+    /// Find the shortest Redi Cube algorithm which cycles three edges and leaves the rest solved
+    RediEdgeCycle(EdgeCycleArgs),
+    /// Print a short code for the state a scramble produces, which decode turns back into the state
+    Encode {
+        #[arg(value_enum)]
+        alg: DescribeAlg,
+        /// The scramble, with moves separated by spaces
+        scramble: String,
+    },
+    /// Read a state code (from encode) and print a solution for the state it describes
+    Decode {
+        /// The code, like pocket-cube:AAAAAAAAAAA
+        code: String,
+    },
+    /// Print the shortest sequence of moves taking the Pocket Cube state a scramble produces to a
+    /// partial goal, as a hint for someone learning to solve it
+    Hint {
+        #[arg(value_enum)]
+        goal: HintGoal,
+        /// The scramble, with moves separated by spaces
+        scramble: String,
+        /// The longest hint to look for
+        #[arg(long, default_value_t = 8)]
+        max_len: usize,
+    },
+    /// Print the shortest sequence of moves taking the 3x3x3 state a scramble produces to the end
+    /// of one of the steps speedcubers solve it in
+    Step {
+        #[arg(value_enum)]
+        goal: StepGoal,
+        /// The scramble, with moves separated by spaces
+        scramble: String,
+        /// How many moves out from the goal to cache exact distances for
+        #[arg(long, default_value_t = 5)]
+        cache_depth: usize,
+        /// The longest step to look for
+        #[arg(long, default_value_t = 12)]
+        max_len: usize,
+    },
+    /// Unpack a state key into its fields, for debugging the packing
+    DescribeKey {
+        #[arg(value_enum)]
+        alg: DescribeAlg,
+        key: u64,
+    },
+    /// Solve random states and write them to a CSV file with their optimal solution lengths and
+    /// solutions, as a labeled dataset
+    Dataset {
+        #[arg(value_enum)]
+        alg: DescribeAlg,
+        /// How many random states to solve
+        count: usize,
+        /// The seed for generating the states, so the same dataset can be made again
+        #[arg(long, default_value_t = 15)]
+        seed: u64,
+        out: PathBuf,
+    },
+    /// Find the smallest depth at which a bounded cache holds every state of the puzzle, which is
+    /// its diameter
+    CacheDepth {
+        #[arg(value_enum)]
+        alg: CacheDepthAlg,
+    },
+}
+
+#[derive(Args)]
+struct EdgeCycleArgs {
+    /// The edges to cycle, named by their faces (like UF); the piece at the first goes to the
+    /// second, and so on
+    #[arg(num_args = 3, required = true)]
+    edges: Vec<EdgeCubelet>,
+    /// The longest algorithm to look for
+    #[arg(long, default_value_t = 8)]
+    max_len: usize,
+}
+
+/// Partial goals for the Pocket Cube, which hint solves toward
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum HintGoal {
+    /// The D face is all one color
+    DFace,
+    /// The whole D layer is solved
+    DLayer,
+}
+
+/// Steps of the 3x3x3 speedcubing methods, which step solves toward
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum StepGoal {
+    /// The D and middle layers are solved (F2L, in CFOP)
+    FirstTwoLayers,
+    /// The first two layers are solved and the U face is all one color (OLL, in CFOP)
+    LastLayerOriented,
+    /// The 1x2x3 block on the bottom of the L side is solved (the first block, in Roux)
+    RouxFirstBlock,
+}
+
+/// The Dino Cube's two solutions (the original and its mirror image) look the same once the
+/// stickers are on, so either counts as solved; this picks which one to solve toward
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum DinoTarget {
+    /// The original solution
+    Solved,
+    /// The mirror image of the original solution
+    Mirror,
+    /// Whichever of the two is closer
+    Nearest,
+}
+
+impl From<DinoTarget> for SolveTarget {
+    fn from(target: DinoTarget) -> Self {
+        match target {
+            DinoTarget::Solved => SolveTarget::Solved,
+            DinoTarget::Mirror => SolveTarget::Mirror,
+            DinoTarget::Nearest => SolveTarget::Nearest,
+        }
+    }
+}
+
+/// Puzzles whose keys can be unpacked; the value names double as the puzzle ids in state codes
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)] // renaming them would change the ids in existing codes
+enum DescribeAlg {
+    PocketCube,
+    RediCube,
+    DinoCube,
+}
+
+/// Puzzles which are small enough to enumerate, and whose keys can be written to a file
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum PatternDbAlg {
+    IvyCube,
+    SquareOneShape,
+}
+
+/// Puzzles which can read and solve scrambles one at a time, for solve-stream and repl
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum StreamAlg {
+    Floppy1x2x2,
+    Floppy1x2x3,
+    Floppy1x3x3,
+    PocketCube,
+    Cuboid2x2x3,
+    Cuboid2x3x3,
+    DinoCube,
+    Bandaged3x3x3With1x2x3,
+    RediCube,
+    IvyCube,
+    SquareZero,
+    // not in random-scramble, since a uniformly random state takes far too long to solve, but a
+    // given scramble usually doesn't
+    Rubiks3x3x3,
+}
+
+/// Puzzles which can be rebuilt from their keys, for compare-key-frontier
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum KeyFrontierAlg {
+    CoinPyraminx,
+    Cuboid2x3x3,
+}
+
+/// Puzzles which are small enough to search all the way through, for cache-depth
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum CacheDepthAlg {
+    Floppy1x2x2,
+    Floppy1x2x3,
+    Floppy1x3x3,
+    IvyCube,
+    SquareZero,
+    PocketCube,
+}
+
+/// Puzzles whose moves can be parsed and printed, for the commands which work with the moves
+/// themselves: listing them, and inverting or connecting scrambles
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq)]
+enum MovesAlg {
+    Floppy1x2x2,
+    Floppy1x2x3,
+    Floppy1x3x3,
+    Cuboid2x2x3,
+    Cuboid2x3x3,
+    SuperCuboid2x3x3,
+    DinoCube,
+    Bandaged3x3x3With1x2x3,
+    RediCube,
+    IvyCube,
+    SquareZero,
+    SuperSkewb,
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    /// Process each stage of the enumeration in parallel; the counts are the same either way
+    #[arg(long)]
+    parallel: bool,
+    /// Also count the locally maximal configurations (with no neighbor any deeper) in this many of
+    /// the deepest layers; always serial, and needs more memory
+    #[arg(long, conflicts_with = "parallel")]
+    local_maxima: Option<usize>,
+    #[command(flatten)]
+    print: PrintArgs,
+    #[command(subcommand)]
+    alg: ConfigAlg,
+}
+
+#[derive(Args)]
+struct PrintArgs {
+    /// Digits after the decimal point, for the percentages
+    #[arg(long, default_value_t = 3)]
+    precision: usize,
+    /// Only show the depths with the most configurations, this many of them
+    #[arg(long)]
+    top_n: Option<usize>,
+    /// Also show the percentage of configurations at each depth or less
+    #[arg(long)]
+    cumulative: bool,
+}
+
+impl From<PrintArgs> for PrintOptions {
+    fn from(args: PrintArgs) -> Self {
+        PrintOptions {
+            precision: args.precision,
+            top_n: args.top_n,
+            cumulative: args.cumulative,
+        }
+    }
+}
+
+#[derive(Args)]
+struct ScrambleArgs {
+    /// Give up on solutions longer than this; defaults to the puzzle's own limit
+    #[arg(long)]
+    max_fuel: Option<usize>,
+    /// Instead of solving uniformly random states, make this many random moves from solved; much
+    /// faster, but the scrambles are longer than they need to be, and not uniformly distributed
+    #[arg(long, conflicts_with = "max_fuel")]
+    random_walk: Option<usize>,
+    /// Use a pattern database written by dump-pattern-db as the heuristic, instead of building one;
+    /// only for the puzzles dump-pattern-db supports
+    #[arg(long, conflicts_with = "random_walk")]
+    pattern_db: Option<PathBuf>,
     #[command(subcommand)]
-    ConfigDepthSampling(ScrambleAlg),
+    alg: ScrambleAlg,
+}
+
+#[derive(Args)]
+struct SamplingArgs {
+    /// Print this many of the sampled scrambles, along with the optimal solutions found for them
+    #[arg(long, default_value_t = 0)]
+    sample_solutions: usize,
+    /// Give up on solutions longer than this; defaults to the puzzle's own limit
+    #[arg(long)]
+    max_fuel: Option<usize>,
+    /// Also enumerate the whole puzzle, and check the sampled depths against the exact distribution;
+    /// this is how to tell whether the random states are really uniform
+    #[arg(long)]
+    compare_theoretical: bool,
+    /// How many random states to solve
+    #[arg(long, default_value_t = 250_000)]
+    num_scrambles: usize,
+    /// Also write the count of each solution length to this file, as CSV
+    #[arg(long)]
+    out: Option<PathBuf>,
     #[command(subcommand)]
-    RandomScramble(ScrambleAlg),
+    alg: ScrambleAlg,
 }
 
-#[derive(Subcommand, Copy, Clone, PartialEq, Eq)]
+#[derive(Args)]
+struct LayerArgs {
+    /// The number of moves from solved
+    #[arg(long)]
+    depth: usize,
+    #[command(subcommand)]
+    alg: ScrambleAlg,
+}
+
+#[derive(Args)]
+struct SweepArgs {
+    /// The smallest cache depth to try
+    #[arg(long, default_value_t = 1)]
+    min_depth: usize,
+    /// The largest cache depth to try
+    #[arg(long)]
+    max_depth: usize,
+    /// Solve this many random states (the same ones for each depth)
+    #[arg(long, default_value_t = 100)]
+    samples: usize,
+    #[command(subcommand)]
+    alg: ScrambleAlg,
+}
+
+#[derive(Subcommand, Copy, Clone, PartialEq, Eq, Debug, Sequence)]
 enum ConfigAlg {
     Floppy1x2x2,
     Floppy1x2x3,
@@ -96,13 +511,18 @@ enum ConfigAlg {
     DinoCubeEitherSolution,
     IvyCube,
     Skewb,
+    SkewbCornersOnly,
+    SuperSkewb,
     MirrorPocketCube,
     PocketCube,
+    PocketCubeQtm,
+    PocketCubeUpToSymmetry,
     PyraminxNoTips,
     PyraminxWithTips,
     CoinPyraminx,
     SquareZero,
     SquareOneShape,
+    CurvyCopter,
 }
 
 impl ConfigAlg {
@@ -126,14 +546,19 @@ impl ConfigAlg {
             ConfigAlg::DinoCubeOneSolution => "Dino Cube (To One Solution)",
             ConfigAlg::DinoCubeEitherSolution => "Dino Cube (To Either Solution)",
             ConfigAlg::Skewb => "Skewb",
+            ConfigAlg::SkewbCornersOnly => "Skewb (Corners Only)",
+            ConfigAlg::SuperSkewb => "Super Skewb",
             ConfigAlg::MirrorPocketCube => "Mirror Pocket Cube",
             ConfigAlg::PocketCube => "Pocket Cube",
+            ConfigAlg::PocketCubeQtm => "Pocket Cube (Quarter Turns)",
+            ConfigAlg::PocketCubeUpToSymmetry => "Pocket Cube (Up To Symmetry)",
             ConfigAlg::PyraminxNoTips => "Pyraminx (No Tips)",
             ConfigAlg::PyraminxWithTips => "Pyraminx (With Tips)",
             ConfigAlg::CoinPyraminx => "Coin Pyraminx",
             ConfigAlg::SquareZero => "Square Zero",
             ConfigAlg::SquareOneShape => "Square One Shape",
             ConfigAlg::IvyCube => "Ivy Cube",
+            ConfigAlg::CurvyCopter => "Curvy Copter",
         }
     }
 }
@@ -144,13 +569,25 @@ enum ScrambleAlg {
     Floppy1x2x3,
     Floppy1x3x3,
     Cuboid2x2x3,
-    Cuboid2x3x3,
-    DinoCube,
+    Cuboid2x3x3 {
+        /// Mark the U and D centers, so they need to be turned the right way around, too
+        #[arg(long)]
+        super_domino: bool,
+    },
+    DinoCube {
+        /// Count the mirrored solution as solved, too, instead of just the original one
+        #[arg(long)]
+        either_solution: bool,
+    },
     Bandaged3x3x3With1x2x3,
     RediCube,
     IvyCube,
     SquareZero,
+    SquareOneShape,
     CurvyCopter,
+    MirrorPocketCube,
+    PyraminxWithTips,
+    HelicopterCube,
 }
 
 impl ScrambleAlg {
@@ -160,204 +597,568 @@ impl ScrambleAlg {
             ScrambleAlg::Floppy1x2x3 => "Floppy 1x2x3",
             ScrambleAlg::Floppy1x3x3 => "Floppy 1x3x3",
             ScrambleAlg::Cuboid2x2x3 => "Cuboid 2x2x3",
-            ScrambleAlg::Cuboid2x3x3 => "Cuboid 2x3x3",
-            ScrambleAlg::DinoCube => "Dino Cube",
+            ScrambleAlg::Cuboid2x3x3 { super_domino: false } => "Cuboid 2x3x3",
+            ScrambleAlg::Cuboid2x3x3 { super_domino: true } => "Super Cuboid 2x3x3",
+            ScrambleAlg::DinoCube { either_solution: false } => "Dino Cube (To One Solution)",
+            ScrambleAlg::DinoCube { either_solution: true } => "Dino Cube (To Either Solution)",
             ScrambleAlg::Bandaged3x3x3With1x2x3 => "Bandaged 3x3x3 with 1x2x3",
             ScrambleAlg::RediCube => "Redi Cube",
             ScrambleAlg::IvyCube => "Ivy Cube",
             ScrambleAlg::SquareZero => "Square Zero",
+            ScrambleAlg::SquareOneShape => "Square One Shape",
             ScrambleAlg::CurvyCopter => "Curvy Copter",
+            ScrambleAlg::MirrorPocketCube => "Mirror Pocket Cube",
+            ScrambleAlg::PyraminxWithTips => "Pyraminx (With Tips)",
+            ScrambleAlg::HelicopterCube => "Helicopter Cube",
         }
     }
 }
 
-fn configuration_depth(alg: ConfigAlg) {
-    println!("Computing configuration depth summary for {}", alg.nice_name());
+/// Enumerates the state space, either one state at a time or with each stage spread over all the
+/// threads
+fn enumerate<T>(max_states: Option<u128>, parallel: bool) -> Option<(Duration, HashMap<u128, u128>)>
+where
+    T: State + Hash + Eq + Send + Sync,
+    T::UniqueKey: Send,
+{
+    if parallel {
+        enumerate_state_space_par::<T>(max_states)
+    } else {
+        enumerate_state_space::<T>(max_states)
+    }
+}
 
-    let (elapsed, gn_count) = match alg {
-        ConfigAlg::Floppy1x2x2 => enumerate_state_space::<Floppy1x2x2>(),
-        ConfigAlg::Floppy1x2x3 => enumerate_state_space::<Floppy1x2x3>(),
-        ConfigAlg::Floppy1x3x3 => enumerate_state_space::<Floppy1x3x3>(),
-        ConfigAlg::BigFloppy1x3x3 => enumerate_state_space::<Floppy1xMxN<1, 1>>(),
-        ConfigAlg::BigFloppy1x3x4 => enumerate_state_space::<Floppy1xMxN<1, 2>>(),
-        ConfigAlg::BigFloppy1x3x5 => enumerate_state_space::<Floppy1xMxN<1, 3>>(),
-        ConfigAlg::BigFloppy1x3x6 => enumerate_state_space::<Floppy1xMxN<1, 4>>(),
-        ConfigAlg::BigFloppy1x4x4 => enumerate_state_space::<Floppy1xMxN<2, 2>>(),
-        ConfigAlg::BigFloppy1x4x5 => enumerate_state_space::<Floppy1xMxN<2, 3>>(),
-        ConfigAlg::BigFloppy1x4x6 => enumerate_state_space::<Floppy1xMxN<2, 4>>(),
-        ConfigAlg::BigFloppy1x5x5 => enumerate_state_space::<Floppy1xMxN<3, 3>>(),
-        ConfigAlg::BigFloppy1x5x6 => enumerate_state_space::<Floppy1xMxN<3, 4>>(),
-        ConfigAlg::BigFloppy1x6x6 => enumerate_state_space::<Floppy1xMxN<4, 4>>(),
-        ConfigAlg::Cuboid2x2x3 => enumerate_state_space::<Cuboid2x2x3>(),
-        ConfigAlg::Cuboid2x3x3 => enumerate_state_space::<Cuboid2x3x3>(),
-        ConfigAlg::DinoCubeOneSolution => enumerate_state_space::<DinoCube>(),
+/// Enumerates the whole state space of the given puzzle, returning the elapsed time and the count of
+/// states at each depth. Gives up (and returns None) if there are more than `max_states` states.
+fn enumerate_alg(alg: ConfigAlg, max_states: Option<u128>, parallel: bool) -> Option<(Duration, HashMap<u128, u128>)> {
+    match alg {
+        ConfigAlg::Floppy1x2x2 => enumerate::<Floppy1x2x2>(max_states, parallel),
+        ConfigAlg::Floppy1x2x3 => enumerate::<Floppy1x2x3>(max_states, parallel),
+        ConfigAlg::Floppy1x3x3 => enumerate::<Floppy1x3x3>(max_states, parallel),
+        ConfigAlg::BigFloppy1x3x3 => enumerate::<Floppy1xMxN<1, 1>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x3x4 => enumerate::<Floppy1xMxN<1, 2>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x3x5 => enumerate::<Floppy1xMxN<1, 3>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x3x6 => enumerate::<Floppy1xMxN<1, 4>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x4x4 => enumerate::<Floppy1xMxN<2, 2>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x4x5 => enumerate::<Floppy1xMxN<2, 3>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x4x6 => enumerate::<Floppy1xMxN<2, 4>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x5x5 => enumerate::<Floppy1xMxN<3, 3>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x5x6 => enumerate::<Floppy1xMxN<3, 4>>(max_states, parallel),
+        ConfigAlg::BigFloppy1x6x6 => enumerate::<Floppy1xMxN<4, 4>>(max_states, parallel),
+        ConfigAlg::Cuboid2x2x3 => enumerate::<Cuboid2x2x3>(max_states, parallel),
+        ConfigAlg::Cuboid2x3x3 => enumerate::<Cuboid2x3x3>(max_states, parallel),
+        ConfigAlg::DinoCubeOneSolution => enumerate::<DinoCube>(max_states, parallel),
         ConfigAlg::DinoCubeEitherSolution => {
-            enumerate_state_space_started::<DinoCube>(vec![DinoCube::solved_state(), DinoCube::solved_mirrored()])
+            let starts = vec![DinoCube::solved(), DinoCube::solved_mirrored()];
+            if parallel {
+                enumerate_state_space_par_started(starts, max_states)
+            } else {
+                enumerate_state_space_started(starts, max_states)
+            }
         }
-        ConfigAlg::Skewb => enumerate_state_space::<skewb::Skewb>(),
-        ConfigAlg::MirrorPocketCube => enumerate_state_space::<MirrorPocketCube>(),
-        ConfigAlg::PocketCube => enumerate_state_space::<PocketCube>(),
-        ConfigAlg::PyraminxNoTips => enumerate_state_space::<Pyraminx>(),
+        ConfigAlg::Skewb => enumerate::<skewb::Skewb>(max_states, parallel),
+        ConfigAlg::SkewbCornersOnly => enumerate::<skewb::SkewbCornersOnly>(max_states, parallel),
+        ConfigAlg::SuperSkewb => enumerate::<skewb::SuperSkewb>(max_states, parallel),
+        ConfigAlg::MirrorPocketCube => enumerate::<MirrorPocketCube>(max_states, parallel),
+        ConfigAlg::PocketCube => enumerate::<PocketCube>(max_states, parallel),
+        ConfigAlg::PocketCubeQtm => enumerate::<PocketCubeQtm>(max_states, parallel),
+        ConfigAlg::PocketCubeUpToSymmetry => enumerate::<PocketCubeUpToSymmetry>(max_states, parallel),
+        ConfigAlg::PyraminxNoTips => enumerate::<Pyraminx>(max_states, parallel),
         ConfigAlg::PyraminxWithTips => {
             let start = Instant::now();
-            let (_, gn_count) = enumerate_state_space::<Pyraminx>();
+            let (_, gn_count) = enumerate::<Pyraminx>(max_states, parallel)?;
             let gn_count = pyraminx::gn_count_with_tips(gn_count);
-            (start.elapsed(), gn_count)
+
+            // the tips multiply the count, so the cap needs to be checked again
+            if max_states.is_some_and(|max_states| gn_count.values().sum::<u128>() > max_states) {
+                return None;
+            }
+
+            Some((start.elapsed(), gn_count))
         }
-        ConfigAlg::CoinPyraminx => enumerate_state_space::<CoinPyraminx>(),
-        ConfigAlg::SquareOneShape => enumerate_state_space::<SquareOneShape>(),
-        ConfigAlg::SquareZero => enumerate_state_space::<SquareZero>(),
-        ConfigAlg::IvyCube => enumerate_state_space::<IvyCube>(),
-    };
+        ConfigAlg::CoinPyraminx => enumerate_dense::<CoinPyraminx>(max_states),
+        ConfigAlg::SquareOneShape => enumerate_dense::<SquareOneShape>(max_states),
+        ConfigAlg::SquareZero => enumerate::<SquareZero>(max_states, parallel),
+        ConfigAlg::IvyCube => enumerate::<IvyCube>(max_states, parallel),
+        // around 3 * 10^21 states (more with jumbling), so this won't finish: each stage is five or
+        // six times the last, and the first nine (4 million states) take a few seconds in release,
+        // but with 6 GB memory runs out partway through depth 10. Pass --max-states to stop sooner
+        ConfigAlg::CurvyCopter => enumerate::<curvy_copter::CurvyCopter>(max_states, parallel),
+    }
+}
+
+/// Same as [enumerate_alg], but also finds the locally maximal states (see [enumerate_local_maxima]),
+/// with no limit. Puzzles which are counted some other way than visiting every state give None.
+fn enumerate_alg_maxima(alg: ConfigAlg) -> Option<MaximaEnumeration> {
+    fn maxima_from<T: State>(starts: Vec<T>) -> Option<MaximaEnumeration> {
+        Some(enumerate_local_maxima(starts, None).expect("No limit, so should always finish"))
+    }
+
+    match alg {
+        ConfigAlg::Floppy1x2x2 => maxima_from(vec![Floppy1x2x2::start()]),
+        ConfigAlg::Floppy1x2x3 => maxima_from(vec![Floppy1x2x3::start()]),
+        ConfigAlg::Floppy1x3x3 => maxima_from(vec![Floppy1x3x3::start()]),
+        ConfigAlg::BigFloppy1x3x3 => maxima_from(vec![Floppy1xMxN::<1, 1>::start()]),
+        ConfigAlg::BigFloppy1x3x4 => maxima_from(vec![Floppy1xMxN::<1, 2>::start()]),
+        ConfigAlg::BigFloppy1x3x5 => maxima_from(vec![Floppy1xMxN::<1, 3>::start()]),
+        ConfigAlg::BigFloppy1x3x6 => maxima_from(vec![Floppy1xMxN::<1, 4>::start()]),
+        ConfigAlg::BigFloppy1x4x4 => maxima_from(vec![Floppy1xMxN::<2, 2>::start()]),
+        ConfigAlg::BigFloppy1x4x5 => maxima_from(vec![Floppy1xMxN::<2, 3>::start()]),
+        ConfigAlg::BigFloppy1x4x6 => maxima_from(vec![Floppy1xMxN::<2, 4>::start()]),
+        ConfigAlg::BigFloppy1x5x5 => maxima_from(vec![Floppy1xMxN::<3, 3>::start()]),
+        ConfigAlg::BigFloppy1x5x6 => maxima_from(vec![Floppy1xMxN::<3, 4>::start()]),
+        ConfigAlg::BigFloppy1x6x6 => maxima_from(vec![Floppy1xMxN::<4, 4>::start()]),
+        ConfigAlg::Cuboid2x2x3 => maxima_from(vec![Cuboid2x2x3::start()]),
+        ConfigAlg::Cuboid2x3x3 => maxima_from(vec![Cuboid2x3x3::start()]),
+        ConfigAlg::DinoCubeOneSolution => maxima_from(vec![DinoCube::start()]),
+        ConfigAlg::DinoCubeEitherSolution => maxima_from(vec![DinoCube::solved(), DinoCube::solved_mirrored()]),
+        ConfigAlg::Skewb => maxima_from(vec![skewb::Skewb::start()]),
+        ConfigAlg::SkewbCornersOnly => maxima_from(vec![skewb::SkewbCornersOnly::start()]),
+        ConfigAlg::SuperSkewb => maxima_from(vec![skewb::SuperSkewb::start()]),
+        ConfigAlg::MirrorPocketCube => maxima_from(vec![MirrorPocketCube::start()]),
+        ConfigAlg::PocketCube => maxima_from(vec![PocketCube::start()]),
+        ConfigAlg::PocketCubeQtm => maxima_from(vec![PocketCubeQtm::start()]),
+        ConfigAlg::PocketCubeUpToSymmetry => maxima_from(vec![PocketCubeUpToSymmetry::start()]),
+        ConfigAlg::PyraminxNoTips => maxima_from(vec![Pyraminx::start()]),
+        // the tips are multiplied in afterward, from the counts for the puzzle without them
+        ConfigAlg::PyraminxWithTips => None,
+        ConfigAlg::CoinPyraminx => maxima_from(vec![CoinPyraminx::start()]),
+        ConfigAlg::SquareOneShape => maxima_from(vec![SquareOneShape::start()]),
+        ConfigAlg::SquareZero => maxima_from(vec![SquareZero::start()]),
+        ConfigAlg::IvyCube => maxima_from(vec![IvyCube::start()]),
+        ConfigAlg::CurvyCopter => maxima_from(vec![curvy_copter::CurvyCopter::start()]),
+    }
+}
+
+fn configuration_depth(args: ConfigArgs) {
+    let ConfigArgs {
+        parallel,
+        local_maxima,
+        print,
+        alg,
+    } = args;
+
+    println!("Computing configuration depth summary for {}", alg.nice_name());
+
+    if let Some(last_n) = local_maxima {
+        let Some(MaximaEnumeration {
+            elapsed,
+            counts,
+            local_maxima,
+        }) = enumerate_alg_maxima(alg)
+        else {
+            println!(
+                "{} is counted without visiting its states, so there are no maxima to find",
+                alg.nice_name()
+            );
+            return;
+        };
+
+        println!("Processing took {elapsed:?}");
+
+        nice_print(alg.nice_name(), &counts, &print.into());
+        print!("{}", format_local_maxima(&counts, &local_maxima, last_n));
+        return;
+    }
+
+    let (elapsed, gn_count) = enumerate_alg(alg, None, parallel).expect("No limit, so should always finish");
 
     println!("Processing took {elapsed:?}");
 
-    nice_print(alg.nice_name(), &gn_count);
+    nice_print(alg.nice_name(), &gn_count, &print.into());
 }
 
-fn config_depth_sampling(alg: ScrambleAlg) {
-    // TODO: make this a config argument
-    const NUM_SCRAMBLES: usize = 250_000;
-    println!("Computing {NUM_SCRAMBLES} scramble depths for {}", alg.nice_name());
+#[derive(Debug, Eq, PartialEq)]
+struct DiameterRow {
+    states: u128,
+    diameter: u128,
+}
 
-    println!("Precomputing heuristics...");
+fn diameter_row(alg: ConfigAlg, max_states: Option<u128>) -> Option<(Duration, DiameterRow)> {
+    let (elapsed, gn_count) = enumerate_alg(alg, max_states, false)?;
 
-    // hard-coded seed for reproducibility
-    // let mut rng = StdRng::from_seed([15; 32]);
-    // random seed for actual scrambling
-    let mut rng = StdRng::from_entropy();
+    let row = DiameterRow {
+        states: gn_count.values().sum(),
+        diameter: gn_count.keys().copied().max().unwrap_or(0),
+    };
 
-    let setup_time = Instant::now();
+    Some((elapsed, row))
+}
 
-    let mut scrambler: Box<dyn FnMut() -> Result<Vec<usize>, SolveError>> = match alg {
-        ScrambleAlg::Floppy1x2x2 => {
-            Box::new(|| scrambles::bulk_scramble::<_, _, Floppy1x2x2, _>(&mut rng, &no_heuristic, NUM_SCRAMBLES))
-        }
-        ScrambleAlg::Floppy1x2x3 => {
-            Box::new(|| scrambles::bulk_scramble::<_, _, Floppy1x2x3, _>(&mut rng, &no_heuristic, NUM_SCRAMBLES))
-        }
-        ScrambleAlg::Floppy1x3x3 => {
-            Box::new(|| scrambles::bulk_scramble::<_, _, Floppy1x3x3, _>(&mut rng, &no_heuristic, NUM_SCRAMBLES))
+fn diameter_table(max_states: u128) {
+    println!("{:<30} {:>15} {:>9} {:>15}", "Puzzle", "States", "Diameter", "Elapsed");
+
+    // each row is printed as soon as it's done, so a slow puzzle doesn't hold up the rest; the
+    // progress goes to stderr, so stdout is just the table
+    for alg in all::<ConfigAlg>() {
+        eprintln!("Computing configuration depth summary for {}", alg.nice_name());
+        match diameter_row(alg, Some(max_states)) {
+            Some((elapsed, DiameterRow { states, diameter })) => {
+                let elapsed = format!("{elapsed:.3?}");
+                println!("{:<30} {states:>15} {diameter:>9} {elapsed:>15}", alg.nice_name());
+            }
+            None => {
+                println!("{:<30} {:>15}", alg.nice_name(), "(skipped)");
+            }
         }
+    }
+}
+
+/// Builds the sampler for config-depth-sampling, which solves `num_scrambles` uniformly random
+/// states and counts the solution lengths (or why there wasn't one). Building it precomputes the
+/// heuristic, so it's split from running it, to time the two separately.
+fn sample_depths(
+    alg: ScrambleAlg,
+    mut rng: StdRng,
+    num_scrambles: usize,
+    sample_solutions: usize,
+    max_fuel: Option<usize>,
+) -> Box<dyn FnMut() -> HashMap<Result<usize, SolveError>, usize>> {
+    match alg {
+        ScrambleAlg::Floppy1x2x2 => Box::new(move || {
+            scrambles::bulk_scramble::<_, _, Floppy1x2x2, _>(
+                &mut rng,
+                &no_heuristic,
+                num_scrambles,
+                sample_solutions,
+                max_fuel,
+            )
+        }),
+        ScrambleAlg::Floppy1x2x3 => Box::new(move || {
+            scrambles::bulk_scramble::<_, _, Floppy1x2x3, _>(
+                &mut rng,
+                &no_heuristic,
+                num_scrambles,
+                sample_solutions,
+                max_fuel,
+            )
+        }),
+        ScrambleAlg::Floppy1x3x3 => Box::new(move || {
+            scrambles::bulk_scramble::<_, _, Floppy1x3x3, _>(
+                &mut rng,
+                &no_heuristic,
+                num_scrambles,
+                sample_solutions,
+                max_fuel,
+            )
+        }),
         ScrambleAlg::Cuboid2x2x3 => {
             let heuristic = cuboid_2x2x3::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
-        ScrambleAlg::Cuboid2x3x3 => {
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => {
             let heuristic = cuboid_2x3x3::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
+        }
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true } => {
+            let heuristic = cuboid_2x3x3::make_super_heuristic();
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
-        ScrambleAlg::DinoCube => {
+        ScrambleAlg::DinoCube { either_solution: false } => {
             let heuristic = dino_cube::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || {
+                scrambles::bulk_scramble::<_, _, DinoCubeOneSolution, _>(
+                    &mut rng,
+                    &heuristic,
+                    num_scrambles,
+                    sample_solutions,
+                    max_fuel,
+                )
+            })
+        }
+        ScrambleAlg::DinoCube { either_solution: true } => {
+            let heuristic = dino_cube::make_heuristic_multigoal();
+            Box::new(move || {
+                let (results, samples) = scrambles::bulk_scramble_by(
+                    &mut rng,
+                    &heuristic,
+                    num_scrambles,
+                    sample_solutions,
+                    max_fuel,
+                    |state, solution| (solution.len(), dino_cube::ends_mirrored(state, solution)),
+                );
+
+                scrambles::print_samples(&samples);
+
+                let mut length_counts: HashMap<Result<usize, SolveError>, usize> = HashMap::default();
+                let mut num_mirrored = 0;
+                for (result, count) in results {
+                    if let Ok((_, true)) = result {
+                        num_mirrored += count;
+                    }
+                    *length_counts.entry(result.map(|(len, _)| len)).or_default() += count;
+                }
+
+                println!("{num_mirrored} of {num_scrambles} random states were closer to the mirrored solution");
+
+                length_counts
+            })
         }
         ScrambleAlg::Bandaged3x3x3With1x2x3 => {
             let heuristic = bandaged_3x3x3_1x2x3::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
         ScrambleAlg::RediCube => {
             // turns out sample depth 9 makes it OOM
             let heuristic = redi_cube::make_heuristic(8);
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
         ScrambleAlg::SquareZero => {
             let heuristic = square_zero::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
+        }
+        ScrambleAlg::SquareOneShape => {
+            let heuristic = square_one_shape::make_heuristic();
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
         ScrambleAlg::IvyCube => {
             let heuristic = ivy_cube::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
         ScrambleAlg::CurvyCopter => {
             let heuristic = curvy_copter::make_heuristic();
-            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, NUM_SCRAMBLES))
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
         }
-    };
+        ScrambleAlg::MirrorPocketCube => {
+            let heuristic = mirror_pocket_cube::make_heuristic();
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
+        }
+        ScrambleAlg::PyraminxWithTips => {
+            let heuristic = pyraminx::make_heuristic();
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
+        }
+        ScrambleAlg::HelicopterCube => {
+            let heuristic = helicopter_cube::make_heuristic();
+            Box::new(move || scrambles::bulk_scramble(&mut rng, &heuristic, num_scrambles, sample_solutions, max_fuel))
+        }
+    }
+}
+
+fn config_depth_sampling(args: SamplingArgs) {
+    let SamplingArgs {
+        sample_solutions,
+        max_fuel,
+        compare_theoretical,
+        num_scrambles,
+        out,
+        alg,
+    } = args;
+
+    println!("Computing {num_scrambles} scramble depths for {}", alg.nice_name());
+
+    println!("Precomputing heuristics...");
+
+    // hard-coded seed for reproducibility
+    // let rng = StdRng::from_seed([15; 32]);
+    // random seed for actual scrambling
+    let rng = StdRng::from_entropy();
+
+    let setup_time = Instant::now();
+
+    let mut scrambler = sample_depths(alg, rng, num_scrambles, sample_solutions, max_fuel);
 
     let elapsed = setup_time.elapsed();
     println!("Setting up heuristics took {elapsed:?}");
 
     let start = Instant::now();
 
-    let scramble_lengths: Vec<usize> = scrambler().expect("Should not have any issues");
+    let results: HashMap<Result<usize, SolveError>, usize> = scrambler();
 
     let elapsed = start.elapsed();
-    let ms_per_state = (elapsed.as_secs_f32() * 1000.0) / (NUM_SCRAMBLES as f32);
-    println!("Computed {NUM_SCRAMBLES} random states in {elapsed:?} ({ms_per_state:.3} ms per state)");
+    let ms_per_state = (elapsed.as_secs_f32() * 1000.0) / (num_scrambles as f32);
+    println!("Computed {num_scrambles} random states in {elapsed:?} ({ms_per_state:.3} ms per state)");
+
+    if let Some(path) = out {
+        match write_length_csv(&path, &results) {
+            Ok(()) => println!("Wrote the length counts to {}", path.display()),
+            Err(e) => eprintln!("Could not write {}: {e}", path.display()),
+        }
+    }
+
+    let pct = |count: usize| ((count * 100) as f64) / (num_scrambles as f64);
+
+    let (mut lengths, mut errors): (Vec<_>, Vec<_>) = results.into_iter().partition(|(result, _)| result.is_ok());
+    lengths.sort_by_key(|(result, _)| result.ok());
+    errors.sort_by_key(|(result, _)| result.err().map(|e| e.to_string()));
+
+    for (len, count) in lengths.iter() {
+        let len = len.expect("Partitioned to only the successes");
+        println!("    Scramble length {len} had {count} results ({:.3} %)", pct(*count));
+    }
 
-    let mut length_counts: HashMap<usize, usize> = HashMap::default();
-    for len in scramble_lengths {
-        *length_counts.entry(len).or_default() += 1;
+    for (err, count) in errors {
+        let err = err.expect_err("Partitioned to only the failures");
+        println!("    {count} states could not be solved ({:.3} %): {err}", pct(count));
     }
 
-    let mut items: Vec<(usize, usize)> = length_counts.into_iter().collect();
-    items.sort();
+    if compare_theoretical {
+        let sampled: HashMap<usize, usize> = lengths
+            .into_iter()
+            .map(|(len, count)| (len.expect("Partitioned to only the successes"), count))
+            .collect();
+        print_depth_comparison(alg, &sampled);
+    }
+}
+
+/// Writes the count of each solution length, shortest first, with a header row. States which
+/// couldn't be solved have no length, so they're left out.
+fn write_length_csv(path: &Path, results: &HashMap<Result<usize, SolveError>, usize>) -> io::Result<()> {
+    let mut lengths: Vec<(usize, usize)> = results
+        .iter()
+        .filter_map(|(result, count)| result.ok().map(|len| (len, *count)))
+        .collect();
+    lengths.sort();
+
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "length,count")?;
+    for (len, count) in lengths {
+        writeln!(file, "{len},{count}")?;
+    }
+
+    file.flush()
+}
+
+/// The enumeration which gives the exact depth distribution for the puzzle, if it's small enough
+/// to have one
+fn theoretical_alg(alg: ScrambleAlg) -> Option<ConfigAlg> {
+    match alg {
+        ScrambleAlg::Floppy1x2x2 => Some(ConfigAlg::Floppy1x2x2),
+        ScrambleAlg::Floppy1x2x3 => Some(ConfigAlg::Floppy1x2x3),
+        ScrambleAlg::Floppy1x3x3 => Some(ConfigAlg::Floppy1x3x3),
+        ScrambleAlg::Cuboid2x2x3 => Some(ConfigAlg::Cuboid2x2x3),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => Some(ConfigAlg::Cuboid2x3x3),
+        ScrambleAlg::DinoCube { either_solution: false } => Some(ConfigAlg::DinoCubeOneSolution),
+        ScrambleAlg::DinoCube { either_solution: true } => Some(ConfigAlg::DinoCubeEitherSolution),
+        ScrambleAlg::IvyCube => Some(ConfigAlg::IvyCube),
+        ScrambleAlg::SquareZero => Some(ConfigAlg::SquareZero),
+        ScrambleAlg::SquareOneShape => Some(ConfigAlg::SquareOneShape),
+        ScrambleAlg::MirrorPocketCube => Some(ConfigAlg::MirrorPocketCube),
+        ScrambleAlg::PyraminxWithTips => Some(ConfigAlg::PyraminxWithTips),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true }
+        | ScrambleAlg::Bandaged3x3x3With1x2x3
+        | ScrambleAlg::RediCube
+        | ScrambleAlg::CurvyCopter
+        | ScrambleAlg::HelicopterCube => None,
+    }
+}
+
+fn print_depth_comparison(alg: ScrambleAlg, sampled: &HashMap<usize, usize>) {
+    let Some(config_alg) = theoretical_alg(alg) else {
+        println!(
+            "No full enumeration for {}, so nothing to compare against",
+            alg.nice_name()
+        );
+        return;
+    };
+
+    println!(
+        "Enumerating {} for the theoretical distribution...",
+        config_alg.nice_name()
+    );
+    let (_, theoretical) = enumerate_alg(config_alg, None, false).expect("No limit, so should always finish");
 
-    for (len, count) in items {
+    let comparison = scrambles::compare_depth_distribution(sampled, &theoretical);
+
+    println!("{:>6} {:>10} {:>12} {:>12}", "Depth", "Count", "Sampled", "Theoretical");
+    for row in comparison.rows.iter() {
         println!(
-            "    Scramble length {len} had {count} results ({:.3} %)",
-            ((count * 100) as f64) / (NUM_SCRAMBLES as f64)
+            "{:>6} {:>10} {:>10.3} % {:>10.3} %",
+            row.depth,
+            row.sampled,
+            row.sampled_fraction * 100.0,
+            row.theoretical_fraction * 100.0
         );
     }
+
+    let verdict = if comparison.within_tolerance() {
+        "consistent with uniform random states"
+    } else {
+        "NOT consistent with uniform random states"
+    };
+    println!(
+        "Chi-square {:.3} with {} degrees of freedom; {verdict}",
+        comparison.chi_square, comparison.degrees_of_freedom
+    );
 }
 
-fn random_scramble(alg: ScrambleAlg) {
+fn random_scramble(args: ScrambleArgs) {
+    let ScrambleArgs {
+        max_fuel,
+        random_walk,
+        pattern_db,
+        alg,
+    } = args;
+
     // TODO: make this a clap argument
     const NUM_SCRAMBLES: usize = 10;
+
+    if let Some(num_moves) = random_walk {
+        random_walk_scrambles(alg, num_moves, NUM_SCRAMBLES);
+        return;
+    }
+
+    if pattern_db.is_some() && !matches!(alg, ScrambleAlg::IvyCube | ScrambleAlg::SquareOneShape) {
+        println!("There's no pattern database support for {}", alg.nice_name());
+        return;
+    }
     println!("Computing {NUM_SCRAMBLES} random scrambles for {}", alg.nice_name());
 
     // TODO: make this a clap argument
     // hard-coded seed for reproducibility
-    let mut rng = StdRng::from_seed([15; 32]);
+    let rng = StdRng::from_seed([15; 32]);
     // random seed for actual scrambling
-    // let mut rng = StdRng::from_entropy();
+    // let rng = StdRng::from_entropy();
 
     let setup_time = Instant::now();
 
     let mut scrambler: Box<dyn FnMut() -> Result<String, SolveError>> = match alg {
-        ScrambleAlg::Floppy1x2x2 => {
-            Box::new(|| scrambles::random_scramble_string::<_, _, Floppy1x2x2, _>(&mut rng, &no_heuristic))
-        }
-        ScrambleAlg::Floppy1x2x3 => {
-            Box::new(|| scrambles::random_scramble_string::<_, _, Floppy1x2x3, _>(&mut rng, &no_heuristic))
+        ScrambleAlg::Floppy1x2x2 => scramble_strings(Scrambler::<Floppy1x2x2, _>::new(|| no_heuristic, max_fuel), rng),
+        ScrambleAlg::Floppy1x2x3 => scramble_strings(Scrambler::<Floppy1x2x3, _>::new(|| no_heuristic, max_fuel), rng),
+        ScrambleAlg::Floppy1x3x3 => scramble_strings(Scrambler::<Floppy1x3x3, _>::new(|| no_heuristic, max_fuel), rng),
+        ScrambleAlg::Cuboid2x2x3 => scramble_strings(Scrambler::new(cuboid_2x2x3::make_heuristic, max_fuel), rng),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => {
+            scramble_strings(Scrambler::new(cuboid_2x3x3::make_heuristic, max_fuel), rng)
         }
-        ScrambleAlg::Floppy1x3x3 => {
-            Box::new(|| scrambles::random_scramble_string::<_, _, Floppy1x3x3, _>(&mut rng, &no_heuristic))
-        }
-        ScrambleAlg::Cuboid2x2x3 => {
-            let heuristic = cuboid_2x2x3::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
-        }
-        ScrambleAlg::Cuboid2x3x3 => {
-            let heuristic = cuboid_2x3x3::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
-        }
-        ScrambleAlg::DinoCube => {
-            let heuristic = dino_cube::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true } => {
+            scramble_strings(Scrambler::new(cuboid_2x3x3::make_super_heuristic, max_fuel), rng)
         }
+        ScrambleAlg::DinoCube { either_solution: false } => scramble_strings(
+            Scrambler::<DinoCubeOneSolution, _>::new(dino_cube::make_heuristic, max_fuel),
+            rng,
+        ),
+        ScrambleAlg::DinoCube { either_solution: true } => scramble_strings(
+            Scrambler::<DinoCube, _>::new(dino_cube::make_heuristic_multigoal, max_fuel),
+            rng,
+        ),
         ScrambleAlg::Bandaged3x3x3With1x2x3 => {
-            let heuristic = bandaged_3x3x3_1x2x3::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
+            scramble_strings(Scrambler::new(bandaged_3x3x3_1x2x3::make_heuristic, max_fuel), rng)
         }
         ScrambleAlg::RediCube => {
             // heuristic is expensive, turn it down for few scrambles
             // TODO: make this depth a config argument
-            let heuristic = redi_cube::make_heuristic(7);
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
+            scramble_strings(Scrambler::new(|| redi_cube::make_heuristic(7), max_fuel), rng)
         }
-        ScrambleAlg::SquareZero => {
-            let heuristic = square_zero::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
-        }
-        ScrambleAlg::IvyCube => {
-            let heuristic = ivy_cube::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
-        }
-        ScrambleAlg::CurvyCopter => {
-            let heuristic = curvy_copter::make_heuristic();
-            Box::new(move || scrambles::random_scramble_string(&mut rng, &heuristic))
+        ScrambleAlg::SquareZero => scramble_strings(Scrambler::new(square_zero::make_heuristic, max_fuel), rng),
+        ScrambleAlg::SquareOneShape => scramble_strings(
+            Scrambler::new(
+                || heuristic_or_pattern_db(pattern_db.as_deref(), square_one_shape::make_heuristic),
+                max_fuel,
+            ),
+            rng,
+        ),
+        ScrambleAlg::IvyCube => scramble_strings(
+            Scrambler::new(
+                || heuristic_or_pattern_db(pattern_db.as_deref(), ivy_cube::make_heuristic),
+                max_fuel,
+            ),
+            rng,
+        ),
+        ScrambleAlg::CurvyCopter => scramble_strings(Scrambler::new(curvy_copter::make_heuristic, max_fuel), rng),
+        ScrambleAlg::MirrorPocketCube => {
+            scramble_strings(Scrambler::new(mirror_pocket_cube::make_heuristic, max_fuel), rng)
         }
+        ScrambleAlg::PyraminxWithTips => scramble_strings(Scrambler::new(pyraminx::make_heuristic, max_fuel), rng),
+        ScrambleAlg::HelicopterCube => scramble_strings(Scrambler::new(helicopter_cube::make_heuristic, max_fuel), rng),
     };
 
     let elapsed = setup_time.elapsed();
@@ -380,6 +1181,9 @@ fn random_scramble(alg: ScrambleAlg) {
                 println!("Could not find a solution to random state");
                 println!("    (out of gas with max fuel of length {max_fuel} took {elapsed:?})");
             }
+            Err(SolveError::BadSolution { step }) => {
+                panic!("Solution to random state failed to replay at step {step}; the puzzle definition is broken");
+            }
         }
     }
 
@@ -387,12 +1191,876 @@ fn random_scramble(alg: ScrambleAlg) {
     println!("Total scramble generation time {elapsed:?}");
 }
 
+/// Turns a scrambler, and the rng it should draw from, into the next-scramble function that
+/// random-scramble loops over; the heuristic inside is built already, and shared by every call
+fn scramble_strings<S, H>(scrambler: Scrambler<S, H>, mut rng: StdRng) -> Box<dyn FnMut() -> Result<String, SolveError>>
+where
+    S: RandomInit + Solvable + 'static,
+    S::Move: CanReverse + Display,
+    H: Heuristic<S> + 'static,
+{
+    Box::new(move || scrambler.scramble_string(&mut rng))
+}
+
+/// The pattern database at `pattern_db`, if there is one, or else the puzzle's usual heuristic
+fn heuristic_or_pattern_db<S, H, F>(pattern_db: Option<&Path>, make_heuristic: F) -> Box<dyn Fn(&S) -> usize>
+where
+    S: State + 'static,
+    S::UniqueKey: serde::de::DeserializeOwned,
+    H: Heuristic<S> + 'static,
+    F: FnOnce() -> H,
+{
+    match pattern_db {
+        Some(path) => {
+            let db = load_pattern_db::<S::UniqueKey>(path).expect("Should be able to load the pattern database");
+            Box::new(move |s: &S| db.estimated_remaining_cost(s))
+        }
+        None => {
+            let h = make_heuristic();
+            Box::new(move |s: &S| h.estimated_remaining_cost(s))
+        }
+    }
+}
+
+fn dump_pattern_db(alg: PatternDbAlg, path: &Path, hash_seed: Option<usize>) {
+    let start = Instant::now();
+
+    let (name, counts) = match alg {
+        PatternDbAlg::IvyCube => ("Ivy Cube", enumerate_and_dump_pattern_db::<IvyCube>(path, hash_seed)),
+        PatternDbAlg::SquareOneShape => (
+            "Square One Shape",
+            enumerate_and_dump_pattern_db::<SquareOneShape>(path, hash_seed),
+        ),
+    };
+    let counts = counts.expect("Should be able to write the pattern database");
+
+    println!(
+        "Wrote the pattern database to {} in {:?}",
+        path.display(),
+        start.elapsed()
+    );
+    nice_print(name, &counts, &PrintOptions::default());
+}
+
+fn print_random_walks<S>(num_moves: usize, num_scrambles: usize)
+where
+    S: HasSolvedState + Solvable,
+    S::Move: Display,
+{
+    // hard-coded seed for reproducibility, same as the solved scrambles
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for i in 0..num_scrambles {
+        let moves = scrambles::random_walk_scramble::<S, _>(&mut rng, num_moves);
+        println!("Random walk {i}: {}", scrambles::format_moves(&moves));
+    }
+}
+
+fn random_walk_scrambles(alg: ScrambleAlg, num_moves: usize, num_scrambles: usize) {
+    println!(
+        "Computing {num_scrambles} random walks of {num_moves} moves for {}",
+        alg.nice_name()
+    );
+
+    match alg {
+        ScrambleAlg::Floppy1x2x2 => print_random_walks::<Floppy1x2x2>(num_moves, num_scrambles),
+        ScrambleAlg::Floppy1x2x3 => print_random_walks::<Floppy1x2x3>(num_moves, num_scrambles),
+        ScrambleAlg::Floppy1x3x3 => print_random_walks::<Floppy1x3x3>(num_moves, num_scrambles),
+        ScrambleAlg::Cuboid2x2x3 => print_random_walks::<Cuboid2x2x3>(num_moves, num_scrambles),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => print_random_walks::<Cuboid2x3x3>(num_moves, num_scrambles),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true } => {
+            print_random_walks::<SuperCuboid2x3x3>(num_moves, num_scrambles)
+        }
+        // which solved state the walk starts from doesn't change the moves available
+        ScrambleAlg::DinoCube { .. } => print_random_walks::<DinoCube>(num_moves, num_scrambles),
+        ScrambleAlg::Bandaged3x3x3With1x2x3 => print_random_walks::<Bandaged3x3x3with1x2x3>(num_moves, num_scrambles),
+        ScrambleAlg::RediCube => print_random_walks::<RediCube>(num_moves, num_scrambles),
+        ScrambleAlg::IvyCube => print_random_walks::<IvyCube>(num_moves, num_scrambles),
+        ScrambleAlg::SquareZero => print_random_walks::<SquareZero>(num_moves, num_scrambles),
+        ScrambleAlg::SquareOneShape => print_random_walks::<SquareOneShape>(num_moves, num_scrambles),
+        ScrambleAlg::CurvyCopter => print_random_walks::<curvy_copter::CurvyCopter>(num_moves, num_scrambles),
+        ScrambleAlg::MirrorPocketCube => print_random_walks::<MirrorPocketCube>(num_moves, num_scrambles),
+        ScrambleAlg::PyraminxWithTips => print_random_walks::<PyraminxWithTips>(num_moves, num_scrambles),
+        ScrambleAlg::HelicopterCube => print_random_walks::<HelicopterCube>(num_moves, num_scrambles),
+    }
+}
+
+fn layer_size(args: LayerArgs) {
+    let LayerArgs { depth, alg } = args;
+
+    println!("Finding the states at depth {depth} for {}", alg.nice_name());
+
+    let start = Instant::now();
+
+    let count = match alg {
+        ScrambleAlg::Floppy1x2x2 => states_at_depth::<Floppy1x2x2>(depth).len(),
+        ScrambleAlg::Floppy1x2x3 => states_at_depth::<Floppy1x2x3>(depth).len(),
+        ScrambleAlg::Floppy1x3x3 => states_at_depth::<Floppy1x3x3>(depth).len(),
+        ScrambleAlg::Cuboid2x2x3 => states_at_depth::<Cuboid2x2x3>(depth).len(),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => states_at_depth::<Cuboid2x3x3>(depth).len(),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true } => states_at_depth::<SuperCuboid2x3x3>(depth).len(),
+        ScrambleAlg::DinoCube { either_solution: false } => states_at_depth::<DinoCube>(depth).len(),
+        ScrambleAlg::DinoCube { either_solution: true } => {
+            states_at_depth_from(vec![DinoCube::solved(), DinoCube::solved_mirrored()], depth).len()
+        }
+        ScrambleAlg::Bandaged3x3x3With1x2x3 => states_at_depth::<Bandaged3x3x3with1x2x3>(depth).len(),
+        ScrambleAlg::RediCube => states_at_depth::<RediCube>(depth).len(),
+        ScrambleAlg::IvyCube => states_at_depth::<IvyCube>(depth).len(),
+        ScrambleAlg::SquareZero => states_at_depth::<SquareZero>(depth).len(),
+        ScrambleAlg::SquareOneShape => states_at_depth::<SquareOneShape>(depth).len(),
+        ScrambleAlg::CurvyCopter => states_at_depth::<curvy_copter::CurvyCopter>(depth).len(),
+        ScrambleAlg::MirrorPocketCube => states_at_depth::<MirrorPocketCube>(depth).len(),
+        ScrambleAlg::PyraminxWithTips => states_at_depth::<PyraminxWithTips>(depth).len(),
+        ScrambleAlg::HelicopterCube => states_at_depth::<HelicopterCube>(depth).len(),
+    };
+
+    println!("Found {count} states at depth {depth} in {:?}", start.elapsed());
+}
+
+/// How many random moves the bandaged 3x3x3's heuristic sweep scrambles with
+const BANDAGED_SWEEP_WALK: usize = 12;
+
+/// Sweeps a plain cache heuristic (BFS out from the start state) over the given depths
+fn sweep_cache<S>(depths: RangeInclusive<usize>, samples: usize) -> Result<Vec<SweepRow>, SolveError>
+where
+    S: State + Solvable + RandomInit,
+{
+    scrambles::heuristic_sweep::<S, _, _>(depths, samples, bounded_cache::<S>)
+}
+
+fn heuristic_sweep(args: SweepArgs) {
+    let SweepArgs {
+        min_depth,
+        max_depth,
+        samples,
+        alg,
+    } = args;
+
+    println!(
+        "Sweeping cache depths {min_depth} to {max_depth} for {}, with {samples} scrambles each",
+        alg.nice_name()
+    );
+
+    let depths = min_depth..=max_depth;
+
+    let rows = match alg {
+        ScrambleAlg::Floppy1x2x2 => sweep_cache::<Floppy1x2x2>(depths, samples),
+        ScrambleAlg::Floppy1x2x3 => sweep_cache::<Floppy1x2x3>(depths, samples),
+        ScrambleAlg::Floppy1x3x3 => sweep_cache::<Floppy1x3x3>(depths, samples),
+        ScrambleAlg::Cuboid2x2x3 => sweep_cache::<Cuboid2x2x3>(depths, samples),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => sweep_cache::<Cuboid2x3x3>(depths, samples),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true } => sweep_cache::<SuperCuboid2x3x3>(depths, samples),
+        ScrambleAlg::DinoCube { either_solution: false } => sweep_cache::<DinoCubeOneSolution>(depths, samples),
+        ScrambleAlg::DinoCube { either_solution: true } => {
+            scrambles::heuristic_sweep::<DinoCube, _, _>(depths, samples, |depth| {
+                bounded_cache_from(vec![DinoCube::solved(), DinoCube::solved_mirrored()], depth)
+            })
+        }
+        ScrambleAlg::Bandaged3x3x3With1x2x3 => {
+            // no random states for this one yet, so walk out from solved instead; short walks are
+            // biased toward easy states, but keep the shallow caches finishing
+            let mut rng = StdRng::from_seed([15; 32]);
+            let states: Vec<Bandaged3x3x3with1x2x3> = (0..samples)
+                .map(|_| {
+                    let walk =
+                        scrambles::random_walk_scramble::<Bandaged3x3x3with1x2x3, _>(&mut rng, BANDAGED_SWEEP_WALK);
+                    scrambles::apply_moves(&Bandaged3x3x3with1x2x3::start(), &walk)
+                })
+                .collect();
+            scrambles::heuristic_sweep_on(&states, depths, bounded_cache::<Bandaged3x3x3with1x2x3>)
+        }
+        ScrambleAlg::RediCube => sweep_cache::<RediCube>(depths, samples),
+        ScrambleAlg::IvyCube => sweep_cache::<IvyCube>(depths, samples),
+        ScrambleAlg::SquareZero => sweep_cache::<SquareZero>(depths, samples),
+        ScrambleAlg::SquareOneShape => sweep_cache::<SquareOneShape>(depths, samples),
+        ScrambleAlg::CurvyCopter => sweep_cache::<curvy_copter::CurvyCopter>(depths, samples),
+        ScrambleAlg::MirrorPocketCube => sweep_cache::<MirrorPocketCube>(depths, samples),
+        ScrambleAlg::PyraminxWithTips => sweep_cache::<PyraminxWithTips>(depths, samples),
+        ScrambleAlg::HelicopterCube => sweep_cache::<HelicopterCube>(depths, samples),
+    };
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => panic!("Could not solve a sampled state: {e:?}"),
+    };
+
+    println!();
+    println!(
+        "{:>6} {:>15} {:>15} {:>15}",
+        "Depth", "Setup", "Mean solve", "Total nodes"
+    );
+    for row in rows {
+        let setup = format!("{:.3?}", row.setup_time);
+        let solve = format!("{:.3?}", row.mean_solve_time);
+        println!("{:>6} {setup:>15} {solve:>15} {:>15}", row.depth, row.total_nodes);
+    }
+}
+
+fn print_moves<S>()
+where
+    S: Solvable,
+    S::Move: Display,
+{
+    println!("{}", scrambles::format_moves(&S::all_moves()));
+}
+
+fn list_moves(alg: MovesAlg) {
+    match alg {
+        MovesAlg::Floppy1x2x2 => print_moves::<Floppy1x2x2>(),
+        MovesAlg::Floppy1x2x3 => print_moves::<Floppy1x2x3>(),
+        MovesAlg::Floppy1x3x3 => print_moves::<Floppy1x3x3>(),
+        MovesAlg::Cuboid2x2x3 => print_moves::<Cuboid2x2x3>(),
+        MovesAlg::Cuboid2x3x3 => print_moves::<Cuboid2x3x3>(),
+        MovesAlg::SuperCuboid2x3x3 => print_moves::<SuperCuboid2x3x3>(),
+        MovesAlg::DinoCube => print_moves::<DinoCubeOneSolution>(),
+        MovesAlg::Bandaged3x3x3With1x2x3 => print_moves::<Bandaged3x3x3with1x2x3>(),
+        MovesAlg::RediCube => print_moves::<RediCube>(),
+        MovesAlg::IvyCube => print_moves::<IvyCube>(),
+        MovesAlg::SquareZero => print_moves::<SquareZero>(),
+        MovesAlg::SuperSkewb => print_moves::<skewb::SuperSkewb>(),
+    }
+}
+
+fn print_state_code<S>(puzzle_id: &str, scramble: &str)
+where
+    S: State<UniqueKey = u64> + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+{
+    match scrambles::parse_scramble::<S>(scramble) {
+        Ok(moves) => println!(
+            "{}",
+            scrambles::encode_state(puzzle_id, &scrambles::apply_moves(&S::start(), &moves))
+        ),
+        Err(e) => {
+            eprintln!("Could not parse scramble: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn encode(alg: DescribeAlg, scramble: &str) {
+    let puzzle_id = alg.to_possible_value().expect("No skipped values");
+
+    match alg {
+        DescribeAlg::PocketCube => print_state_code::<PocketCube>(puzzle_id.get_name(), scramble),
+        DescribeAlg::RediCube => print_state_code::<RediCube>(puzzle_id.get_name(), scramble),
+        DescribeAlg::DinoCube => print_state_code::<DinoCube>(puzzle_id.get_name(), scramble),
+    }
+}
+
+fn print_decoded_solution<S, H>(puzzle_id: &str, code: &str, h: &H)
+where
+    S: DecodeKey + Solvable,
+    S::Move: Display,
+    H: Heuristic<S>,
+{
+    let state = match scrambles::decode_state::<S>(puzzle_id, code) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    match idasearch::solve(&state, h) {
+        Ok(solution) => println!("{}", scrambles::format_moves(&solution)),
+        Err(e) => {
+            eprintln!("Could not solve the decoded state: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn decode(code: &str) {
+    let alg = match scrambles::split_state_code(code)
+        .and_then(|(id, _)| <DescribeAlg as ValueEnum>::from_str(id, false).map_err(|_| format!("Unknown puzzle {id}")))
+    {
+        Ok(alg) => alg,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let puzzle_id = alg.to_possible_value().expect("No skipped values");
+
+    match alg {
+        DescribeAlg::PocketCube => {
+            print_decoded_solution::<PocketCube, _>(puzzle_id.get_name(), code, &pocket_cube::make_heuristic())
+        }
+        DescribeAlg::RediCube => {
+            print_decoded_solution::<RediCube, _>(puzzle_id.get_name(), code, &redi_cube::make_heuristic(7))
+        }
+        DescribeAlg::DinoCube => print_decoded_solution::<DinoCube, _>(
+            puzzle_id.get_name(),
+            code,
+            &dino_cube::make_heuristic_toward(SolveTarget::Nearest),
+        ),
+    }
+}
+
+fn write_dataset<S, H>(puzzle_id: &str, count: usize, seed: u64, h: &H, out: &Path)
+where
+    S: State<UniqueKey = u64> + Solvable + RandomInit + Sync + Send,
+    S::Move: Display + Send,
+    H: Heuristic<S> + Sync,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let states: Vec<S> = (0..count).map(|_| S::random_state(&mut rng)).collect();
+
+    let result =
+        File::create(out).and_then(|file| scrambles::write_dataset(puzzle_id, states, h, BufWriter::new(file)));
+
+    if let Err(e) = result {
+        eprintln!("Could not write the dataset to {}: {e}", out.display());
+        std::process::exit(1);
+    }
+}
+
+fn dataset(alg: DescribeAlg, count: usize, seed: u64, out: &Path) {
+    let puzzle_id = alg.to_possible_value().expect("No skipped values");
+    let puzzle_id = puzzle_id.get_name();
+
+    match alg {
+        DescribeAlg::PocketCube => {
+            write_dataset::<PocketCube, _>(puzzle_id, count, seed, &pocket_cube::make_heuristic(), out)
+        }
+        DescribeAlg::RediCube => {
+            write_dataset::<RediCube, _>(puzzle_id, count, seed, &redi_cube::make_heuristic(7), out)
+        }
+        DescribeAlg::DinoCube => write_dataset::<DinoCube, _>(
+            puzzle_id,
+            count,
+            seed,
+            &dino_cube::make_heuristic_toward(SolveTarget::Nearest),
+            out,
+        ),
+    }
+}
+
+fn print_inverse<S>(scramble: &str)
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + CanReverse + Display,
+{
+    let (inverse, round_trips) = match scrambles::invert_scramble::<S>(scramble) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Could not parse scramble: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    println!("{}", scrambles::format_moves(&inverse));
+
+    if !round_trips {
+        eprintln!("Warning: applying the scramble and then this inverse did not get back to solved");
+    }
+}
+
+fn invert(alg: MovesAlg, scramble: &str) {
+    match alg {
+        MovesAlg::Floppy1x2x2 => print_inverse::<Floppy1x2x2>(scramble),
+        MovesAlg::Floppy1x2x3 => print_inverse::<Floppy1x2x3>(scramble),
+        MovesAlg::Floppy1x3x3 => print_inverse::<Floppy1x3x3>(scramble),
+        MovesAlg::Cuboid2x2x3 => print_inverse::<Cuboid2x2x3>(scramble),
+        MovesAlg::Cuboid2x3x3 => print_inverse::<Cuboid2x3x3>(scramble),
+        MovesAlg::SuperCuboid2x3x3 => print_inverse::<SuperCuboid2x3x3>(scramble),
+        MovesAlg::DinoCube => print_inverse::<DinoCubeOneSolution>(scramble),
+        MovesAlg::Bandaged3x3x3With1x2x3 => print_inverse::<Bandaged3x3x3with1x2x3>(scramble),
+        MovesAlg::RediCube => print_inverse::<RediCube>(scramble),
+        MovesAlg::IvyCube => print_inverse::<IvyCube>(scramble),
+        MovesAlg::SquareZero => print_inverse::<SquareZero>(scramble),
+        MovesAlg::SuperSkewb => print_inverse::<skewb::SuperSkewb>(scramble),
+    }
+}
+
+fn print_connection<S>(from: &str, to: &str, cache_depth: usize)
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + CanReverse + Display,
+{
+    match scrambles::connect_scrambles::<S>(from, to, cache_depth) {
+        Ok(moves) => println!("{}", scrambles::format_moves(&moves)),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn connect(alg: MovesAlg, from: &str, to: &str, cache_depth: usize) {
+    match alg {
+        MovesAlg::Floppy1x2x2 => print_connection::<Floppy1x2x2>(from, to, cache_depth),
+        MovesAlg::Floppy1x2x3 => print_connection::<Floppy1x2x3>(from, to, cache_depth),
+        MovesAlg::Floppy1x3x3 => print_connection::<Floppy1x3x3>(from, to, cache_depth),
+        MovesAlg::Cuboid2x2x3 => print_connection::<Cuboid2x2x3>(from, to, cache_depth),
+        MovesAlg::Cuboid2x3x3 => print_connection::<Cuboid2x3x3>(from, to, cache_depth),
+        MovesAlg::SuperCuboid2x3x3 => print_connection::<SuperCuboid2x3x3>(from, to, cache_depth),
+        MovesAlg::DinoCube => print_connection::<DinoCubeOneSolution>(from, to, cache_depth),
+        MovesAlg::Bandaged3x3x3With1x2x3 => print_connection::<Bandaged3x3x3with1x2x3>(from, to, cache_depth),
+        MovesAlg::RediCube => print_connection::<RediCube>(from, to, cache_depth),
+        MovesAlg::IvyCube => print_connection::<IvyCube>(from, to, cache_depth),
+        MovesAlg::SquareZero => print_connection::<SquareZero>(from, to, cache_depth),
+        MovesAlg::SuperSkewb => print_connection::<skewb::SuperSkewb>(from, to, cache_depth),
+    }
+}
+
+fn stream_solutions<S, H>(h: &H, max_fuel: Option<usize>, cache_size: Option<usize>)
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+    H: Heuristic<S>,
+{
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    let cache = cache_size.map(SolveCache::new);
+
+    if let Err(e) = scrambles::solve_stream::<S, _, _, _>(stdin, stdout, h, max_fuel, cache.as_ref()) {
+        eprintln!("Could not stream scrambles: {e}");
+        std::process::exit(1);
+    }
+
+    report_cache_hits(cache.as_ref());
+}
+
+/// Same as [stream_solutions], but toward whichever of the Dino Cube's solutions `target` picks
+fn stream_dino_solutions(target: SolveTarget, max_fuel: Option<usize>, cache_size: Option<usize>) {
+    let h = dino_cube::make_heuristic_toward(target);
+    let max_fuel = max_fuel.unwrap_or_else(DinoCube::max_fuel);
+
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    let cache = cache_size.map(SolveCache::new);
+    let solve_uncached = |state: &DinoCube| dino_cube::solve_toward(state, target, &h, max_fuel);
+    let solve = |state: &DinoCube| match &cache {
+        Some(cache) => cache.solve(state, solve_uncached),
+        None => solve_uncached(state),
+    };
+
+    if let Err(e) = scrambles::solve_stream_by(stdin, stdout, solve) {
+        eprintln!("Could not stream scrambles: {e}");
+        std::process::exit(1);
+    }
+
+    report_cache_hits(cache.as_ref());
+}
+
+fn report_cache_hits<S: State + Solvable>(cache: Option<&SolveCache<S>>) {
+    if let Some(cache) = cache {
+        eprintln!("{} solutions came from the cache", cache.hits());
+    }
+}
+
+fn solve_stream(alg: StreamAlg, max_fuel: Option<usize>, target: Option<DinoTarget>, cache_size: Option<usize>) {
+    if target.is_some() && alg != StreamAlg::DinoCube {
+        eprintln!("Only the Dino Cube has more than one solution to target");
+        std::process::exit(1);
+    }
+
+    // each heuristic is built once, up front, and shared by every line
+    match alg {
+        StreamAlg::Floppy1x2x2 => stream_solutions::<Floppy1x2x2, _>(&no_heuristic, max_fuel, cache_size),
+        StreamAlg::Floppy1x2x3 => stream_solutions::<Floppy1x2x3, _>(&no_heuristic, max_fuel, cache_size),
+        StreamAlg::Floppy1x3x3 => stream_solutions::<Floppy1x3x3, _>(&no_heuristic, max_fuel, cache_size),
+        StreamAlg::PocketCube => stream_solutions(&pocket_cube::make_heuristic(), max_fuel, cache_size),
+        StreamAlg::Cuboid2x2x3 => stream_solutions(&cuboid_2x2x3::make_heuristic(), max_fuel, cache_size),
+        StreamAlg::Cuboid2x3x3 => stream_solutions(&cuboid_2x3x3::make_heuristic(), max_fuel, cache_size),
+        StreamAlg::DinoCube => match target {
+            None => stream_solutions::<DinoCubeOneSolution, _>(&dino_cube::make_heuristic(), max_fuel, cache_size),
+            Some(target) => stream_dino_solutions(target.into(), max_fuel, cache_size),
+        },
+        StreamAlg::Bandaged3x3x3With1x2x3 => {
+            stream_solutions(&bandaged_3x3x3_1x2x3::make_heuristic(), max_fuel, cache_size)
+        }
+        StreamAlg::RediCube => stream_solutions(&redi_cube::make_heuristic(7), max_fuel, cache_size),
+        StreamAlg::IvyCube => stream_solutions(&ivy_cube::make_heuristic(), max_fuel, cache_size),
+        StreamAlg::SquareZero => stream_solutions(&square_zero::make_heuristic(), max_fuel, cache_size),
+        StreamAlg::Rubiks3x3x3 => stream_solutions(&rubiks_3x3x3::make_heuristic(), max_fuel, cache_size),
+    }
+}
+
+fn run_puzzle_repl<S, H>(h: &H, cache_size: Option<usize>)
+where
+    S: HasSolvedState + Solvable + PrettyState,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+    H: Heuristic<S>,
+{
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    let mut rng = StdRng::from_entropy();
+    let cache = cache_size.map(SolveCache::new);
+
+    if let Err(e) = repl::run_repl::<S, _, _, _, _>(stdin, stdout, h, cache.as_ref(), &mut rng) {
+        eprintln!("REPL stopped: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn repl(alg: StreamAlg, cache_size: Option<usize>) {
+    match alg {
+        StreamAlg::Floppy1x2x2 => run_puzzle_repl::<Floppy1x2x2, _>(&no_heuristic, cache_size),
+        StreamAlg::Floppy1x2x3 => run_puzzle_repl::<Floppy1x2x3, _>(&no_heuristic, cache_size),
+        StreamAlg::Floppy1x3x3 => run_puzzle_repl::<Floppy1x3x3, _>(&no_heuristic, cache_size),
+        StreamAlg::PocketCube => run_puzzle_repl(&pocket_cube::make_heuristic(), cache_size),
+        StreamAlg::Cuboid2x2x3 => run_puzzle_repl(&cuboid_2x2x3::make_heuristic(), cache_size),
+        StreamAlg::Cuboid2x3x3 => run_puzzle_repl(&cuboid_2x3x3::make_heuristic(), cache_size),
+        StreamAlg::DinoCube => run_puzzle_repl::<DinoCubeOneSolution, _>(&dino_cube::make_heuristic(), cache_size),
+        StreamAlg::Bandaged3x3x3With1x2x3 => run_puzzle_repl(&bandaged_3x3x3_1x2x3::make_heuristic(), cache_size),
+        StreamAlg::RediCube => run_puzzle_repl(&redi_cube::make_heuristic(7), cache_size),
+        StreamAlg::IvyCube => run_puzzle_repl(&ivy_cube::make_heuristic(), cache_size),
+        StreamAlg::SquareZero => run_puzzle_repl(&square_zero::make_heuristic(), cache_size),
+        StreamAlg::Rubiks3x3x3 => run_puzzle_repl(&rubiks_3x3x3::make_heuristic(), cache_size),
+    }
+}
+
+fn compare_pruning_from<T>(name: &str, starts: Vec<T>)
+where
+    T: State + Solvable + Clone,
+{
+    let (full_time, full_counts) =
+        enumerate_state_space_started(starts.clone(), None).expect("No state limit, so should finish");
+    let pruned = enumerate_state_space_pruned(starts, None).expect("No state limit, so should finish");
+
+    assert_eq!(
+        full_counts, pruned.counts,
+        "Pruning redundant moves should not change the counts"
+    );
+
+    let all_moves = pruned.neighbors_generated + pruned.neighbors_skipped;
+    let pct_skipped = (pruned.neighbors_skipped as f64) / (all_moves as f64) * 100.0;
+
+    nice_print(name, &pruned.counts, &PrintOptions::default());
+    println!("Without pruning: {all_moves} neighbors generated in {full_time:?}");
+    println!(
+        "With pruning: {} neighbors generated ({pct_skipped:.1}% skipped) in {:?}",
+        pruned.neighbors_generated, pruned.elapsed
+    );
+}
+
+fn compare_pruning(alg: ScrambleAlg) {
+    let name = alg.nice_name();
+
+    match alg {
+        ScrambleAlg::Floppy1x2x2 => compare_pruning_from(name, vec![Floppy1x2x2::start()]),
+        ScrambleAlg::Floppy1x2x3 => compare_pruning_from(name, vec![Floppy1x2x3::start()]),
+        ScrambleAlg::Floppy1x3x3 => compare_pruning_from(name, vec![Floppy1x3x3::start()]),
+        ScrambleAlg::Cuboid2x2x3 => compare_pruning_from(name, vec![Cuboid2x2x3::start()]),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: false } => compare_pruning_from(name, vec![Cuboid2x3x3::start()]),
+        ScrambleAlg::Cuboid2x3x3 { super_domino: true } => compare_pruning_from(name, vec![SuperCuboid2x3x3::solved()]),
+        ScrambleAlg::DinoCube { either_solution: false } => compare_pruning_from(name, vec![DinoCube::start()]),
+        ScrambleAlg::DinoCube { either_solution: true } => {
+            compare_pruning_from(name, vec![DinoCube::solved(), DinoCube::solved_mirrored()])
+        }
+        ScrambleAlg::Bandaged3x3x3With1x2x3 => compare_pruning_from(name, vec![Bandaged3x3x3with1x2x3::start()]),
+        ScrambleAlg::RediCube => compare_pruning_from(name, vec![RediCube::solved()]),
+        ScrambleAlg::IvyCube => compare_pruning_from(name, vec![IvyCube::start()]),
+        ScrambleAlg::SquareZero => compare_pruning_from(name, vec![SquareZero::start()]),
+        ScrambleAlg::SquareOneShape => compare_pruning_from(name, vec![SquareOneShape::start()]),
+        #[cfg(not(feature = "jumbling"))]
+        ScrambleAlg::CurvyCopter => compare_pruning_from(name, vec![curvy_copter::CurvyCopter::start()]),
+        // with every center free to go anywhere, there's no hope of enumerating the whole thing;
+        // layer-size still works, since it stops at the given depth
+        #[cfg(feature = "jumbling")]
+        ScrambleAlg::CurvyCopter => println!("{name} is too big to enumerate with jumbling on; try layer-size instead"),
+        ScrambleAlg::MirrorPocketCube => compare_pruning_from(name, vec![MirrorPocketCube::start()]),
+        // the tips multiply the no-tips puzzle by 81, which is too many states to hold at once
+        ScrambleAlg::PyraminxWithTips => println!("{name} is too big to enumerate; try layer-size instead"),
+        ScrambleAlg::HelicopterCube => println!("{name} is too big to enumerate; try layer-size instead"),
+    }
+}
+
+fn compare_transition_table() {
+    let (hashset_time, expected) = enumerate_state_space::<Pyraminx>(None).expect("No state limit, so should finish");
+    let (dense_time, dense) = enumerate_dense::<Pyraminx>(None).expect("No state limit, so should finish");
+
+    let build_start = Instant::now();
+    let table = TransitionTable::new::<Pyraminx>();
+    let build_time = build_start.elapsed();
+    let (table_time, tabled) = enumerate_with_table(&table, None).expect("No state limit, so should finish");
+
+    assert_eq!(dense, expected, "Dense enumeration should match the HashSet");
+    assert_eq!(tabled, expected, "Table enumeration should match the HashSet");
+
+    nice_print("Pyraminx (No Tips)", &expected, &PrintOptions::default());
+    println!("With a HashSet: {hashset_time:?}");
+    println!("With a dense bitset: {dense_time:?}");
+    println!(
+        "With a transition table: {table_time:?} (plus {build_time:?} to build the table; {:?} total)",
+        build_time + table_time
+    );
+}
+
+fn compare_canonical_frontier() {
+    let plain = enumerate_deduped_frontier::<PocketCube>(None, false).expect("No state limit, so should finish");
+    let canonical = enumerate_deduped_frontier::<PocketCube>(None, true).expect("No state limit, so should finish");
+
+    assert_eq!(
+        canonical.counts, plain.counts,
+        "Weighting each class by its orbit should give the same counts"
+    );
+
+    nice_print("Pocket Cube", &plain.counts, &PrintOptions::default());
+    println!(
+        "Every state: at most {} in the frontier, in {:?}",
+        plain.peak_frontier, plain.elapsed
+    );
+    println!(
+        "One state per symmetry class: at most {} in the frontier, in {:?}",
+        canonical.peak_frontier, canonical.elapsed
+    );
+}
+
+fn compare_key_frontier<T>(name: &str)
+where
+    T: FromKey + Hash + Eq,
+    T::UniqueKey: Copy,
+{
+    let (state_time, states) = enumerate_state_space::<T>(None).expect("No state limit, so should finish");
+    let (key_time, keys) = enumerate_key_frontier::<T>(None).expect("No state limit, so should finish");
+
+    assert_eq!(keys, states, "Holding keys shouldn't change the counts");
+
+    nice_print(name, &states, &PrintOptions::default());
+    println!(
+        "Holding states ({} bytes each) took {state_time:?}",
+        std::mem::size_of::<T>()
+    );
+    println!(
+        "Holding keys ({} bytes each) took {key_time:?}",
+        std::mem::size_of::<T::UniqueKey>()
+    );
+}
+
+fn compare_parallel_solve(samples: usize) {
+    let mut rng = StdRng::from_seed([15; 32]);
+    let heuristic = redi_cube::make_heuristic(7);
+
+    for i in 0..samples {
+        let state = RediCube::random_state(&mut rng);
+
+        let start = Instant::now();
+        let serial = idasearch::solve(&state, &heuristic).expect("Random states should be solvable");
+        let serial_time = start.elapsed();
+
+        let start = Instant::now();
+        let parallel = idasearch::solve_parallel(&state, &heuristic).expect("Random states should be solvable");
+        let parallel_time = start.elapsed();
+
+        assert_eq!(parallel, serial, "Splitting the search shouldn't change the solution");
+
+        println!(
+            "State {i}: {} moves; {serial_time:?} on one thread, {parallel_time:?} split up",
+            serial.len()
+        );
+    }
+}
+
+fn hint(goal: HintGoal, scramble: &str, max_len: usize) {
+    let moves = match scrambles::parse_scramble::<PocketCube>(scramble) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("Could not parse scramble: {e}");
+            std::process::exit(1);
+        }
+    };
+    let state = scrambles::apply_moves(&PocketCube::solved(), &moves);
+
+    let goal = match goal {
+        HintGoal::DFace => PocketCube::d_face_colored,
+        HintGoal::DLayer => PocketCube::d_layer_solved,
+    };
+
+    match idasearch::solve_predicate(&state, &idasearch::no_heuristic, goal, max_len) {
+        Ok(hint) => println!("{} ({} moves)", scrambles::format_moves(&hint), hint.len()),
+        Err(e) => println!("{e}"),
+    }
+}
+
+fn step(goal: StepGoal, scramble: &str, cache_depth: usize, max_len: usize) {
+    let moves = match scrambles::parse_scramble::<Rubiks3x3x3>(scramble) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("Could not parse scramble: {e}");
+            std::process::exit(1);
+        }
+    };
+    let state = scrambles::apply_moves(&Rubiks3x3x3::solved(), &moves);
+
+    let solution = match goal {
+        StepGoal::FirstTwoLayers => idasearch::solve_predicate(
+            &state,
+            &predicates::first_two_layers_heuristic(cache_depth),
+            predicates::first_two_layers,
+            max_len,
+        ),
+        StepGoal::LastLayerOriented => idasearch::solve_predicate(
+            &state,
+            &predicates::last_layer_oriented_heuristic(cache_depth),
+            predicates::last_layer_oriented,
+            max_len,
+        ),
+        StepGoal::RouxFirstBlock => idasearch::solve_predicate(
+            &state,
+            &predicates::roux_first_block_heuristic(cache_depth),
+            predicates::roux_first_block,
+            max_len,
+        ),
+    };
+
+    match solution {
+        Ok(step) => println!("{} ({} moves)", scrambles::format_moves(&step), step.len()),
+        Err(e) => println!("{e}"),
+    }
+}
+
+fn redi_edge_cycle(args: EdgeCycleArgs) {
+    let EdgeCycleArgs { edges, max_len } = args;
+    let cycle: [EdgeCubelet; 3] = edges.try_into().expect("Clap should require exactly three edges");
+
+    let start = Instant::now();
+    let found = redi_cube::find_edge_cycle(cycle, max_len);
+    let elapsed = start.elapsed();
+
+    match found {
+        Some(alg) => println!(
+            "{} ({} moves, found in {elapsed:?})",
+            scrambles::format_moves(&alg),
+            alg.len()
+        ),
+        None => println!("No algorithm of at most {max_len} moves does that (searched for {elapsed:?})"),
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::ConfigDepth(alg) => configuration_depth(alg),
-        Commands::ConfigDepthSampling(alg) => config_depth_sampling(alg),
-        Commands::RandomScramble(alg) => random_scramble(alg),
+    scrambles::with_threads(cli.threads, || run(cli.command));
+}
+
+fn run(command: Commands) {
+    match command {
+        Commands::ConfigDepth(args) => configuration_depth(args),
+        Commands::ConfigDepthSampling(args) => config_depth_sampling(args),
+        Commands::RandomScramble(args) => random_scramble(args),
+        Commands::DiameterTable { max_states } => diameter_table(max_states),
+        Commands::LayerSize(args) => layer_size(args),
+        Commands::HeuristicSweep(args) => heuristic_sweep(args),
+        Commands::ListMoves { alg } => list_moves(alg),
+        Commands::Invert { alg, scramble } => invert(alg, &scramble),
+        Commands::Connect {
+            alg,
+            from,
+            to,
+            cache_depth,
+        } => connect(alg, &from, &to, cache_depth),
+        Commands::SolveStream {
+            alg,
+            max_fuel,
+            target,
+            cache_size,
+        } => solve_stream(alg, max_fuel, target, cache_size),
+        Commands::Repl { alg, cache_size } => repl(alg, cache_size),
+        Commands::DumpPatternDb { alg, path, hash_seed } => dump_pattern_db(alg, &path, hash_seed),
+        Commands::ComparePruning(alg) => compare_pruning(alg),
+        Commands::CompareTransitionTable => compare_transition_table(),
+        Commands::CompareCanonicalFrontier => compare_canonical_frontier(),
+        Commands::CompareKeyFrontier { alg } => match alg {
+            KeyFrontierAlg::CoinPyraminx => compare_key_frontier::<CoinPyraminx>("Coin Pyraminx"),
+            KeyFrontierAlg::Cuboid2x3x3 => compare_key_frontier::<Cuboid2x3x3>("Cuboid 2x3x3"),
+        },
+        Commands::CompareParallelSolve { samples } => compare_parallel_solve(samples),
+        Commands::RediEdgeCycle(args) => redi_edge_cycle(args),
+        Commands::Encode { alg, scramble } => encode(alg, &scramble),
+        Commands::Decode { code } => decode(&code),
+        Commands::Hint {
+            goal,
+            scramble,
+            max_len,
+        } => hint(goal, &scramble, max_len),
+        Commands::Step {
+            goal,
+            scramble,
+            cache_depth,
+            max_len,
+        } => step(goal, &scramble, cache_depth, max_len),
+        Commands::DescribeKey { alg, key } => {
+            let description = match alg {
+                DescribeAlg::PocketCube => PocketCube::describe_key(&key),
+                DescribeAlg::RediCube => RediCube::describe_key(&key),
+                DescribeAlg::DinoCube => DinoCube::describe_key(&key),
+            };
+            println!("{description}");
+        }
+        Commands::Dataset { alg, count, seed, out } => dataset(alg, count, seed, &out),
+        Commands::CacheDepth { alg } => {
+            let depth = match alg {
+                CacheDepthAlg::Floppy1x2x2 => min_complete_depth::<Floppy1x2x2>(),
+                CacheDepthAlg::Floppy1x2x3 => min_complete_depth::<Floppy1x2x3>(),
+                CacheDepthAlg::Floppy1x3x3 => min_complete_depth::<Floppy1x3x3>(),
+                CacheDepthAlg::IvyCube => min_complete_depth::<IvyCube>(),
+                CacheDepthAlg::SquareZero => min_complete_depth::<SquareZero>(),
+                CacheDepthAlg::PocketCube => min_complete_depth::<PocketCube>(),
+            };
+            println!("A bounded cache of depth {depth} holds every state");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diameter_rows_match_enumeration() {
+        fn expected<T: cubesearch::State + std::hash::Hash + Eq>() -> DiameterRow {
+            let (_, counts) = enumerate_state_space::<T>(None).unwrap();
+            DiameterRow {
+                states: counts.values().sum(),
+                diameter: counts.keys().copied().max().unwrap(),
+            }
+        }
+
+        let cases = [
+            (ConfigAlg::Floppy1x2x2, expected::<Floppy1x2x2>()),
+            (ConfigAlg::Floppy1x2x3, expected::<Floppy1x2x3>()),
+            (ConfigAlg::Floppy1x3x3, expected::<Floppy1x3x3>()),
+            (ConfigAlg::BigFloppy1x3x4, expected::<Floppy1xMxN<1, 2>>()),
+            (ConfigAlg::Cuboid2x2x3, expected::<Cuboid2x2x3>()),
+        ];
+
+        for (alg, expected) in cases {
+            let (_, actual) = diameter_row(alg, None).unwrap();
+            assert_eq!(actual, expected, "Row for {alg:?} should match");
+        }
+    }
+
+    #[test]
+    fn sampled_depths_are_written_as_csv() {
+        let results = sample_depths(ScrambleAlg::Floppy1x2x3, StdRng::from_seed([15; 32]), 100, 0, None)();
+        assert_eq!(results.values().sum::<usize>(), 100);
+
+        let path = std::env::temp_dir().join(format!("twisty-depths-{}.csv", std::process::id()));
+        write_length_csv(&path, &results).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // every state is solvable, so every result is a length, and gets a row
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "length,count");
+        assert_eq!(lines.len(), results.len() + 1);
+
+        let total: usize = lines[1..]
+            .iter()
+            .map(|line| line.split_once(',').unwrap().1.parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn diameter_rows_respect_max_states() {
+        // 192 states total
+        assert!(diameter_row(ConfigAlg::Floppy1x3x3, Some(192)).is_some());
+        assert!(diameter_row(ConfigAlg::Floppy1x3x3, Some(191)).is_none());
     }
 }
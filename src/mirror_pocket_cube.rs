@@ -1,4 +1,12 @@
-use crate::cubesearch::State;
+use derive_more::Display;
+use enum_iterator::{all, Sequence};
+use rand::Rng;
+
+use crate::cubesearch::{HasSolvedState, State};
+use crate::idasearch::heuristic_helpers::bounded_cache;
+use crate::idasearch::{Heuristic, Solvable};
+use crate::moves::{impl_can_reverse, CubeMoveAmt};
+use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd)]
 enum Cubelet {
@@ -304,3 +312,220 @@ impl State for MirrorPocketCube {
         *self
     }
 }
+
+impl HasSolvedState for MirrorPocketCube {}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Sequence)]
+pub enum Move {
+    #[display(fmt = "R{}", _0)]
+    R(CubeMoveAmt),
+    #[display(fmt = "F{}", _0)]
+    F(CubeMoveAmt),
+    #[display(fmt = "D{}", _0)]
+    D(CubeMoveAmt),
+}
+
+impl_can_reverse!(Move {
+    self_inverse: [],
+    by_amount: [R, F, D],
+});
+
+impl Solvable for MirrorPocketCube {
+    type Move = Move;
+
+    /// The pieces are only told apart by their shape, so "solved" means the shape is a cube again;
+    /// that is, every piece is a piece of the right size, turned the right way. Pieces of the same
+    /// size are interchangeable, and since the state only records sizes, swapping two of them
+    /// gives literally the same state. The same goes for turning the whole cube around the BUL
+    /// corner, which takes the solved state to itself.
+    fn is_solved(&self) -> bool {
+        self == &<Self as CubeState>::start()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        // turning the same face twice in a row is never useful
+        matches!(
+            (last_move, next_move),
+            (Move::R(_), Move::R(_)) | (Move::F(_), Move::F(_)) | (Move::D(_), Move::D(_))
+        )
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        let (turn, amt): (fn(&Self) -> Self, CubeMoveAmt) = match m {
+            Move::R(amt) => (Self::r, amt),
+            Move::F(amt) => (Self::f, amt),
+            Move::D(amt) => (Self::d, amt),
+        };
+
+        match amt {
+            CubeMoveAmt::One => turn(self),
+            CubeMoveAmt::Two => turn(&turn(self)),
+            CubeMoveAmt::Rev => turn(&turn(&turn(self))),
+        }
+    }
+
+    fn max_fuel() -> usize {
+        // found by enumeration
+        9
+    }
+}
+
+impl RandomInit for MirrorPocketCube {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        const SIZES: [Cubelet; 7] = [
+            Cubelet::Narrow,
+            Cubelet::Narrow,
+            Cubelet::Narrow,
+            Cubelet::Wide,
+            Cubelet::Wide,
+            Cubelet::Wide,
+            Cubelet::BigCube,
+        ];
+
+        // every arrangement of the pieces is reachable, and so is every twist of the pieces other
+        // than the big cube (which can soak up whatever twist the others need); so it's enough to
+        // shuffle, then twist. Since same-size pieces look alike, the shuffle can't tell apart
+        // permutations which only swap them, but each arrangement is still equally likely.
+        loop {
+            let (pieces, _) = crate::random_helpers::shuffle_any(r, SIZES);
+            let mut orientations = pieces.iter().map(|&piece| match piece {
+                Cubelet::BigCube => Orientation::Fixed,
+                _ => [Orientation::Normal, Orientation::CW, Orientation::CCW][r.gen_range(0..3)],
+            });
+
+            let mut next = || orientations.next().expect("One orientation per piece");
+
+            let out = Self {
+                pos: PosState {
+                    ful: pieces[0],
+                    fur: pieces[1],
+                    bur: pieces[2],
+                    fdl: pieces[3],
+                    fdr: pieces[4],
+                    bdl: pieces[5],
+                    bdr: pieces[6],
+                },
+                orr: OrientationState {
+                    ful: next(),
+                    fur: next(),
+                    bur: next(),
+                    fdl: next(),
+                    fdr: next(),
+                    bdl: next(),
+                    bdr: next(),
+                },
+            };
+
+            // turning the whole cube around BUL gives the same physical puzzle, and most states
+            // have three ways to write them down (but some only have one); keeping just the
+            // canonical one makes every physical state equally likely
+            if out.should_count_as_config() {
+                return out;
+            }
+        }
+    }
+}
+
+pub fn make_heuristic() -> impl Heuristic<MirrorPocketCube> {
+    // the whole space is tiny, so just store all of it
+    bounded_cache::<MirrorPocketCube>(MirrorPocketCube::max_fuel())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::solve;
+
+    #[test]
+    fn swapping_same_size_pieces_is_still_solved() {
+        let start = <MirrorPocketCube as CubeState>::start();
+
+        // FUL and BUR both hold narrow pieces, so trading them changes nothing
+        let mut swapped = start;
+        std::mem::swap(&mut swapped.pos.ful, &mut swapped.pos.bur);
+        std::mem::swap(&mut swapped.orr.ful, &mut swapped.orr.bur);
+
+        assert!(swapped.is_solved());
+        assert_eq!(solve(&swapped, &make_heuristic()), Ok(vec![]));
+
+        // but trading pieces of different sizes does change things
+        let mut swapped = start;
+        std::mem::swap(&mut swapped.pos.ful, &mut swapped.pos.fur);
+        assert!(!swapped.is_solved());
+    }
+
+    #[test]
+    fn config_count_matches_rotation_orbits() {
+        use ahash::HashSet;
+
+        use crate::cubesearch::enumerate_state_space;
+
+        // every reachable way of writing a state down, not deduplicated at all
+        let start = <MirrorPocketCube as CubeState>::start();
+        let mut raw: HashSet<MirrorPocketCube> = HashSet::default();
+        raw.insert(start);
+
+        let mut frontier = vec![start];
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for state in frontier {
+                state.neighbors(&mut |neighbor| {
+                    if raw.insert(neighbor) {
+                        next.push(neighbor);
+                    }
+                });
+            }
+            frontier = next;
+        }
+
+        // each state is one of three ways of writing down the same physical state, unless the
+        // twist leaves it alone, in which case it's the only one
+        let fixed = raw.iter().filter(|s| s.twist() == **s).count();
+        assert_eq!((raw.len() - fixed) % 3, 0);
+        let orbits = fixed + (raw.len() - fixed) / 3;
+
+        for state in raw.iter() {
+            let a = state.twist();
+            let b = a.twist();
+            assert_eq!(b.twist(), *state, "Twisting three times should do nothing");
+            assert!(
+                raw.contains(&a) && raw.contains(&b),
+                "Twists of reachable states are reachable"
+            );
+
+            let counted = HashSet::from_iter([*state, a, b])
+                .into_iter()
+                .filter(|s| s.should_count_as_config())
+                .count();
+            assert_eq!(counted, 1, "Exactly one way of writing {state:?} should count");
+        }
+
+        let (_, counts) = enumerate_state_space::<MirrorPocketCube>(None).unwrap();
+        assert_eq!(counts.values().sum::<u128>(), orbits as u128);
+    }
+
+    #[test]
+    fn random_states_solve_within_max_fuel() {
+        let heuristic = make_heuristic();
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..100 {
+            let state = MirrorPocketCube::random_state(&mut rng);
+            assert!(state.should_count_as_config(), "Random states should be canonical");
+
+            let solution = solve(&state, &heuristic).expect("Random states should be solvable");
+            assert!(solution.len() <= MirrorPocketCube::max_fuel());
+        }
+    }
+}
@@ -1,13 +1,53 @@
 //! Traits and reusable data structures for describing moves.
-use derive_more::Display;
-use enum_iterator::Sequence;
+use std::fmt::Display;
+
+use enum_iterator::{all, Sequence};
 
 pub trait CanReverse: Sized {
     fn reverse(&self) -> Self;
 }
 
+/// Implements [CanReverse] for a move enum whose variants each either undo themselves (half turns
+/// of a cuboid, say) or carry an amount which is itself [CanReverse]:
+///
+/// ```ignore
+/// impl_can_reverse!(Move {
+///     self_inverse: [R2, F2],
+///     by_amount: [U, D],
+/// });
+/// ```
+///
+/// Every variant has to be listed in one or the other, or the match won't be exhaustive.
+macro_rules! impl_can_reverse {
+    ($t:ident { self_inverse: [$($inv:ident),* $(,)?], by_amount: [$($amt:ident),* $(,)?] $(,)? }) => {
+        impl $crate::moves::CanReverse for $t {
+            fn reverse(&self) -> Self {
+                match self {
+                    $($t::$inv => $t::$inv,)*
+                    $($t::$amt(amt) => $t::$amt($crate::moves::CanReverse::reverse(amt)),)*
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use impl_can_reverse;
+
+/// A move string which doesn't match any move of the puzzle
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "Unrecognized move: {:?}", _0)]
+pub struct ParseMoveError(pub String);
+
+/// Parses a move by finding the one which displays as the given string. The move sets are small,
+/// so this is cheap enough, and means parsing always agrees with how moves are printed.
+pub fn parse_by_display<M: Sequence + Display>(s: &str) -> Result<M, ParseMoveError> {
+    all::<M>()
+        .find(|m| m.to_string() == s)
+        .ok_or_else(|| ParseMoveError(s.to_string()))
+}
+
 /// Typical moves for a cube twist -- one step, two steps, rev (three steps)
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display, Sequence)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, derive_more::Display, Sequence)]
 pub enum CubeMoveAmt {
     #[display(fmt = "")]
     One,
@@ -28,7 +68,7 @@ impl CanReverse for CubeMoveAmt {
 }
 
 /// Typical moves for a cube twist -- one step, two steps, rev (three steps)
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display, Sequence)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, derive_more::Display, Sequence)]
 pub enum CornerTwistAmt {
     #[display(fmt = "")]
     Cw,
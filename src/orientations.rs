@@ -1,11 +1,13 @@
+use ahash::HashMap;
 use enum_iterator::Sequence;
 use rand::distributions::Standard;
 use rand::prelude::Distribution;
 use rand::Rng;
 
 /// A 3-variant orientation enum which matches corners on many common types of twist puzzles.
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Sequence)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Sequence, Default)]
 pub enum CornerOrientation {
+    #[default]
     Normal,
     CW,
     CCW,
@@ -52,12 +54,6 @@ impl std::ops::Add for CornerOrientation {
     }
 }
 
-impl Default for CornerOrientation {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 impl Distribution<CornerOrientation> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> CornerOrientation {
         let val = rng.gen_range(0..3);
@@ -130,18 +126,13 @@ impl CornerOrientation {
 }
 
 /// A two-variant orientation enum which behaves like edges in many common types of twist puzzles.
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence, Default)]
 pub enum EdgeOrientation {
+    #[default]
     Normal,
     Flipped,
 }
 
-impl Default for EdgeOrientation {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 impl EdgeOrientation {
     #[inline(always)]
     pub fn flipped(&self) -> Self {
@@ -173,3 +164,149 @@ impl EdgeOrientation {
         *bits = (*bits << 1) + (self.as_u8_one_bit() as u64)
     }
 }
+
+/// A four-variant orientation enum, for square-ish centers which can be turned a quarter at a time,
+/// such as on the Super Skewb. Turns are clockwise, looking at the face from outside.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence, Default)]
+pub enum CenterOrientation {
+    #[default]
+    Normal,
+    CW,
+    Half,
+    CCW,
+}
+
+impl CenterOrientation {
+    /// A simple cast to u8 for encoding. Guaranteed to have minimal size, that is,
+    /// using at most two bits.
+    #[inline(always)]
+    pub fn as_u8_two_bits(self) -> u8 {
+        match self {
+            CenterOrientation::Normal => 0,
+            CenterOrientation::CW => 1,
+            CenterOrientation::Half => 2,
+            CenterOrientation::CCW => 3,
+        }
+    }
+
+    #[inline(always)]
+    fn from_two_bits(bits: u8) -> Self {
+        match bits % 4 {
+            0 => CenterOrientation::Normal,
+            1 => CenterOrientation::CW,
+            2 => CenterOrientation::Half,
+            _ => CenterOrientation::CCW,
+        }
+    }
+
+    #[inline(always)]
+    pub fn cw(self) -> Self {
+        self + CenterOrientation::CW
+    }
+
+    #[inline(always)]
+    pub fn half(self) -> Self {
+        self + CenterOrientation::Half
+    }
+
+    #[inline(always)]
+    pub fn ccw(self) -> Self {
+        self + CenterOrientation::CCW
+    }
+
+    pub fn total(orientations: &[CenterOrientation]) -> CenterOrientation {
+        orientations
+            .iter()
+            .copied()
+            .fold(CenterOrientation::Normal, |total, o| total + o)
+    }
+}
+
+impl std::ops::Add for CenterOrientation {
+    type Output = CenterOrientation;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::from_two_bits(self.as_u8_two_bits() + rhs.as_u8_two_bits())
+    }
+}
+
+impl std::ops::Neg for CenterOrientation {
+    type Output = CenterOrientation;
+
+    #[inline(always)]
+    fn neg(self) -> Self::Output {
+        Self::from_two_bits(4 - self.as_u8_two_bits())
+    }
+}
+
+impl Distribution<CenterOrientation> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> CenterOrientation {
+        CenterOrientation::from_two_bits(rng.gen_range(0..4))
+    }
+}
+
+/// Extends a count of positions by depth to account for `num_pieces` extra pieces (like the
+/// Pyraminx tips) which turn independently of everything else, and take one move each to fix.
+/// Each piece is either solved or in one of `states_per_piece` wrong states, so a position with k
+/// of them wrong is k moves further out, and there are C(n, k) * states_per_piece^k such positions
+/// for each one in the base count.
+pub fn multiply_by_independent_trivial_group(
+    counts: HashMap<u128, u128>,
+    num_pieces: u32,
+    states_per_piece: u128,
+) -> HashMap<u128, u128> {
+    // ways[k] is how many arrangements have exactly k of the pieces wrong
+    let mut ways = Vec::with_capacity(num_pieces as usize + 1);
+    let mut choose: u128 = 1;
+    for k in 0..=num_pieces {
+        ways.push(choose * states_per_piece.pow(k));
+        choose = choose * (num_pieces - k) as u128 / (k + 1) as u128;
+    }
+
+    let mut out = HashMap::default();
+
+    for (cost, count) in counts {
+        for (k, &w) in ways.iter().enumerate() {
+            *out.entry(cost + k as u128).or_insert(0) += count * w;
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(counts: HashMap<u128, u128>) -> Vec<(u128, u128)> {
+        let mut out: Vec<_> = counts.into_iter().collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn independent_pieces_spread_out_the_counts() {
+        // the Pyraminx tips: four of them, each either solved or twisted one of two ways
+        let solved_only = HashMap::from_iter([(0, 1)]);
+        assert_eq!(
+            sorted(multiply_by_independent_trivial_group(solved_only.clone(), 4, 2)),
+            vec![(0, 1), (1, 8), (2, 24), (3, 32), (4, 16)]
+        );
+
+        // six of them, which all together make 3^6 positions
+        let six = sorted(multiply_by_independent_trivial_group(solved_only, 6, 2));
+        assert_eq!(
+            six,
+            vec![(0, 1), (1, 12), (2, 60), (3, 160), (4, 240), (5, 192), (6, 64)]
+        );
+        assert_eq!(six.iter().map(|(_, c)| c).sum::<u128>(), 3u128.pow(6));
+
+        // every base depth gets the same spread, shifted out by its own depth
+        let base = HashMap::from_iter([(0, 1), (1, 3), (2, 5)]);
+        assert_eq!(
+            sorted(multiply_by_independent_trivial_group(base, 1, 2)),
+            vec![(0, 1), (1, 3 + 2), (2, 5 + 6), (3, 10)]
+        );
+    }
+}
@@ -8,9 +8,51 @@ where
     *a = old_c;
 }
 
+/// The rank of a permutation of `0..n` among all n! of them, in lexicographic order, by way of
+/// its Lehmer code; each digit is the number of later pieces which are smaller
+pub fn lehmer_rank(pieces: &[usize]) -> usize {
+    (0..pieces.len()).fold(0, |acc, i| {
+        let smaller_later = pieces[i + 1..].iter().filter(|&&later| later < pieces[i]).count();
+        acc * (pieces.len() - i) + smaller_later
+    })
+}
+
+/// The inverse of [lehmer_rank]; the permutation of `0..N` with the given rank
+pub fn lehmer_unrank<const N: usize>(mut rank: usize) -> [usize; N] {
+    // digits come off last first
+    let mut digits = [0; N];
+    for i in (0..N).rev() {
+        digits[i] = rank % (N - i);
+        rank /= N - i;
+    }
+
+    // each digit picks out a piece by its rank among the ones not used yet
+    let mut unused: Vec<usize> = (0..N).collect();
+    digits.map(|digit| unused.remove(digit))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::permutation_helpers::cycle_cw;
+    use crate::permutation_helpers::{cycle_cw, lehmer_rank, lehmer_unrank};
+
+    #[test]
+    fn lehmer_ranks_are_lexicographic() {
+        assert_eq!(lehmer_rank(&[0, 1, 2, 3]), 0);
+        assert_eq!(lehmer_rank(&[0, 1, 3, 2]), 1);
+        assert_eq!(lehmer_rank(&[1, 0, 2, 3]), 6);
+        assert_eq!(lehmer_rank(&[3, 2, 1, 0]), 23);
+
+        // every rank comes back to the permutation it came from, and they count up in order
+        let mut last: Option<[usize; 5]> = None;
+        for rank in 0..120 {
+            let perm = lehmer_unrank::<5>(rank);
+            assert_eq!(lehmer_rank(&perm), rank);
+            if let Some(last) = last {
+                assert!(last < perm, "{last:?} should come before {perm:?}");
+            }
+            last = Some(perm);
+        }
+    }
 
     #[test]
     fn cycle_cw_test() {
@@ -1,7 +1,23 @@
-use crate::cubesearch::State;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use derive_more::Display;
+use enum_iterator::{all, Sequence};
+use itertools::Itertools;
+use rand::Rng;
+
+use crate::cubesearch::{assert_bit_budget, HasSolvedState, State};
+use crate::describe::{DecodeKey, DescribeKey, KeyReader, PrettyState};
+use crate::idasearch::heuristic_helpers::{bounded_cache, MaxHeuristic, Project, ProjectedCache};
+use crate::idasearch::{Heuristic, Solvable};
+use crate::legality::{permutation_parity, IllegalReason, ValidState};
+use crate::moves::{impl_can_reverse, parse_by_display, CubeMoveAmt, ParseMoveError};
 use crate::orientations::CornerOrientation;
+use crate::random_helpers::shuffle_any;
+use crate::scrambles::RandomInit;
+use crate::symmetry::{CornerPos, CornerState, CornerSymmetries, SymmetricState};
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Sequence, Display)]
 enum Cubelet {
     // we leave one cube in the DBL position, and it never comes up again
     DBR,
@@ -198,6 +214,26 @@ impl CubeState for PocketCube {
     }
 }
 
+/// Every state one move away
+fn each_move<C: CubeState>(state: &C, to_add: &mut impl FnMut(C)) {
+    // three moves -- R/F/D -- with three orientations each (1/2/rev)
+
+    // R
+    to_add(state.r());
+    to_add(state.r().r());
+    to_add(state.r().r().r());
+
+    // F
+    to_add(state.f());
+    to_add(state.f().f());
+    to_add(state.f().f().f());
+
+    // U
+    to_add(state.u());
+    to_add(state.u().u());
+    to_add(state.u().u().u());
+}
+
 impl State for PocketCube {
     type UniqueKey = u64;
 
@@ -205,22 +241,7 @@ impl State for PocketCube {
     where
         Recv: FnMut(Self),
     {
-        // three moves -- R/F/D -- with three orientations each (1/2/rev)
-
-        // R
-        to_add(self.r());
-        to_add(self.r().r());
-        to_add(self.r().r().r());
-
-        // F
-        to_add(self.f());
-        to_add(self.f().f());
-        to_add(self.f().f().f());
-
-        // U
-        to_add(self.u());
-        to_add(self.u().u());
-        to_add(self.u().u().u());
+        each_move(self, to_add);
     }
 
     fn start() -> Self {
@@ -231,7 +252,7 @@ impl State for PocketCube {
         let mut out: u64 = 0;
 
         // can't _quite_ fit it into 32 bits
-        debug_assert!(7 * 3 + 7 * 2 < 64, "State should fit into 64 bits");
+        const { assert_bit_budget(7 * 3 + 7 * 2, 64) };
 
         out = (out << 3) | self.pos.ufl.as_u8_three_bits() as u64;
         out = (out << 3) | self.pos.ufr.as_u8_three_bits() as u64;
@@ -252,3 +273,796 @@ impl State for PocketCube {
         out
     }
 }
+
+impl HasSolvedState for PocketCube {}
+
+/// Just the corner twists, wherever the corners are; there are only 3^6 of these, so the whole
+/// thing can be cached, and it's a cheap lower bound for the whole cube.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct PocketCubeOrientation(OrientationState);
+
+impl State for PocketCubeOrientation {
+    type UniqueKey = u16;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        each_move(&self.0, &mut |orr| to_add(Self(orr)));
+    }
+
+    fn start() -> Self {
+        Self(OrientationState::start())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        let o = &self.0;
+        [o.ufl, o.ufr, o.dfl, o.dfr, o.ubl, o.ubr, o.dbr]
+            .into_iter()
+            .fold(0, |out, orr| (out << 2) | orr.as_u8_two_bits() as u16)
+    }
+}
+
+impl Project<PocketCubeOrientation> for PocketCube {
+    fn project(&self) -> PocketCubeOrientation {
+        PocketCubeOrientation(self.orr)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Sequence)]
+pub enum Move {
+    #[display(fmt = "R{}", _0)]
+    R(CubeMoveAmt),
+    #[display(fmt = "F{}", _0)]
+    F(CubeMoveAmt),
+    #[display(fmt = "U{}", _0)]
+    U(CubeMoveAmt),
+}
+
+impl_can_reverse!(Move {
+    self_inverse: [],
+    by_amount: [R, F, U],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
+impl Solvable for PocketCube {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self == &<Self as CubeState>::start()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        // turning the same face twice in a row is never useful
+        matches!(
+            (last_move, next_move),
+            (Move::R(_), Move::R(_)) | (Move::F(_), Move::F(_)) | (Move::U(_), Move::U(_))
+        )
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        let (turn, amt): (fn(&Self) -> Self, CubeMoveAmt) = match m {
+            Move::R(amt) => (Self::r, amt),
+            Move::F(amt) => (Self::f, amt),
+            Move::U(amt) => (Self::u, amt),
+        };
+
+        match amt {
+            CubeMoveAmt::One => turn(self),
+            CubeMoveAmt::Two => turn(&turn(self)),
+            CubeMoveAmt::Rev => turn(&turn(&turn(self))),
+        }
+    }
+
+    fn max_fuel() -> usize {
+        // every state is within 11 half turns of solved
+        11
+    }
+}
+
+pub fn make_heuristic() -> impl Heuristic<PocketCube> {
+    // the deepest layers are most of the puzzle, so stop well short of them; the twists alone are
+    // few enough to cache completely, and often know better than the fallback past that
+    MaxHeuristic::new(
+        bounded_cache::<PocketCube>(6),
+        ProjectedCache::<PocketCubeOrientation>::new(ORIENTATION_DIAMETER),
+    )
+}
+
+/// No arrangement of the twists alone is further than this from solved; checked in the tests
+const ORIENTATION_DIAMETER: usize = 6;
+
+/// Partial goals, for hints which only get part of the way to solved. DBL never moves, so it
+/// decides what the D layer should be.
+impl PocketCube {
+    /// The D face is all one color, although the pieces under it may be in the wrong places
+    pub fn d_face_colored(&self) -> bool {
+        let (p, o) = (&self.pos, &self.orr);
+        let is_d = |c: Cubelet| matches!(c, Cubelet::DBR | Cubelet::DFL | Cubelet::DFR);
+
+        [p.dbr, p.dfl, p.dfr].into_iter().all(is_d)
+            && [o.dbr, o.dfl, o.dfr]
+                .into_iter()
+                .all(|o| o == CornerOrientation::Normal)
+    }
+
+    /// The D layer is solved; the first step of a layer-by-layer solve
+    pub fn d_layer_solved(&self) -> bool {
+        self.d_face_colored()
+            && (self.pos.dbr, self.pos.dfl, self.pos.dfr) == (Cubelet::DBR, Cubelet::DFL, Cubelet::DFR)
+    }
+}
+
+impl DescribeKey for PocketCube {
+    fn describe_key(key: &u64) -> String {
+        // the positions, in the order uniq_key packs them (after the positions, it packs the
+        // orientations in the same order)
+        const NAMES: [&str; 7] = ["UFL", "UFR", "DFL", "DFR", "UBL", "UBR", "DBR"];
+
+        let mut reader = KeyReader::new(*key);
+
+        let mut orientations: Vec<String> = NAMES
+            .iter()
+            .map(|_| reader.take_value::<CornerOrientation>(2, |o| o.as_u8_two_bits() as u64))
+            .collect();
+        let mut pieces: Vec<String> = NAMES
+            .iter()
+            .map(|_| reader.take_value::<Cubelet>(3, |c| c.as_u8_three_bits() as u64))
+            .collect();
+
+        // they came off last-packed first
+        orientations.reverse();
+        pieces.reverse();
+
+        NAMES
+            .iter()
+            .zip(pieces)
+            .zip(orientations)
+            .map(|((name, piece), orr)| format!("{name}: {piece} ({orr})"))
+            .join("\n")
+    }
+}
+
+impl DecodeKey for PocketCube {
+    fn decode_key(key: u64) -> Option<Self> {
+        let mut reader = KeyReader::new(key);
+
+        // same order as describe_key, and they come off last-packed first
+        let mut orientations = [CornerOrientation::Normal; 7];
+        for orr in orientations.iter_mut().rev() {
+            *orr = reader.take_exact(2, |o: &CornerOrientation| o.as_u8_two_bits() as u64)?;
+        }
+
+        let mut pieces = [Cubelet::DBR; 7];
+        for piece in pieces.iter_mut().rev() {
+            *piece = reader.take_exact(3, |c: &Cubelet| c.as_u8_three_bits() as u64)?;
+        }
+
+        let [ufl, ufr, dfl, dfr, ubl, ubr, dbr] = pieces;
+        let pos = PosState {
+            dbr,
+            dfl,
+            dfr,
+            ubl,
+            ubr,
+            ufl,
+            ufr,
+        };
+
+        let [ufl, ufr, dfl, dfr, ubl, ubr, dbr] = orientations;
+        let orr = OrientationState {
+            dbr,
+            dfl,
+            dfr,
+            ubl,
+            ubr,
+            ufl,
+            ufr,
+        };
+
+        Some(Self { pos, orr })
+    }
+}
+
+impl ValidState for PocketCube {
+    fn is_legal(&self) -> Result<(), IllegalReason> {
+        // with DBL held fixed, every permutation of the other seven is reachable, so this is just
+        // checking that nothing is duplicated
+        let solved = PosState::start();
+        let as_array = |p: &PosState| [p.dbr, p.dfl, p.dfr, p.ubl, p.ubr, p.ufl, p.ufr];
+        permutation_parity(&as_array(&solved), &as_array(&self.pos))?;
+
+        let o = &self.orr;
+        if CornerOrientation::total(&[o.dbr, o.dfl, o.dfr, o.ubl, o.ubr, o.ufl, o.ufr]) != CornerOrientation::Normal {
+            return Err(IllegalReason::TwistedCorner);
+        }
+
+        Ok(())
+    }
+}
+
+impl PrettyState for PocketCube {
+    fn slots(&self) -> Vec<(&'static str, String)> {
+        let (p, o) = (&self.pos, &self.orr);
+
+        vec![
+            ("UFL", format!("{} ({:?})", p.ufl, o.ufl)),
+            ("UFR", format!("{} ({:?})", p.ufr, o.ufr)),
+            ("UBL", format!("{} ({:?})", p.ubl, o.ubl)),
+            ("UBR", format!("{} ({:?})", p.ubr, o.ubr)),
+            ("DFL", format!("{} ({:?})", p.dfl, o.dfl)),
+            ("DFR", format!("{} ({:?})", p.dfr, o.dfr)),
+            ("DBR", format!("{} ({:?})", p.dbr, o.dbr)),
+        ]
+    }
+}
+
+impl RandomInit for PocketCube {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        // with DBL fixed the other seven can be in any order, and any twists which add up to zero
+        let (pos, _) = shuffle_any(r, all::<Cubelet>());
+
+        let mut orr: Vec<CornerOrientation> = (0..6).map(|_| r.gen()).collect();
+        orr.push(CornerOrientation::total(&orr).flip());
+
+        Self {
+            pos: PosState {
+                dbr: pos[0],
+                dfl: pos[1],
+                dfr: pos[2],
+                ubl: pos[3],
+                ubr: pos[4],
+                ufl: pos[5],
+                ufr: pos[6],
+            },
+            orr: OrientationState {
+                dbr: orr[0],
+                dfl: orr[1],
+                dfr: orr[2],
+                ubl: orr[3],
+                ubr: orr[4],
+                ufl: orr[5],
+                ufr: orr[6],
+            },
+        }
+    }
+}
+
+/// Where each corner sits, indexed the same way as [Cubelet::as_u8_three_bits], with the fixed DBL
+/// corner tacked on the end
+const CORNER_POSITIONS: [CornerPos; 8] = [
+    [1, -1, -1],  // DBR
+    [-1, -1, 1],  // DFL
+    [1, -1, 1],   // DFR
+    [-1, 1, -1],  // UBL
+    [1, 1, -1],   // UBR
+    [-1, 1, 1],   // UFL
+    [1, 1, 1],    // UFR
+    [-1, -1, -1], // DBL
+];
+
+const DBL_INDEX: usize = 7;
+
+const CUBELETS: [Cubelet; 7] = [
+    Cubelet::DBR,
+    Cubelet::DFL,
+    Cubelet::DFR,
+    Cubelet::UBL,
+    Cubelet::UBR,
+    Cubelet::UFL,
+    Cubelet::UFR,
+];
+
+// twists count clockwise, which is how CornerOrientation counts them, too
+const ORIENTATIONS: [CornerOrientation; 3] = [CornerOrientation::Normal, CornerOrientation::CW, CornerOrientation::CCW];
+
+impl PocketCube {
+    fn corners(&self) -> CornerState<8> {
+        let (p, o) = (&self.pos, &self.orr);
+        let pieces = [p.dbr, p.dfl, p.dfr, p.ubl, p.ubr, p.ufl, p.ufr].map(|c| c.as_u8_three_bits() as usize);
+        let twists = [o.dbr, o.dfl, o.dfr, o.ubl, o.ubr, o.ufl, o.ufr].map(|o| o.as_u8_two_bits());
+
+        CornerState {
+            pieces: [
+                pieces[0], pieces[1], pieces[2], pieces[3], pieces[4], pieces[5], pieces[6], DBL_INDEX,
+            ],
+            twists: [
+                twists[0], twists[1], twists[2], twists[3], twists[4], twists[5], twists[6], 0,
+            ],
+        }
+    }
+
+    fn from_corners(corners: &CornerState<8>) -> Self {
+        debug_assert_eq!(corners.pieces[DBL_INDEX], DBL_INDEX, "DBL should be held in place");
+        debug_assert_eq!(corners.twists[DBL_INDEX], 0, "DBL should be held in place");
+
+        let c = |i: usize| CUBELETS[corners.pieces[i]];
+        let o = |i: usize| ORIENTATIONS[corners.twists[i] as usize];
+
+        Self {
+            pos: PosState {
+                dbr: c(0),
+                dfl: c(1),
+                dfr: c(2),
+                ubl: c(3),
+                ubr: c(4),
+                ufl: c(5),
+                ufr: c(6),
+            },
+            orr: OrientationState {
+                dbr: o(0),
+                dfl: o(1),
+                dfr: o(2),
+                ubl: o(3),
+                ubr: o(4),
+                ufl: o(5),
+                ufr: o(6),
+            },
+        }
+    }
+}
+
+/// Builds a cube from its corners, in the order of [CORNER_POSITIONS], checking that the result
+/// can actually be reached by turning the cube; so this is the way in for states from outside.
+impl TryFrom<CornerState<8>> for PocketCube {
+    type Error = IllegalReason;
+
+    fn try_from(corners: CornerState<8>) -> Result<Self, Self::Error> {
+        if let Some(piece) = corners.pieces.iter().find(|&&p| p >= CORNER_POSITIONS.len()) {
+            return Err(IllegalReason::OutOfRange(format!("Piece {piece}")));
+        }
+        if let Some(twist) = corners.twists.iter().find(|&&t| t as usize >= ORIENTATIONS.len()) {
+            return Err(IllegalReason::OutOfRange(format!("Twist {twist}")));
+        }
+
+        let solved: Vec<usize> = (0..CORNER_POSITIONS.len()).collect();
+        permutation_parity(&solved, &corners.pieces)?;
+
+        if corners.pieces[DBL_INDEX] != DBL_INDEX || corners.twists[DBL_INDEX] != 0 {
+            return Err(IllegalReason::FixedPieceMoved("DBL"));
+        }
+
+        let out = Self::from_corners(&corners);
+        out.is_legal()?;
+        Ok(out)
+    }
+}
+
+impl PocketCube {
+    /// The keys of all 48 symmetric images of this state (with repeats, if it's symmetric itself)
+    fn image_keys(&self) -> Vec<u64> {
+        static SYMMETRIES: OnceLock<CornerSymmetries<8>> = OnceLock::new();
+        let symmetries = SYMMETRIES.get_or_init(|| CornerSymmetries::new(&CORNER_POSITIONS));
+
+        let corners = self.corners();
+
+        symmetries
+            .images_fixing(&corners, DBL_INDEX)
+            .map(|image| Self::from_corners(&image).uniq_key())
+            .collect()
+    }
+}
+
+impl SymmetricState for PocketCube {
+    fn canonical_under_symmetry(&self) -> Self::UniqueKey {
+        self.image_keys()
+            .into_iter()
+            .min()
+            .expect("There should be at least one symmetry")
+    }
+
+    fn orbit_size(&self) -> u128 {
+        self.image_keys().into_iter().unique().count() as u128
+    }
+}
+
+/// The Pocket Cube, counting states which are symmetric images of each other (including mirror
+/// images) only once
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct PocketCubeUpToSymmetry(PocketCube);
+
+impl State for PocketCubeUpToSymmetry {
+    type UniqueKey = u64;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        // symmetric images are the same distance from solved, so any member of the class will do
+        self.0.neighbors(&mut |next| to_add(Self(next)));
+    }
+
+    fn start() -> Self {
+        Self(<PocketCube as CubeState>::start())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        self.0.canonical_under_symmetry()
+    }
+}
+
+impl HasSolvedState for PocketCubeUpToSymmetry {}
+
+/// The Pocket Cube in the quarter turn metric, where a half turn counts as two moves. Same states
+/// as the usual half turn metric (it's the same group) but they're spread over more depths.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct PocketCubeQtm(PocketCube);
+
+impl State for PocketCubeQtm {
+    type UniqueKey = u64;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        // three moves -- R/F/U -- with two orientations each (1/rev)
+        let cube = &self.0;
+
+        to_add(Self(cube.r()));
+        to_add(Self(cube.r().r().r()));
+
+        to_add(Self(cube.f()));
+        to_add(Self(cube.f().f().f()));
+
+        to_add(Self(cube.u()));
+        to_add(Self(cube.u().u().u()));
+    }
+
+    fn start() -> Self {
+        Self(<PocketCube as CubeState>::start())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        self.0.uniq_key()
+    }
+}
+
+impl HasSolvedState for PocketCubeQtm {}
+
+#[cfg(test)]
+mod hint_tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::{find_algorithm, no_heuristic, solve_predicate};
+    use crate::scrambles::apply_moves;
+    use crate::test_helpers::random_walk_states;
+
+    #[test]
+    fn goals_hold_when_solved() {
+        let solved = <PocketCube as State>::start();
+        assert!(solved.d_face_colored());
+        assert!(solved.d_layer_solved());
+
+        // U doesn't touch the D layer, but R does
+        assert!(solved.u().d_layer_solved());
+        assert!(!solved.r().d_face_colored());
+
+        // but the D face can be one color with its pieces out of place
+        let swapped = find_algorithm::<PocketCube, _>(|s| s.d_face_colored() && !s.d_layer_solved(), 6);
+        assert!(
+            swapped.is_some(),
+            "Some short sequence should swap pieces under the D face"
+        );
+    }
+
+    #[test]
+    fn hints_reach_the_goal_and_are_shortest() {
+        type Goal = fn(&PocketCube) -> bool;
+        let goals: [(&str, Goal); 2] = [
+            ("D face", PocketCube::d_face_colored),
+            ("D layer", PocketCube::d_layer_solved),
+        ];
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for state in random_walk_states::<PocketCube, _>(&mut rng, 20) {
+            for (name, goal) in goals {
+                let hint = solve_predicate(&state, &no_heuristic, goal, 8).expect("Hint should be found");
+                assert!(
+                    goal(&apply_moves(&state, &hint)),
+                    "{hint:?} should reach the {name} goal"
+                );
+
+                if let Some(shorter) = hint.len().checked_sub(1) {
+                    assert!(
+                        solve_predicate(&state, &no_heuristic, goal, shorter).is_err(),
+                        "Nothing shorter than {hint:?} should reach the {name} goal"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubesearch::{enumerate_deduped_frontier, enumerate_state_space};
+
+    #[test]
+    fn random_states_are_legal() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..1000 {
+            assert_eq!(PocketCube::random_state(&mut rng).is_legal(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn orientation_projection_is_small_and_shallow() {
+        let (_, counts) = enumerate_state_space::<PocketCubeOrientation>(None).unwrap();
+
+        // any twists adding up to zero, and each of those is reachable
+        assert_eq!(counts.values().sum::<u128>(), 729);
+        assert_eq!(counts.keys().copied().max(), Some(ORIENTATION_DIAMETER as u128));
+    }
+
+    #[test]
+    fn projected_heuristic_is_admissible() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        use crate::idasearch::solve;
+        use crate::test_helpers::random_walk_states;
+
+        let projected = ProjectedCache::<PocketCubeOrientation>::new(ORIENTATION_DIAMETER);
+        let exact = bounded_cache::<PocketCube>(5);
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..50 {
+            for state in random_walk_states::<PocketCube, _>(&mut rng, 20) {
+                let distance = solve(&state, &exact).unwrap().len();
+                let estimate = projected.estimated_remaining_cost(&state);
+
+                assert!(
+                    estimate <= distance,
+                    "Estimated {estimate} for {state:?}, which is only {distance} from solved"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn solved_description_has_every_corner_home() {
+        let description = <PocketCube as State>::start().describe_state();
+
+        assert_eq!(
+            description,
+            [
+                "UFL: UFL (Normal)",
+                "UFR: UFR (Normal)",
+                "DFL: DFL (Normal)",
+                "DFR: DFR (Normal)",
+                "UBL: UBL (Normal)",
+                "UBR: UBR (Normal)",
+                "DBR: DBR (Normal)",
+            ]
+            .join("\n")
+        );
+
+        // R moves the pieces around the R face, twisting them as it goes
+        let description = <PocketCube as CubeState>::start().r().describe_state();
+        assert!(description.contains("UFR: DFR (CCW)"), "{description}");
+        assert!(description.contains("UFL: UFL (Normal)"), "{description}");
+    }
+
+    #[test]
+    fn twisted_corner_is_illegal() {
+        let state = <PocketCube as CubeState>::start().r().u().f().f().r();
+        assert_eq!(state.is_legal(), Ok(()));
+
+        let mut twisted = state;
+        twisted.orr.ufr = twisted.orr.ufr.cw();
+        assert_eq!(twisted.is_legal(), Err(IllegalReason::TwistedCorner));
+
+        // any swap is fine, since the fixed DBL corner can make up the parity
+        let mut swapped = state;
+        std::mem::swap(&mut swapped.pos.ufl, &mut swapped.pos.ufr);
+        assert_eq!(swapped.is_legal(), Ok(()));
+
+        let mut duplicated = state;
+        duplicated.pos.ufl = duplicated.pos.ufr;
+        assert!(matches!(duplicated.is_legal(), Err(IllegalReason::DuplicatePiece(_))));
+    }
+
+    #[test]
+    fn checked_construction_from_corners() {
+        let state = apply_moves("RUFFR");
+        assert_eq!(PocketCube::try_from(state.corners()), Ok(state));
+        assert_eq!(
+            PocketCube::try_from(<PocketCube as CubeState>::start().corners()),
+            Ok(<PocketCube as CubeState>::start())
+        );
+
+        let mut twisted = state.corners();
+        twisted.twists[0] = (twisted.twists[0] + 1) % 3;
+        let err = PocketCube::try_from(twisted).unwrap_err();
+        assert_eq!(err, IllegalReason::TwistedCorner);
+        assert_eq!(err.to_string(), "The corner twists don't add up to zero");
+
+        let mut duplicated = state.corners();
+        duplicated.pieces[1] = duplicated.pieces[0];
+        assert!(matches!(
+            PocketCube::try_from(duplicated),
+            Err(IllegalReason::DuplicatePiece(_))
+        ));
+
+        let mut moved_dbl = state.corners();
+        moved_dbl.pieces.swap(0, DBL_INDEX);
+        assert_eq!(
+            PocketCube::try_from(moved_dbl),
+            Err(IllegalReason::FixedPieceMoved("DBL"))
+        );
+
+        let mut out_of_range = state.corners();
+        out_of_range.twists[2] = 3;
+        let err = PocketCube::try_from(out_of_range).unwrap_err();
+        assert_eq!(err.to_string(), "Twist 3 is out of range");
+    }
+
+    fn apply_moves(moves: &str) -> PocketCube {
+        moves
+            .chars()
+            .fold(<PocketCube as CubeState>::start(), |cube, m| match m {
+                'R' => cube.r(),
+                'U' => cube.u(),
+                'F' => cube.f(),
+                other => panic!("Unknown move {other}"),
+            })
+    }
+
+    #[test]
+    fn symmetric_images_share_canonical_key() {
+        // each of these moves is written as a run of quarter turns; "RRR" is R'
+        let original = "RUUUFFRRRUFRRUFFF";
+
+        // only the symmetries which keep DBL in place turn R, U, and F turns into each other, so
+        // those are the ones we can write down directly
+        let images = [
+            // reflected through the plane through UFR, DBL, and the F face centers; swaps R and U,
+            // and every turn goes the other way
+            "UUURFFURRRFFFUURRRF",
+            // reflected through the plane swapping U and F, instead
+            "RRRFUURFFFUUURRFFFU",
+            // turned around the UFR-DBL diagonal, taking R to U, U to F, and F to R
+            "UFFFRRUUUFRUUFRRR",
+        ];
+
+        let key = apply_moves(original).canonical_under_symmetry();
+        assert_ne!(key, <PocketCube as CubeState>::start().canonical_under_symmetry());
+
+        for image in images {
+            let image_state = apply_moves(image);
+            assert_ne!(apply_moves(original), image_state, "Images should really move things");
+            assert_eq!(
+                image_state.canonical_under_symmetry(),
+                key,
+                "Image {image} should match"
+            );
+        }
+
+        // and the key really is canonical; it's the same for every state in the class
+        assert!(key <= apply_moves(original).uniq_key());
+    }
+
+    #[test]
+    fn every_image_has_the_same_canonical_key() {
+        // this covers the symmetries which move DBL, and so need the whole cube turned back
+        let symmetries = CornerSymmetries::new(&CORNER_POSITIONS);
+
+        for moves in ["R", "RUFFRRRU", "FFURRRFUUURRF"] {
+            let state = apply_moves(moves);
+            let key = state.canonical_under_symmetry();
+
+            let images: Vec<PocketCube> = symmetries
+                .images_fixing(&state.corners(), DBL_INDEX)
+                .map(|image| PocketCube::from_corners(&image))
+                .collect();
+
+            assert_eq!(images.len(), 48);
+            assert!(images.contains(&state), "The identity should be one of the images");
+
+            for image in images {
+                assert_eq!(image.is_legal(), Ok(()), "Images of {moves} should be legal");
+                assert_eq!(image.canonical_under_symmetry(), key, "Images of {moves} should match");
+            }
+        }
+    }
+
+    #[test]
+    fn different_states_have_different_canonical_keys() {
+        // R and R2 aren't symmetric to each other, and neither is RU to RU'
+        let pairs = [("R", "RR"), ("RU", "RUUU")];
+
+        for (a, b) in pairs {
+            assert_ne!(
+                apply_moves(a).canonical_under_symmetry(),
+                apply_moves(b).canonical_under_symmetry(),
+                "{a} and {b} should not be symmetric"
+            );
+        }
+
+        // but R, U, F, and R' are all the same thing up to symmetry
+        let r_key = apply_moves("R").canonical_under_symmetry();
+        for other in ["U", "F", "RRR", "UUU"] {
+            assert_eq!(
+                apply_moves(other).canonical_under_symmetry(),
+                r_key,
+                "{other} should match R"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn symmetry_classes_match_known_counts() {
+        let (_, counts) = enumerate_state_space::<PocketCubeUpToSymmetry>(None).unwrap();
+
+        let expected: [u128; 12] = [1, 2, 5, 19, 68, 271, 1148, 4915, 18364, 39707, 13225, 77];
+        for (depth, expected) in expected.into_iter().enumerate() {
+            assert_eq!(
+                counts.get(&(depth as u128)),
+                Some(&expected),
+                "Class count at depth {depth}"
+            );
+        }
+        assert_eq!(counts.values().sum::<u128>(), 77802);
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn canonical_frontier_is_smaller_with_the_same_counts() {
+        let plain = enumerate_deduped_frontier::<PocketCube>(None, false).unwrap();
+        let canonical = enumerate_deduped_frontier::<PocketCube>(None, true).unwrap();
+
+        assert_eq!(plain.counts.values().sum::<u128>(), 3674160);
+        assert_eq!(canonical.counts, plain.counts);
+
+        assert!(
+            canonical.peak_frontier * 10 < plain.peak_frontier,
+            "Canonical frontier peaked at {}, against {} for every state",
+            canonical.peak_frontier,
+            plain.peak_frontier
+        );
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn qtm_has_same_states_but_deeper() {
+        let (_, htm) = enumerate_state_space::<PocketCube>(None).unwrap();
+        let (_, qtm) = enumerate_state_space::<PocketCubeQtm>(None).unwrap();
+
+        assert_eq!(htm.values().sum::<u128>(), 3674160);
+        assert_eq!(qtm.values().sum::<u128>(), 3674160);
+
+        // God's number is 11 in HTM but 14 in QTM
+        assert_eq!(htm.keys().max(), Some(&11));
+        assert_eq!(qtm.keys().max(), Some(&14));
+
+        let expected_qtm: [u128; 15] = [
+            1, 6, 27, 120, 534, 2256, 8969, 33058, 114149, 360508, 930588, 1350852, 782536, 90280, 276,
+        ];
+        for (depth, expected) in expected_qtm.into_iter().enumerate() {
+            assert_eq!(qtm.get(&(depth as u128)), Some(&expected), "QTM count at depth {depth}");
+        }
+    }
+}
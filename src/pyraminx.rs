@@ -1,7 +1,19 @@
-use ahash::HashMap;
+use std::fmt::Formatter;
+use std::str::FromStr;
 
-use crate::cubesearch::State;
-use crate::orientations::{CornerOrientation, EdgeOrientation};
+use ahash::HashMap;
+use derive_more::Display;
+use enum_iterator::{all, Sequence};
+use rand::Rng;
+
+use crate::cubesearch::{HasSolvedState, IndexedState, State};
+use crate::idasearch::heuristic_helpers::DenseCache;
+use crate::idasearch::{Heuristic, Solvable};
+use crate::moves::{CanReverse, CornerTwistAmt, ParseMoveError};
+use crate::orientations::{multiply_by_independent_trivial_group, CornerOrientation, EdgeOrientation};
+use crate::permutation_helpers::lehmer_rank;
+use crate::random_helpers::{flips_with_parity, shuffle_with_parity, TwoParity};
+use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 enum EdgeCubelet {
@@ -277,29 +289,342 @@ impl State for Pyraminx {
     }
 }
 
+impl HasSolvedState for Pyraminx {}
+
+impl IndexedState for Pyraminx {
+    // axials in base 3, then the edge permutation, then the edge flips in base 2; only a quarter
+    // of these are reachable (the permutation and the flips both have to be even) but it keeps
+    // the index simple
+    const NUM_INDICES: usize = 81 * 720 * 64;
+
+    fn index(&self) -> usize {
+        let a = &self.axials;
+        let axials = [a.u, a.l, a.r, a.b]
+            .into_iter()
+            .fold(0, |acc, orr| acc * 3 + orr.as_u8_two_bits() as usize);
+
+        let p = &self.edge_pos;
+        let pieces = [p.ul, p.ur, p.ub, p.dl, p.dr, p.df].map(|piece| piece as usize);
+
+        let perm = lehmer_rank(&pieces);
+
+        let o = &self.edge_orr;
+        let flips = [o.ul, o.ur, o.ub, o.dl, o.dr, o.df]
+            .into_iter()
+            .fold(0, |acc, orr| acc * 2 + (orr == EdgeOrientation::Flipped) as usize);
+
+        (axials * 720 + perm) * 64 + flips
+    }
+}
+
+/// State of a pyraminx puzzle including the tips. Each tip is kept relative to the axial under it;
+/// a big turn carries the tip along with the axial, so it leaves that alone, and only the tip turns
+/// change it. That makes the tips independent of everything else, which is the whole trick of
+/// [gn_count_with_tips].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct PyraminxWithTips {
+    core: Pyraminx,
+    tips: AxialState,
+}
+
+impl PyraminxWithTips {
+    fn twisted_tips(&self) -> usize {
+        let t = &self.tips;
+        [t.u, t.l, t.r, t.b]
+            .into_iter()
+            .filter(|&orr| orr != CornerOrientation::Normal)
+            .count()
+    }
+}
+
+impl State for PyraminxWithTips {
+    type UniqueKey = Self;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        for m in all::<Move>() {
+            to_add(self.apply(m));
+        }
+    }
+
+    fn start() -> Self {
+        Self {
+            core: <Pyraminx as PyraminxState>::start(),
+            tips: AxialState::start(),
+        }
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        *self
+    }
+}
+
+impl HasSolvedState for PyraminxWithTips {}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display, Sequence)]
+pub enum Axis {
+    U,
+    L,
+    R,
+    B,
+}
+
+/// The big turns are written in capitals, and the tips in lower case
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Sequence)]
+pub enum Move {
+    Turn(Axis, CornerTwistAmt),
+    Tip(Axis, CornerTwistAmt),
+}
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Turn(axis, amt) => write!(f, "{axis}{amt}"),
+            Move::Tip(axis, amt) => write!(f, "{}{amt}", axis.to_string().to_lowercase()),
+        }
+    }
+}
+
+impl CanReverse for Move {
+    fn reverse(&self) -> Self {
+        match *self {
+            Move::Turn(axis, amt) => Move::Turn(axis, amt.reverse()),
+            Move::Tip(axis, amt) => Move::Tip(axis, amt.reverse()),
+        }
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::moves::parse_by_display(s)
+    }
+}
+
+#[inline(always)]
+fn turn<S: PyraminxState>(state: &S, axis: Axis) -> S {
+    match axis {
+        Axis::U => state.u(),
+        Axis::L => state.l(),
+        Axis::R => state.r(),
+        Axis::B => state.b(),
+    }
+}
+
+impl Solvable for PyraminxWithTips {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self == &<Self as State>::start()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        match (last_move, next_move) {
+            (Move::Turn(a, _), Move::Turn(b, _)) => a == b,
+            // the tips commute with everything, so they can all go at the end, in axis order
+            (Move::Tip(..), Move::Turn(..)) => true,
+            (Move::Tip(a, _), Move::Tip(b, _)) => b <= a,
+            (Move::Turn(..), Move::Tip(..)) => false,
+        }
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        match m {
+            Move::Turn(axis, CornerTwistAmt::Cw) => Self {
+                core: turn(&self.core, axis),
+                ..*self
+            },
+            Move::Turn(axis, CornerTwistAmt::Ccw) => Self {
+                core: turn(&turn(&self.core, axis), axis),
+                ..*self
+            },
+            Move::Tip(axis, CornerTwistAmt::Cw) => Self {
+                tips: turn(&self.tips, axis),
+                ..*self
+            },
+            Move::Tip(axis, CornerTwistAmt::Ccw) => Self {
+                tips: turn(&turn(&self.tips, axis), axis),
+                ..*self
+            },
+        }
+    }
+
+    fn max_fuel() -> usize {
+        // the deepest no-tips states are 11 moves out, and then each tip could be off
+        15
+    }
+}
+
+impl RandomInit for PyraminxWithTips {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        // the edges can be any even permutation, with any even number flipped; the axials and the
+        // tips are independent of them, and of each other
+        let edges = [
+            EdgeCubelet::UL,
+            EdgeCubelet::UR,
+            EdgeCubelet::UB,
+            EdgeCubelet::DL,
+            EdgeCubelet::DR,
+            EdgeCubelet::DF,
+        ];
+        let pos = shuffle_with_parity(r, &edges, TwoParity::Even);
+        let orr = flips_with_parity(r, 6, TwoParity::Even);
+
+        let mut random_axials = || AxialState {
+            u: r.gen(),
+            l: r.gen(),
+            r: r.gen(),
+            b: r.gen(),
+        };
+
+        Self {
+            core: Pyraminx {
+                axials: random_axials(),
+                edge_pos: EdgePositions {
+                    ul: pos[0],
+                    ur: pos[1],
+                    ub: pos[2],
+                    dl: pos[3],
+                    dr: pos[4],
+                    df: pos[5],
+                },
+                edge_orr: EdgeOrientations {
+                    ul: orr[0],
+                    ur: orr[1],
+                    ub: orr[2],
+                    dl: orr[3],
+                    dr: orr[4],
+                    df: orr[5],
+                },
+            },
+            tips: random_axials(),
+        }
+    }
+}
+
+pub fn make_heuristic() -> impl Heuristic<PyraminxWithTips> {
+    // the no-tips puzzle is small enough to store all of, and each twisted tip costs exactly one
+    // more move on top of that, so this is exact
+    let core = DenseCache::new::<Pyraminx>();
+
+    move |s: &PyraminxWithTips| core.distance(&s.core).unwrap_or(0) + s.twisted_tips()
+}
+
+/// Each tip turns on its own, and is either solved or twisted one of two ways; so a tip which is
+/// wrong costs exactly one more move, whatever the rest of the puzzle is doing.
 pub fn gn_count_with_tips(gn_count_no_tips: HashMap<u128, u128>) -> HashMap<u128, u128> {
-    let mut out = HashMap::default();
+    multiply_by_independent_trivial_group(gn_count_no_tips, 4, 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubesearch::{enumerate_dense, enumerate_state_space, enumerate_with_table, TransitionTable};
+
+    #[test]
+    fn tip_layers_match_gn_count_with_tips() {
+        use crate::idasearch::heuristic_helpers::states_at_depth;
+
+        // the full with-tips puzzle is far too big to enumerate here, but the count at each depth
+        // only depends on the no-tips counts at that depth and shallower ones
+        const DEPTH: usize = 5;
+
+        let no_tips: HashMap<u128, u128> = (0..=DEPTH)
+            .map(|d| (d as u128, states_at_depth::<Pyraminx>(d).len() as u128))
+            .collect();
+        let expected = gn_count_with_tips(no_tips);
+
+        for d in 0..=DEPTH {
+            let actual = states_at_depth::<PyraminxWithTips>(d).len() as u128;
+            assert_eq!(actual, expected[&(d as u128)], "Layer size at depth {d}");
+        }
+    }
+
+    #[test]
+    fn random_states_are_reachable() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let core = DenseCache::new::<Pyraminx>();
+        let mut rng = StdRng::from_seed([15; 32]);
 
-    for (cost, count) in gn_count_no_tips {
-        // For each no-tips state, there is one way for the tips to all be correct
-        *out.entry(cost).or_insert(0) += count;
+        for _ in 0..1000 {
+            let state = PyraminxWithTips::random_state(&mut rng);
+            assert!(core.distance(&state.core).is_some(), "{state:?} should be reachable");
+        }
+    }
+
+    #[test]
+    fn nearby_states_have_distinct_indices() {
+        let mut seen: HashMap<usize, Pyraminx> = HashMap::default();
+        let mut frontier = vec![<Pyraminx as State>::start()];
+
+        for _ in 0..=5 {
+            let mut next = Vec::new();
+            for state in frontier {
+                let index = state.index();
+                assert!(index < Pyraminx::NUM_INDICES);
+
+                match seen.insert(index, state) {
+                    Some(other) => assert_eq!(state, other, "Different states share index {index}"),
+                    None => state.neighbors(&mut |n| next.push(n)),
+                }
+            }
+            frontier = next;
+        }
+
+        assert!(seen.len() > 1000);
+    }
+
+    // each of these walks the whole puzzle, which is far too slow in a debug build
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn table_transitions_match_moves() {
+        let table = TransitionTable::new::<Pyraminx>();
 
-        // For each no-tips state, there are eight ways for the tips to be wrong one time --
-        // four ways to pick the wrong tip, two ways for it to be wrong
-        *out.entry(cost + 1).or_insert(0) += count * 8;
+        // walk every state, checking the table agrees with actually making the moves
+        let mut seen: HashMap<usize, Pyraminx> = HashMap::default();
+        let mut to_process = vec![<Pyraminx as State>::start()];
 
-        // For each no-tips state, there are 24 ways for the tips to be wrong twice --
-        // six ways to pick two wrong tips, and two ways (each) for them to be wrong
-        *out.entry(cost + 2).or_insert(0) += count * 24;
+        while let Some(state) = to_process.pop() {
+            let index = state.index();
+            if let Some(other) = seen.insert(index, state) {
+                assert_eq!(state, other, "Different states share index {index}");
+                continue;
+            }
 
-        // For each no-tips state, there are 32 ways for the tips to be wrong thrice --
-        // four ways to pick the three wrong tips, and two ways (each) for them to be wrong
-        *out.entry(cost + 3).or_insert(0) += count * 32;
+            let mut direct = Vec::new();
+            state.neighbors(&mut |n| direct.push(n));
 
-        // For each no-tips state, there are 16 ways for the tips to all be wrong --
-        // one way to pick every tip (obviously?), and two ways (each) for them to be wrong
-        *out.entry(cost + 4).or_insert(0) += count * 16;
+            let expected: Vec<u32> = direct.iter().map(|n| n.index() as u32).collect();
+            assert_eq!(table.neighbors(index as u32), expected.as_slice(), "{state:?}");
+
+            to_process.extend(direct);
+        }
+
+        assert_eq!(seen.len(), 933120);
     }
 
-    out
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn table_enumeration_matches_others() {
+        let (_, expected) = enumerate_state_space::<Pyraminx>(None).unwrap();
+
+        let (_, dense) = enumerate_dense::<Pyraminx>(None).unwrap();
+        assert_eq!(dense, expected);
+
+        let (_, tabled) = enumerate_with_table(&TransitionTable::new::<Pyraminx>(), None).unwrap();
+        assert_eq!(tabled, expected);
+    }
 }
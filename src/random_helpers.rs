@@ -5,7 +5,7 @@ use rand::Rng;
 
 use crate::orientations::EdgeOrientation;
 
-#[derive(Ord, PartialEq, Eq, PartialOrd, Debug, Display)]
+#[derive(Copy, Clone, Ord, PartialEq, Eq, PartialOrd, Debug, Display)]
 pub enum TwoParity {
     Even,
     Odd,
@@ -25,7 +25,13 @@ pub fn shuffle_any<R: Rng, T: Copy>(rng: &mut R, arr: impl IntoIterator<Item = T
     (shuffled, parity)
 }
 
+/// Shuffles the given array into a uniformly random permutation of the desired parity. Panics if
+/// asked for an odd shuffle of fewer than two pieces, since there isn't one.
 pub fn shuffle_with_parity<R: Rng, T: Copy>(rng: &mut R, arr: &[T], desired: TwoParity) -> Vec<T> {
+    if arr.len() < 2 && desired == TwoParity::Odd {
+        panic!("Can't shuffle {} pieces and make it odd", arr.len())
+    }
+
     let permutation = permutations::with_parity(rng, arr.len(), desired);
 
     let shuffled: Vec<T> = (0..arr.len()).map(|i| arr[permutation.apply(i)]).collect();
@@ -33,6 +39,38 @@ pub fn shuffle_with_parity<R: Rng, T: Copy>(rng: &mut R, arr: &[T], desired: Two
     shuffled
 }
 
+/// The six pieces of a shuffled orbit, as an array to destructure; panics if there aren't six
+pub fn take_six<T: Copy>(v: Vec<T>) -> [T; 6] {
+    assert_eq!(v.len(), 6);
+
+    [v[0], v[1], v[2], v[3], v[4], v[5]]
+}
+
+/// Total parity of a collection of edge flips; that is, whether an odd or even number of them
+/// are flipped.
+pub fn flip_parity(eo: &[EdgeOrientation]) -> TwoParity {
+    let mut total_flipped = EdgeOrientation::Normal;
+
+    for e in eo.iter().copied() {
+        total_flipped = match e {
+            EdgeOrientation::Normal => total_flipped,
+            EdgeOrientation::Flipped => total_flipped.flipped(),
+        };
+    }
+
+    match total_flipped {
+        EdgeOrientation::Normal => TwoParity::Even,
+        EdgeOrientation::Flipped => TwoParity::Odd,
+    }
+}
+
+/// Shuffles an orbit of pieces (typically centers) whose permutation parity is tied to the flips
+/// of some set of edges; the resulting permutation is odd exactly when an odd number of the given
+/// edges are flipped.
+pub fn couple_orbit_to_edges<R: Rng, T: Copy>(rng: &mut R, orbit: &[T], edges: &[EdgeOrientation]) -> Vec<T> {
+    shuffle_with_parity(rng, orbit, flip_parity(edges))
+}
+
 pub fn flips_with_parity<R: Rng>(rng: &mut R, len: usize, desired: TwoParity) -> Vec<EdgeOrientation> {
     if len == 0 && desired == TwoParity::Odd {
         panic!("Can't flip nothing and make it odd")
@@ -57,3 +95,92 @@ pub fn flips_with_parity<R: Rng>(rng: &mut R, len: usize, desired: TwoParity) ->
 
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// Parity of the permutation taking `orig` to `shuffled`, by counting inversions
+    fn parity_of<T: PartialEq>(orig: &[T], shuffled: &[T]) -> TwoParity {
+        let indices: Vec<usize> = shuffled
+            .iter()
+            .map(|x| {
+                orig.iter()
+                    .position(|y| y == x)
+                    .expect("Shuffle should keep the same elements")
+            })
+            .collect();
+
+        let inversions = (0..indices.len())
+            .flat_map(|i| (i + 1..indices.len()).map(move |j| (i, j)))
+            .filter(|&(i, j)| indices[i] > indices[j])
+            .count();
+
+        if inversions % 2 == 0 {
+            TwoParity::Even
+        } else {
+            TwoParity::Odd
+        }
+    }
+
+    #[test]
+    fn shuffles_have_the_desired_parity() {
+        for len in 0..=8 {
+            let orig: Vec<usize> = (0..len).collect();
+
+            for desired in [TwoParity::Even, TwoParity::Odd] {
+                if len < 2 && desired == TwoParity::Odd {
+                    continue;
+                }
+
+                for seed in 0..=63 {
+                    let mut rng = StdRng::from_seed([seed; 32]);
+                    let shuffled = shuffle_with_parity(&mut rng, &orig, desired);
+
+                    let mut sorted = shuffled.clone();
+                    sorted.sort();
+                    assert_eq!(sorted, orig, "Should be a permutation of {orig:?}, not {shuffled:?}");
+
+                    assert_eq!(
+                        parity_of(&orig, &shuffled),
+                        desired,
+                        "Parity of {shuffled:?} (length {len}, seed {seed})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't shuffle 1 pieces and make it odd")]
+    fn one_piece_cannot_be_shuffled_odd() {
+        shuffle_with_parity(&mut StdRng::from_seed([0; 32]), &['U'], TwoParity::Odd);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't shuffle 0 pieces and make it odd")]
+    fn no_pieces_cannot_be_shuffled_odd() {
+        shuffle_with_parity::<_, char>(&mut StdRng::from_seed([0; 32]), &[], TwoParity::Odd);
+    }
+
+    #[test]
+    fn coupled_orbit_parity_matches_edge_flips() {
+        let orbit = ['U', 'F', 'R', 'D', 'B', 'L'];
+
+        for seed in 0..=255 {
+            let mut rng = StdRng::from_seed([seed; 32]);
+            let edges: Vec<EdgeOrientation> = (0..6).map(|_| EdgeOrientation::random(&mut rng)).collect();
+
+            let shuffled = couple_orbit_to_edges(&mut rng, &orbit, &edges);
+
+            assert_eq!(
+                parity_of(&orbit, &shuffled),
+                flip_parity(&edges),
+                "Orbit parity should match edge flips {edges:?} (seed {seed})"
+            );
+        }
+    }
+}
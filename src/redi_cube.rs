@@ -1,20 +1,22 @@
-#[cfg(feature = "hit_rate")]
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::str::FromStr;
 
 use derive_more::Display;
-use enum_iterator::Sequence;
+use enum_iterator::{all, Sequence};
+use itertools::Itertools;
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
-use crate::idasearch::heuristic_helpers::{bounded_cache, BoundedStateCache};
-use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CornerTwistAmt};
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::describe::{DecodeKey, DescribeKey, KeyReader, PrettyState};
+use crate::idasearch::heuristic_helpers::{bounded_cache, CachedPlusLowerBound};
+use crate::idasearch::{find_algorithm, Heuristic, Solvable};
+use crate::legality::{require_even, IllegalReason, ValidState};
+use crate::moves::{parse_by_display, CanReverse, CornerTwistAmt, ParseMoveError};
 use crate::orientations::CornerOrientation;
 use crate::permutation_helpers::cycle_cw;
 use crate::random_helpers::TwoParity;
 use crate::scrambles::RandomInit;
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Sequence)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Sequence, Display)]
 #[repr(u8)]
 pub enum EdgeCubelet {
     // 12 values, so fits in 4 bits
@@ -39,6 +41,16 @@ impl EdgeCubelet {
     }
 }
 
+impl FromStr for EdgeCubelet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        all::<EdgeCubelet>()
+            .find(|e| format!("{e:?}") == s)
+            .ok_or_else(|| format!("Unknown edge {s}; edges are named by their faces, like UF or BL"))
+    }
+}
+
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Sequence)]
 struct EdgeState {
     uf: EdgeCubelet,
@@ -185,7 +197,7 @@ pub struct RediCube {
     corners: CornerState,
 }
 
-impl RediCube {
+impl HasSolvedState for RediCube {
     #[inline(always)]
     fn solved() -> Self {
         Self {
@@ -193,7 +205,9 @@ impl RediCube {
             corners: CornerState::solved(),
         }
     }
+}
 
+impl RediCube {
     #[inline(always)]
     fn ufr(&mut self) {
         self.edges.ufr();
@@ -280,6 +294,14 @@ impl CanReverse for Move {
     }
 }
 
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
 macro_rules! do_twist {
     ($corner_name:ident, $amt_name:ident, $out:ident) => {
         match $amt_name {
@@ -321,6 +343,10 @@ impl Solvable for RediCube {
         ]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         // basically we'll say that moves are in reverse order -- so
         //      UFR > UFL > UBR > UBL > DFR > DFL > DFR > DBR > DBL
@@ -368,50 +394,9 @@ impl Solvable for RediCube {
     }
 }
 
-// TODO: generify this somehow so I can use it in other places, if it works
-struct RediHeuristic {
-    bounded_cache: BoundedStateCache<u64>,
-    #[cfg(feature = "hit_rate")]
-    heuristic_hits: AtomicUsize,
-    #[cfg(feature = "hit_rate")]
-    heuristic_misses: AtomicUsize,
-}
+type DistHeuristicFn = fn(&RediCube) -> usize;
 
-#[cfg(feature = "hit_rate")]
-impl Drop for RediHeuristic {
-    fn drop(&mut self) {
-        let hits = self.heuristic_hits.load(Ordering::Relaxed);
-        let misses = self.heuristic_misses.load(Ordering::Relaxed);
-        let pct = (hits as f32) / ((hits + misses) as f32) * 100.0;
-        println!(
-            "Final stats for redi heuristic: {hits} heuristic hits and {misses} heuristic misses ({pct:.2}% hit rate)"
-        )
-    }
-}
-
-impl Heuristic<RediCube> for RediHeuristic {
-    fn estimated_remaining_cost(&self, t: &RediCube) -> usize {
-        // turns out this hashmap lookup is still the pain point; more efficient packing or lookups
-        // may help performance further
-        if let Some(known_cost) = self.bounded_cache.remaining_cost_if_known(t) {
-            return known_cost;
-        }
-
-        let fb = self.bounded_cache.fallback_depth();
-        let heuristic = dist_heuristic(t);
-
-        #[cfg(feature = "hit_rate")]
-        {
-            if heuristic > fb {
-                self.heuristic_hits.fetch_add(1, Ordering::Relaxed);
-            } else {
-                self.heuristic_misses.fetch_add(1, Ordering::Relaxed);
-            }
-        }
-
-        fb.max(heuristic)
-    }
-}
+type RediHeuristic = CachedPlusLowerBound<RediCube, DistHeuristicFn>;
 
 // compute the minimum cost (number of times this edge cubelet must move) based on the
 // orientations of its associated corners. This is assuming the cubelet is in its home position.
@@ -508,10 +493,10 @@ fn dist(source_position: EdgeCubelet, goal_position: EdgeCubelet, cube: &RediCub
             match source_position {
                 EdgeCubelet::DF => in_place_cost(cube.corners.dfl, cube.corners.dfr),
                 EdgeCubelet::UB => 3,
-                EdgeCubelet::DR => one_off_cost(cube.corners.dfl, CornerOrientation::CCW),
-                EdgeCubelet::FR => one_off_cost(cube.corners.dfl, CornerOrientation::CW),
-                EdgeCubelet::DL => one_off_cost(cube.corners.dfr, CornerOrientation::CW),
-                EdgeCubelet::FL => one_off_cost(cube.corners.dfr, CornerOrientation::CCW),
+                EdgeCubelet::DR => one_off_cost(cube.corners.dfr, CornerOrientation::CCW),
+                EdgeCubelet::FR => one_off_cost(cube.corners.dfr, CornerOrientation::CW),
+                EdgeCubelet::DL => one_off_cost(cube.corners.dfl, CornerOrientation::CW),
+                EdgeCubelet::FL => one_off_cost(cube.corners.dfl, CornerOrientation::CCW),
                 _ => 2,
             }
         }
@@ -554,7 +539,7 @@ fn dist(source_position: EdgeCubelet, goal_position: EdgeCubelet, cube: &RediCub
         }
         // for mid layer, when choosing which face gets rotated to top, prefer F/B over L/R
         EdgeCubelet::FL => match source_position {
-            EdgeCubelet::FL => in_place_cost(cube.corners.dbl, cube.corners.dbr),
+            EdgeCubelet::FL => in_place_cost(cube.corners.ufl, cube.corners.dfl),
             EdgeCubelet::BR => 3,
             EdgeCubelet::UF => one_off_cost(cube.corners.ufl, CornerOrientation::CCW),
             EdgeCubelet::UL => one_off_cost(cube.corners.ufl, CornerOrientation::CW),
@@ -624,9 +609,15 @@ fn dist_heuristic(cube: &RediCube) -> usize {
     // - using just rule 1, we get about 1% hit rate
     // - using rules 1 and 2, we get about 9-10% hit rate
     //
+    // - using rules 1 through 3, we get about 83% hit rate
+    //
     // against cache of depth 8 (so heuristic cost must be at least 10 to matter):
     // - using just rule 1, we get less than 0.1% hit rate (essentially nothing)
     // - using rules 1 and 2, we get about 2% hit rate
+    // - using rules 1 through 3, we get about 42% hit rate
+    //
+    // (the rule 3 numbers are over uniformly random states; over the states IDA* actually visits
+    // while solving, it's closer to 56% at depth 7)
     let mut total_cost = 0;
 
     // upper layer
@@ -648,18 +639,12 @@ fn dist_heuristic(cube: &RediCube) -> usize {
     total_cost += dist(EdgeCubelet::BR, cube.edges.br, cube);
 
     // divide by three, rounded up
-    (total_cost + 2) / 3
+    total_cost.div_ceil(3)
 }
 
 pub fn make_heuristic(max_depth: usize) -> impl Heuristic<RediCube> {
     let cache = bounded_cache::<RediCube>(max_depth);
-    RediHeuristic {
-        bounded_cache: cache,
-        #[cfg(feature = "hit_rate")]
-        heuristic_hits: Default::default(),
-        #[cfg(feature = "hit_rate")]
-        heuristic_misses: Default::default(),
-    }
+    RediHeuristic::new(cache, dist_heuristic)
 }
 
 impl SimpleStartState for RediCube {
@@ -683,6 +668,166 @@ impl SimpleStartState for RediCube {
     }
 }
 
+/// The shortest algorithm which cycles the three given edges (the piece at the first goes to the
+/// second, and so on) and leaves everything else solved, if there's one of at most `max_len` moves
+pub fn find_edge_cycle(cycle: [EdgeCubelet; 3], max_len: usize) -> Option<Vec<Move>> {
+    let solved = EdgeState::solved().as_array();
+
+    let expected = solved.map(|home| match cycle.iter().position(|&c| c == home) {
+        Some(i) => cycle[(i + 2) % 3],
+        None => home,
+    });
+
+    find_algorithm(
+        |s: &RediCube| s.corners == CornerState::solved() && s.edges.as_array() == expected,
+        max_len,
+    )
+}
+
+impl DescribeKey for RediCube {
+    fn describe_key(key: &u64) -> String {
+        // the positions, in the order uniq_key packs them; edges first, then corners
+        const EDGES: [&str; 12] = ["UF", "UR", "UL", "UB", "DF", "DR", "DL", "DB", "FL", "FR", "BL", "BR"];
+        const CORNERS: [&str; 8] = ["UFL", "UFR", "UBL", "UBR", "DFL", "DFR", "DBL", "DBR"];
+
+        let mut reader = KeyReader::new(*key);
+
+        // they come off last-packed first
+        let mut corners: Vec<String> = CORNERS
+            .iter()
+            .map(|_| reader.take_value::<CornerOrientation>(2, |o| o.as_u8_two_bits() as u64))
+            .collect();
+        let mut edges: Vec<String> = EDGES
+            .iter()
+            .map(|_| reader.take_value::<EdgeCubelet>(4, |e| *e as u64))
+            .collect();
+        corners.reverse();
+        edges.reverse();
+
+        let edge_lines = EDGES.iter().zip(edges).map(|(name, piece)| format!("{name}: {piece}"));
+        let corner_lines = CORNERS.iter().zip(corners).map(|(name, orr)| format!("{name}: {orr}"));
+
+        edge_lines.chain(corner_lines).join("\n")
+    }
+}
+
+impl DecodeKey for RediCube {
+    fn decode_key(key: u64) -> Option<Self> {
+        let mut reader = KeyReader::new(key);
+
+        // same order as describe_key, and they come off last-packed first
+        let mut corners = [CornerOrientation::Normal; 8];
+        for orr in corners.iter_mut().rev() {
+            *orr = reader.take_exact(2, |o: &CornerOrientation| o.as_u8_two_bits() as u64)?;
+        }
+
+        let mut edges = [EdgeCubelet::UF; 12];
+        for edge in edges.iter_mut().rev() {
+            *edge = reader.take_exact(4, |e: &EdgeCubelet| *e as u64)?;
+        }
+
+        Some(Self::from_pieces(RediCubePieces { edges, corners }))
+    }
+}
+
+/// The raw arrangement of a Redi Cube, with nothing checked: the edge at each position (in the
+/// order UF, UR, UL, UB, DF, DR, DL, DB, FL, FR, BL, BR) and the twist of each corner (in the order
+/// UFL, UFR, UBL, UBR, DFL, DFR, DBL, DBR)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RediCubePieces {
+    pub edges: [EdgeCubelet; 12],
+    pub corners: [CornerOrientation; 8],
+}
+
+/// Checks that the arrangement can actually be reached by turning the cube; so this is the way in
+/// for states from outside
+impl TryFrom<RediCubePieces> for RediCube {
+    type Error = IllegalReason;
+
+    fn try_from(pieces: RediCubePieces) -> Result<Self, Self::Error> {
+        let out = Self::from_pieces(pieces);
+        out.is_legal()?;
+        Ok(out)
+    }
+}
+
+impl RediCube {
+    /// Unchecked; see the TryFrom impl for the checked version
+    fn from_pieces(pieces: RediCubePieces) -> Self {
+        let [uf, ur, ul, ub, df, dr, dl, db, fl, fr, bl, br] = pieces.edges;
+        let [ufl, ufr, ubl, ubr, dfl, dfr, dbl, dbr] = pieces.corners;
+
+        Self {
+            edges: EdgeState {
+                uf,
+                ur,
+                ul,
+                ub,
+                df,
+                dr,
+                dl,
+                db,
+                fl,
+                fr,
+                bl,
+                br,
+            },
+            corners: CornerState {
+                ufl,
+                ufr,
+                ubl,
+                ubr,
+                dfl,
+                dfr,
+                dbl,
+                dbr,
+            },
+        }
+    }
+}
+
+impl EdgeState {
+    fn as_array(&self) -> [EdgeCubelet; 12] {
+        [
+            self.uf, self.ur, self.ul, self.ub, self.df, self.dr, self.dl, self.db, self.fl, self.fr, self.bl, self.br,
+        ]
+    }
+}
+
+impl ValidState for RediCube {
+    fn is_legal(&self) -> Result<(), IllegalReason> {
+        // every move is a 3-cycle of edges, and the corners twist freely in place
+        require_even("edges", &EdgeState::solved().as_array(), &self.edges.as_array())
+    }
+}
+
+impl PrettyState for RediCube {
+    fn slots(&self) -> Vec<(&'static str, String)> {
+        // the same orders as RediCubePieces
+        const EDGES: [&str; 12] = ["UF", "UR", "UL", "UB", "DF", "DR", "DL", "DB", "FL", "FR", "BL", "BR"];
+        let c = &self.corners;
+
+        let edges = EDGES
+            .into_iter()
+            .zip(self.edges.as_array())
+            .map(|(slot, edge)| (slot, edge.to_string()));
+        let corners = [
+            ("UFL", c.ufl),
+            ("UFR", c.ufr),
+            ("UBL", c.ubl),
+            ("UBR", c.ubr),
+            ("DFL", c.dfl),
+            ("DFR", c.dfr),
+            ("DBL", c.dbl),
+            ("DBR", c.dbr),
+        ]
+        .into_iter()
+        .map(|(slot, orr)| (slot, format!("{orr:?}")));
+
+        edges.chain(corners).collect()
+    }
+}
+
 impl RandomInit for RediCube {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         let permutation = crate::random_helpers::shuffle_with_parity(
@@ -732,7 +877,15 @@ impl RandomInit for RediCube {
             dbr: r.gen(),
         };
 
-        RediCube { edges, corners }
+        let out = RediCube { edges, corners };
+        debug_assert_eq!(
+            out.is_legal(),
+            Ok(()),
+            "Random states should be legal, but got\n{}",
+            out.describe_state()
+        );
+
+        out
     }
 }
 
@@ -794,4 +947,168 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn description_shows_moved_pieces() {
+        let mut state = RediCube::solved();
+        std::mem::swap(&mut state.edges.uf, &mut state.edges.br);
+        state.corners.dbl = CornerOrientation::CCW;
+
+        let description = state.describe_state();
+        let lines: Vec<&str> = description.lines().collect();
+
+        assert_eq!(lines.len(), 20);
+        assert_eq!(lines[0], "UF: BR");
+        assert_eq!(lines[1], "UR: UR");
+        assert_eq!(lines[11], "BR: UF");
+        assert_eq!(lines[12], "UFL: Normal");
+        assert_eq!(lines[18], "DBL: CCW");
+    }
+
+    #[test]
+    fn swapped_edges_are_illegal() {
+        let mut state = RediCube::solved();
+        state = state.apply(Move::UFR(Cw)).apply(Move::DBL(Ccw));
+        assert_eq!(state.is_legal(), Ok(()));
+
+        // a single swap can't be done with 3-cycles
+        std::mem::swap(&mut state.edges.uf, &mut state.edges.ur);
+        assert_eq!(state.is_legal(), Err(IllegalReason::OddPermutation("edges")));
+
+        // and a piece can't be in two places at once
+        let mut state = RediCube::solved();
+        state.edges.uf = EdgeCubelet::DB;
+        assert_eq!(state.is_legal(), Err(IllegalReason::DuplicatePiece("DB".to_string())));
+
+        // but the corners twist freely
+        let mut state = RediCube::solved();
+        state.corners.ufl = CornerOrientation::CW;
+        assert_eq!(state.is_legal(), Ok(()));
+    }
+
+    #[test]
+    fn checked_construction_from_pieces() {
+        use CornerOrientation::*;
+        use EdgeCubelet::*;
+
+        let solved_edges = [UF, UR, UL, UB, DF, DR, DL, DB, FL, FR, BL, BR];
+
+        // a 3-cycle of edges, with whatever corner twists, is fine
+        let pieces = RediCubePieces {
+            edges: [UR, UL, UF, UB, DF, DR, DL, DB, FL, FR, BL, BR],
+            corners: [CW, Normal, CCW, Normal, Normal, Normal, Normal, CW],
+        };
+        let state = RediCube::try_from(pieces).unwrap();
+        assert_eq!(state.edges.as_array(), pieces.edges);
+        assert_eq!(state.corners.ufl, CW);
+        assert_eq!(state.corners.dbr, CW);
+
+        let solved = RediCubePieces {
+            edges: solved_edges,
+            corners: [Normal; 8],
+        };
+        assert_eq!(RediCube::try_from(solved), Ok(RediCube::solved()));
+
+        // but a single swap is not
+        let swapped = RediCubePieces {
+            edges: [UR, UF, UL, UB, DF, DR, DL, DB, FL, FR, BL, BR],
+            corners: [Normal; 8],
+        };
+        let err = RediCube::try_from(swapped).unwrap_err();
+        assert_eq!(err, IllegalReason::OddPermutation("edges"));
+        assert_eq!(err.to_string(), "The edges are in an odd permutation");
+
+        let duplicated = RediCubePieces {
+            edges: [UF, UF, UL, UB, DF, DR, DL, DB, FL, FR, BL, BR],
+            corners: [Normal; 8],
+        };
+        assert_eq!(
+            RediCube::try_from(duplicated),
+            Err(IllegalReason::DuplicatePiece("UF".to_string()))
+        );
+    }
+
+    #[test]
+    fn combined_heuristic_matches_cache_or_dist() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        use crate::test_helpers::random_walk_states;
+
+        let depth = 4;
+        let cache = bounded_cache::<RediCube>(depth);
+        let combined = make_heuristic(depth);
+
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        let mut states = random_walk_states::<RediCube, _>(&mut rng, 20);
+        states.extend((0..100).map(|_| RediCube::random_state(&mut rng)));
+
+        for state in states {
+            // the original, hand-written cache-or-compute logic
+            let expected = cache
+                .remaining_cost_if_known(&state)
+                .unwrap_or_else(|| cache.fallback_depth().max(dist_heuristic(&state)));
+
+            assert_eq!(combined.estimated_remaining_cost(&state), expected);
+        }
+    }
+
+    #[test]
+    fn dist_heuristic_is_admissible() {
+        // every state within a few moves, checked against its exact distance
+        let cache = bounded_cache::<RediCube>(5);
+
+        for (&key, optimal) in cache.entries() {
+            let state = RediCube::decode_key(key).expect("Keys come from uniq_key, so they decode");
+            let estimate = dist_heuristic(&state);
+
+            assert!(
+                estimate <= optimal,
+                "Estimated {estimate} moves, but it's solvable in {optimal}:\n{}",
+                state.describe_state()
+            );
+        }
+    }
+
+    // takes a few seconds in release, but far too long in a debug build
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn golden_scrambles() {
+        crate::test_helpers::assert_golden_scrambles::<RediCube, _>(
+            &make_heuristic(6),
+            15,
+            &[
+                "UFL' UFR' DFR DBR' UBL' DBL DBR' DFR' UBR UBL' UBR DBR' DFL' UFL' UFR",
+                "DFL DBL' UBR UBL DBL' DFR DFL UFR UBR UBL DBL DFR' DBR' UBR' UFL",
+                "UFL' DFL DBL UBR DBR DFR' UFR' UBR' UBL UFL DFL' DFR' DBR' UBR' UFL'",
+                "DBR DFL DBL' UFR DFR' UBR DBR DFL' DBL DFL UBL DBL UFL UFR UFL'",
+                "UFL UFR UBR' UBL' DBL' UFR UFL UBL DBL' DBR DFL' UBR' UFR' UFL UBL'",
+            ],
+        );
+    }
+
+    #[test]
+    fn finds_short_edge_cycle() {
+        use EdgeCubelet::{FR, UF, UL};
+
+        // the commutator UFR UFL UFR' UFL' cycles three edges, and the twists cancel out, so
+        // there's a 3-cycle in four moves
+        let cycle = [UF, UL, FR];
+        let alg = find_edge_cycle(cycle, 6).expect("Should find a short 3-cycle");
+        assert_eq!(alg.len(), 4);
+
+        let end = alg.iter().fold(RediCube::solved(), |s, &m| s.apply(m));
+        assert_eq!(end.corners, CornerState::solved());
+        assert_eq!((end.edges.ul, end.edges.fr, end.edges.uf), (UF, UL, FR));
+        assert_eq!(end.edges.ur, EdgeCubelet::UR);
+
+        // going the other way round is a different algorithm
+        let reverse = find_edge_cycle([UF, FR, UL], 6).unwrap();
+        assert_ne!(reverse, alg);
+        assert_eq!(reverse.len(), 4);
+
+        // cycling a piece to itself is no change at all
+        assert_eq!(find_edge_cycle([UF, UF, UF], 0), Some(vec![]));
+    }
 }
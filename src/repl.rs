@@ -0,0 +1,189 @@
+//! An interactive loop for playing with a puzzle: type moves to apply them, and ask for the
+//! optimal solution from wherever they've left it.
+
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::cubesearch::HasSolvedState;
+use crate::describe::PrettyState;
+use crate::idasearch::{self, Heuristic, Solvable, SolveCache};
+use crate::moves::ParseMoveError;
+use crate::scrambles::{apply_moves, format_moves, parse_scramble, random_walk_scramble};
+
+const SCRAMBLE_MOVES: usize = 30;
+
+/// Reads one command per line, and writes whatever it has to say about each. A line which isn't
+/// one of the commands is taken as moves, separated by spaces, to apply to the current state.
+/// There's no drawing of the puzzle yet, so `show` lists what's in each slot, and `steps` prints
+/// the state's fields after each move of the solution.
+///
+/// Scrambling is a long random walk from solved, rather than a uniformly random state; that's good
+/// enough to play with, and works for every puzzle. With a `cache`, solving a state which has
+/// already been solved is instant.
+pub fn run_repl<S, H, R, W, G>(
+    input: R,
+    mut output: W,
+    h: &H,
+    cache: Option<&SolveCache<S>>,
+    rng: &mut G,
+) -> io::Result<()>
+where
+    S: HasSolvedState + Solvable + PrettyState,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+    H: Heuristic<S>,
+    R: BufRead,
+    W: Write,
+    G: Rng,
+{
+    let mut state = S::solved();
+
+    writeln!(
+        output,
+        "Type moves to apply them, or one of: solve, steps, scramble, reset, show, quit"
+    )?;
+
+    let solve = |state: &S| match cache {
+        Some(cache) => cache.solve(state, |state| idasearch::solve(state, h)),
+        None => idasearch::solve(state, h),
+    };
+
+    for line in input.lines() {
+        match line?.trim() {
+            "" => {}
+            "quit" | "exit" => break,
+            "solve" => match solve(&state) {
+                Ok(solution) if solution.is_empty() => writeln!(output, "Already solved")?,
+                Ok(solution) => writeln!(output, "{}", format_moves(&solution))?,
+                Err(e) => writeln!(output, "Error: {e}")?,
+            },
+            "steps" => match idasearch::solve_with_path(&state, h) {
+                Ok(path) if path.is_empty() => writeln!(output, "Already solved")?,
+                Ok(path) => {
+                    for (m, after) in path {
+                        writeln!(output, "{m}")?;
+                        writeln!(output, "{after:#?}")?;
+                    }
+                }
+                Err(e) => writeln!(output, "Error: {e}")?,
+            },
+            "scramble" => {
+                let scramble = random_walk_scramble::<S, _>(rng, SCRAMBLE_MOVES);
+                state = apply_moves(&S::solved(), &scramble);
+                writeln!(output, "Scrambled with {}", format_moves(&scramble))?;
+            }
+            "reset" => {
+                state = S::solved();
+                writeln!(output, "Reset to solved")?;
+            }
+            "show" => writeln!(output, "{}", state.pretty())?,
+            moves => match parse_scramble::<S>(moves) {
+                Ok(moves) => {
+                    state = apply_moves(&state, &moves);
+                    if state.is_solved() {
+                        writeln!(output, "Solved!")?;
+                    }
+                }
+                Err(e) => writeln!(output, "Error: {e}")?,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::heuristic_helpers::bounded_cache;
+    use crate::pocket_cube::PocketCube;
+
+    fn run_script(script: &str) -> Vec<String> {
+        run_script_with_cache(script, None)
+    }
+
+    fn run_script_with_cache(script: &str, cache: Option<&SolveCache<PocketCube>>) -> Vec<String> {
+        let mut output = Vec::new();
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        run_repl::<PocketCube, _, _, _, _>(
+            Cursor::new(script),
+            &mut output,
+            &bounded_cache::<PocketCube>(5),
+            cache,
+            &mut rng,
+        )
+        .unwrap();
+
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn scripted_session() {
+        let lines = run_script("R\nU\nsolve\nreset\nsolve\n");
+
+        assert_eq!(
+            lines[1..],
+            ["U' R'", "Reset to solved", "Already solved"],
+            "Whole output: {lines:?}"
+        );
+    }
+
+    #[test]
+    fn undoing_moves_solves_and_bad_moves_are_reported() {
+        let lines = run_script("R U\nX\nU' R'\nquit\nsolve\n");
+
+        assert_eq!(
+            lines[1..],
+            ["Error: Unrecognized move: \"X\"", "Solved!"],
+            "Whole output: {lines:?}"
+        );
+    }
+
+    #[test]
+    fn solving_again_hits_the_cache() {
+        let cache = SolveCache::new(10);
+        let lines = run_script_with_cache("R U F2\nsolve\nsolve\n", Some(&cache));
+
+        assert_eq!(lines[1], "F2 U' R'", "Whole output: {lines:?}");
+        assert_eq!(lines[2], lines[1], "Whole output: {lines:?}");
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn steps_show_the_state_after_each_move() {
+        let lines = run_script("R\nsteps\nsteps\n");
+
+        assert_eq!(lines[1], "R'", "Whole output: {lines:?}");
+        assert!(lines[2].starts_with("PocketCube"), "Whole output: {lines:?}");
+        // steps only shows the solution; it doesn't apply it
+        assert_eq!(
+            lines.iter().filter(|line| *line == "R'").count(),
+            2,
+            "Whole output: {lines:?}"
+        );
+    }
+
+    #[test]
+    fn scrambles_can_be_solved() {
+        let lines = run_script("scramble\nsolve\n");
+
+        assert!(lines[1].starts_with("Scrambled with "), "Unexpected {:?}", lines[1]);
+        assert!(
+            !lines[2].starts_with("Error"),
+            "Should find a solution, not {:?}",
+            lines[2]
+        );
+    }
+}
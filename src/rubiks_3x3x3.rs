@@ -0,0 +1,987 @@
+use std::str::FromStr;
+
+use derive_more::Display;
+use enum_iterator::{all, Sequence};
+use rand::Rng;
+
+use crate::cubesearch::{assert_bit_budget, FromIndex, HasSolvedState, IndexedState, SimpleStartState, State};
+use crate::describe::PrettyState;
+use crate::idasearch::heuristic_helpers::{MaxHeuristic, Project, ProjectedDenseCache};
+use crate::idasearch::{Heuristic, Solvable};
+use crate::moves::{impl_can_reverse, parse_by_display, CubeMoveAmt, ParseMoveError};
+use crate::orientations::{CornerOrientation, EdgeOrientation};
+use crate::permutation_helpers::{lehmer_rank, lehmer_unrank};
+use crate::random_helpers::{flips_with_parity, shuffle_any, shuffle_with_parity, TwoParity};
+use crate::scrambles::RandomInit;
+
+pub mod predicates;
+
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
+#[repr(u8)]
+enum CornerCubelet {
+    UFL,
+    UFR,
+    UBL,
+    UBR,
+    DFL,
+    DFR,
+    DBL,
+    DBR,
+}
+
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
+#[repr(u8)]
+enum EdgeCubelet {
+    UF,
+    UL,
+    UB,
+    UR,
+    FL,
+    FR,
+    BL,
+    BR,
+    DF,
+    DL,
+    DB,
+    DR,
+}
+
+/// A corner cubelet, and how it's twisted in the slot it's in
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct CornerSlot {
+    cubelet: CornerCubelet,
+    orr: CornerOrientation,
+}
+
+impl CornerSlot {
+    fn solved(cubelet: CornerCubelet) -> Self {
+        Self {
+            cubelet,
+            orr: CornerOrientation::Normal,
+        }
+    }
+
+    #[inline(always)]
+    fn cw(self) -> Self {
+        Self {
+            orr: self.orr.cw(),
+            ..self
+        }
+    }
+
+    #[inline(always)]
+    fn ccw(self) -> Self {
+        Self {
+            orr: self.orr.ccw(),
+            ..self
+        }
+    }
+
+    #[inline(always)]
+    fn pack(self, source: &mut u64) {
+        *source = (*source << 3) | (self.cubelet as u64);
+        *source = (*source << 2) | (self.orr.as_u8_two_bits() as u64);
+    }
+}
+
+/// An edge cubelet, and whether it's flipped in the slot it's in
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct EdgeSlot {
+    cubelet: EdgeCubelet,
+    orr: EdgeOrientation,
+}
+
+impl EdgeSlot {
+    fn solved(cubelet: EdgeCubelet) -> Self {
+        Self {
+            cubelet,
+            orr: EdgeOrientation::Normal,
+        }
+    }
+
+    #[inline(always)]
+    fn flipped(self) -> Self {
+        Self {
+            orr: self.orr.flipped(),
+            ..self
+        }
+    }
+
+    #[inline(always)]
+    fn pack(self, source: &mut u64) {
+        // twelve of them, so it takes four bits
+        *source = (*source << 4) | (self.cubelet as u64);
+        *source = (*source << 1) | (self.orr.as_u8_one_bit() as u64);
+    }
+}
+
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct CornerState {
+    ufl: CornerSlot,
+    ufr: CornerSlot,
+    ubl: CornerSlot,
+    ubr: CornerSlot,
+    dfl: CornerSlot,
+    dfr: CornerSlot,
+    dbl: CornerSlot,
+    dbr: CornerSlot,
+}
+
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct EdgeState {
+    uf: EdgeSlot,
+    ul: EdgeSlot,
+    ub: EdgeSlot,
+    ur: EdgeSlot,
+    fl: EdgeSlot,
+    fr: EdgeSlot,
+    bl: EdgeSlot,
+    br: EdgeSlot,
+    df: EdgeSlot,
+    dl: EdgeSlot,
+    db: EdgeSlot,
+    dr: EdgeSlot,
+}
+
+/// The centers never move, so the whole cube is just its corners and its edges
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Rubiks3x3x3 {
+    corners: CornerState,
+    edges: EdgeState,
+}
+
+/// Clockwise quarter turns of each face. U and D turns don't twist or flip anything; with the
+/// edge orientation measured this way, only F and B turns flip edges.
+trait CubeState: Sized {
+    fn start() -> Self;
+
+    fn u(&self) -> Self;
+
+    fn d(&self) -> Self;
+
+    fn f(&self) -> Self;
+
+    fn b(&self) -> Self;
+
+    fn l(&self) -> Self;
+
+    fn r(&self) -> Self;
+}
+
+impl CubeState for CornerState {
+    fn start() -> Self {
+        Self {
+            ufl: CornerSlot::solved(CornerCubelet::UFL),
+            ufr: CornerSlot::solved(CornerCubelet::UFR),
+            ubl: CornerSlot::solved(CornerCubelet::UBL),
+            ubr: CornerSlot::solved(CornerCubelet::UBR),
+            dfl: CornerSlot::solved(CornerCubelet::DFL),
+            dfr: CornerSlot::solved(CornerCubelet::DFR),
+            dbl: CornerSlot::solved(CornerCubelet::DBL),
+            dbr: CornerSlot::solved(CornerCubelet::DBR),
+        }
+    }
+
+    #[inline(always)]
+    fn u(&self) -> Self {
+        Self {
+            ufl: self.ufr,
+            ufr: self.ubr,
+            ubr: self.ubl,
+            ubl: self.ufl,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn d(&self) -> Self {
+        Self {
+            dfr: self.dfl,
+            dfl: self.dbl,
+            dbl: self.dbr,
+            dbr: self.dfr,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn f(&self) -> Self {
+        Self {
+            ufr: self.ufl.cw(),
+            ufl: self.dfl.ccw(),
+            dfl: self.dfr.cw(),
+            dfr: self.ufr.ccw(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn b(&self) -> Self {
+        Self {
+            ubl: self.ubr.cw(),
+            ubr: self.dbr.ccw(),
+            dbr: self.dbl.cw(),
+            dbl: self.ubl.ccw(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn l(&self) -> Self {
+        Self {
+            ufl: self.ubl.cw(),
+            ubl: self.dbl.ccw(),
+            dbl: self.dfl.cw(),
+            dfl: self.ufl.ccw(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn r(&self) -> Self {
+        Self {
+            ufr: self.dfr.ccw(),
+            dfr: self.dbr.cw(),
+            dbr: self.ubr.ccw(),
+            ubr: self.ufr.cw(),
+            ..*self
+        }
+    }
+}
+
+impl CubeState for EdgeState {
+    fn start() -> Self {
+        Self {
+            uf: EdgeSlot::solved(EdgeCubelet::UF),
+            ul: EdgeSlot::solved(EdgeCubelet::UL),
+            ub: EdgeSlot::solved(EdgeCubelet::UB),
+            ur: EdgeSlot::solved(EdgeCubelet::UR),
+            fl: EdgeSlot::solved(EdgeCubelet::FL),
+            fr: EdgeSlot::solved(EdgeCubelet::FR),
+            bl: EdgeSlot::solved(EdgeCubelet::BL),
+            br: EdgeSlot::solved(EdgeCubelet::BR),
+            df: EdgeSlot::solved(EdgeCubelet::DF),
+            dl: EdgeSlot::solved(EdgeCubelet::DL),
+            db: EdgeSlot::solved(EdgeCubelet::DB),
+            dr: EdgeSlot::solved(EdgeCubelet::DR),
+        }
+    }
+
+    #[inline(always)]
+    fn u(&self) -> Self {
+        Self {
+            uf: self.ur,
+            ur: self.ub,
+            ub: self.ul,
+            ul: self.uf,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn d(&self) -> Self {
+        Self {
+            df: self.dl,
+            dl: self.db,
+            db: self.dr,
+            dr: self.df,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn f(&self) -> Self {
+        Self {
+            uf: self.fl.flipped(),
+            fl: self.df.flipped(),
+            df: self.fr.flipped(),
+            fr: self.uf.flipped(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn b(&self) -> Self {
+        Self {
+            ub: self.br.flipped(),
+            br: self.db.flipped(),
+            db: self.bl.flipped(),
+            bl: self.ub.flipped(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn l(&self) -> Self {
+        Self {
+            ul: self.bl,
+            bl: self.dl,
+            dl: self.fl,
+            fl: self.ul,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn r(&self) -> Self {
+        Self {
+            ur: self.fr,
+            fr: self.dr,
+            dr: self.br,
+            br: self.ur,
+            ..*self
+        }
+    }
+}
+
+impl CubeState for Rubiks3x3x3 {
+    fn start() -> Self {
+        Self {
+            corners: CornerState::start(),
+            edges: EdgeState::start(),
+        }
+    }
+
+    #[inline(always)]
+    fn u(&self) -> Self {
+        Self {
+            corners: self.corners.u(),
+            edges: self.edges.u(),
+        }
+    }
+
+    #[inline(always)]
+    fn d(&self) -> Self {
+        Self {
+            corners: self.corners.d(),
+            edges: self.edges.d(),
+        }
+    }
+
+    #[inline(always)]
+    fn f(&self) -> Self {
+        Self {
+            corners: self.corners.f(),
+            edges: self.edges.f(),
+        }
+    }
+
+    #[inline(always)]
+    fn b(&self) -> Self {
+        Self {
+            corners: self.corners.b(),
+            edges: self.edges.b(),
+        }
+    }
+
+    #[inline(always)]
+    fn l(&self) -> Self {
+        Self {
+            corners: self.corners.l(),
+            edges: self.edges.l(),
+        }
+    }
+
+    #[inline(always)]
+    fn r(&self) -> Self {
+        Self {
+            corners: self.corners.r(),
+            edges: self.edges.r(),
+        }
+    }
+}
+
+/// Every state one move away
+fn each_move<C: CubeState>(state: &C, to_add: &mut impl FnMut(C)) {
+    // six faces, with three amounts each (1/2/rev)
+    for turn in [C::u, C::d, C::f, C::b, C::l, C::r] {
+        let once = turn(state);
+        let twice = turn(&once);
+        let thrice = turn(&twice);
+
+        to_add(once);
+        to_add(twice);
+        to_add(thrice);
+    }
+}
+
+impl CornerState {
+    /// The slots, in the same order as the [CornerCubelet]s which belong in them
+    fn as_array(&self) -> [CornerSlot; 8] {
+        [
+            self.ufl, self.ufr, self.ubl, self.ubr, self.dfl, self.dfr, self.dbl, self.dbr,
+        ]
+    }
+
+    fn from_array([ufl, ufr, ubl, ubr, dfl, dfr, dbl, dbr]: [CornerSlot; 8]) -> Self {
+        Self {
+            ufl,
+            ufr,
+            ubl,
+            ubr,
+            dfl,
+            dfr,
+            dbl,
+            dbr,
+        }
+    }
+
+    fn pack(&self) -> u64 {
+        // 8 corners: 3 bits for pos, 2 bits for orr
+        const { assert_bit_budget(8 * (3 + 2), 64) };
+
+        let mut out = 0;
+        for slot in self.as_array() {
+            slot.pack(&mut out);
+        }
+        out
+    }
+}
+
+impl EdgeState {
+    /// The slots, in the same order as the [EdgeCubelet]s which belong in them
+    fn as_array(&self) -> [EdgeSlot; 12] {
+        [
+            self.uf, self.ul, self.ub, self.ur, self.fl, self.fr, self.bl, self.br, self.df, self.dl, self.db, self.dr,
+        ]
+    }
+
+    fn from_array([uf, ul, ub, ur, fl, fr, bl, br, df, dl, db, dr]: [EdgeSlot; 12]) -> Self {
+        Self {
+            uf,
+            ul,
+            ub,
+            ur,
+            fl,
+            fr,
+            bl,
+            br,
+            df,
+            dl,
+            db,
+            dr,
+        }
+    }
+
+    fn pack(&self) -> u64 {
+        // 12 edges: 4 bits for pos, 1 bit for orr
+        const { assert_bit_budget(12 * (4 + 1), 64) };
+
+        let mut out = 0;
+        for slot in self.as_array() {
+            slot.pack(&mut out);
+        }
+        out
+    }
+}
+
+impl SimpleStartState for Rubiks3x3x3 {
+    type UniqueKey = (u64, u64);
+
+    fn start() -> Self {
+        <Self as CubeState>::start()
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        // corners on one side, edges on the other; neither fits alongside the other
+        (self.corners.pack(), self.edges.pack())
+    }
+}
+
+impl HasSolvedState for Rubiks3x3x3 {}
+
+impl PrettyState for Rubiks3x3x3 {}
+
+/// Just the corners, ignoring the edges entirely. There are 88 million of these, few enough to
+/// cache every one of them at a byte each.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Rubiks3x3x3Corners(CornerState);
+
+impl State for Rubiks3x3x3Corners {
+    type UniqueKey = u64;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        each_move(&self.0, &mut |corners| to_add(Self(corners)));
+    }
+
+    fn start() -> Self {
+        Self(CornerState::start())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        self.0.pack()
+    }
+}
+
+/// The twists, by [CornerOrientation::as_u8_two_bits]
+const TWISTS: [CornerOrientation; 3] = [CornerOrientation::Normal, CornerOrientation::CW, CornerOrientation::CCW];
+
+impl IndexedState for Rubiks3x3x3Corners {
+    // the permutation, then the twists of the first seven slots in base 3; the last twist is
+    // whatever makes the total come out to zero, so every index is reachable
+    const NUM_INDICES: usize = 40320 * 2187;
+
+    fn index(&self) -> usize {
+        let slots = self.0.as_array();
+        let pieces = slots.map(|slot| slot.cubelet as usize);
+
+        let perm = lehmer_rank(&pieces);
+
+        let twists = slots[..7]
+            .iter()
+            .fold(0, |acc, slot| acc * 3 + slot.orr.as_u8_two_bits() as usize);
+
+        perm * 2187 + twists
+    }
+}
+
+impl FromIndex for Rubiks3x3x3Corners {
+    fn from_index(index: usize) -> Self {
+        let (perm, mut twists) = (index / 2187, index % 2187);
+
+        let mut orrs = [CornerOrientation::Normal; 8];
+        for i in (0..7).rev() {
+            orrs[i] = TWISTS[twists % 3];
+            twists /= 3;
+        }
+        orrs[7] = CornerOrientation::total(&orrs[..7]).flip();
+
+        let cubelets: Vec<CornerCubelet> = all::<CornerCubelet>().collect();
+        let pieces = lehmer_unrank::<8>(perm);
+        let slots = std::array::from_fn(|i| CornerSlot {
+            cubelet: cubelets[pieces[i]],
+            orr: orrs[i],
+        });
+
+        Self(CornerState::from_array(slots))
+    }
+}
+
+impl Project<Rubiks3x3x3Corners> for Rubiks3x3x3 {
+    fn project(&self) -> Rubiks3x3x3Corners {
+        Rubiks3x3x3Corners(self.corners)
+    }
+}
+
+/// Just six of the edges, the cubelets from `FIRST` on in [EdgeCubelet] order, ignoring the
+/// corners and the other edges entirely. All twelve edges are 980 billion states, far too many,
+/// but any six are 42 million, and each half is a lower bound on its own.
+///
+/// The other six edges still ride along, to turn with the rest, but they don't count toward the
+/// key, so states which only differ in where they are count as the same.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Rubiks3x3x3EdgeHalf<const FIRST: usize>(EdgeState);
+
+impl<const FIRST: usize> State for Rubiks3x3x3EdgeHalf<FIRST> {
+    type UniqueKey = usize;
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        each_move(&self.0, &mut |edges| to_add(Self(edges)));
+    }
+
+    fn start() -> Self {
+        Self(EdgeState::start())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        self.index()
+    }
+}
+
+impl<const FIRST: usize> IndexedState for Rubiks3x3x3EdgeHalf<FIRST> {
+    // the slots the six pieces are in, then their flips in base 2; any six edges can be anywhere,
+    // flipped any way, since the other six can make up the parities
+    const NUM_INDICES: usize = (12 * 11 * 10 * 9 * 8 * 7) * 64;
+
+    fn index(&self) -> usize {
+        let mut positions = [0; 6];
+        let mut flips = [EdgeOrientation::Normal; 6];
+
+        for (pos, slot) in self.0.as_array().into_iter().enumerate() {
+            let piece = slot.cubelet as usize;
+            if (FIRST..FIRST + 6).contains(&piece) {
+                positions[piece - FIRST] = pos;
+                flips[piece - FIRST] = slot.orr;
+            }
+        }
+
+        // like a Lehmer code, but for six of twelve; each digit is the number of slots before
+        // this piece's which no earlier piece took
+        let placement = (0..6).fold(0, |acc, i| {
+            let smaller_earlier = positions[..i].iter().filter(|&&earlier| earlier < positions[i]).count();
+            acc * (12 - i) + positions[i] - smaller_earlier
+        });
+
+        let flips = flips
+            .into_iter()
+            .fold(0, |acc, orr| acc * 2 + (orr == EdgeOrientation::Flipped) as usize);
+
+        placement * 64 + flips
+    }
+}
+
+impl<const FIRST: usize> FromIndex for Rubiks3x3x3EdgeHalf<FIRST> {
+    fn from_index(index: usize) -> Self {
+        let (mut placement, flips) = (index / 64, index % 64);
+
+        // digits come off last first
+        let mut digits = [0; 6];
+        for i in (0..6).rev() {
+            digits[i] = placement % (12 - i);
+            placement /= 12 - i;
+        }
+
+        let cubelets: Vec<EdgeCubelet> = all::<EdgeCubelet>().collect();
+        let mut slots: [Option<EdgeSlot>; 12] = [None; 12];
+
+        // each digit picks out a slot by its rank among the ones not taken yet
+        let mut free: Vec<usize> = (0..12).collect();
+        for (i, digit) in digits.into_iter().enumerate() {
+            let orr = if (flips >> (5 - i)) & 1 == 1 {
+                EdgeOrientation::Flipped
+            } else {
+                EdgeOrientation::Normal
+            };
+            slots[free.remove(digit)] = Some(EdgeSlot {
+                cubelet: cubelets[FIRST + i],
+                orr,
+            });
+        }
+
+        // the rest go wherever's left; they don't count, so any way will do
+        let mut others = cubelets
+            .into_iter()
+            .enumerate()
+            .filter(|(piece, _)| !(FIRST..FIRST + 6).contains(piece))
+            .map(|(_, cubelet)| EdgeSlot::solved(cubelet));
+
+        Self(EdgeState::from_array(slots.map(|slot| {
+            slot.unwrap_or_else(|| others.next().expect("Six others for six slots"))
+        })))
+    }
+}
+
+impl<const FIRST: usize> Project<Rubiks3x3x3EdgeHalf<FIRST>> for Rubiks3x3x3 {
+    fn project(&self) -> Rubiks3x3x3EdgeHalf<FIRST> {
+        Rubiks3x3x3EdgeHalf(self.edges)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Sequence)]
+pub enum Move {
+    #[display(fmt = "U{}", _0)]
+    U(CubeMoveAmt),
+    #[display(fmt = "D{}", _0)]
+    D(CubeMoveAmt),
+    #[display(fmt = "F{}", _0)]
+    F(CubeMoveAmt),
+    #[display(fmt = "B{}", _0)]
+    B(CubeMoveAmt),
+    #[display(fmt = "L{}", _0)]
+    L(CubeMoveAmt),
+    #[display(fmt = "R{}", _0)]
+    R(CubeMoveAmt),
+}
+
+impl_can_reverse!(Move {
+    self_inverse: [],
+    by_amount: [U, D, F, B, L, R],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
+impl Solvable for Rubiks3x3x3 {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self == &<Self as CubeState>::start()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        // turning the same face twice in a row is never useful, and opposite faces commute, so
+        // only take them in one order (U before D, F before B, L before R)
+        matches!(
+            (last_move, next_move),
+            (Move::U(_) | Move::D(_), Move::U(_))
+                | (Move::D(_), Move::D(_))
+                | (Move::F(_) | Move::B(_), Move::F(_))
+                | (Move::B(_), Move::B(_))
+                | (Move::L(_) | Move::R(_), Move::L(_))
+                | (Move::R(_), Move::R(_))
+        )
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        let (turn, amt): (fn(&Self) -> Self, CubeMoveAmt) = match m {
+            Move::U(amt) => (Self::u, amt),
+            Move::D(amt) => (Self::d, amt),
+            Move::F(amt) => (Self::f, amt),
+            Move::B(amt) => (Self::b, amt),
+            Move::L(amt) => (Self::l, amt),
+            Move::R(amt) => (Self::r, amt),
+        };
+
+        match amt {
+            CubeMoveAmt::One => turn(self),
+            CubeMoveAmt::Two => turn(&turn(self)),
+            CubeMoveAmt::Rev => turn(&turn(&turn(self))),
+        }
+    }
+
+    fn max_fuel() -> usize {
+        // every state is within 20 half turns of solved
+        20
+    }
+}
+
+pub fn make_heuristic() -> impl Heuristic<Rubiks3x3x3> {
+    // every corner state, and every state of each half of the edges; none of them knows about the
+    // others, so the best bound is just the biggest of the three
+    MaxHeuristic::new(
+        ProjectedDenseCache::<Rubiks3x3x3Corners>::new(),
+        MaxHeuristic::new(
+            ProjectedDenseCache::<Rubiks3x3x3EdgeHalf<0>>::new(),
+            ProjectedDenseCache::<Rubiks3x3x3EdgeHalf<6>>::new(),
+        ),
+    )
+}
+
+impl RandomInit for Rubiks3x3x3 {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        // the corners can be in any order, as long as the edges have the same parity; the twists
+        // have to add up to zero, and so do the flips
+        let (corners, parity) = shuffle_any(r, all::<CornerCubelet>());
+        let edges = shuffle_with_parity(r, &all::<EdgeCubelet>().collect::<Vec<_>>(), parity);
+
+        let mut twists: Vec<CornerOrientation> = (0..7).map(|_| r.gen()).collect();
+        twists.push(CornerOrientation::total(&twists).flip());
+
+        let flips = flips_with_parity(r, 12, TwoParity::Even);
+
+        let corner = |i: usize| CornerSlot {
+            cubelet: corners[i],
+            orr: twists[i],
+        };
+        let edge = |i: usize| EdgeSlot {
+            cubelet: edges[i],
+            orr: flips[i],
+        };
+
+        Self {
+            corners: CornerState {
+                ufl: corner(0),
+                ufr: corner(1),
+                ubl: corner(2),
+                ubr: corner(3),
+                dfl: corner(4),
+                dfr: corner(5),
+                dbl: corner(6),
+                dbr: corner(7),
+            },
+            edges: EdgeState {
+                uf: edge(0),
+                ul: edge(1),
+                ub: edge(2),
+                ur: edge(3),
+                fl: edge(4),
+                fr: edge(5),
+                bl: edge(6),
+                br: edge(7),
+                df: edge(8),
+                dl: edge(9),
+                db: edge(10),
+                dr: edge(11),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::idasearch::heuristic_helpers::states_at_depth;
+    use crate::legality::permutation_parity;
+    use crate::scrambles::{apply_moves, parse_moves};
+    use crate::test_helpers::random_walk_states;
+
+    /// How many times the moves have to be repeated to get back to solved
+    fn order_of(moves: &str) -> usize {
+        let moves = parse_moves::<Move>(moves).unwrap();
+        let mut state = apply_moves(&Rubiks3x3x3::solved(), &moves);
+        let mut order = 1;
+
+        while !state.is_solved() {
+            state = apply_moves(&state, &moves);
+            order += 1;
+        }
+
+        order
+    }
+
+    #[test]
+    fn well_known_orders() {
+        assert_eq!(order_of("R"), 4);
+        assert_eq!(order_of("R U R' U'"), 6);
+        assert_eq!(order_of("R U"), 105);
+        assert_eq!(order_of("R U2 D' B D'"), 1260);
+    }
+
+    #[test]
+    fn superflip_flips_every_edge_in_place() {
+        let moves = parse_moves::<Move>("U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2").unwrap();
+        let state = apply_moves(&Rubiks3x3x3::solved(), &moves);
+
+        assert_eq!(state.corners, CornerState::start());
+
+        let solved = EdgeState::start();
+        let e = &state.edges;
+        let s = &solved;
+        for (actual, expected) in [
+            (e.uf, s.uf),
+            (e.ul, s.ul),
+            (e.ub, s.ub),
+            (e.ur, s.ur),
+            (e.fl, s.fl),
+            (e.fr, s.fr),
+            (e.bl, s.bl),
+            (e.br, s.br),
+            (e.df, s.df),
+            (e.dl, s.dl),
+            (e.db, s.db),
+            (e.dr, s.dr),
+        ] {
+            assert_eq!(actual, expected.flipped());
+        }
+    }
+
+    #[test]
+    fn layer_sizes_match_the_known_counts() {
+        // half turn metric, from the usual tables
+        for (depth, expected) in [(1, 18), (2, 243), (3, 3240)] {
+            assert_eq!(states_at_depth::<Rubiks3x3x3>(depth).len(), expected, "Depth {depth}");
+        }
+    }
+
+    #[test]
+    fn random_states_are_legal() {
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..100 {
+            let state = Rubiks3x3x3::random_state(&mut rng);
+            let (c, e) = (&state.corners, &state.edges);
+
+            let corners = [c.ufl, c.ufr, c.ubl, c.ubr, c.dfl, c.dfr, c.dbl, c.dbr];
+            let edges = [e.uf, e.ul, e.ub, e.ur, e.fl, e.fr, e.bl, e.br, e.df, e.dl, e.db, e.dr];
+
+            let solved_corners: Vec<CornerCubelet> = all::<CornerCubelet>().collect();
+            let solved_edges: Vec<EdgeCubelet> = all::<EdgeCubelet>().collect();
+            let corner_parity = permutation_parity(&solved_corners, &corners.map(|s| s.cubelet)).unwrap();
+            let edge_parity = permutation_parity(&solved_edges, &edges.map(|s| s.cubelet)).unwrap();
+            assert_eq!(corner_parity, edge_parity);
+
+            let twists = corners.map(|s| s.orr);
+            assert_eq!(CornerOrientation::total(&twists), CornerOrientation::Normal);
+
+            let flipped = edges.iter().filter(|s| s.orr == EdgeOrientation::Flipped).count();
+            assert_eq!(flipped % 2, 0);
+        }
+    }
+
+    #[test]
+    fn indices_round_trip() {
+        let mut rng = StdRng::from_seed([16; 32]);
+
+        for _ in 0..100 {
+            let state = Rubiks3x3x3::random_state(&mut rng);
+
+            let corners: Rubiks3x3x3Corners = state.project();
+            assert!(corners.index() < Rubiks3x3x3Corners::NUM_INDICES);
+            assert_eq!(Rubiks3x3x3Corners::from_index(corners.index()), corners);
+
+            let first: Rubiks3x3x3EdgeHalf<0> = state.project();
+            assert!(first.index() < Rubiks3x3x3EdgeHalf::<0>::NUM_INDICES);
+            assert_eq!(
+                Rubiks3x3x3EdgeHalf::<0>::from_index(first.index()).index(),
+                first.index()
+            );
+
+            let second: Rubiks3x3x3EdgeHalf<6> = state.project();
+            assert!(second.index() < Rubiks3x3x3EdgeHalf::<6>::NUM_INDICES);
+            assert_eq!(
+                Rubiks3x3x3EdgeHalf::<6>::from_index(second.index()).index(),
+                second.index()
+            );
+        }
+
+        // the last index has everything backwards and as twisted as it can be
+        let last = Rubiks3x3x3Corners::NUM_INDICES - 1;
+        assert_eq!(Rubiks3x3x3Corners::from_index(last).index(), last);
+        let last = Rubiks3x3x3EdgeHalf::<6>::NUM_INDICES - 1;
+        assert_eq!(Rubiks3x3x3EdgeHalf::<6>::from_index(last).index(), last);
+    }
+
+    #[test]
+    fn edge_halves_only_see_their_own_edges() {
+        // R moves UR, FR, BR and DR, so it splits them between the halves; U only moves the first
+        let turned = apply_moves(&Rubiks3x3x3::solved(), &parse_moves::<Move>("U").unwrap());
+        let first: Rubiks3x3x3EdgeHalf<0> = turned.project();
+        let second: Rubiks3x3x3EdgeHalf<6> = turned.project();
+        assert_ne!(first.index(), Rubiks3x3x3EdgeHalf::<0>::start().index());
+        assert_eq!(second.index(), Rubiks3x3x3EdgeHalf::<6>::start().index());
+
+        let turned = apply_moves(&Rubiks3x3x3::solved(), &parse_moves::<Move>("R").unwrap());
+        let first: Rubiks3x3x3EdgeHalf<0> = turned.project();
+        let second: Rubiks3x3x3EdgeHalf<6> = turned.project();
+        assert_ne!(first.index(), Rubiks3x3x3EdgeHalf::<0>::start().index());
+        assert_ne!(second.index(), Rubiks3x3x3EdgeHalf::<6>::start().index());
+    }
+
+    // building the tables takes about a minute in release, and far too long in a debug build
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn solves_long_walks() {
+        use crate::idasearch::solve;
+
+        let h = make_heuristic();
+
+        assert_eq!(h.estimated_remaining_cost(&Rubiks3x3x3::solved()), 0);
+
+        let moves = parse_moves::<Move>("R U F").unwrap();
+        let state = apply_moves(&Rubiks3x3x3::solved(), &moves);
+        assert_eq!(h.estimated_remaining_cost(&state), 3);
+
+        // a uniformly random state is 17 or 18 moves out, which is still far too long a search;
+        // long walks are deep enough that the tables have to do most of the work
+        let mut rng = StdRng::from_seed([17; 32]);
+        for _ in 0..3 {
+            let state = random_walk_states::<Rubiks3x3x3, _>(&mut rng, 14).pop().unwrap();
+            let solution = solve(&state, &h).expect("The walk itself is a solution");
+
+            assert!(solution.len() <= 14);
+            assert!(apply_moves(&state, &solution).is_solved());
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! The steps speedcubers solve the 3x3x3 in, as goals for
+//! [solve_predicate](crate::idasearch::solve_predicate): the first two layers and then orienting
+//! the last layer (CFOP), or the first 1x2x3 block (Roux). The U layer is always the last one, and
+//! the Roux block goes on the L side.
+//!
+//! Each goal only cares about some of the pieces, so each has a reduced cube, with every other
+//! piece blanked out; the distance to solved there is exactly the distance to the goal, and a
+//! bounded cache of it is the heuristic.
+
+use std::marker::PhantomData;
+
+use super::{
+    each_move, CornerCubelet, CornerSlot, CornerState, CubeState, EdgeCubelet, EdgeSlot, EdgeState, Rubiks3x3x3,
+};
+use crate::cubesearch::State;
+use crate::idasearch::heuristic_helpers::{Project, ProjectedCache};
+use crate::idasearch::Heuristic;
+
+// every goal here leaves the U layer for last, so these never need solving, and nothing which does
+// can be mistaken for a blank
+const BLANK_CORNER: CornerCubelet = CornerCubelet::UFL;
+const BLANK_EDGE: EdgeCubelet = EdgeCubelet::UF;
+
+/// The pieces a goal needs in place (by [CornerCubelet] and [EdgeCubelet] order); the rest are
+/// blanked, and if `orient_rest` is set, those still have to be turned the right way
+struct Mask {
+    corners: [bool; 8],
+    edges: [bool; 12],
+    orient_rest: bool,
+}
+
+impl Mask {
+    const fn new(corners: &[CornerCubelet], edges: &[EdgeCubelet], orient_rest: bool) -> Self {
+        let mut out = Self {
+            corners: [false; 8],
+            edges: [false; 12],
+            orient_rest,
+        };
+
+        let mut i = 0;
+        while i < corners.len() {
+            out.corners[corners[i] as usize] = true;
+            i += 1;
+        }
+
+        let mut i = 0;
+        while i < edges.len() {
+            out.edges[edges[i] as usize] = true;
+            i += 1;
+        }
+
+        out
+    }
+}
+
+const F2L_CORNERS: [CornerCubelet; 4] = [
+    CornerCubelet::DFL,
+    CornerCubelet::DFR,
+    CornerCubelet::DBL,
+    CornerCubelet::DBR,
+];
+
+const F2L_EDGES: [EdgeCubelet; 8] = [
+    EdgeCubelet::FL,
+    EdgeCubelet::FR,
+    EdgeCubelet::BL,
+    EdgeCubelet::BR,
+    EdgeCubelet::DF,
+    EdgeCubelet::DL,
+    EdgeCubelet::DB,
+    EdgeCubelet::DR,
+];
+
+trait SubGoal: Copy + PartialEq {
+    const MASK: Mask;
+}
+
+/// The D and middle layers
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct FirstTwoLayers;
+
+impl SubGoal for FirstTwoLayers {
+    const MASK: Mask = Mask::new(&F2L_CORNERS, &F2L_EDGES, false);
+}
+
+/// The first two layers, and the U face all one color
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct LastLayerOriented;
+
+impl SubGoal for LastLayerOriented {
+    const MASK: Mask = Mask::new(&F2L_CORNERS, &F2L_EDGES, true);
+}
+
+/// The 1x2x3 block on the bottom of the L side
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct RouxFirstBlock;
+
+impl SubGoal for RouxFirstBlock {
+    const MASK: Mask = Mask::new(
+        &[CornerCubelet::DFL, CornerCubelet::DBL],
+        &[EdgeCubelet::FL, EdgeCubelet::BL, EdgeCubelet::DL],
+        false,
+    );
+}
+
+/// The cube with everything the goal doesn't care about blanked out. Turns don't look at what a
+/// piece is, only where it is, so blanking commutes with turning, and this is a puzzle in its own
+/// right.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct Reduced<G> {
+    cube: Rubiks3x3x3,
+    _goal: PhantomData<G>,
+}
+
+impl<G: SubGoal> Reduced<G> {
+    fn new(cube: &Rubiks3x3x3) -> Self {
+        let mask = &G::MASK;
+
+        let corners = cube
+            .corners
+            .as_array()
+            .map(|slot| match mask.corners[slot.cubelet as usize] {
+                true => slot,
+                false if mask.orient_rest => CornerSlot {
+                    cubelet: BLANK_CORNER,
+                    orr: slot.orr,
+                },
+                false => CornerSlot::solved(BLANK_CORNER),
+            });
+
+        let edges = cube
+            .edges
+            .as_array()
+            .map(|slot| match mask.edges[slot.cubelet as usize] {
+                true => slot,
+                false if mask.orient_rest => EdgeSlot {
+                    cubelet: BLANK_EDGE,
+                    orr: slot.orr,
+                },
+                false => EdgeSlot::solved(BLANK_EDGE),
+            });
+
+        Self {
+            cube: Rubiks3x3x3 {
+                corners: CornerState::from_array(corners),
+                edges: EdgeState::from_array(edges),
+            },
+            _goal: PhantomData,
+        }
+    }
+}
+
+impl<G: SubGoal> State for Reduced<G> {
+    type UniqueKey = (u64, u64);
+
+    fn neighbors<Recv>(&self, to_add: &mut Recv)
+    where
+        Recv: FnMut(Self),
+    {
+        each_move(&self.cube, &mut |next| to_add(Self::new(&next)));
+    }
+
+    fn start() -> Self {
+        Self::new(&<Rubiks3x3x3 as CubeState>::start())
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        State::uniq_key(&self.cube)
+    }
+}
+
+impl<G: SubGoal> Project<Reduced<G>> for Rubiks3x3x3 {
+    fn project(&self) -> Reduced<G> {
+        Reduced::new(self)
+    }
+}
+
+/// Whether the pieces the goal cares about are all solved
+fn reached<G: SubGoal>(cube: &Rubiks3x3x3) -> bool {
+    Reduced::<G>::new(cube) == Reduced::<G>::start()
+}
+
+/// The D and middle layers are solved
+pub fn first_two_layers(cube: &Rubiks3x3x3) -> bool {
+    reached::<FirstTwoLayers>(cube)
+}
+
+/// The first two layers are solved, and the U face is all one color (so every U layer piece is
+/// turned the right way, wherever it is); OLL, in CFOP
+pub fn last_layer_oriented(cube: &Rubiks3x3x3) -> bool {
+    reached::<LastLayerOriented>(cube)
+}
+
+/// The 1x2x3 block on the bottom of the L side is solved; the first block, in Roux
+pub fn roux_first_block(cube: &Rubiks3x3x3) -> bool {
+    reached::<RouxFirstBlock>(cube)
+}
+
+/// Exact distances to [first_two_layers], out to `depth` moves
+pub fn first_two_layers_heuristic(depth: usize) -> impl Heuristic<Rubiks3x3x3> {
+    ProjectedCache::<Reduced<FirstTwoLayers>>::new(depth)
+}
+
+/// Exact distances to [last_layer_oriented], out to `depth` moves
+pub fn last_layer_oriented_heuristic(depth: usize) -> impl Heuristic<Rubiks3x3x3> {
+    ProjectedCache::<Reduced<LastLayerOriented>>::new(depth)
+}
+
+/// Exact distances to [roux_first_block], out to `depth` moves
+pub fn roux_first_block_heuristic(depth: usize) -> impl Heuristic<Rubiks3x3x3> {
+    ProjectedCache::<Reduced<RouxFirstBlock>>::new(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::cubesearch::HasSolvedState;
+    use crate::idasearch::{solve_predicate, Solvable};
+    use crate::scrambles::{apply_moves, invert, parse_moves};
+    use crate::test_helpers::random_walk_states;
+
+    fn after(moves: &str) -> Rubiks3x3x3 {
+        apply_moves(&Rubiks3x3x3::solved(), &parse_moves(moves).unwrap())
+    }
+
+    #[test]
+    fn which_turns_break_which_goals() {
+        let solved = Rubiks3x3x3::solved();
+        assert!(first_two_layers(&solved));
+        assert!(last_layer_oriented(&solved));
+        assert!(roux_first_block(&solved));
+
+        // U only moves the last layer around, without turning anything over
+        assert!(last_layer_oriented(&after("U")));
+
+        // R leaves the L side alone, but not the first two layers
+        assert!(roux_first_block(&after("R")));
+        assert!(!first_two_layers(&after("R")));
+
+        // a last layer algorithm puts the first two layers back, but turns the U pieces
+        let sune = after("R U R' U R U2 R'");
+        assert!(first_two_layers(&sune));
+        assert!(!last_layer_oriented(&sune));
+        assert!(roux_first_block(&sune));
+
+        assert!(!roux_first_block(&after("L")));
+        assert!(!roux_first_block(&after("D")));
+    }
+
+    #[test]
+    fn solutions_reach_their_goals() {
+        type Goal = fn(&Rubiks3x3x3) -> bool;
+
+        let goals: [(Goal, Box<dyn Heuristic<Rubiks3x3x3>>); 3] = [
+            (first_two_layers, Box::new(first_two_layers_heuristic(4))),
+            (last_layer_oriented, Box::new(last_layer_oriented_heuristic(4))),
+            (roux_first_block, Box::new(roux_first_block_heuristic(4))),
+        ];
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for state in random_walk_states::<Rubiks3x3x3, _>(&mut rng, 6) {
+            for (goal, h) in &goals {
+                let h = |s: &Rubiks3x3x3| h.estimated_remaining_cost(s);
+                let solution = solve_predicate(&state, &h, goal, 6).expect("The walk gets there in 6");
+
+                assert!(goal(&apply_moves(&state, &solution)));
+
+                // the heuristic is exact for the goal, as far out as it goes
+                assert_eq!(h(&state), solution.len().min(5));
+            }
+        }
+    }
+
+    #[test]
+    fn oll_cases_take_their_algorithms() {
+        // Sune twists three corners, and the T case flips two edges and twists two corners; no
+        // shorter sequence orients either, however it leaves the last layer
+        for (alg, expected) in [("R U R' U R U2 R'", 7), ("F R U R' U' F'", 6)] {
+            let alg = parse_moves(alg).unwrap();
+            let state = apply_moves(&Rubiks3x3x3::solved(), &invert(&alg));
+            assert!(first_two_layers(&state));
+
+            let h = last_layer_oriented_heuristic(4);
+            let solution = solve_predicate(&state, &h, last_layer_oriented, Rubiks3x3x3::max_fuel()).unwrap();
+
+            assert!(last_layer_oriented(&apply_moves(&state, &solution)));
+            assert_eq!(solution.len(), expected);
+        }
+    }
+}
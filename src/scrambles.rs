@@ -1,84 +1,1143 @@
+use std::cell::Cell;
 use std::fmt::Display;
+use std::hash::Hash;
+use std::io::{self, BufRead, Write};
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Instant;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use rand::Rng;
+use ahash::HashMap;
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 
+use crate::cubesearch::{HasSolvedState, State};
+use crate::describe::DecodeKey;
 use crate::idasearch;
-use crate::idasearch::{Heuristic, Solvable, SolveError};
-use crate::moves::CanReverse;
+use crate::idasearch::heuristic_helpers::bounded_cache_from;
+use crate::idasearch::{Heuristic, Solvable, SolveCache, SolveError};
+use crate::moves::{CanReverse, ParseMoveError};
 
 pub trait RandomInit: Sized {
     fn random_state<R: Rng>(r: &mut R) -> Self;
 }
 
+/// A scramble (applied from solved) together with the optimal solution that was found for it
+pub struct SampledSolve<M> {
+    pub scramble: Vec<M>,
+    pub solution: Vec<M>,
+}
+
+impl<M: Display> Display for SampledSolve<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Scramble: {}; solution: {}",
+            format_moves(&self.scramble),
+            format_moves(&self.solution)
+        )
+    }
+}
+
+/// A histogram of keys (or of the errors, for states which couldn't be solved), along with a few
+/// sampled solves
+pub type SampledCounts<K, M> = (HashMap<Result<K, SolveError>, usize>, Vec<SampledSolve<M>>);
+
+pub fn print_samples<M: Display>(samples: &[SampledSolve<M>]) {
+    for (i, sample) in samples.iter().enumerate() {
+        println!("    Sample {i}: {sample}");
+    }
+}
+
+/// Solves `num_scrambles` random states and returns a histogram of the solution lengths; that is,
+/// a map from solution length to the number of states with that length. States which couldn't be
+/// solved are counted by error instead. The first `num_samples` scrambles are printed, along with
+/// their solutions. The search depth is capped at `max_fuel`, if given, or the puzzle's own cap
+/// otherwise.
 pub fn bulk_scramble<
     R: Rng,
-    M: CanReverse,
+    M: CanReverse + Display + Send,
     State: RandomInit + Solvable<Move = M> + Sized + Sync + Send + 'static,
     H: Heuristic<State> + Sized + Sync + Send + 'static,
 >(
     rng: &mut R,
     h: &H,
     num_scrambles: usize,
-) -> Result<Vec<usize>, SolveError> {
-    let states: Vec<State> = (0..num_scrambles).map(|_| State::random_state(rng)).collect();
+    num_samples: usize,
+    max_fuel: Option<usize>,
+) -> HashMap<Result<usize, SolveError>, usize> {
+    let (counts, samples) = bulk_scramble_by(rng, h, num_scrambles, num_samples, max_fuel, |_, sol| sol.len());
+
+    print_samples(&samples);
+
+    counts
+}
 
-    let completed = AtomicUsize::new(0);
-    let start = Instant::now();
+/// Solves each of the given states, in parallel, giving the solutions in the same order as the
+/// states. The search depth is capped at `max_fuel`, if given, or the puzzle's own cap otherwise.
+pub fn bulk_solve<S, H>(states: Vec<S>, h: &H, max_fuel: Option<usize>) -> Vec<Result<Vec<S::Move>, SolveError>>
+where
+    S: Solvable + Sync + Send,
+    S::Move: Send,
+    H: Heuristic<S> + Sync,
+{
+    let max_fuel = max_fuel.unwrap_or_else(S::max_fuel);
+    let progress = Progress::new(states.len());
 
     states
         .into_par_iter()
-        .map(|s| {
-            let solution: Vec<M> = idasearch::solve(&s, h)?;
-            let out = solution.len();
-
-            let c = completed.fetch_add(1, Ordering::SeqCst);
-            let c = c + 1; // fetch_add gets the OLD value
-            if c % 100 == 0 {
-                let elapsed = start.elapsed();
-                let elapsed_ms = elapsed.as_secs_f32() * 1000.0;
-                let rate = elapsed_ms / (c as f32);
-                let pct_complete = (c as f32) / (num_scrambles as f32) * 100.0;
-                let rem_time = ((num_scrambles - c) as f32) * rate / 1000.0;
-                println!("    Solved {c} states in {elapsed:?} -- {rate:.3} ms per iter ({pct_complete:.3}% complete; est {rem_time:.3} seconds remaining)");
-            }
-            Ok(out)
-        })
+        .map(|s| solve_counted(&s, h, max_fuel, &progress))
         .collect()
 }
 
+/// Solves one state of a batch, ticking the batch's progress once it's done
+fn solve_counted<S: Solvable, H: Heuristic<S>>(
+    s: &S,
+    h: &H,
+    max_fuel: usize,
+    progress: &Progress,
+) -> Result<Vec<S::Move>, SolveError> {
+    let out = idasearch::solve_with_fuel(s, h, max_fuel);
+    progress.tick();
+    out
+}
+
+/// Runs `f` with the bulk operations inside it (solving, enumerating in parallel) spread over
+/// `threads` threads, or over every core if that's None. Results don't depend on the count; every
+/// random state is generated up front, from the one rng, before any of them is handed out.
+pub fn with_threads<T: Send>(threads: Option<usize>, f: impl FnOnce() -> T + Send) -> T {
+    match threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Should be able to start the thread pool")
+            .install(f),
+        None => f(),
+    }
+}
+
+/// Prints a running count (every hundred states) of how far along a batch of solves is
+struct Progress {
+    total: usize,
+    completed: AtomicUsize,
+    start: Instant,
+}
+
+impl Progress {
+    fn new(total: usize) -> Self {
+        Self {
+            total,
+            completed: AtomicUsize::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    fn tick(&self) {
+        let c = self.completed.fetch_add(1, Ordering::SeqCst);
+        let c = c + 1; // fetch_add gets the OLD value
+        if c.is_multiple_of(100) {
+            let elapsed = self.start.elapsed();
+            let elapsed_ms = elapsed.as_secs_f32() * 1000.0;
+            let rate = elapsed_ms / (c as f32);
+            let pct_complete = (c as f32) / (self.total as f32) * 100.0;
+            let rem_time = ((self.total - c) as f32) * rate / 1000.0;
+            println!("    Solved {c} states in {elapsed:?} -- {rate:.3} ms per iter ({pct_complete:.3}% complete; est {rem_time:.3} seconds remaining)");
+        }
+    }
+}
+
+/// Same as [bulk_scramble], but the caller decides what to count from each (state, solution)
+/// pair, rather than just the length of the solution. The solves of the first `num_samples` states
+/// are returned (rather than printed) alongside the histogram, in order, whichever finish first.
+///
+/// Results are folded into the histogram as they come in, so the individual solutions are never
+/// all held in memory at once.
+pub fn bulk_scramble_by<
+    R: Rng,
+    M: CanReverse + Send,
+    State: RandomInit + Solvable<Move = M> + Sized + Sync + Send + 'static,
+    H: Heuristic<State> + Sized + Sync + Send + 'static,
+    K: Hash + Eq + Send,
+    F: Fn(&State, &[M]) -> K + Sync,
+>(
+    rng: &mut R,
+    h: &H,
+    num_scrambles: usize,
+    num_samples: usize,
+    max_fuel: Option<usize>,
+    key: F,
+) -> SampledCounts<K, M> {
+    let states: Vec<State> = (0..num_scrambles).map(|_| State::random_state(rng)).collect();
+    let max_fuel = max_fuel.unwrap_or_else(State::max_fuel);
+
+    let progress = Progress::new(num_scrambles);
+
+    // tagged with the index of the state, so they can be put back in order at the end
+    let samples: Mutex<Vec<(usize, SampledSolve<M>)>> = Mutex::new(Vec::with_capacity(num_samples));
+
+    let counts = states
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, s)| {
+            solve_counted(&s, h, max_fuel, &progress).map(|solution| {
+                let out = key(&s, &solution);
+
+                if i < num_samples {
+                    samples.lock().unwrap().push((
+                        i,
+                        SampledSolve {
+                            scramble: invert(&solution),
+                            solution,
+                        },
+                    ));
+                }
+
+                out
+            })
+        })
+        .fold(
+            HashMap::default,
+            |mut counts: HashMap<Result<K, SolveError>, usize>, k| {
+                *counts.entry(k).or_default() += 1;
+                counts
+            },
+        )
+        .reduce(HashMap::default, |mut a, b| {
+            for (k, count) in b {
+                *a.entry(k).or_default() += count;
+            }
+            a
+        });
+
+    let mut samples = samples.into_inner().unwrap();
+    samples.sort_by_key(|(i, _)| *i);
+
+    (counts, samples.into_iter().map(|(_, sample)| sample).collect())
+}
+
+/// How one heuristic (usually a cache of a given depth) did on a fixed batch of scrambles
+#[derive(Debug)]
+pub struct SweepRow {
+    pub depth: usize,
+    pub setup_time: Duration,
+    pub mean_solve_time: Duration,
+    /// The number of states the search generated (and so asked the heuristic about), over all the
+    /// scrambles together
+    pub total_nodes: usize,
+}
+
+/// Wraps a heuristic, counting how many times it gets asked for an estimate
+struct CountingHeuristic<H> {
+    inner: H,
+    calls: Cell<usize>,
+}
+
+impl<S, H: Heuristic<S>> Heuristic<S> for CountingHeuristic<H> {
+    fn estimated_remaining_cost(&self, t: &S) -> usize {
+        self.calls.set(self.calls.get() + 1);
+        self.inner.estimated_remaining_cost(t)
+    }
+}
+
+/// For each depth in turn, builds a heuristic with `make_heuristic` and uses it to solve the same
+/// `samples` random states (from a fixed seed), timing both parts. Bigger caches are slower to
+/// build, but aren't always faster to solve with, and this is the data for deciding.
+pub fn heuristic_sweep<S, H, F>(
+    depths: RangeInclusive<usize>,
+    samples: usize,
+    make_heuristic: F,
+) -> Result<Vec<SweepRow>, SolveError>
+where
+    S: RandomInit + Solvable,
+    H: Heuristic<S>,
+    F: Fn(usize) -> H,
+{
+    let mut rng = StdRng::from_seed([15; 32]);
+    let states: Vec<S> = (0..samples).map(|_| S::random_state(&mut rng)).collect();
+
+    heuristic_sweep_on(&states, depths, make_heuristic)
+}
+
+/// Same as [heuristic_sweep], but on the given states, for puzzles with no [RandomInit] (which can
+/// sample from random walks instead)
+pub fn heuristic_sweep_on<S, H, F>(
+    states: &[S],
+    depths: RangeInclusive<usize>,
+    make_heuristic: F,
+) -> Result<Vec<SweepRow>, SolveError>
+where
+    S: Solvable,
+    H: Heuristic<S>,
+    F: Fn(usize) -> H,
+{
+    let mut rows = Vec::new();
+
+    for depth in depths {
+        let setup_start = Instant::now();
+        let h = CountingHeuristic {
+            inner: make_heuristic(depth),
+            calls: Cell::new(0),
+        };
+        let setup_time = setup_start.elapsed();
+
+        let solve_start = Instant::now();
+        for s in states.iter() {
+            idasearch::solve(s, &h)?;
+        }
+        let mean_solve_time = solve_start.elapsed() / (states.len().max(1) as u32);
+
+        rows.push(SweepRow {
+            depth,
+            setup_time,
+            mean_solve_time,
+            total_nodes: h.calls.get(),
+        });
+    }
+
+    Ok(rows)
+}
+
+/// One depth of a [DepthComparison]
+#[derive(Debug)]
+pub struct DepthComparisonRow {
+    pub depth: usize,
+    pub sampled: usize,
+    pub sampled_fraction: f64,
+    pub theoretical_fraction: f64,
+}
+
+/// How a batch of sampled scramble depths lines up against the exact distribution, from
+/// enumerating the whole puzzle. If the random states really are uniform, the two only differ by
+/// sampling noise, which is what the chi-square statistic measures.
+#[derive(Debug)]
+pub struct DepthComparison {
+    pub rows: Vec<DepthComparisonRow>,
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+}
+
+impl DepthComparison {
+    /// Whether the deviation is small enough to put down to chance. The cutoff is four standard
+    /// deviations above the mean of the chi-square distribution; rough, but a biased generator
+    /// usually misses by orders of magnitude, not by a little.
+    pub fn within_tolerance(&self) -> bool {
+        let dof = self.degrees_of_freedom as f64;
+        self.chi_square <= dof + 4.0 * (2.0 * dof).sqrt()
+    }
+}
+
+/// Compares sampled depths (depth to number of samples) to the depth counts from enumerating the
+/// whole puzzle. Depths expected to see fewer than five samples are pooled together for the
+/// chi-square statistic, since a single stray sample there would otherwise swamp everything else;
+/// they still get their own rows.
+pub fn compare_depth_distribution(
+    sampled: &HashMap<usize, usize>,
+    theoretical: &HashMap<u128, u128>,
+) -> DepthComparison {
+    const MIN_EXPECTED: f64 = 5.0;
+
+    let num_sampled: usize = sampled.values().sum();
+    let num_states: u128 = theoretical.values().sum();
+
+    let max_depth = sampled
+        .keys()
+        .copied()
+        .chain(theoretical.keys().map(|&d| d as usize))
+        .max()
+        .unwrap_or(0);
+
+    let mut rows = Vec::new();
+    let mut chi_square = 0.0;
+    let mut num_bins = 0;
+    let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+
+    for depth in 0..=max_depth {
+        let observed = sampled.get(&depth).copied().unwrap_or(0);
+        let theoretical_fraction = theoretical.get(&(depth as u128)).copied().unwrap_or(0) as f64 / num_states as f64;
+
+        let expected = theoretical_fraction * num_sampled as f64;
+        if expected >= MIN_EXPECTED {
+            chi_square += (observed as f64 - expected).powi(2) / expected;
+            num_bins += 1;
+        } else {
+            pooled_observed += observed as f64;
+            pooled_expected += expected;
+        }
+
+        rows.push(DepthComparisonRow {
+            depth,
+            sampled: observed,
+            sampled_fraction: observed as f64 / num_sampled as f64,
+            theoretical_fraction,
+        });
+    }
+
+    if pooled_expected > 0.0 || pooled_observed > 0.0 {
+        // a sample at a depth which can't happen makes this infinite, which is the right answer
+        chi_square += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+        num_bins += 1;
+    }
+
+    DepthComparison {
+        rows,
+        chi_square,
+        degrees_of_freedom: num_bins.max(2) - 1,
+    }
+}
+
+/// The sequence of moves which undoes the given one; reverse the order, and reverse each move
+pub fn invert<M: CanReverse>(moves: &[M]) -> Vec<M> {
+    moves.iter().rev().map(|m| m.reverse()).collect()
+}
+
+/// Parses a space-separated sequence of moves, the inverse of `format_moves`
+pub fn parse_moves<M: FromStr>(moves: &str) -> Result<Vec<M>, M::Err> {
+    moves.split_whitespace().map(M::from_str).collect()
+}
+
+/// Same as [parse_moves], but also checks every move is one the puzzle actually has; a move type
+/// can often spell more than that (a floppy's wide turns, say, don't know how many layers there are)
+pub fn parse_scramble<S>(scramble: &str) -> Result<Vec<S::Move>, ParseMoveError>
+where
+    S: Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+{
+    let all_moves = S::all_moves();
+    let moves: Vec<S::Move> = parse_moves(scramble)?;
+
+    match moves.iter().find(|m| !all_moves.contains(m)) {
+        Some(bad) => Err(ParseMoveError(bad.to_string())),
+        None => Ok(moves),
+    }
+}
+
+/// URL-safe, so codes can go anywhere without escaping
+const STATE_CODE_DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Six bits a digit, so eleven of them cover a 64-bit key
+const STATE_CODE_LEN: usize = 11;
+
+/// Writes the state as a short code, `puzzle_id:key`, with the key in base64; that's enough to
+/// share a position, as long as the key holds the whole state (see [DecodeKey])
+pub fn encode_state<S: State<UniqueKey = u64>>(puzzle_id: &str, state: &S) -> String {
+    let key = state.uniq_key();
+
+    let digits: String = (0..STATE_CODE_LEN)
+        .rev()
+        .map(|i| STATE_CODE_DIGITS[((key >> (6 * i)) & 63) as usize] as char)
+        .collect();
+
+    format!("{puzzle_id}:{digits}")
+}
+
+/// Solves each of the states (in parallel, like [bulk_solve]) and writes them as CSV, one row per
+/// state: its code (see [encode_state]), the length of its optimal solution, and the solution
+/// itself. That's a labeled dataset, for analysis or training.
+pub fn write_dataset<S, H, W>(puzzle_id: &str, states: Vec<S>, h: &H, mut output: W) -> io::Result<()>
+where
+    S: State<UniqueKey = u64> + Solvable + Sync + Send,
+    S::Move: Display + Send,
+    H: Heuristic<S> + Sync,
+    W: Write,
+{
+    let codes: Vec<String> = states.iter().map(|s| encode_state(puzzle_id, s)).collect();
+    let solutions = bulk_solve(states, h, None);
+
+    writeln!(output, "state,length,solution")?;
+    for (code, solution) in codes.into_iter().zip(solutions) {
+        let solution = solution.map_err(|e| io::Error::other(format!("Could not solve {code}: {e}")))?;
+        writeln!(output, "{code},{},{}", solution.len(), format_moves(&solution))?;
+    }
+
+    Ok(())
+}
+
+/// Splits a code from [encode_state] into the puzzle id and the encoded key
+pub fn split_state_code(code: &str) -> Result<(&str, &str), String> {
+    code.split_once(':')
+        .ok_or_else(|| format!("State code {code:?} should look like puzzle:key"))
+}
+
+/// Reads back a code from [encode_state], checking that it's for the expected puzzle, and that the
+/// key unpacks to a legal state which packs back to the same key
+pub fn decode_state<S: DecodeKey>(puzzle_id: &str, code: &str) -> Result<S, String> {
+    let (id, digits) = split_state_code(code)?;
+
+    if id != puzzle_id {
+        return Err(format!("State code is for {id}, not {puzzle_id}"));
+    }
+
+    if digits.len() != STATE_CODE_LEN {
+        return Err(format!(
+            "State key should be {STATE_CODE_LEN} digits, not {}",
+            digits.len()
+        ));
+    }
+
+    let mut key: u128 = 0;
+    for c in digits.bytes() {
+        let digit = STATE_CODE_DIGITS
+            .iter()
+            .position(|&d| d == c)
+            .ok_or_else(|| format!("{:?} is not a base64 digit", c as char))?;
+        key = (key << 6) | digit as u128;
+    }
+
+    let key = u64::try_from(key).map_err(|_| "State key is too big for 64 bits".to_string())?;
+
+    let state = S::decode_key(key).ok_or_else(|| format!("Key {key} does not unpack to a state"))?;
+    state
+        .is_legal()
+        .map_err(|e| format!("Key {key} is not a legal state: {e}"))?;
+
+    // anything in the unused bits would be lost on the way through, and shouldn't be there
+    if state.uniq_key() != key {
+        return Err(format!("Key {key} has bits set which don't mean anything"));
+    }
+
+    Ok(state)
+}
+
+/// Applies each of the moves in turn, starting from the given state
+pub fn apply_moves<S: Solvable>(start: &S, moves: &[S::Move]) -> S {
+    moves.iter().fold(start.clone(), |state, m| state.apply(*m))
+}
+
+/// Parses the scramble and returns its inverse, along with whether applying the scramble and then
+/// the inverse actually brings the start state back to solved
+pub fn invert_scramble<S>(scramble: &str) -> Result<(Vec<S::Move>, bool), ParseMoveError>
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + CanReverse + Display,
+{
+    let moves: Vec<S::Move> = parse_scramble::<S>(scramble)?;
+    let inverse = invert(&moves);
+
+    let scrambled = apply_moves(&S::start(), &moves);
+    let round_trips = apply_moves(&scrambled, &inverse).is_solved();
+
+    Ok((inverse, round_trips))
+}
+
+/// Solves the state the scramble produces (from solved) with `solve`, which picks the goal and
+/// how far to look for it
+pub fn solve_scramble_by<S, F>(scramble: &str, solve: F) -> Result<Vec<S::Move>, String>
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+    F: Fn(&S) -> Result<Vec<S::Move>, SolveError>,
+{
+    let moves: Vec<S::Move> = parse_scramble::<S>(scramble).map_err(|e| format!("Could not parse scramble: {e}"))?;
+    let scrambled = apply_moves(&S::start(), &moves);
+
+    solve(&scrambled).map_err(|e| format!("Could not solve scramble: {e}"))
+}
+
+/// The shortest sequence of moves taking the state `from` produces to the state `to` produces (both
+/// applied from solved); for finding transitions between two known positions. The heuristic is a
+/// cache of the states within `cache_depth` of the target, which is exact since every move can be
+/// undone, so a state's distance to the target is the same as the target's distance to it.
+pub fn connect_scrambles<S>(from: &str, to: &str, cache_depth: usize) -> Result<Vec<S::Move>, String>
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + CanReverse + Display,
+{
+    let parse = |scramble: &str| -> Result<S, String> {
+        let moves: Vec<S::Move> =
+            parse_scramble::<S>(scramble).map_err(|e| format!("Could not parse scramble: {e}"))?;
+        Ok(apply_moves(&S::start(), &moves))
+    };
+
+    let (from, to) = (parse(from)?, parse(to)?);
+    let heuristic = bounded_cache_from(vec![to.clone()], cache_depth);
+
+    idasearch::solve_to(&from, &to, &heuristic, S::max_fuel()).map_err(|e| format!("Could not connect scrambles: {e}"))
+}
+
+/// Reads one scramble per line, and writes one line for each, in the same order: the solution, or
+/// what went wrong. The heuristic is shared by every line, so it's only built once, however many
+/// scrambles come through; with a `cache`, so are the solutions to any repeated states.
+pub fn solve_stream<S, H, R, W>(
+    input: R,
+    output: W,
+    h: &H,
+    max_fuel: Option<usize>,
+    cache: Option<&SolveCache<S>>,
+) -> io::Result<()>
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+    H: Heuristic<S>,
+    R: BufRead,
+    W: Write,
+{
+    let max_fuel = max_fuel.unwrap_or_else(S::max_fuel);
+
+    let solve = |scrambled: &S| idasearch::solve_with_fuel(scrambled, h, max_fuel);
+
+    solve_stream_by(input, output, |scrambled: &S| match cache {
+        Some(cache) => cache.solve(scrambled, solve),
+        None => solve(scrambled),
+    })
+}
+
+/// Same as [solve_stream], but each scrambled state is solved by `solve`
+pub fn solve_stream_by<S, R, W, F>(input: R, mut output: W, solve: F) -> io::Result<()>
+where
+    S: State + Solvable,
+    S::Move: FromStr<Err = ParseMoveError> + Display,
+    R: BufRead,
+    W: Write,
+    F: Fn(&S) -> Result<Vec<S::Move>, SolveError>,
+{
+    for line in input.lines() {
+        match solve_scramble_by(&line?, &solve) {
+            Ok(solution) => writeln!(output, "{}", format_moves(&solution))?,
+            Err(e) => writeln!(output, "Error: {e}")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Space-separated, the way scrambles are usually written
+pub fn format_moves<M: Display>(moves: &[M]) -> String {
+    moves.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// Generates a random state and solves it, giving the scramble which produces that state. The
+/// search depth is capped at `max_fuel`, if given, or the puzzle's own cap otherwise.
 pub fn random_scramble<R: Rng, M: CanReverse, State: RandomInit + Solvable<Move = M>, H: Heuristic<State>>(
     rng: &mut R,
     h: &H,
+    max_fuel: Option<usize>,
 ) -> Result<Vec<M>, SolveError> {
     let s = State::random_state(rng);
+    let max_fuel = max_fuel.unwrap_or_else(State::max_fuel);
 
-    // solve the scramble
-    let solution: Vec<M> = idasearch::solve(&s, h)?;
+    // solve the scramble; debug builds double-check the solution, in case the puzzle is buggy
+    let solution: Vec<M> = if cfg!(debug_assertions) {
+        idasearch::solve_verified(&s, h, max_fuel)?
+    } else {
+        idasearch::solve_with_fuel(&s, h, max_fuel)?
+    };
 
     // reverse the order and the moves themselves
-    let out = solution.into_iter().rev().map(|m| m.reverse()).collect();
-    Ok(out)
+    Ok(invert(&solution))
 }
 
-pub fn random_scramble_string<
-    R: Rng,
-    M: CanReverse + Display,
-    State: RandomInit + Solvable<Move = M>,
-    H: Heuristic<State>,
->(
-    rng: &mut R,
-    h: &H,
-) -> Result<String, SolveError> {
-    let moves = random_scramble(rng, h)?;
+/// Hands out random scrambles of one puzzle, building the heuristic just once, up front. Building
+/// it is usually far more expensive than any one scramble, so anything wanting more than a few (a
+/// server, say) should build one of these and keep it around.
+pub struct Scrambler<S, H> {
+    heuristic: H,
+    max_fuel: Option<usize>,
+    _state: PhantomData<S>,
+}
+
+impl<S, H> Scrambler<S, H>
+where
+    S: RandomInit + Solvable,
+    S::Move: CanReverse,
+    H: Heuristic<S>,
+{
+    /// Runs `make_heuristic` right away. The search depth is capped at `max_fuel`, if given, or the
+    /// puzzle's own cap otherwise, same as [random_scramble].
+    pub fn new(make_heuristic: impl FnOnce() -> H, max_fuel: Option<usize>) -> Self {
+        Self {
+            heuristic: make_heuristic(),
+            max_fuel,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn scramble<R: Rng>(&self, rng: &mut R) -> Result<Vec<S::Move>, SolveError> {
+        random_scramble(rng, &self.heuristic, self.max_fuel)
+    }
+
+    pub fn scramble_string<R: Rng>(&self, rng: &mut R) -> Result<String, SolveError>
+    where
+        S::Move: Display,
+    {
+        Ok(format_moves(&self.scramble(rng)?))
+    }
+}
+
+/// Picks a move uniformly at random from the ones available in `state`, leaving out any which are
+/// redundant with `last` (undoing it, say, or merging into it), so a string of these doesn't
+/// trivially cancel. If every available move is redundant (which can happen when the redundancy
+/// rules also fix an order for commuting moves), any available move is fair game.
+pub fn random_legal_move<S: Solvable, R: Rng>(rng: &mut R, state: &S, last: Option<S::Move>) -> S::Move {
+    let available: Vec<S::Move> = state.available_moves().into_iter().collect();
+
+    available
+        .iter()
+        .filter(|&&m| last.is_none_or(|l| !S::is_redundant(l, m)))
+        .choose(rng)
+        .or_else(|| available.choose(rng))
+        .copied()
+        .expect("Every state should have at least one move")
+}
+
+/// Applies `num_moves` random moves to the start state, giving the moves. This is much cheaper
+/// than [random_scramble], and works for puzzles without [RandomInit], but the states it reaches
+/// are not uniformly distributed (especially for short walks).
+pub fn random_walk_scramble<S: Solvable + HasSolvedState, R: Rng>(rng: &mut R, num_moves: usize) -> Vec<S::Move> {
+    let mut state = S::solved();
+    let mut moves = Vec::with_capacity(num_moves);
+
+    for _ in 0..num_moves {
+        let m = random_legal_move(rng, &state, moves.last().copied());
+        state = state.apply(m);
+        moves.push(m);
+    }
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cubesearch::enumerate_state_space;
+    use crate::cuboid_2x2x3::Cuboid2x2x3;
+    use crate::floppy_1x3x3::Floppy1x3x3;
+    use crate::idasearch::heuristic_helpers::{bounded_cache, BoundedStateCache};
+    use crate::idasearch::no_heuristic;
+    use crate::pocket_cube::PocketCube;
+    use crate::redi_cube::RediCube;
+    use crate::test_helpers::random_walk_states;
+
+    /// Depths of the given states, looked up in a cache which covers the whole puzzle
+    fn depth_histogram(distances: &BoundedStateCache<Cuboid2x2x3>, states: &[Cuboid2x2x3]) -> HashMap<usize, usize> {
+        let mut out = HashMap::default();
+        for state in states {
+            let depth = distances
+                .remaining_cost_if_known(state)
+                .expect("Cache covers every state");
+            *out.entry(depth).or_default() += 1;
+        }
+        out
+    }
+
+    // the Cuboid 2x3x3 has too many states to enumerate in a test, so this uses its little brother
+    #[test]
+    fn uniform_scrambler_matches_theoretical_depths() {
+        const NUM_SAMPLES: usize = 20_000;
+
+        let (_, theoretical) = enumerate_state_space::<Cuboid2x2x3>(None).unwrap();
+        let distances = bounded_cache::<Cuboid2x2x3>(14);
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        let uniform: Vec<Cuboid2x2x3> = (0..NUM_SAMPLES).map(|_| Cuboid2x2x3::random_state(&mut rng)).collect();
+        let comparison = compare_depth_distribution(&depth_histogram(&distances, &uniform), &theoretical);
+        assert!(
+            comparison.within_tolerance(),
+            "Uniform states should pass: {comparison:?}"
+        );
+
+        // short random walks stay close to solved, so they're heavily biased toward small depths
+        let biased: Vec<Cuboid2x2x3> = (0..NUM_SAMPLES)
+            .map(|_| {
+                let walk = random_walk_scramble::<Cuboid2x2x3, _>(&mut rng, 6);
+                apply_moves(&Cuboid2x2x3::start(), &walk)
+            })
+            .collect();
+        let comparison = compare_depth_distribution(&depth_histogram(&distances, &biased), &theoretical);
+        assert!(
+            !comparison.within_tolerance(),
+            "Short walks should fail: {comparison:?}"
+        );
+    }
+
+    #[test]
+    fn state_codes_round_trip() {
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for pocket in random_walk_states::<PocketCube, _>(&mut rng, 20) {
+            let code = encode_state("pocket-cube", &pocket);
+            assert_eq!(decode_state::<PocketCube>("pocket-cube", &code), Ok(pocket));
+        }
+
+        for _ in 0..20 {
+            let redi = RediCube::random_state(&mut rng);
+            let code = encode_state("redi-cube", &redi);
+            assert_eq!(decode_state::<RediCube>("redi-cube", &code), Ok(redi));
+        }
+    }
+
+    #[test]
+    fn corrupted_state_codes_dont_decode() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let code = encode_state("redi-cube", &RediCube::random_state(&mut rng));
+        let (_, digits) = split_state_code(&code).unwrap();
+
+        // the wrong puzzle, or no puzzle at all
+        assert!(decode_state::<PocketCube>("pocket-cube", &code).is_err());
+        assert!(decode_state::<RediCube>("redi-cube", digits).is_err());
+
+        // a digit missing, or one which isn't base64
+        assert!(decode_state::<RediCube>("redi-cube", &code[..code.len() - 1]).is_err());
+        assert!(decode_state::<RediCube>("redi-cube", &format!("redi-cube:{}*", &digits[1..])).is_err());
+
+        // more than 64 bits
+        assert!(decode_state::<RediCube>("redi-cube", &format!("redi-cube:_{}", &digits[1..])).is_err());
+
+        // the sixth digit from the end is all edge bits; changing it duplicates an edge, or swaps
+        // two of them, which is an odd permutation, so every other digit there is illegal
+        let at = code.len() - 6;
+        for &digit in STATE_CODE_DIGITS.iter().filter(|&&d| d != code.as_bytes()[at]) {
+            let mut corrupted = code.clone().into_bytes();
+            corrupted[at] = digit;
+            let corrupted = String::from_utf8(corrupted).unwrap();
+
+            assert!(
+                decode_state::<RediCube>("redi-cube", &corrupted).is_err(),
+                "{corrupted} should not decode"
+            );
+        }
+    }
+
+    #[test]
+    fn histogram_matches_individual_solves() {
+        const NUM_SCRAMBLES: usize = 200;
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        let actual = bulk_scramble::<_, _, Floppy1x3x3, _>(&mut rng, &no_heuristic, NUM_SCRAMBLES, 0, None);
+
+        // same seed, so the same states, but solved one at a time and collected in full
+        let mut rng = StdRng::from_seed([15; 32]);
+        let lengths: Vec<usize> = (0..NUM_SCRAMBLES)
+            .map(|_| Floppy1x3x3::random_state(&mut rng))
+            .map(|s| idasearch::solve(&s, &no_heuristic).unwrap().len())
+            .collect();
+
+        let mut expected: HashMap<Result<usize, SolveError>, usize> = HashMap::default();
+        for len in lengths {
+            *expected.entry(Ok(len)).or_default() += 1;
+        }
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual.values().sum::<usize>(), NUM_SCRAMBLES);
+
+        // each key is hashed as it goes into the histogram; if the histogram is only built once
+        // every solve is done, the heuristic never sees a single one of them counted
+        let counted = Arc::new(AtomicUsize::new(0));
+        let most_counted_while_solving = Arc::new(AtomicUsize::new(0));
+        let h = WatchingHeuristic {
+            counted: counted.clone(),
+            most_counted: most_counted_while_solving.clone(),
+        };
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        let (by_key, _) = with_threads(Some(1), || {
+            bulk_scramble_by::<_, _, Floppy1x3x3, _, _, _>(&mut rng, &h, NUM_SCRAMBLES, 0, None, |_, sol| HashCounted {
+                len: sol.len(),
+                counted: counted.clone(),
+            })
+        });
+
+        let by_key: HashMap<Result<usize, SolveError>, usize> =
+            by_key.into_iter().map(|(k, count)| (k.map(|k| k.len), count)).collect();
+        assert_eq!(by_key, expected);
+        // (a histogram can rehash its keys as it grows, so this can overcount, but never under)
+        assert!(
+            most_counted_while_solving.load(Ordering::SeqCst) >= NUM_SCRAMBLES - 1,
+            "Every solution should be counted before the next state is solved"
+        );
+    }
+
+    /// A solution length which notes every time it's hashed (that is, put into a histogram)
+    struct HashCounted {
+        len: usize,
+        counted: Arc<AtomicUsize>,
+    }
+
+    impl PartialEq for HashCounted {
+        fn eq(&self, other: &Self) -> bool {
+            self.len == other.len
+        }
+    }
+
+    impl Eq for HashCounted {}
+
+    impl Hash for HashCounted {
+        fn hash<HS: std::hash::Hasher>(&self, state: &mut HS) {
+            self.counted.fetch_add(1, Ordering::SeqCst);
+            self.len.hash(state);
+        }
+    }
+
+    /// No heuristic at all, but it remembers the most solutions it ever saw counted
+    struct WatchingHeuristic {
+        counted: Arc<AtomicUsize>,
+        most_counted: Arc<AtomicUsize>,
+    }
+
+    impl<S> Heuristic<S> for WatchingHeuristic {
+        fn estimated_remaining_cost(&self, _: &S) -> usize {
+            self.most_counted
+                .fetch_max(self.counted.load(Ordering::SeqCst), Ordering::SeqCst);
+            0
+        }
+    }
+
+    #[test]
+    fn failures_are_counted_by_error() {
+        const NUM_SCRAMBLES: usize = 50;
+
+        // nothing gets solved with no moves at all, except the solved state
+        let mut rng = StdRng::from_seed([15; 32]);
+        let counts = bulk_scramble::<_, _, Floppy1x3x3, _>(&mut rng, &no_heuristic, NUM_SCRAMBLES, 0, Some(0));
+
+        let failures = counts.get(&Err(SolveError::OutOfGas { max_fuel: 0 })).copied();
+        let solved = counts.get(&Ok(0)).copied().unwrap_or(0);
+        assert_eq!(failures.unwrap_or(0) + solved, NUM_SCRAMBLES);
+        assert!(failures.is_some());
+    }
+
+    #[test]
+    fn sampled_solutions_solve_their_scrambles() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let (counts, samples) =
+            bulk_scramble_by::<_, _, Floppy1x3x3, _, _, _>(&mut rng, &no_heuristic, 50, 3, None, |_, s| s.len());
+
+        assert_eq!(counts.values().sum::<usize>(), 50);
+        assert_eq!(samples.len(), 3);
+
+        for sample in samples {
+            let mut state = Floppy1x3x3::start();
+            for m in sample.scramble.iter().chain(sample.solution.iter()) {
+                state = state.apply(*m);
+            }
+
+            assert!(state.is_solved(), "Solution should solve its scramble: {sample}");
+        }
+    }
+
+    #[test]
+    fn bulk_solutions_solve_their_states() {
+        use crate::idasearch::heuristic_helpers::bounded_cache;
+        use crate::pocket_cube::PocketCube;
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        let states: Vec<PocketCube> = (0..5)
+            .flat_map(|_| random_walk_states::<PocketCube, _>(&mut rng, 6))
+            .collect();
+
+        let solutions = bulk_solve(states.clone(), &bounded_cache::<PocketCube>(4), None);
+        assert_eq!(solutions.len(), states.len());
+
+        for (state, solution) in states.iter().zip(solutions) {
+            let solution = solution.expect("A short walk should be solvable");
+            assert!(solution.len() <= 6, "Solution should be no longer than the walk");
+            assert!(
+                apply_moves(state, &solution).is_solved(),
+                "Solution {} should solve its state",
+                format_moves(&solution)
+            );
+        }
+    }
+
+    #[test]
+    fn dino_dataset_round_trips() {
+        use crate::dino_cube::{self, DinoCube, SolveTarget};
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        let states: Vec<DinoCube> = (0..5).map(|_| DinoCube::random_state(&mut rng)).collect();
+        let h = dino_cube::make_heuristic_toward(SolveTarget::Nearest);
+
+        let mut output = Vec::new();
+        write_dataset("dino-cube", states.clone(), &h, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("state,length,solution"));
+
+        let rows: Vec<&str> = lines.collect();
+        assert_eq!(rows.len(), states.len());
+
+        for (row, original) in rows.into_iter().zip(&states) {
+            let [code, length, solution] = row.split(',').collect::<Vec<_>>()[..] else {
+                panic!("Row {row:?} should have three columns");
+            };
+
+            let state = decode_state::<DinoCube>("dino-cube", code).unwrap();
+            assert_eq!(&state, original);
+
+            let length: usize = length.parse().unwrap();
+            assert_eq!(idasearch::solve(&state, &h).unwrap().len(), length, "Length of {code}");
+
+            let solution = parse_scramble::<DinoCube>(solution).unwrap();
+            assert_eq!(solution.len(), length);
+            assert!(apply_moves(&state, &solution).is_solved(), "Solution of {code}");
+        }
+    }
+
+    #[test]
+    fn dino_sweep_caches_grow_with_depth() {
+        use std::cell::RefCell;
+
+        use crate::dino_cube::DinoCubeOneSolution;
+        use crate::idasearch::heuristic_helpers::bounded_cache;
+
+        // setup times are too noisy to compare, so check the caches the sweep built instead
+        let sizes = RefCell::new(Vec::new());
+        let rows = heuristic_sweep::<DinoCubeOneSolution, _, _>(3..=6, 2, |depth| {
+            let cache = bounded_cache::<DinoCubeOneSolution>(depth);
+            sizes.borrow_mut().push(cache.entries().count());
+            cache
+        })
+        .unwrap();
+
+        assert_eq!(rows.iter().map(|r| r.depth).collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+        let sizes = sizes.into_inner();
+        assert_eq!(sizes.len(), 4);
+        for pair in sizes.windows(2) {
+            assert!(pair[0] < pair[1], "Cache should grow with depth: {sizes:?}");
+        }
+
+        // same states every time, and a better heuristic never searches more
+        assert!(rows.iter().all(|r| r.total_nodes > 0));
+        for pair in rows.windows(2) {
+            assert!(pair[0].total_nodes >= pair[1].total_nodes, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn inverse_scramble_returns_to_solved() {
+        let scramble = "U2 R2 U' F2 U F2 U' F2 U F2 U' R2";
+        let (inverse, round_trips) = invert_scramble::<Cuboid2x2x3>(scramble).unwrap();
+
+        assert_eq!(format_moves(&inverse), "R2 U F2 U' F2 U F2 U' F2 U R2 U2");
+        assert!(round_trips);
+
+        let moves: Vec<_> = parse_moves(scramble).unwrap();
+        let scrambled = apply_moves(&Cuboid2x2x3::start(), &moves);
+        assert!(!scrambled.is_solved());
+        assert!(apply_moves(&scrambled, &inverse).is_solved());
+    }
+
+    #[test]
+    fn unknown_moves_fail_to_parse() {
+        let err = invert_scramble::<Cuboid2x2x3>("U2 R").unwrap_err();
+        assert_eq!(err, ParseMoveError("R".to_string()));
+    }
+
+    #[test]
+    fn random_moves_are_never_redundant() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let states = random_walk_states::<RediCube, _>(&mut rng, 50);
+
+        for state in states {
+            for last in state.available_moves() {
+                for _ in 0..20 {
+                    let m = random_legal_move(&mut rng, &state, Some(last));
+                    assert!(!RediCube::is_redundant(last, m), "{m:?} is redundant after {last:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stream_solves_each_line_in_order() {
+        use crate::idasearch::heuristic_helpers::bounded_cache;
+        use crate::pocket_cube::{Move, PocketCube};
+
+        let scrambles = ["R U F'", "F2 U' R2 F", "U R' F2 U2 R F'"];
+        let input = scrambles.join("\n");
+        let heuristic = bounded_cache::<PocketCube>(4);
+
+        let mut output = Vec::new();
+        solve_stream::<PocketCube, _, _, _>(input.as_bytes(), &mut output, &heuristic, None, None).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), scrambles.len());
+
+        for (scramble, line) in scrambles.iter().zip(lines) {
+            let moves: Vec<Move> = parse_moves(scramble).unwrap();
+            let solution: Vec<Move> = parse_moves(line).unwrap();
+
+            let scrambled = apply_moves(&PocketCube::solved(), &moves);
+            assert!(
+                apply_moves(&scrambled, &solution).is_solved(),
+                "{line:?} should solve {scramble:?}"
+            );
+            assert!(solution.len() <= moves.len());
+        }
+    }
+
+    #[test]
+    fn connecting_scrambles() {
+        use crate::redi_cube::Move;
+
+        let scramble = "UFR DBL' UFL UBR'";
+        let from_itself = connect_scrambles::<RediCube>(scramble, scramble, 3).unwrap();
+        assert!(from_itself.is_empty(), "A scramble is already where it's going");
+
+        let one_more = format!("{scramble} DFR");
+        let connection = connect_scrambles::<RediCube>(scramble, &one_more, 3).unwrap();
+        assert_eq!(connection, parse_moves::<Move>("DFR").unwrap());
+    }
+
+    #[test]
+    fn one_scrambler_makes_many_scrambles() {
+        let builds = Cell::new(0);
+        let scrambler = Scrambler::<Cuboid2x2x3, _>::new(
+            || {
+                builds.set(builds.get() + 1);
+                bounded_cache::<Cuboid2x2x3>(5)
+            },
+            None,
+        );
+        assert_eq!(builds.get(), 1, "Construction should build the heuristic");
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        let h = bounded_cache::<Cuboid2x2x3>(5);
+        for _ in 0..20 {
+            let scramble = scrambler.scramble(&mut rng).unwrap();
+            let scrambled = apply_moves(&Cuboid2x2x3::solved(), &scramble);
+
+            // the scramble is an optimal solution, reversed, so nothing shorter reaches the state
+            let solution = idasearch::solve(&scrambled, &h).unwrap();
+            assert_eq!(solution.len(), scramble.len(), "{}", format_moves(&scramble));
+        }
+
+        let scramble = scrambler.scramble_string(&mut rng).unwrap();
+        assert!(!scramble.is_empty());
+        assert_eq!(builds.get(), 1, "Scrambling should reuse the heuristic");
+    }
+
+    #[test]
+    fn thread_count_does_not_change_results() {
+        use crate::dino_cube::DinoCube;
+
+        let h = bounded_cache_from(vec![DinoCube::solved(), DinoCube::solved_mirrored()], 5);
+
+        let run = |threads: usize| {
+            with_threads(Some(threads), || {
+                let mut rng = StdRng::from_seed([15; 32]);
+                let states: Vec<DinoCube> = (0..8).map(|_| DinoCube::random_state(&mut rng)).collect();
+                let solutions = bulk_solve(states, &h, None);
+
+                let mut rng = StdRng::from_seed([15; 32]);
+                let (counts, samples) =
+                    bulk_scramble_by(&mut rng, &h, 8, 4, None, |_: &DinoCube, solution| solution.len());
+                let samples: Vec<_> = samples.iter().map(|sample| sample.to_string()).collect();
 
-    let out = moves
-        .into_iter()
-        .map(|m| format!("{m}"))
-        .reduce(|a, b| format!("{a} {b}"))
-        .unwrap_or_else(|| "".to_string());
+                (solutions, counts, samples)
+            })
+        };
 
-    Ok(out)
+        assert_eq!(run(1), run(4));
+    }
 }
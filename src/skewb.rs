@@ -1,5 +1,16 @@
-use crate::cubesearch::State;
-use crate::orientations::CornerOrientation;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use derive_more::Display;
+use enum_iterator::{all, Sequence};
+use rand::Rng;
+
+use crate::cubesearch::{assert_bit_budget, HasSolvedState, SimpleStartState, State};
+use crate::idasearch::Solvable;
+use crate::moves::{parse_by_display, CanReverse, CornerTwistAmt, ParseMoveError};
+use crate::orientations::{CenterOrientation, CornerOrientation};
+use crate::random_helpers::{shuffle_with_parity, TwoParity};
+use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 enum CornerCubelet {
@@ -347,7 +358,7 @@ impl State for Skewb {
         let mut out: u64 = 0;
 
         // can't fit it into 32 bits
-        debug_assert!(7 * 5 + 6 * 3 < 64, "State should fit into 64 bits");
+        const { assert_bit_budget(7 * 5 + 6 * 3, 64) };
 
         out = (out << 3) | self.corner_pos.fdr.as_u8_three_bits() as u64;
         out = (out << 3) | self.corner_pos.fdl.as_u8_three_bits() as u64;
@@ -375,3 +386,565 @@ impl State for Skewb {
         out
     }
 }
+
+impl HasSolvedState for Skewb {}
+
+/// Orientation of each center, as a quarter turn relative to the solved orientation of whichever
+/// face it's currently on. For the U and D faces "up" is toward F; for the rest it's toward U.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+struct CenterOrientationState {
+    u: CenterOrientation,
+    d: CenterOrientation,
+    r: CenterOrientation,
+    l: CenterOrientation,
+    f: CenterOrientation,
+    b: CenterOrientation,
+}
+
+impl SkewbState for CenterOrientationState {
+    fn start() -> Self {
+        Self {
+            u: CenterOrientation::Normal,
+            d: CenterOrientation::Normal,
+            r: CenterOrientation::Normal,
+            l: CenterOrientation::Normal,
+            f: CenterOrientation::Normal,
+            b: CenterOrientation::Normal,
+        }
+    }
+
+    // the centers move the same way as in CenterState; each picks up whatever turn the move
+    // applies to it, relative to the face it lands on
+
+    #[inline(always)]
+    fn dfl(&self) -> Self {
+        Self {
+            f: self.l.cw(),
+            l: self.d.half(),
+            d: self.f.cw(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn dfr(&self) -> Self {
+        Self {
+            r: self.f.cw(),
+            f: self.d.cw(),
+            d: self.r.half(),
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn dbr(&self) -> Self {
+        Self {
+            b: self.r.cw(),
+            d: self.b.ccw(),
+            r: self.d,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn ufr(&self) -> Self {
+        Self {
+            u: self.f.ccw(),
+            f: self.r.cw(),
+            r: self.u,
+            ..*self
+        }
+    }
+}
+
+/// Skewb with oriented centers (the "Super Skewb"), so a center turned in place is not solved
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct SuperSkewb {
+    skewb: Skewb,
+    center_orr: CenterOrientationState,
+}
+
+impl SkewbState for SuperSkewb {
+    fn start() -> Self {
+        Self {
+            skewb: <Skewb as SkewbState>::start(),
+            center_orr: CenterOrientationState::start(),
+        }
+    }
+
+    #[inline(always)]
+    fn dfl(&self) -> Self {
+        Self {
+            skewb: self.skewb.dfl(),
+            center_orr: self.center_orr.dfl(),
+        }
+    }
+
+    #[inline(always)]
+    fn dfr(&self) -> Self {
+        Self {
+            skewb: self.skewb.dfr(),
+            center_orr: self.center_orr.dfr(),
+        }
+    }
+
+    #[inline(always)]
+    fn dbr(&self) -> Self {
+        Self {
+            skewb: self.skewb.dbr(),
+            center_orr: self.center_orr.dbr(),
+        }
+    }
+
+    #[inline(always)]
+    fn ufr(&self) -> Self {
+        Self {
+            skewb: self.skewb.ufr(),
+            center_orr: self.center_orr.ufr(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Sequence)]
+enum Dir {
+    DFL,
+    DFR,
+    DBR,
+    UFR,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Sequence)]
+pub struct Move(Dir, CornerTwistAmt);
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.0, self.1)
+    }
+}
+
+impl CanReverse for Move {
+    fn reverse(&self) -> Self {
+        Move(self.0, self.1.reverse())
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
+    }
+}
+
+#[inline(always)]
+fn apply_move<S: SkewbState>(state: &S, m: Move) -> S {
+    match (m.0, m.1) {
+        (Dir::DFL, CornerTwistAmt::Cw) => state.dfl(),
+        (Dir::DFL, CornerTwistAmt::Ccw) => state.dfl().dfl(),
+        (Dir::DFR, CornerTwistAmt::Cw) => state.dfr(),
+        (Dir::DFR, CornerTwistAmt::Ccw) => state.dfr().dfr(),
+        (Dir::DBR, CornerTwistAmt::Cw) => state.dbr(),
+        (Dir::DBR, CornerTwistAmt::Ccw) => state.dbr().dbr(),
+        (Dir::UFR, CornerTwistAmt::Cw) => state.ufr(),
+        (Dir::UFR, CornerTwistAmt::Ccw) => state.ufr().ufr(),
+    }
+}
+
+impl Solvable for SuperSkewb {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self == &<Self as SkewbState>::start()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        // none of the twists commute with each other
+        last_move.0 == next_move.0
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        apply_move(self, m)
+    }
+
+    fn max_fuel() -> usize {
+        20
+    }
+}
+
+impl Solvable for Skewb {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        self == &<Self as SkewbState>::start()
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        SuperSkewb::is_redundant(last_move, next_move)
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        apply_move(self, m)
+    }
+
+    fn max_fuel() -> usize {
+        // the whole space is enumerable, and the deepest states are 11 moves out
+        11
+    }
+}
+
+/// Skewb where only the corners need solving; the centers can end up in any (even) permutation.
+/// Since the BUL corner never moves, the corners alone pin down how the puzzle is held, so this
+/// is the Skewb solved "up to the centers". The centers move independently of the corners (see
+/// random_state), so this cuts the state space by exactly a factor of 360.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct SkewbCornersOnly {
+    skewb: Skewb,
+}
+
+impl Solvable for SkewbCornersOnly {
+    type Move = Move;
+
+    fn is_solved(&self) -> bool {
+        let start = <Skewb as SkewbState>::start();
+        self.skewb.corner_pos == start.corner_pos && self.skewb.corner_orr == start.corner_orr
+    }
+
+    fn available_moves(&self) -> impl IntoIterator<Item = Self::Move> {
+        all::<Move>()
+    }
+
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
+    fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
+        SuperSkewb::is_redundant(last_move, next_move)
+    }
+
+    fn apply(&self, m: Self::Move) -> Self {
+        Self {
+            skewb: apply_move(&self.skewb, m),
+        }
+    }
+
+    fn max_fuel() -> usize {
+        // no deeper than the full Skewb
+        Skewb::max_fuel()
+    }
+}
+
+impl SimpleStartState for SkewbCornersOnly {
+    type UniqueKey = u64;
+
+    fn start() -> Self {
+        Self {
+            skewb: <Skewb as SkewbState>::start(),
+        }
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        // the centers are the last 18 bits of the full key
+        self.skewb.uniq_key() >> 18
+    }
+}
+
+impl HasSolvedState for SkewbCornersOnly {}
+
+impl SimpleStartState for SuperSkewb {
+    type UniqueKey = u64;
+
+    fn start() -> Self {
+        <Self as SkewbState>::start()
+    }
+
+    fn uniq_key(&self) -> Self::UniqueKey {
+        // The parity of each center's orientation is fixed by where the centers are (see
+        // random_state), so only the high bit is needed, and 53 + 6 bits still fits
+        let mut out: u64 = self.skewb.uniq_key();
+
+        for o in [
+            self.center_orr.u,
+            self.center_orr.d,
+            self.center_orr.r,
+            self.center_orr.l,
+            self.center_orr.f,
+            self.center_orr.b,
+        ] {
+            out = (out << 1) | (o.as_u8_two_bits() >> 1) as u64;
+        }
+
+        out
+    }
+}
+
+impl HasSolvedState for SuperSkewb {}
+
+impl RandomInit for Skewb {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        // centers can be any even permutation, independent of the corners
+        let centers = [
+            CenterCubelet::U,
+            CenterCubelet::D,
+            CenterCubelet::R,
+            CenterCubelet::L,
+            CenterCubelet::F,
+            CenterCubelet::B,
+        ];
+        let centers = shuffle_with_parity(r, &centers, TwoParity::Even);
+
+        // The corners come in two tetrads which never mix. The one containing the fixed BUL
+        // corner can only be cycled; the other can have any even permutation.
+        let tetrad_a = [CornerCubelet::FUR, CornerCubelet::FDL, CornerCubelet::BDR];
+        let tetrad_a = shuffle_with_parity(r, &tetrad_a, TwoParity::Even);
+        let tetrad_b = [
+            CornerCubelet::FUL,
+            CornerCubelet::BUR,
+            CornerCubelet::FDR,
+            CornerCubelet::BDL,
+        ];
+        let tetrad_b = shuffle_with_parity(r, &tetrad_b, TwoParity::Even);
+
+        // The total twist of each tetrad is fixed by how the _other_ tetrad is permuted. For the
+        // B tetrad it's how far the A tetrad has been cycled.
+        let b_twist = match tetrad_a[0] {
+            CornerCubelet::FUR => CornerOrientation::Normal,
+            CornerCubelet::FDL => CornerOrientation::CW,
+            CornerCubelet::BDR => CornerOrientation::CCW,
+            other => unreachable!("{other:?} is not in the A tetrad"),
+        };
+
+        // For the A tetrad it's which of the three ways of pairing up the B positions has FUL and
+        // BUR together; an even permutation of four things cycles the three pairings.
+        let position_of = |c: CornerCubelet| tetrad_b.iter().position(|&x| x == c).unwrap();
+        let a_twist = match (position_of(CornerCubelet::FUL), position_of(CornerCubelet::BUR)) {
+            (0, 1) | (1, 0) | (2, 3) | (3, 2) => CornerOrientation::Normal,
+            (0, 3) | (3, 0) | (1, 2) | (2, 1) => CornerOrientation::CW,
+            _ => CornerOrientation::CCW,
+        };
+
+        let mut a_orr: Vec<CornerOrientation> = vec![r.gen(), r.gen()];
+        a_orr.push(a_twist + CornerOrientation::total(&a_orr).flip());
+
+        let mut b_orr: Vec<CornerOrientation> = vec![r.gen(), r.gen(), r.gen()];
+        b_orr.push(b_twist + CornerOrientation::total(&b_orr).flip());
+
+        Self {
+            centers: CenterState {
+                u: centers[0],
+                d: centers[1],
+                r: centers[2],
+                l: centers[3],
+                f: centers[4],
+                b: centers[5],
+            },
+            corner_pos: CornerPosState {
+                fur: tetrad_a[0],
+                fdl: tetrad_a[1],
+                bdr: tetrad_a[2],
+                ful: tetrad_b[0],
+                bur: tetrad_b[1],
+                fdr: tetrad_b[2],
+                bdl: tetrad_b[3],
+            },
+            corner_orr: CornerOrientationState {
+                fur: a_orr[0],
+                fdl: a_orr[1],
+                bdr: a_orr[2],
+                ful: b_orr[0],
+                bur: b_orr[1],
+                fdr: b_orr[2],
+                bdl: b_orr[3],
+            },
+        }
+    }
+}
+
+impl RandomInit for SuperSkewb {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        let skewb = Skewb::random_state(r);
+
+        // A center's orientation is odd exactly when one, but not both, of its home face and its
+        // current face is F or B; that's where the "up" direction used for orientations changes.
+        // Past that it can be turned by a half, as long as the total turn stays zero.
+        let is_front_or_back = |c: CenterCubelet| matches!(c, CenterCubelet::F | CenterCubelet::B);
+        let mut orientation_at = |piece: CenterCubelet, face_is_front_or_back: bool| {
+            let base = if is_front_or_back(piece) == face_is_front_or_back {
+                CenterOrientation::Normal
+            } else {
+                CenterOrientation::CW
+            };
+
+            if r.gen_bool(0.5) {
+                base.half()
+            } else {
+                base
+            }
+        };
+
+        let c = skewb.centers;
+        let mut orientations = [
+            orientation_at(c.u, false),
+            orientation_at(c.d, false),
+            orientation_at(c.r, false),
+            orientation_at(c.l, false),
+            orientation_at(c.f, true),
+            orientation_at(c.b, true),
+        ];
+
+        // the parities always add up to something even, so a half turn can fix the total
+        if CenterOrientation::total(&orientations) != CenterOrientation::Normal {
+            orientations[5] = orientations[5].half();
+        }
+
+        Self {
+            skewb,
+            center_orr: CenterOrientationState {
+                u: orientations[0],
+                d: orientations[1],
+                r: orientations[2],
+                l: orientations[3],
+                f: orientations[4],
+                b: orientations[5],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::Entry;
+
+    use ahash::HashMap;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::cubesearch::enumerate_state_space;
+    use crate::idasearch::heuristic_helpers::bounded_cache;
+    use crate::idasearch::solve;
+    use crate::scrambles::{apply_moves, parse_moves};
+
+    #[test]
+    fn corners_only_is_smaller() {
+        let (_, counts) = enumerate_state_space::<SkewbCornersOnly>(None).unwrap();
+
+        // the full Skewb has 3149280 states, 11 moves deep
+        assert_eq!(counts.values().sum::<u128>(), 3149280 / 360);
+        assert_eq!(counts.keys().max().copied(), Some(7));
+    }
+
+    #[test]
+    fn corners_only_solutions_solve_the_corners() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let full_heuristic = bounded_cache::<Skewb>(6);
+        let corners_heuristic = bounded_cache::<SkewbCornersOnly>(7);
+
+        for _ in 0..10 {
+            let skewb = Skewb::random_state(&mut rng);
+            let corners = SkewbCornersOnly { skewb };
+
+            let full_solution = solve(&skewb, &full_heuristic).unwrap();
+            let corners_solution = solve(&corners, &corners_heuristic).unwrap();
+
+            // any full solution solves the corners too, so the corners can't need more moves
+            assert!(apply_moves(&corners, &full_solution).is_solved());
+            assert!(corners_solution.len() <= full_solution.len());
+
+            // and solving just the corners leaves the full puzzle at most a center shuffle away
+            let end = apply_moves(&skewb, &corners_solution);
+            let start = <Skewb as SkewbState>::start();
+            assert_eq!(end.corner_pos, start.corner_pos);
+            assert_eq!(end.corner_orr, start.corner_orr);
+        }
+    }
+
+    #[test]
+    fn plain_solution_can_leave_centers_turned() {
+        let start = <SuperSkewb as SkewbState>::start();
+
+        let moves = parse_moves("DFL DFR DFL' DFR DFL DBR UFR' DFR' UFR DFR' UFR' DBR'").unwrap();
+        let end = apply_moves(&start, &moves);
+
+        assert_eq!(end.skewb, start.skewb, "Plain Skewb should be solved");
+        assert!(!end.is_solved(), "Super Skewb should not be solved");
+        assert_eq!(
+            end.center_orr,
+            CenterOrientationState {
+                u: CenterOrientation::Half,
+                d: CenterOrientation::Normal,
+                r: CenterOrientation::Half,
+                l: CenterOrientation::Half,
+                f: CenterOrientation::Normal,
+                b: CenterOrientation::Half,
+            }
+        );
+    }
+
+    #[test]
+    #[ignore = "slow; run with cargo test --release -- --ignored"]
+    fn random_states_are_reachable() {
+        // For every plain Skewb state, find one way of reaching it, and note the centers that way.
+        // Any other way of reaching it differs in the centers only by a half turn of an even
+        // number of them (there are exactly 32 such differences).
+        let start = <SuperSkewb as SkewbState>::start();
+        let mut reached: HashMap<u64, CenterOrientationState> = HashMap::default();
+        reached.insert(start.skewb.uniq_key(), start.center_orr);
+
+        let mut to_process = vec![start];
+        while !to_process.is_empty() {
+            let mut next_stage = Vec::new();
+            for state in to_process {
+                for next in all::<Move>().map(|m| state.apply(m)) {
+                    if let Entry::Vacant(entry) = reached.entry(next.skewb.uniq_key()) {
+                        entry.insert(next.center_orr);
+                        next_stage.push(next);
+                    }
+                }
+            }
+            to_process = next_stage;
+        }
+
+        assert_eq!(reached.len(), 3149280);
+
+        let mut rng = StdRng::from_seed([15; 32]);
+        for _ in 0..1000 {
+            let state = SuperSkewb::random_state(&mut rng);
+            let expected = reached
+                .get(&state.skewb.uniq_key())
+                .expect("Plain Skewb part should be reachable");
+
+            let diffs = [
+                state.center_orr.u + -expected.u,
+                state.center_orr.d + -expected.d,
+                state.center_orr.r + -expected.r,
+                state.center_orr.l + -expected.l,
+                state.center_orr.f + -expected.f,
+                state.center_orr.b + -expected.b,
+            ];
+
+            assert!(
+                diffs
+                    .iter()
+                    .all(|&d| d == CenterOrientation::Normal || d == CenterOrientation::Half),
+                "Centers should only differ by half turns: {state:?}"
+            );
+            assert_eq!(CenterOrientation::total(&diffs), CenterOrientation::Normal);
+        }
+    }
+}
@@ -1,7 +1,14 @@
 use std::mem::swap;
 
-use crate::cubesearch::State;
-use crate::idasearch::Solvable;
+use derive_more::Display;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::cubesearch::{HasSolvedState, IndexedState, State};
+use crate::idasearch::heuristic_helpers::bounded_cache;
+use crate::idasearch::{Heuristic, Solvable};
+use crate::moves::CanReverse;
+use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
 enum Piecelet {
@@ -21,15 +28,28 @@ pub struct SquareOneShape {
     bot: [Piecelet; 12],
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Ord, PartialOrd, Display)]
 pub enum Move {
     // actually, from 1 to 11
+    #[display(fmt = "U{}", _0)]
     U(u8),
     // actually, from 1 to 11
+    #[display(fmt = "D{}", _0)]
     D(u8),
+    #[display(fmt = "/")]
     Slice,
 }
 
+impl CanReverse for Move {
+    fn reverse(&self) -> Self {
+        match self {
+            Move::U(amt) => Move::U(12 - amt),
+            Move::D(amt) => Move::D(12 - amt),
+            Move::Slice => Move::Slice,
+        }
+    }
+}
+
 impl SquareOneShape {
     fn u(&self, amt: usize) -> Self {
         debug_assert!(amt < 12);
@@ -92,6 +112,15 @@ impl Solvable for SquareOneShape {
             .chain(std::iter::once(Move::Slice).filter(|_| self.can_slice()))
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        // the slice is only sometimes available, but it's still a move
+        (1..=11)
+            .map(Move::U)
+            .chain((1..=11).map(Move::D))
+            .chain(std::iter::once(Move::Slice))
+            .collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         match last_move {
             Move::U(_) => matches!(next_move, Move::U(_)),
@@ -101,27 +130,34 @@ impl Solvable for SquareOneShape {
     }
 
     fn max_fuel() -> usize {
-        10
+        // the whole space is enumerable, and the deepest shapes are 15 moves out
+        15
     }
 }
 
 impl State for SquareOneShape {
     // there are 16 pieces total and only their order matters (top, in clockwise order, then
-    // bottom, in clockwise order), so we only need 16 bits (0 for edge, 1 for corner)
-    type UniqueKey = u16;
-
-    fn uniq_key(&self) -> u16 {
-        let mut out: u16 = 0;
-
-        for e in self.top {
-            match e {
-                Piecelet::Edge => {
-                    out <<= 1;
-                }
-                Piecelet::StartCorner => {
-                    out = (out << 1) + 1;
+    // bottom, in clockwise order), so we only need 16 bits (0 for edge, 1 for corner). But that
+    // doesn't pin down where each layer starts, so we also need one bit per layer for whether
+    // a corner straddles its first slot; that's 18 bits
+    type UniqueKey = u32;
+
+    fn uniq_key(&self) -> u32 {
+        let mut out: u32 = 0;
+
+        for layer in [&self.top, &self.bot] {
+            out = (out << 1) + (layer[0] == Piecelet::EndCorner) as u32;
+
+            for e in layer {
+                match e {
+                    Piecelet::Edge => {
+                        out <<= 1;
+                    }
+                    Piecelet::StartCorner => {
+                        out = (out << 1) + 1;
+                    }
+                    Piecelet::EndCorner => {}
                 }
-                Piecelet::EndCorner => {}
             }
         }
 
@@ -176,11 +212,97 @@ impl State for SquareOneShape {
     }
 }
 
+impl HasSolvedState for SquareOneShape {}
+
+impl SquareOneShape {
+    /// Lays out a random ordering of all 16 pieces, filling the top and then the bottom. Returns
+    /// None if a corner would straddle the two layers, or (see below) to correct for bias.
+    fn try_random_state<R: Rng>(r: &mut R) -> Option<Self> {
+        let mut is_corner = [
+            true, true, true, true, true, true, true, true, false, false, false, false, false, false, false, false,
+        ];
+        is_corner.shuffle(r);
+
+        let mut top: Vec<Piecelet> = Vec::with_capacity(12);
+        let mut bot: Vec<Piecelet> = Vec::with_capacity(12);
+        let mut num_top_pieces = 0;
+
+        for c in is_corner {
+            let layer = if top.len() < 12 {
+                num_top_pieces += 1;
+                &mut top
+            } else {
+                &mut bot
+            };
+
+            if c {
+                layer.push(Piecelet::StartCorner);
+                layer.push(Piecelet::EndCorner);
+            } else {
+                layer.push(Piecelet::Edge);
+            }
+        }
+
+        // a corner went over the slice point, so this layout can't exist
+        if top.len() != 12 {
+            return None;
+        }
+
+        // each layer is then rotated at random; a layer with n pieces can come from n different
+        // orderings (one for each piece which could be first), so layouts with more pieces are
+        // overrepresented. Fix this by accepting each layout with probability inversely
+        // proportional to that; 60 is the smallest possible product (6 corners on one side)
+        let num_bot_pieces = 16 - num_top_pieces;
+        if !r.gen_ratio(60, num_top_pieces * num_bot_pieces) {
+            return None;
+        }
+
+        let mut out = Self {
+            top: top.try_into().unwrap(),
+            bot: bot.try_into().unwrap(),
+        };
+
+        out.top.rotate_right(r.gen_range(0..12));
+        out.bot.rotate_right(r.gen_range(0..12));
+
+        Some(out)
+    }
+}
+
+impl RandomInit for SquareOneShape {
+    fn random_state<R: Rng>(r: &mut R) -> Self {
+        loop {
+            if let Some(out) = Self::try_random_state(r) {
+                return out;
+            }
+        }
+    }
+}
+
+pub fn make_heuristic() -> impl Heuristic<SquareOneShape> {
+    // the whole space is tiny, so just store all of it
+    bounded_cache::<SquareOneShape>(SquareOneShape::max_fuel())
+}
+
+impl IndexedState for SquareOneShape {
+    const NUM_INDICES: usize = 1 << 18;
+
+    fn index(&self) -> usize {
+        self.uniq_key() as usize
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use Piecelet::*;
 
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     use super::*;
+    use crate::cubesearch::{enumerate_dense, enumerate_state_space};
+    use crate::idasearch::solve;
+    use crate::test_helpers::random_walk_states;
 
     #[test]
     fn u_one_test() {
@@ -361,4 +483,38 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn dense_enumeration_matches_hashset() {
+        let (_, sparse) = enumerate_state_space::<SquareOneShape>(None).unwrap();
+        let (_, dense) = enumerate_dense::<SquareOneShape>(None).unwrap();
+
+        assert_eq!(sparse, dense);
+    }
+
+    #[test]
+    fn random_shapes_solve_within_max_fuel() {
+        let heuristic = make_heuristic();
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        for _ in 0..100 {
+            let state = SquareOneShape::random_state(&mut rng);
+            let solution = solve(&state, &heuristic).expect("Random shapes should be solvable");
+            assert!(solution.len() <= SquareOneShape::max_fuel());
+        }
+    }
+
+    #[test]
+    fn slice_only_offered_when_possible() {
+        let mut rng = StdRng::from_seed([15; 32]);
+
+        let states = random_walk_states::<SquareOneShape, _>(&mut rng, 200)
+            .into_iter()
+            .chain((0..200).map(|_| SquareOneShape::random_state(&mut rng)));
+
+        for state in states {
+            let offers_slice = state.available_moves().into_iter().any(|m| m == Move::Slice);
+            assert_eq!(offers_slice, state.can_slice());
+        }
+    }
 }
@@ -1,16 +1,20 @@
+use std::str::FromStr;
+
 use derive_more::Display;
 use enum_iterator::{all, Sequence};
 use rand::Rng;
 
-use crate::cubesearch::SimpleStartState;
+use crate::cubesearch::{HasSolvedState, SimpleStartState};
+use crate::describe::PrettyState;
+use crate::half_turn_cuboid::HalfTurnCuboid;
 use crate::idasearch::heuristic_helpers::bounded_cache;
 use crate::idasearch::{Heuristic, Solvable};
-use crate::moves::{CanReverse, CubeMoveAmt};
+use crate::moves::{impl_can_reverse, parse_by_display, CubeMoveAmt, ParseMoveError};
 use crate::random_helpers;
 use crate::scrambles::RandomInit;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Sequence)]
-enum CornerCubelet {
+pub enum CornerCubelet {
     UFL,
     UFR,
     UBL,
@@ -23,71 +27,41 @@ enum CornerCubelet {
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub struct SquareZero {
-    // eight corners
-    ufl: CornerCubelet,
-    ufr: CornerCubelet,
-    ubl: CornerCubelet,
-    ubr: CornerCubelet,
-    dfl: CornerCubelet,
-    dfr: CornerCubelet,
-    dbl: CornerCubelet,
-    dbr: CornerCubelet,
-
-    // three movable centers
+    // eight corners, in the order HalfTurnCuboid uses
+    corners: [CornerCubelet; 8],
+
     middle_flipped: bool, // true is flipped, false is normal
 }
 
-impl SquareZero {
+impl HasSolvedState for SquareZero {
     #[inline(always)]
     fn solved() -> Self {
         Self {
-            ufl: CornerCubelet::UFL,
-            ufr: CornerCubelet::UFR,
-            ubl: CornerCubelet::UBL,
-            ubr: CornerCubelet::UBR,
-            dfl: CornerCubelet::DFL,
-            dfr: CornerCubelet::DFR,
-            dbl: CornerCubelet::DBL,
-            dbr: CornerCubelet::DBR,
+            corners: [
+                CornerCubelet::UFL,
+                CornerCubelet::UFR,
+                CornerCubelet::UBL,
+                CornerCubelet::UBR,
+                CornerCubelet::DFL,
+                CornerCubelet::DFR,
+                CornerCubelet::DBL,
+                CornerCubelet::DBR,
+            ],
             middle_flipped: false,
         }
     }
+}
 
-    #[inline(always)]
-    fn u(&self) -> Self {
-        Self {
-            ufl: self.ufr,
-            ufr: self.ubr,
-            ubr: self.ubl,
-            ubl: self.ufl,
-            ..*self
-        }
-    }
+impl HalfTurnCuboid for SquareZero {
+    type Corner = CornerCubelet;
 
-    #[inline(always)]
-    fn d(&self) -> Self {
-        Self {
-            dfl: self.dbl,
-            dbl: self.dbr,
-            dbr: self.dfr,
-            dfr: self.dfl,
-            ..*self
-        }
+    fn corners_mut(&mut self) -> &mut [CornerCubelet; 8] {
+        &mut self.corners
     }
 
-    #[inline(always)]
-    fn r2(&self) -> Self {
-        Self {
-            // cycle right corners
-            dfr: self.ubr,
-            ubr: self.dfr,
-            dbr: self.ufr,
-            ufr: self.dbr,
-            // flip the middle
-            middle_flipped: !self.middle_flipped,
-            // left side unchanged
-            ..*self
-        }
+    fn r2_rest(&mut self) {
+        // flip the middle
+        self.middle_flipped = !self.middle_flipped;
     }
 }
 
@@ -113,13 +87,16 @@ pub enum Move {
     D(CubeMoveAmt),
 }
 
-impl CanReverse for Move {
-    fn reverse(&self) -> Self {
-        match self {
-            Move::R2 => Move::R2,
-            Move::U(amt) => Move::U(amt.reverse()),
-            Move::D(amt) => Move::D(amt.reverse()),
-        }
+impl_can_reverse!(Move {
+    self_inverse: [R2],
+    by_amount: [U, D],
+});
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_by_display(s)
     }
 }
 
@@ -142,6 +119,10 @@ impl Solvable for SquareZero {
         ]
     }
 
+    fn all_moves() -> Vec<Self::Move> {
+        all::<Move>().collect()
+    }
+
     fn is_redundant(last_move: Self::Move, next_move: Self::Move) -> bool {
         match last_move {
             Move::R2 => next_move == Move::R2,
@@ -153,38 +134,25 @@ impl Solvable for SquareZero {
     fn apply(&self, m: Self::Move) -> Self {
         match m {
             Move::R2 => self.r2(),
-            Move::U(amt) => match amt {
-                CubeMoveAmt::One => self.u(),
-                CubeMoveAmt::Two => self.u().u(),
-                CubeMoveAmt::Rev => self.u().u().u(),
-            },
-            Move::D(amt) => match amt {
-                CubeMoveAmt::One => self.d(),
-                CubeMoveAmt::Two => self.d().d(),
-                CubeMoveAmt::Rev => self.d().d().d(),
-            },
+            Move::U(amt) => self.turn_u(amt),
+            Move::D(amt) => self.turn_d(amt),
         }
     }
 
     fn max_fuel() -> usize {
-        14
+        16
     }
 }
 
+impl PrettyState for SquareZero {}
+
 impl RandomInit for SquareZero {
     fn random_state<R: Rng>(r: &mut R) -> Self {
         // any permutation is fine
         let (corners, _) = random_helpers::shuffle_any(r, all::<CornerCubelet>());
 
         Self {
-            ufl: corners[0],
-            ufr: corners[1],
-            ubl: corners[2],
-            ubr: corners[3],
-            dfl: corners[4],
-            dfr: corners[5],
-            dbl: corners[6],
-            dbr: corners[7],
+            corners: corners.try_into().expect("Shuffling keeps all eight corners"),
             middle_flipped: r.gen(),
         }
     }
@@ -193,3 +161,65 @@ impl RandomInit for SquareZero {
 pub fn make_heuristic() -> impl Heuristic<SquareZero> {
     bounded_cache::<SquareZero>(8)
 }
+
+#[cfg(test)]
+mod golden_tests {
+    use super::*;
+    use crate::test_helpers::assert_golden_scrambles;
+
+    #[test]
+    fn golden_scrambles() {
+        assert_golden_scrambles::<SquareZero, _>(
+            &bounded_cache::<SquareZero>(5),
+            15,
+            &[
+                "R2 U2 R2 D R2 U R2 U' R2 U R2 D' R2",
+                "U' R2 D' R2 U R2 U' R2 U R2 U' R2",
+                "U2 R2 D R2 D' U' R2 U' R2 D U'",
+                "U R2 D R2 D' R2 U R2 U R2 D' U2",
+                "R2 D' R2 D R2 D U' R2 D2 R2",
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod refactor_tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::cubesearch::enumerate_state_space;
+    use crate::scrambles::bulk_scramble;
+
+    // both pinned from the Square Zero as it was before it moved onto HalfTurnCuboid
+
+    #[test]
+    fn enumeration_matches_field_by_field_version() {
+        let (_, counts) = enumerate_state_space::<SquareZero>(None).unwrap();
+
+        let expected: [u128; 17] = [
+            1, 7, 21, 60, 189, 525, 1197, 2420, 4776, 7672, 11932, 14350, 15244, 14124, 6790, 1308, 24,
+        ];
+        assert_eq!(counts.len(), expected.len());
+        for (depth, expected) in expected.into_iter().enumerate() {
+            assert_eq!(counts.get(&(depth as u128)), Some(&expected), "Count at depth {depth}");
+        }
+    }
+
+    #[test]
+    fn scramble_lengths_match_field_by_field_version() {
+        let mut rng = StdRng::from_seed([15; 32]);
+        let results = bulk_scramble::<_, _, SquareZero, _>(&mut rng, &make_heuristic(), 200, 0, None);
+
+        let expected: [usize; 16] = [0, 0, 1, 0, 0, 0, 1, 5, 12, 13, 25, 46, 41, 31, 22, 3];
+        assert_eq!(results.len(), 11, "Every state should solve, with 11 distinct lengths");
+        for (len, expected) in expected.into_iter().enumerate() {
+            assert_eq!(
+                results.get(&Ok(len)).copied().unwrap_or(0),
+                expected,
+                "Count of length {len}"
+            );
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Pins the size and diameter of every puzzle small enough to enumerate. A bad move table or a
+//! packing collision (two states sharing a key) almost always changes one or the other, so this
+//! catches those no matter which puzzle they're in.
+//!
+//! Counts are checked against published figures where there are any. The rest were computed with
+//! `config-depth` and checked against the formula given alongside; diameters without a published
+//! figure are just what `config-depth` found, pinned so they don't drift.
+//!
+//! Left out, since enumerating them needs more memory than a test should: the Big Floppy 1x5x6 and
+//! 1x6x6, the Cuboid 2x3x3, the Dino Cube to either solution, the Super Skewb and the Coin
+//! Pyraminx (whose count is pinned by its own dense enumeration test).
+
+use crate::{enumerate_alg, ConfigAlg};
+
+fn assert_counts(alg: ConfigAlg, total: u128, diameter: u128) {
+    let (_, counts) = enumerate_alg(alg, None, false).unwrap();
+
+    assert_eq!(counts.values().sum::<u128>(), total, "Total states of {alg:?}");
+    assert_eq!(counts.keys().copied().max(), Some(diameter), "Diameter of {alg:?}");
+}
+
+#[test]
+fn floppies() {
+    // 3! positions for the three corners
+    assert_counts(ConfigAlg::Floppy1x2x2, 6, 3);
+    // 4! corner positions * 2 for the right center
+    assert_counts(ConfigAlg::Floppy1x2x3, 48, 6);
+    // 192, per Jaap's Puzzle Page (Floppy Cube); two models of the same puzzle, so they must agree
+    assert_counts(ConfigAlg::Floppy1x3x3, 192, 8);
+    assert_counts(ConfigAlg::BigFloppy1x3x3, 192, 8);
+}
+
+#[test]
+fn big_floppies() {
+    assert_counts(ConfigAlg::BigFloppy1x3x4, 2304, 10);
+    assert_counts(ConfigAlg::BigFloppy1x3x5, 9216, 11);
+    assert_counts(ConfigAlg::BigFloppy1x3x6, 110592, 13);
+    assert_counts(ConfigAlg::BigFloppy1x4x4, 20736, 11);
+    assert_counts(ConfigAlg::BigFloppy1x4x5, 331776, 13);
+}
+
+#[test]
+#[ignore = "slow; run with cargo test --release -- --ignored"]
+fn biggest_floppies() {
+    assert_counts(ConfigAlg::BigFloppy1x4x6, 2985984, 15);
+    assert_counts(ConfigAlg::BigFloppy1x5x5, 2654208, 14);
+}
+
+#[test]
+fn cuboids() {
+    // 8! corner positions * 3! for the centers, holding the BL center in place
+    assert_counts(ConfigAlg::Cuboid2x2x3, 241920, 14);
+}
+
+#[test]
+fn corner_turners() {
+    // 6!/2 center positions * 3^4 free corner twists = 29160, per Jaap's Puzzle Page (Ivy Cube)
+    assert_counts(ConfigAlg::IvyCube, 29160, 8);
+    // counted by `config-depth`; 4 * 3^7
+    assert_counts(ConfigAlg::SkewbCornersOnly, 8748, 7);
+}
+
+#[test]
+#[ignore = "slow; run with cargo test --release -- --ignored"]
+fn skewb() {
+    // 3149280, with God's number 11, per Jaap's Puzzle Page (Skewb)
+    assert_counts(ConfigAlg::Skewb, 3149280, 11);
+}
+
+#[test]
+#[ignore = "slow; run with cargo test --release -- --ignored"]
+fn dino_cube() {
+    // 12!/2 even edge permutations, up to the 12 rotations which are themselves even permutations
+    // of the edges: 19958400, per Jaap's Puzzle Page (Dino Cube); the diameter is what
+    // `config-depth` found
+    assert_counts(ConfigAlg::DinoCubeOneSolution, 19958400, 11);
+}
+
+#[test]
+fn mirror_pocket_cube() {
+    // the mirror blocks tell apart fewer states than stickers would; counted by `config-depth`
+    assert_counts(ConfigAlg::MirrorPocketCube, 34032, 9);
+}
+
+#[test]
+#[ignore = "slow; run with cargo test --release -- --ignored"]
+fn pocket_cubes() {
+    // 7! * 3^6 = 3674160, with God's number 11 (HTM) and 14 (QTM), per Jaap's Puzzle Page (Pocket
+    // Cube); 77802 of those up to symmetry, per the same page
+    assert_counts(ConfigAlg::PocketCube, 3674160, 11);
+    assert_counts(ConfigAlg::PocketCubeQtm, 3674160, 14);
+    assert_counts(ConfigAlg::PocketCubeUpToSymmetry, 77802, 11);
+}
+
+#[test]
+#[ignore = "slow; run with cargo test --release -- --ignored"]
+fn pyraminx() {
+    // 933120 without the tips, with God's number 11, per Jaap's Puzzle Page (Pyraminx); the tips
+    // multiply that by 3^4 and can each add a move
+    assert_counts(ConfigAlg::PyraminxNoTips, 933120, 11);
+    assert_counts(ConfigAlg::PyraminxWithTips, 75582720, 15);
+}
+
+#[test]
+fn square_ones() {
+    // 8! corner positions * 2 for the middle layer
+    assert_counts(ConfigAlg::SquareZero, 80640, 16);
+    // shapes, counted by `config-depth`
+    assert_counts(ConfigAlg::SquareOneShape, 19305, 15);
+}
@@ -0,0 +1,219 @@
+//! Symmetries of the cube -- the 24 rotations, and their 24 mirror images -- and how they act on
+//! the corners of a puzzle. Two states which are symmetric images of each other are "the same"
+//! for most purposes (they're the same distance from solved, for one), so this is the tool for
+//! cutting down enumerations and tables by up to a factor of 48.
+
+use crate::cubesearch::State;
+
+/// Coordinates of a corner; x is R (+) or L (-), y is U or D, and z is F or B.
+pub type CornerPos = [i8; 3];
+
+pub trait SymmetricState: State {
+    /// The smallest key among all the symmetric images of this state. Two states have the same
+    /// canonical key exactly when one is a symmetric image of the other.
+    fn canonical_under_symmetry(&self) -> Self::UniqueKey;
+
+    /// How many different states are symmetric images of this one, itself included; that is, how
+    /// many states share its canonical key.
+    fn orbit_size(&self) -> u128;
+}
+
+/// A symmetry of the cube, as a signed permutation of the axes; output axis `i` is input axis
+/// `perm[i]`, times `signs[i]`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CubeSymmetry {
+    perm: [usize; 3],
+    signs: [i8; 3],
+}
+
+impl CubeSymmetry {
+    /// All 48 symmetries, rotations and reflections both
+    pub fn all() -> Vec<CubeSymmetry> {
+        let perms = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+
+        let mut out = Vec::with_capacity(48);
+        for perm in perms {
+            for bits in 0..8 {
+                let signs = [0, 1, 2].map(|i| if bits & (1 << i) == 0 { 1 } else { -1 });
+                out.push(CubeSymmetry { perm, signs });
+            }
+        }
+
+        out
+    }
+
+    fn apply(&self, v: [i8; 3]) -> [i8; 3] {
+        [0, 1, 2].map(|i| self.signs[i] * v[self.perm[i]])
+    }
+
+    /// Rotations keep the handedness of the cube; reflections swap it
+    pub fn is_rotation(&self) -> bool {
+        let [a, b, c] = [0, 1, 2].map(|i| self.apply(unit(i)));
+        det(a, b, c) > 0
+    }
+}
+
+fn unit(i: usize) -> [i8; 3] {
+    let mut out = [0; 3];
+    out[i] = 1;
+    out
+}
+
+fn det(a: [i8; 3], b: [i8; 3], c: [i8; 3]) -> i8 {
+    a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0]) + a[2] * (b[0] * c[1] - b[1] * c[0])
+}
+
+/// The outward normals of the three stickers of a corner, starting from the U/D sticker and going
+/// clockwise (seen from outside the cube). A corner's orientation is which of these three slots
+/// its own U/D sticker is in.
+fn sticker_normals(pos: CornerPos) -> [[i8; 3]; 3] {
+    let x = [pos[0], 0, 0];
+    let y = [0, pos[1], 0];
+    let z = [0, 0, pos[2]];
+
+    // seen from outside, a right-handed triple goes counterclockwise
+    if det(y, x, z) < 0 {
+        [y, x, z]
+    } else {
+        [y, z, x]
+    }
+}
+
+/// The corners of a puzzle, in the puzzle's own order. `pieces[i]` is the piece currently at
+/// position `i` (named by its solved position), and `twists[i]` is the slot (see [sticker_normals])
+/// holding that piece's U/D sticker; so 0 is untwisted.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CornerState<const N: usize> {
+    pub pieces: [usize; N],
+    pub twists: [u8; N],
+}
+
+/// How one symmetry acts on the corner positions of a particular puzzle
+#[derive(Clone, Debug)]
+struct CornerSymmetry<const N: usize> {
+    /// position `i` goes to position `targets[i]`
+    targets: [usize; N],
+    /// sticker slot `k` at position `i` goes to slot `offsets[i] + k` of the target, for a
+    /// rotation, or `offsets[i] - k` for a reflection (which runs the other way around)
+    offsets: [u8; N],
+    rotation: bool,
+}
+
+impl<const N: usize> CornerSymmetry<N> {
+    fn new(sym: CubeSymmetry, positions: &[CornerPos; N]) -> Self {
+        let index_of = |pos: CornerPos| {
+            positions
+                .iter()
+                .position(|p| *p == pos)
+                .expect("Symmetry should take corners to corners")
+        };
+
+        let targets: [usize; N] = positions.map(|p| index_of(sym.apply(p)));
+
+        let offsets: [u8; N] = std::array::from_fn(|i| {
+            let moved = sym.apply(sticker_normals(positions[i])[0]);
+            sticker_normals(positions[targets[i]])
+                .iter()
+                .position(|n| *n == moved)
+                .expect("Symmetry should take stickers to stickers") as u8
+        });
+
+        Self {
+            targets,
+            offsets,
+            rotation: sym.is_rotation(),
+        }
+    }
+
+    /// The image of the state under this symmetry; that is, the state you get by doing the mirror
+    /// (or rotated) version of whatever moves produced the original
+    fn conjugate(&self, state: &CornerState<N>) -> CornerState<N> {
+        let mut out = *state;
+
+        for p in 0..N {
+            let piece = state.pieces[p];
+            let (d_p, d_piece, t) = (self.offsets[p], self.offsets[piece], state.twists[p]);
+
+            out.pieces[self.targets[p]] = self.targets[piece];
+            out.twists[self.targets[p]] = if self.rotation {
+                (t + d_p + 3 - d_piece) % 3
+            } else {
+                (d_p + 6 - d_piece - t) % 3
+            };
+        }
+
+        out
+    }
+
+    /// The same state, with the whole cube turned by this (rotation) symmetry
+    fn turn_whole_cube(&self, state: &CornerState<N>) -> CornerState<N> {
+        debug_assert!(self.rotation, "Can't physically turn a cube into its mirror image");
+
+        let mut out = *state;
+
+        for p in 0..N {
+            out.pieces[self.targets[p]] = state.pieces[p];
+            out.twists[self.targets[p]] = (state.twists[p] + self.offsets[p]) % 3;
+        }
+
+        out
+    }
+}
+
+/// Every symmetry, as it acts on one puzzle's corners. Puzzles which hold one corner in place (to
+/// factor out turning the whole cube) get their images turned so that corner is back home.
+pub struct CornerSymmetries<const N: usize> {
+    all: Vec<CornerSymmetry<N>>,
+    rotations: Vec<CornerSymmetry<N>>,
+}
+
+impl<const N: usize> CornerSymmetries<N> {
+    pub fn new(positions: &[CornerPos; N]) -> Self {
+        let all: Vec<CornerSymmetry<N>> = CubeSymmetry::all()
+            .into_iter()
+            .map(|sym| CornerSymmetry::new(sym, positions))
+            .collect();
+
+        let rotations = all.iter().filter(|s| s.rotation).cloned().collect();
+
+        Self { all, rotations }
+    }
+
+    /// All 48 images of the state, each turned as a whole so the `fixed` corner is solved
+    pub fn images_fixing<'a>(
+        &'a self,
+        state: &'a CornerState<N>,
+        fixed: usize,
+    ) -> impl Iterator<Item = CornerState<N>> + 'a {
+        self.all.iter().map(move |sym| {
+            let image = sym.conjugate(state);
+
+            let at = image
+                .pieces
+                .iter()
+                .position(|&piece| piece == fixed)
+                .expect("Every piece should be somewhere");
+
+            let turn = self
+                .rotations
+                .iter()
+                .find(|r| r.targets[at] == fixed && (image.twists[at] + r.offsets[at]) % 3 == 0)
+                .expect("Some rotation should bring any corner home");
+
+            turn.turn_whole_cube(&image)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_are_rotations() {
+        let all = CubeSymmetry::all();
+
+        assert_eq!(all.len(), 48);
+        assert_eq!(all.iter().filter(|s| s.is_rotation()).count(), 24);
+    }
+}
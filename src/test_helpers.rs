@@ -0,0 +1,641 @@
+//! Generic correctness checks which apply to every puzzle, instantiated for each of them.
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use ahash::HashSet;
+
+use enum_iterator::{all, Sequence};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::cubesearch::{HasSolvedState, State};
+use crate::idasearch::heuristic_helpers::bounded_cache;
+use crate::idasearch::{solve, Heuristic, Solvable};
+use crate::moves::CanReverse;
+use crate::scrambles::{format_moves, random_legal_move, random_scramble, RandomInit};
+
+/// Walks randomly from the start state, returning every state seen along the way (including the
+/// start). Unlike `RandomInit`, this only ever produces reachable states, and works for every
+/// puzzle.
+pub fn random_walk_states<S: Solvable + HasSolvedState, R: Rng>(rng: &mut R, num_moves: usize) -> Vec<S> {
+    let mut state = S::solved();
+    let mut out = vec![state.clone()];
+    let mut last = None;
+
+    for _ in 0..num_moves {
+        let m = random_legal_move(rng, &state, last);
+        state = state.apply(m);
+        last = Some(m);
+        out.push(state.clone());
+    }
+
+    out
+}
+
+/// Checks that every move reverses properly; that is, reversing twice is a no-op, and applying
+/// a move then its reverse gets you back where you started.
+pub fn assert_reverse_consistent<S>()
+where
+    S: Solvable + HasSolvedState + PartialEq,
+    S::Move: CanReverse + Debug,
+{
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for state in random_walk_states::<S, _>(&mut rng, 50) {
+        for m in state.available_moves() {
+            let rev = m.reverse();
+
+            assert_eq!(rev.reverse(), m, "Reversing {m:?} twice should give {m:?} again");
+            assert!(
+                state.apply(m).apply(rev) == state,
+                "Applying {m:?} then {rev:?} should be a no-op"
+            );
+        }
+    }
+}
+
+/// Checks that every move available from the start state actually changes the state (by
+/// `uniq_key`). This catches moves which were accidentally wired up as no-ops.
+pub fn assert_move_changes_state<S>()
+where
+    S: Solvable + HasSolvedState,
+    S::Move: Debug,
+{
+    assert_move_changes_state_except::<S>(&[]);
+}
+
+/// Same as [assert_move_changes_state], but the listed moves are expected to be no-ops from the
+/// start state (e.g. because of a symmetry), and the check asserts that they are.
+pub fn assert_move_changes_state_except<S>(no_ops: &[S::Move])
+where
+    S: Solvable + HasSolvedState,
+    S::Move: Debug,
+{
+    let start = S::solved();
+    let start_key = start.uniq_key();
+
+    for m in start.available_moves() {
+        let changed = start.apply(m).uniq_key() != start_key;
+        if no_ops.contains(&m) {
+            assert!(
+                !changed,
+                "Move {m:?} was listed as a no-op, but changed the start state"
+            );
+        } else {
+            assert!(changed, "Move {m:?} did not change the start state");
+        }
+    }
+}
+
+/// Checks that `available_moves` only ever offers moves from `all_moves`, along random walks from
+/// the start state (so puzzles whose moves depend on the state get a few different states)
+pub fn assert_available_moves_listed<S>()
+where
+    S: Solvable + HasSolvedState,
+    S::Move: Debug,
+{
+    let all_moves = S::all_moves();
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for state in random_walk_states::<S, _>(&mut rng, 50) {
+        for m in state.available_moves() {
+            assert!(all_moves.contains(&m), "Move {m:?} is available, but not in all_moves");
+        }
+    }
+}
+
+/// Checks that [HasSolvedState::solved] is the same state as [State::start], and that it counts
+/// as solved; puzzles which build their solved state by hand could get either wrong.
+pub fn assert_solved_is_start<S: HasSolvedState + Solvable>() {
+    assert!(
+        S::solved().uniq_key() == <S as State>::start().uniq_key(),
+        "The solved state should be the start state"
+    );
+    assert!(S::solved().is_solved(), "The solved state should count as solved");
+}
+
+/// For puzzles whose moves don't depend on the state: checks that `available_moves` offers exactly
+/// the variants of the `Move` type, from every state along random walks, and that each of them
+/// applies without panicking. This catches a variant which was added to the type but never wired
+/// up (or the other way around).
+pub fn assert_every_variant_available<S>()
+where
+    S: Solvable + HasSolvedState,
+    S::Move: Sequence + Debug,
+{
+    let variants: Vec<S::Move> = all::<S::Move>().collect();
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for state in random_walk_states::<S, _>(&mut rng, 50) {
+        let available: Vec<S::Move> = state.available_moves().into_iter().collect();
+
+        for &m in &variants {
+            assert!(available.contains(&m), "Move {m:?} is a variant, but not available");
+            state.apply(m);
+        }
+
+        assert_eq!(
+            available.len(),
+            variants.len(),
+            "Some move is available twice, or isn't a variant: {available:?}"
+        );
+    }
+}
+
+/// Checks that the half and reverse turns of each face do the same as its quarter turn, applied
+/// two or three times, along random walks from the start state. This catches a turn wired up to
+/// the wrong face, which is easy to do when every amount is spelled out by hand.
+///
+/// Moves are matched up by name, the way [CubeMoveAmt](crate::moves::CubeMoveAmt) prints them:
+/// `X2` and `X'` are checked against `X`. Moves with no quarter turn to match (like the `R2` of a
+/// cuboid) are skipped.
+pub fn assert_amounts_compose<S>()
+where
+    S: Solvable + HasSolvedState + PartialEq,
+    S::Move: Debug + Display,
+{
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for state in random_walk_states::<S, _>(&mut rng, 50) {
+        let moves: Vec<S::Move> = state.available_moves().into_iter().collect();
+
+        for &m in &moves {
+            let name = m.to_string();
+            let (face, turns) = match (name.strip_suffix('2'), name.strip_suffix('\'')) {
+                (Some(face), _) => (face, 2),
+                (_, Some(face)) => (face, 3),
+                _ => continue,
+            };
+            let Some(&quarter) = moves.iter().find(|q| q.to_string() == face) else {
+                continue;
+            };
+
+            let expected = (0..turns).fold(state.clone(), |s, _| s.apply(quarter));
+            assert!(
+                state.apply(m) == expected,
+                "Applying {m:?} should be the same as applying {quarter:?} {turns} times"
+            );
+        }
+    }
+}
+
+/// Checks that `is_redundant` never rules out every shortest solution. The true distance of each
+/// state within `depth` of solved comes from a plain BFS, which doesn't skip any moves; `solve`
+/// has to match it exactly, on random walks from solved. A longer (or missing) solution means some
+/// move was called redundant when it wasn't.
+///
+/// The cache doubles as the heuristic, which is fine: it's admissible, and whatever the heuristic,
+/// IDA* finds the shortest solution among the move sequences `is_redundant` lets through.
+pub fn assert_redundancy_sound<S>(depth: usize, num_walks: usize)
+where
+    S: Solvable + HasSolvedState,
+    S::Move: Debug,
+{
+    let distances = bounded_cache::<S>(depth);
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for _ in 0..num_walks {
+        for state in random_walk_states::<S, _>(&mut rng, depth) {
+            let expected = distances
+                .remaining_cost_if_known(&state)
+                .expect("A walk of `depth` moves stays within `depth` of solved");
+
+            let solution = solve(&state, &distances).expect("State should be solvable");
+            assert_eq!(
+                solution.len(),
+                expected,
+                "Solve found {solution:?}, but the state is {expected} moves from solved"
+            );
+        }
+    }
+}
+
+/// Checks that `is_redundant` cuts every pair of the given moves which commute, one way or the
+/// other, so the search never tries both orders. Two moves commute if they do from every one of
+/// `states`. Returns the commuting pairs of different moves, in both orders, for checking against
+/// what the puzzle should have.
+pub fn assert_commuting_pairs_cut<S>(states: &[S], moves: &[S::Move]) -> Vec<(S::Move, S::Move)>
+where
+    S: Solvable + State,
+    S::Move: Display,
+{
+    let mut commuting = Vec::new();
+
+    for &a in moves {
+        for &b in moves {
+            let commute = states
+                .iter()
+                .all(|s| s.apply(a).apply(b).uniq_key() == s.apply(b).apply(a).uniq_key());
+            if !commute {
+                continue;
+            }
+
+            assert!(
+                S::is_redundant(a, b) || S::is_redundant(b, a),
+                "{a} and {b} commute, but neither order is ruled redundant"
+            );
+
+            if a != b {
+                commuting.push((a, b));
+            }
+        }
+    }
+
+    commuting
+}
+
+/// Checks each pair of moves `is_redundant` rejects, one pair at a time, by brute force: doing the
+/// two moves has to give a state which some sequence the rule _accepts_ also gets to, in at most
+/// two moves (none, one, or an accepted pair). Otherwise the rule is cutting off states the search
+/// can't get to some other way, and `solve` can miss the shortest solution. This is checked from
+/// every state along a few random walks, for puzzles whose moves depend on the state.
+///
+/// Unlike [assert_redundancy_sound], this names the offending pair, and doesn't need a cache.
+pub fn assert_rejected_pairs_redundant<S>()
+where
+    S: Solvable + HasSolvedState,
+    S::Move: Debug,
+{
+    let mut rng = StdRng::from_seed([15; 32]);
+
+    for _ in 0..5 {
+        for state in random_walk_states::<S, _>(&mut rng, 10) {
+            let moves: Vec<S::Move> = state.available_moves().into_iter().collect();
+
+            // everything reachable in at most two moves the rule lets through
+            let mut accepted = vec![state.uniq_key()];
+            for &c in &moves {
+                let after_c = state.apply(c);
+                for d in after_c.available_moves() {
+                    if !S::is_redundant(c, d) {
+                        accepted.push(after_c.apply(d).uniq_key());
+                    }
+                }
+                accepted.push(after_c.uniq_key());
+            }
+
+            for &a in &moves {
+                let after_a = state.apply(a);
+                for b in after_a.available_moves() {
+                    if !S::is_redundant(a, b) {
+                        continue;
+                    }
+
+                    let target = after_a.apply(b).uniq_key();
+                    assert!(
+                        accepted.contains(&target),
+                        "{a:?} then {b:?} is ruled redundant, but nothing else gets there in two moves"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Checks that no two states within `depth` moves of solved share a `uniq_key`. The states are
+/// walked (breadth first) by the states themselves, not by their keys, so a collision in the
+/// packing can't hide any of them. Returns the keys, for puzzles which want to check more about
+/// them.
+pub fn assert_keys_distinct<S>(depth: usize) -> HashSet<<S as State>::UniqueKey>
+where
+    S: Solvable + HasSolvedState + Hash + Eq,
+{
+    let mut seen: HashSet<S> = HashSet::default();
+    seen.insert(S::solved());
+    let mut frontier = vec![S::solved()];
+
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for state in frontier {
+            for m in state.available_moves() {
+                let neighbor = state.apply(m);
+                if !seen.contains(&neighbor) {
+                    seen.insert(neighbor.clone());
+                    next.push(neighbor);
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    let keys: HashSet<_> = seen.iter().map(|state| state.uniq_key()).collect();
+    assert_eq!(keys.len(), seen.len(), "Some states within {depth} moves share a key");
+
+    keys
+}
+
+/// Generates one scramble per expected value, from a fixed seed, and checks they match exactly.
+/// This pins down the random state generator, the move tables, and the move ordering; if any of
+/// those change on purpose, the golden values need to be updated by hand.
+///
+/// Since IDA* returns the first optimal solution in move order, the result does not depend on
+/// the heuristic (as long as it's admissible), so tests can use a smaller cache than the CLI does.
+pub fn assert_golden_scrambles<S, H>(h: &H, seed: u8, expected: &[&str])
+where
+    S: RandomInit + Solvable,
+    S::Move: CanReverse + Display,
+    H: Heuristic<S>,
+{
+    let mut rng = StdRng::from_seed([seed; 32]);
+
+    let actual: Vec<String> = expected
+        .iter()
+        .map(|_| random_scramble::<_, _, S, _>(&mut rng, h, None).expect("Scramble should be solvable"))
+        .map(|scramble| format_moves(&scramble))
+        .collect();
+
+    assert_eq!(
+        actual, expected,
+        "Scrambles changed; if this is intended, update the golden values"
+    );
+}
+
+macro_rules! all_puzzle_tests {
+    ($helper:ident, $($test_name:ident: $puzzle:ty),* $(,)?) => {
+        all_puzzle_tests!($helper(), $($test_name: $puzzle),*);
+    };
+    ($helper:ident $args:tt, $($test_name:ident: $puzzle:ty),* $(,)?) => {
+        $(
+            #[test]
+            fn $test_name() {
+                $helper::<$puzzle> $args;
+            }
+        )*
+    };
+}
+
+mod reverse_consistency {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_reverse_consistent,
+        floppy_1x2x2: crate::floppy_1x2x2::Floppy1x2x2,
+        floppy_1x2x3: crate::floppy_1x2x3::Floppy1x2x3,
+        floppy_1x3x3: crate::floppy_1x3x3::Floppy1x3x3,
+        floppy_1x3x4: crate::floppy_1xnxn::Floppy1xMxN<1, 2>,
+        floppy_1x4x5: crate::floppy_1xnxn::Floppy1xMxN<2, 3>,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        dino_cube: crate::dino_cube::DinoCube,
+        dino_cube_one_solution: crate::dino_cube::DinoCubeOneSolution,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        redi_cube: crate::redi_cube::RediCube,
+        ivy_cube: crate::ivy_cube::IvyCube,
+        square_zero: crate::square_zero::SquareZero,
+        square_one_shape: crate::square_one_shape::SquareOneShape,
+        curvy_copter: crate::curvy_copter::CurvyCopter,
+        skewb: crate::skewb::Skewb,
+        skewb_corners_only: crate::skewb::SkewbCornersOnly,
+        super_skewb: crate::skewb::SuperSkewb,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod move_changes_state {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_move_changes_state,
+        floppy_1x2x2: crate::floppy_1x2x2::Floppy1x2x2,
+        floppy_1x2x3: crate::floppy_1x2x3::Floppy1x2x3,
+        floppy_1x3x3: crate::floppy_1x3x3::Floppy1x3x3,
+        floppy_1x3x4: crate::floppy_1xnxn::Floppy1xMxN<1, 2>,
+        floppy_1x4x5: crate::floppy_1xnxn::Floppy1xMxN<2, 3>,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        dino_cube: crate::dino_cube::DinoCube,
+        dino_cube_one_solution: crate::dino_cube::DinoCubeOneSolution,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        redi_cube: crate::redi_cube::RediCube,
+        ivy_cube: crate::ivy_cube::IvyCube,
+        square_zero: crate::square_zero::SquareZero,
+        curvy_copter: crate::curvy_copter::CurvyCopter,
+        skewb: crate::skewb::Skewb,
+        skewb_corners_only: crate::skewb::SkewbCornersOnly,
+        super_skewb: crate::skewb::SuperSkewb,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+
+    #[test]
+    fn square_one_shape() {
+        use crate::square_one_shape::{Move, SquareOneShape};
+
+        // each layer of the solved shape repeats every quarter turn, so those turns do nothing
+        let no_ops: Vec<Move> = [3, 6, 9]
+            .into_iter()
+            .flat_map(|amt| [Move::U(amt), Move::D(amt)])
+            .collect();
+
+        assert_move_changes_state_except::<SquareOneShape>(&no_ops);
+    }
+}
+
+mod available_moves_listed {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_available_moves_listed,
+        floppy_1x2x2: crate::floppy_1x2x2::Floppy1x2x2,
+        floppy_1x2x3: crate::floppy_1x2x3::Floppy1x2x3,
+        floppy_1x3x3: crate::floppy_1x3x3::Floppy1x3x3,
+        floppy_1x3x4: crate::floppy_1xnxn::Floppy1xMxN<1, 2>,
+        floppy_1x4x5: crate::floppy_1xnxn::Floppy1xMxN<2, 3>,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        dino_cube: crate::dino_cube::DinoCube,
+        dino_cube_one_solution: crate::dino_cube::DinoCubeOneSolution,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        redi_cube: crate::redi_cube::RediCube,
+        ivy_cube: crate::ivy_cube::IvyCube,
+        square_zero: crate::square_zero::SquareZero,
+        square_one_shape: crate::square_one_shape::SquareOneShape,
+        curvy_copter: crate::curvy_copter::CurvyCopter,
+        skewb: crate::skewb::Skewb,
+        skewb_corners_only: crate::skewb::SkewbCornersOnly,
+        super_skewb: crate::skewb::SuperSkewb,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod solved_is_start {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_solved_is_start,
+        floppy_1x2x2: crate::floppy_1x2x2::Floppy1x2x2,
+        floppy_1x2x3: crate::floppy_1x2x3::Floppy1x2x3,
+        floppy_1x3x3: crate::floppy_1x3x3::Floppy1x3x3,
+        floppy_1x3x4: crate::floppy_1xnxn::Floppy1xMxN<1, 2>,
+        floppy_1x4x5: crate::floppy_1xnxn::Floppy1xMxN<2, 3>,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        dino_cube: crate::dino_cube::DinoCube,
+        dino_cube_one_solution: crate::dino_cube::DinoCubeOneSolution,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        redi_cube: crate::redi_cube::RediCube,
+        ivy_cube: crate::ivy_cube::IvyCube,
+        square_zero: crate::square_zero::SquareZero,
+        square_one_shape: crate::square_one_shape::SquareOneShape,
+        curvy_copter: crate::curvy_copter::CurvyCopter,
+        skewb: crate::skewb::Skewb,
+        skewb_corners_only: crate::skewb::SkewbCornersOnly,
+        super_skewb: crate::skewb::SuperSkewb,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod every_variant_available {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_every_variant_available,
+        floppy_1x2x2: crate::floppy_1x2x2::Floppy1x2x2,
+        floppy_1x2x3: crate::floppy_1x2x3::Floppy1x2x3,
+        floppy_1x3x3: crate::floppy_1x3x3::Floppy1x3x3,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        dino_cube: crate::dino_cube::DinoCube,
+        dino_cube_one_solution: crate::dino_cube::DinoCubeOneSolution,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        redi_cube: crate::redi_cube::RediCube,
+        ivy_cube: crate::ivy_cube::IvyCube,
+        square_zero: crate::square_zero::SquareZero,
+        skewb: crate::skewb::Skewb,
+        skewb_corners_only: crate::skewb::SkewbCornersOnly,
+        super_skewb: crate::skewb::SuperSkewb,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod amounts_compose {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_amounts_compose,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        square_zero: crate::square_zero::SquareZero,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod keys_distinct {
+    use super::*;
+
+    // not the Skewb corners only, whose key leaves out the centers on purpose
+    all_puzzle_tests!(
+        assert_keys_distinct(4),
+        floppy_1x2x2: crate::floppy_1x2x2::Floppy1x2x2,
+        floppy_1x2x3: crate::floppy_1x2x3::Floppy1x2x3,
+        floppy_1x3x3: crate::floppy_1x3x3::Floppy1x3x3,
+        floppy_1x3x4: crate::floppy_1xnxn::Floppy1xMxN<1, 2>,
+        floppy_1x4x5: crate::floppy_1xnxn::Floppy1xMxN<2, 3>,
+        pocket_cube: crate::pocket_cube::PocketCube,
+        mirror_pocket_cube: crate::mirror_pocket_cube::MirrorPocketCube,
+        cuboid_2x2x3: crate::cuboid_2x2x3::Cuboid2x2x3,
+        cuboid_2x3x3: crate::cuboid_2x3x3::Cuboid2x3x3,
+        super_cuboid_2x3x3: crate::cuboid_2x3x3::SuperCuboid2x3x3,
+        dino_cube: crate::dino_cube::DinoCube,
+        dino_cube_one_solution: crate::dino_cube::DinoCubeOneSolution,
+        bandaged_3x3x3_1x2x3: crate::bandaged_3x3x3_1x2x3::Bandaged3x3x3with1x2x3,
+        redi_cube: crate::redi_cube::RediCube,
+        ivy_cube: crate::ivy_cube::IvyCube,
+        square_zero: crate::square_zero::SquareZero,
+        square_one_shape: crate::square_one_shape::SquareOneShape,
+        curvy_copter: crate::curvy_copter::CurvyCopter,
+        skewb: crate::skewb::Skewb,
+        super_skewb: crate::skewb::SuperSkewb,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod rejected_pairs_redundant {
+    use super::*;
+
+    all_puzzle_tests!(
+        assert_rejected_pairs_redundant,
+        redi_cube: crate::redi_cube::RediCube,
+        curvy_copter: crate::curvy_copter::CurvyCopter,
+        pyraminx_with_tips: crate::pyraminx::PyraminxWithTips,
+        rubiks_3x3x3: crate::rubiks_3x3x3::Rubiks3x3x3,
+        helicopter_cube: crate::helicopter_cube::HelicopterCube,
+    );
+}
+
+mod redundancy_sound {
+    use super::*;
+
+    #[test]
+    fn cuboid_2x2x3() {
+        // small enough to take the whole puzzle; nothing is more than 14 moves out
+        assert_redundancy_sound::<crate::cuboid_2x2x3::Cuboid2x2x3>(14, 20);
+    }
+
+    #[test]
+    fn cuboid_2x3x3() {
+        assert_redundancy_sound::<crate::cuboid_2x3x3::Cuboid2x3x3>(8, 20);
+    }
+
+    #[test]
+    fn super_cuboid_2x3x3() {
+        assert_redundancy_sound::<crate::cuboid_2x3x3::SuperCuboid2x3x3>(8, 20);
+    }
+
+    #[test]
+    fn dino_cube() {
+        assert_redundancy_sound::<crate::dino_cube::DinoCube>(6, 20);
+    }
+
+    #[test]
+    fn dino_cube_one_solution() {
+        assert_redundancy_sound::<crate::dino_cube::DinoCubeOneSolution>(6, 20);
+    }
+
+    #[test]
+    fn redi_cube() {
+        assert_redundancy_sound::<crate::redi_cube::RediCube>(6, 20);
+    }
+
+    #[test]
+    fn rubiks_3x3x3() {
+        assert_redundancy_sound::<crate::rubiks_3x3x3::Rubiks3x3x3>(4, 20);
+    }
+
+    #[test]
+    fn helicopter_cube() {
+        assert_redundancy_sound::<crate::helicopter_cube::HelicopterCube>(4, 20);
+    }
+}